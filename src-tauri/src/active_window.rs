@@ -0,0 +1,130 @@
+// 前台窗口检测模块（用于按目标应用匹配规则、检测焦点变化、把文本插回录音时的窗口）
+
+/// 前台窗口句柄标识，用于比较焦点是否变化、切回目标窗口
+pub type WindowId = isize;
+
+#[cfg(windows)]
+mod imp {
+    use super::WindowId;
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::System::Threading::{
+        AttachThreadInput, GetCurrentThreadId, OpenProcess, QueryFullProcessImageNameW,
+        PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        GetClassNameW, GetForegroundWindow, GetWindowThreadProcessId, IsIconic, IsWindow,
+        SetForegroundWindow, ShowWindow, SW_RESTORE,
+    };
+
+    pub fn foreground_window() -> Option<WindowId> {
+        let hwnd = unsafe { GetForegroundWindow() };
+        if hwnd == 0 {
+            None
+        } else {
+            Some(hwnd)
+        }
+    }
+
+    pub fn process_name(hwnd: WindowId) -> Option<String> {
+        let mut pid: u32 = 0;
+        unsafe { GetWindowThreadProcessId(hwnd, &mut pid) };
+        if pid == 0 {
+            return None;
+        }
+
+        let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) };
+        if process == 0 {
+            return None;
+        }
+
+        let mut buf = [0u16; 260];
+        let mut len = buf.len() as u32;
+        let ok = unsafe {
+            QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, buf.as_mut_ptr(), &mut len)
+        };
+        unsafe { CloseHandle(process) };
+        if ok == 0 {
+            return None;
+        }
+
+        // 只取可执行文件名，如 C:\...\WeChat.exe -> WeChat.exe
+        let path = String::from_utf16_lossy(&buf[..len as usize]);
+        path.rsplit(['\\', '/']).next().map(|name| name.to_string())
+    }
+
+    pub fn window_class(hwnd: WindowId) -> Option<String> {
+        // 窗口类名最长 256 个字符
+        let mut buf = [0u16; 257];
+        let len = unsafe { GetClassNameW(hwnd, buf.as_mut_ptr(), buf.len() as i32) };
+        if len <= 0 {
+            return None;
+        }
+        Some(String::from_utf16_lossy(&buf[..len as usize]))
+    }
+
+    pub fn focus_window(hwnd: WindowId) -> bool {
+        unsafe {
+            if IsWindow(hwnd) == 0 {
+                return false;
+            }
+            if IsIconic(hwnd) != 0 {
+                ShowWindow(hwnd, SW_RESTORE);
+            }
+            if SetForegroundWindow(hwnd) != 0 {
+                return true;
+            }
+
+            // 受前台锁限制时，临时挂到当前前台窗口线程的输入队列上再切换
+            let foreground_thread = GetWindowThreadProcessId(GetForegroundWindow(), std::ptr::null_mut());
+            let current_thread = GetCurrentThreadId();
+            if foreground_thread == 0 || foreground_thread == current_thread {
+                return false;
+            }
+            AttachThreadInput(current_thread, foreground_thread, 1);
+            let focused = SetForegroundWindow(hwnd) != 0;
+            AttachThreadInput(current_thread, foreground_thread, 0);
+            focused
+        }
+    }
+}
+
+#[cfg(not(windows))]
+mod imp {
+    use super::WindowId;
+
+    pub fn foreground_window() -> Option<WindowId> {
+        None
+    }
+
+    pub fn process_name(_window: WindowId) -> Option<String> {
+        None
+    }
+
+    pub fn window_class(_window: WindowId) -> Option<String> {
+        None
+    }
+
+    pub fn focus_window(_window: WindowId) -> bool {
+        false
+    }
+}
+
+/// 当前前台窗口（不支持的平台返回 None）
+pub fn foreground_window() -> Option<WindowId> {
+    imp::foreground_window()
+}
+
+/// 窗口所属进程的可执行文件名（不支持的平台返回 None）
+pub fn process_name(window: WindowId) -> Option<String> {
+    imp::process_name(window)
+}
+
+/// 窗口类名，如 "Chrome_WidgetWin_1"（不支持的平台返回 None）
+pub fn window_class(window: WindowId) -> Option<String> {
+    imp::window_class(window)
+}
+
+/// 把指定窗口切到前台，窗口已关闭或切换失败时返回 false
+pub fn focus_window(window: WindowId) -> bool {
+    imp::focus_window(window)
+}
@@ -0,0 +1,165 @@
+// 音频文件解码模块（离线转录）
+// 用 symphonia 解码 wav / mp3 / m4a 等常见格式，转成 16kHz 单声道后按固定时长切成 WAV 片段，
+// 每个片段单独走 HTTP 识别，避免长文件一次上传超时
+
+use anyhow::Result;
+use hound::{WavSpec, WavWriter};
+use std::io::Cursor;
+use std::path::Path;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use crate::audio_processing;
+use crate::resampler;
+use crate::config::ChannelSelection;
+
+// HTTP 识别接口要求的采样率
+const TARGET_SAMPLE_RATE: u32 = 16000;
+// 每个识别片段的时长
+pub const SEGMENT_SECS: usize = 60;
+
+/// 解码音频文件并切成 16kHz 单声道 WAV 片段
+pub fn load_segments(path: &Path) -> Result<Vec<Vec<u8>>> {
+    let (samples, sample_rate) = decode_mono(path)?;
+    let samples = resampler::resample(&samples, sample_rate, TARGET_SAMPLE_RATE);
+    tracing::info!(
+        "音频文件已解码: {:?}, 原采样率 {}Hz, 时长 {:.1} 秒",
+        path,
+        sample_rate,
+        samples.len() as f32 / TARGET_SAMPLE_RATE as f32
+    );
+
+    samples
+        .chunks(SEGMENT_SECS * TARGET_SAMPLE_RATE as usize)
+        .map(encode_wav)
+        .collect()
+}
+
+/// 解码整个文件为单声道 f32 样本（多声道平均混合），返回样本与采样率
+fn decode_mono(path: &Path) -> Result<(Vec<f32>, u32)> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| anyhow::anyhow!("无法打开音频文件 {:?}: {}", path, e))?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|ext| ext.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let probed = symphonia::default::get_probe()
+        .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+        .map_err(|e| anyhow::anyhow!("不支持的音频格式: {}", e))?;
+    let mut format = probed.format;
+
+    let track = format
+        .tracks()
+        .iter()
+        .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| anyhow::anyhow!("音频文件中没有可解码的音轨"))?;
+    let track_id = track.id;
+    let sample_rate = track
+        .codec_params
+        .sample_rate
+        .ok_or_else(|| anyhow::anyhow!("无法获取音频采样率"))?;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|e| anyhow::anyhow!("不支持的音频编码: {}", e))?;
+
+    let mut samples = Vec::new();
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            // 读到文件末尾
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(anyhow::anyhow!("读取音频数据失败: {}", e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // 个别损坏的帧跳过即可
+            Err(SymphoniaError::DecodeError(e)) => {
+                tracing::warn!("跳过无法解码的音频帧: {}", e);
+                continue;
+            }
+            Err(e) => return Err(anyhow::anyhow!("解码音频失败: {}", e)),
+        };
+
+        let channels = decoded.spec().channels.count() as u16;
+        let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, *decoded.spec());
+        buffer.copy_interleaved_ref(decoded);
+        samples.extend(audio_processing::to_mono(buffer.samples(), channels, ChannelSelection::Mix));
+    }
+
+    if samples.is_empty() {
+        anyhow::bail!("音频文件为空");
+    }
+    Ok((samples, sample_rate))
+}
+
+/// 编码为 16kHz 16-bit 单声道 WAV
+pub fn encode_wav(samples: &[f32]) -> Result<Vec<u8>> {
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: TARGET_SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+
+    let mut cursor = Cursor::new(Vec::new());
+    {
+        let mut writer = WavWriter::new(&mut cursor, spec)?;
+        for &sample in samples {
+            let amplitude = (sample * i16::MAX as f32).clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+            writer.write_sample(amplitude)?;
+        }
+        writer.finalize()?;
+    }
+    Ok(cursor.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_long_files_into_16k_segments() {
+        // 70 秒 44.1kHz 立体声 WAV
+        let path = std::env::temp_dir().join(format!("ptt-audio-file-{}.wav", std::process::id()));
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 44100,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = WavWriter::create(&path, spec).unwrap();
+        for i in 0..70 * 44100 {
+            let sample = ((i as f32 * 0.05).sin() * 8000.0) as i16;
+            writer.write_sample(sample).unwrap();
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let segments = load_segments(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(segments.len(), 2);
+        let durations: Vec<u32> = segments
+            .iter()
+            .map(|wav| {
+                let reader = hound::WavReader::new(Cursor::new(wav)).unwrap();
+                assert_eq!(reader.spec().sample_rate, TARGET_SAMPLE_RATE);
+                assert_eq!(reader.spec().channels, 1);
+                reader.duration() / TARGET_SAMPLE_RATE
+            })
+            .collect();
+        assert_eq!(durations, vec![60, 10]);
+    }
+}
@@ -0,0 +1,108 @@
+// 音频帧编码
+// 各家实时协议上传 PCM 的方式不同：支持二进制帧的（火山引擎）直接发送原始字节，
+// 只接受 JSON 事件的（千问 / OpenAI）按 base64 嵌入 input_audio_buffer.append，每帧按最终长度分配一次
+
+use base64::{engine::general_purpose, Engine as _};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::qwen_realtime;
+
+/// 把一段 16-bit PCM 编码为一条 WebSocket 消息
+pub trait AudioFrameEncoder: Send {
+    /// `last` 标记本次录音的最后一包，协议不区分时忽略
+    fn encode(&mut self, pcm: &[u8], last: bool) -> Message;
+}
+
+/// input_audio_buffer.append 事件：base64 直接写入按帧长预留的字符串，不经过 serde_json::Value 和中间字符串
+/// Message::Text 需要取得字符串的所有权，所以每帧仍有一次分配，编码过程中不再扩容
+#[derive(Default)]
+pub struct Base64AppendEncoder;
+
+impl AudioFrameEncoder for Base64AppendEncoder {
+    fn encode(&mut self, pcm: &[u8], _last: bool) -> Message {
+        const PREFIX: &str = r#"{"event_id":""#;
+        const TYPE: &str = r#"","type":"input_audio_buffer.append","audio":""#;
+        const SUFFIX: &str = r#""}"#;
+        let event_id = qwen_realtime::next_event_id();
+        let len = PREFIX.len() + event_id.len() + TYPE.len() + base64_len(pcm.len()) + SUFFIX.len();
+
+        let mut frame = String::with_capacity(len);
+        frame.push_str(PREFIX);
+        frame.push_str(&event_id);
+        frame.push_str(TYPE);
+        general_purpose::STANDARD.encode_string(pcm, &mut frame);
+        frame.push_str(SUFFIX);
+        Message::Text(frame)
+    }
+}
+
+/// 带填充的 base64 编码长度
+fn base64_len(bytes: usize) -> usize {
+    bytes.div_ceil(3) * 4
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn base64_append_allocates_once_per_frame() {
+        let mut encoder = Base64AppendEncoder;
+        let pcm: Vec<u8> = (0..6400u32).map(|i| (i % 251) as u8).collect();
+
+        let Message::Text(text) = encoder.encode(&pcm, false) else {
+            panic!("应为文本帧");
+        };
+        let event: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(event["type"], "input_audio_buffer.append");
+        assert!(event["event_id"].as_str().unwrap().starts_with("event_"));
+        assert_eq!(general_purpose::STANDARD.decode(event["audio"].as_str().unwrap()).unwrap(), pcm);
+
+        // 按最终长度一次分配：容量恰好等于帧长，编码过程中没有扩容
+        for chunk in [&pcm[..], &pcm[..3200], &pcm[..3199]] {
+            let Message::Text(frame) = encoder.encode(chunk, false) else {
+                panic!("应为文本帧");
+            };
+            assert_eq!(frame.capacity(), frame.len());
+        }
+    }
+
+    #[test]
+    fn base64_append_upload_size() {
+        // 60 秒 16kHz 16-bit 录音按 200ms 一块上传：base64 事件约为原始字节的 4/3，JSON 外壳只占很小一部分
+        let chunk: Vec<u8> = (0..6400u32).map(|i| (i.wrapping_mul(2654435761) >> 24) as u8).collect();
+        let chunks = 60 * 1000 / 200;
+        let mut encoder = Base64AppendEncoder;
+
+        let binary: usize = (0..chunks).map(|_| Message::Binary(chunk.clone()).len()).sum();
+        let text: usize = (0..chunks).map(|_| encoder.encode(&chunk, false).len()).sum();
+        assert_eq!(binary, 6400 * chunks);
+        let ratio = text as f64 / binary as f64;
+        assert!((4.0 / 3.0..1.36).contains(&ratio), "上行字节比 {:.3}", ratio);
+    }
+
+    /// 100 段 60 秒 16kHz 录音（200ms 一块）编码为 append 事件的耗时：对比 json! + base64 字符串的旧写法
+    /// cargo test --release audio_frame -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_base64_append() {
+        let chunk: Vec<u8> = (0..6400u32).map(|i| (i.wrapping_mul(2654435761) >> 24) as u8).collect();
+        let frames = 100 * 60 * 1000 / 200;
+        fn time(name: &str, chunk: &[u8], frames: usize, mut encode: impl FnMut(&[u8]) -> Message) {
+            let started = Instant::now();
+            let bytes: usize = (0..frames).map(|_| encode(chunk).len()).sum();
+            println!("{:<16} {:>6} ms, {} 字节", name, started.elapsed().as_millis(), bytes);
+        }
+        time("json! + base64", &chunk, frames, |pcm| {
+            let event = serde_json::json!({
+                "event_id": qwen_realtime::next_event_id(),
+                "type": "input_audio_buffer.append",
+                "audio": general_purpose::STANDARD.encode(pcm),
+            });
+            Message::Text(event.to_string())
+        });
+        let mut encoder = Base64AppendEncoder;
+        time("直接写入", &chunk, frames, |pcm| encoder.encode(pcm, false));
+    }
+}
@@ -0,0 +1,740 @@
+// 录音后处理模块（AudioRecorder 与 StreamingRecorder 共用）
+// 按当前设备生效的参数做声道选择、增益与自动增益
+
+use std::io::Cursor;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use crate::config::{AudioProcessing, ChannelSelection, TrailingSilence};
+
+/// 内存 WAV 的时长（无法解析时为 None）
+pub fn wav_duration(wav: &[u8]) -> Option<Duration> {
+    let reader = hound::WavReader::new(Cursor::new(wav)).ok()?;
+    let sample_rate = reader.spec().sample_rate;
+    (sample_rate > 0).then(|| Duration::from_millis(reader.duration() as u64 * 1000 / sample_rate as u64))
+}
+
+/// 整段录音的电平（dBFS，满刻度为 0，全零样本为负无穷）
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct AudioLevels {
+    pub peak_dbfs: f32,
+    pub rms_dbfs: f32,
+}
+
+impl AudioLevels {
+    /// 全程峰值都低于阈值，即没有收到有效声音（麦克风选错或被静音）
+    pub fn is_silent(&self, threshold_dbfs: f32) -> bool {
+        self.peak_dbfs < threshold_dbfs
+    }
+}
+
+/// 统计 16-bit 内存 WAV 的峰值与平均（RMS）电平（无法解析或没有样本时为 None）
+pub fn wav_levels(wav: &[u8]) -> Option<AudioLevels> {
+    let mut reader = hound::WavReader::new(Cursor::new(wav)).ok()?;
+    let mut peak = 0i32;
+    let mut sum_squares = 0f64;
+    let mut count = 0usize;
+    for sample in reader.samples::<i16>() {
+        let sample = sample.ok()? as i32;
+        peak = peak.max(sample.abs());
+        sum_squares += (sample * sample) as f64;
+        count += 1;
+    }
+    if count == 0 {
+        return None;
+    }
+    let dbfs = |level: f64| (20.0 * (level / i16::MAX as f64).log10()) as f32;
+    Some(AudioLevels {
+        peak_dbfs: dbfs(peak as f64),
+        rms_dbfs: dbfs((sum_squares / count as f64).sqrt()),
+    })
+}
+
+// 末尾静音裁剪按 10ms 一帧计算能量
+const TRIM_FRAME_MS: u32 = 10;
+// 裁剪后在语音末尾保留的余量，避免切掉尾音的自然衰减
+const TRIM_PADDING_MS: u32 = 100;
+
+/// 裁掉末尾低于能量阈值的静音（单声道样本），最多裁 `max_trim_ms`，返回裁掉的样本数
+pub fn trim_trailing_silence(samples: &mut Vec<f32>, sample_rate: u32, config: &TrailingSilence) -> usize {
+    if !config.enabled || sample_rate == 0 {
+        return 0;
+    }
+    let frame = (sample_rate * TRIM_FRAME_MS / 1000).max(1) as usize;
+    let max_trim = (sample_rate as u64 * config.max_trim_ms as u64 / 1000) as usize;
+    let threshold = 10f32.powf(config.threshold_dbfs / 20.0);
+
+    let mut end = samples.len();
+    while end >= frame && samples.len() - end + frame <= max_trim {
+        let tail = &samples[end - frame..end];
+        let rms = (tail.iter().map(|s| s * s).sum::<f32>() / frame as f32).sqrt();
+        if rms >= threshold {
+            break;
+        }
+        end -= frame;
+    }
+    let padding = (sample_rate * TRIM_PADDING_MS / 1000) as usize;
+    let end = (end + padding).min(samples.len());
+    let trimmed = samples.len() - end;
+    samples.truncate(end);
+    trimmed
+}
+
+/// 将多声道音频转换为单声道：平均混合或只取指定声道
+pub fn to_mono(input: &[f32], channels: u16, selection: ChannelSelection) -> Vec<f32> {
+    if channels <= 1 {
+        return input.to_vec();
+    }
+
+    let channels = channels as usize;
+    let frames = input.chunks_exact(channels);
+    match selection {
+        ChannelSelection::Left => frames.map(|frame| frame[0]).collect(),
+        ChannelSelection::Right => frames.map(|frame| frame[1]).collect(),
+        ChannelSelection::Channel(index) if (index as usize) < channels => {
+            frames.map(|frame| frame[index as usize]).collect()
+        }
+        // 设备没有指定的声道时退回混音（开始录音时已记录警告）
+        ChannelSelection::Mix | ChannelSelection::Channel(_) => frames
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect(),
+    }
+}
+
+// 输入增益（AppConfig.input_gain）的范围；录音回调每次读取，调节后立即生效
+const INPUT_GAIN_RANGE: (f32, f32) = (0.1, 8.0);
+static INPUT_GAIN: AtomicU32 = AtomicU32::new(0x3f80_0000);
+
+/// 设置输入增益，超出范围时截断，非法值按 1.0
+pub fn set_input_gain(gain: f32) {
+    let gain = if gain.is_finite() { gain.clamp(INPUT_GAIN_RANGE.0, INPUT_GAIN_RANGE.1) } else { 1.0 };
+    INPUT_GAIN.store(gain.to_bits(), Ordering::Relaxed);
+}
+
+/// 当前输入增益
+pub fn input_gain() -> f32 {
+    f32::from_bits(INPUT_GAIN.load(Ordering::Relaxed))
+}
+
+/// 应用增益并限幅到 [-1, 1]，避免放大后削波溢出
+pub fn apply_gain(samples: &mut [f32], gain: f32) {
+    if gain == 1.0 {
+        return;
+    }
+    for sample in samples.iter_mut() {
+        *sample = (*sample * gain).clamp(-1.0, 1.0);
+    }
+}
+
+/// f32 样本量化为 16 位 PCM。`dither` 开启时先叠加 ±1 LSB 的 TPDF 抖动再取整：量化误差变成与信号无关的平稳底噪（约 -96 dBFS），
+/// 低电平段（尤其归一化之后）不再出现随信号起伏的量化失真；关闭时与原先一样直接截断
+pub fn quantize(samples: &[f32], dither: bool) -> impl Iterator<Item = i16> + '_ {
+    let mut tpdf = dither.then(Tpdf::new);
+    samples.iter().map(move |&sample| {
+        let scaled = sample * i16::MAX as f32;
+        let scaled = match tpdf.as_mut() {
+            Some(tpdf) => (scaled + tpdf.next()).round(),
+            None => scaled,
+        };
+        scaled.clamp(i16::MIN as f32, i16::MAX as f32) as i16
+    })
+}
+
+/// 三角分布抖动：两个 [0, 1) 均匀随机数之差（xorshift32，不需要密码学强度）
+struct Tpdf(u32);
+
+impl Tpdf {
+    fn new() -> Self {
+        Self(0x9e37_79b9)
+    }
+
+    fn uniform(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        (self.0 >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    fn next(&mut self) -> f32 {
+        self.uniform() - self.uniform()
+    }
+}
+
+// 流式自动增益按 10ms 一块跟踪峰值，取最近 1.5s 的最大峰值作为窗口电平
+const AGC_BLOCK_MS: u32 = 10;
+const AGC_WINDOW_MS: u32 = 1500;
+// 增益需要上升时每块向目标逼近的比例（需要下降时立即生效，避免削波）
+const AGC_RELEASE: f32 = 0.02;
+// 流式增益变化超过该值（dB）时记一次日志
+const AGC_LOG_STEP_DB: f32 = 3.0;
+
+fn db_to_amplitude(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+fn amplitude_to_db(amplitude: f32) -> f32 {
+    20.0 * amplitude.max(1e-9).log10()
+}
+
+fn rms_dbfs(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return f32::NEG_INFINITY;
+    }
+    amplitude_to_db((samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt())
+}
+
+/// 整段自动增益（HTTP 模式）：把峰值拉到 `target_dbfs`，增益不超过 `max_gain_db`，只放大不衰减；
+/// 峰值低于 `noise_floor_dbfs`（静音或纯底噪）时不处理。返回应用的增益（dB）
+pub fn normalize(samples: &mut [f32], target_dbfs: f32, max_gain_db: f32, noise_floor_dbfs: f32) -> f32 {
+    let peak = samples.iter().fold(0f32, |peak, s| peak.max(s.abs()));
+    let peak_dbfs = amplitude_to_db(peak);
+    if peak == 0.0 || peak_dbfs < noise_floor_dbfs {
+        return 0.0;
+    }
+    let gain_db = (target_dbfs - peak_dbfs).min(max_gain_db).max(0.0);
+    if gain_db > 0.0 {
+        let before = rms_dbfs(samples);
+        apply_gain(samples, db_to_amplitude(gain_db));
+        tracing::info!(
+            "自动增益: +{:.1} dB (峰值 {:.1} → {:.1} dBFS, RMS {:.1} → {:.1} dBFS)",
+            gain_db,
+            peak_dbfs,
+            peak_dbfs + gain_db,
+            before,
+            before + gain_db
+        );
+    }
+    gain_db
+}
+
+/// 流式自动增益：按滑动窗口的峰值逐块调整增益，窗口内峰值低于底噪阈值时保持当前增益不再放大
+pub struct AutoGain {
+    target: f32,
+    max_gain: f32,
+    noise_floor: f32,
+    block_len: usize,
+    // 最近各块的峰值（滑动窗口）
+    peaks: std::collections::VecDeque<f32>,
+    window_blocks: usize,
+    gain: f32,
+    logged_gain_db: f32,
+}
+
+impl AutoGain {
+    pub fn new(sample_rate: u32, target_dbfs: f32, max_gain_db: f32, noise_floor_dbfs: f32) -> Self {
+        Self {
+            target: db_to_amplitude(target_dbfs),
+            max_gain: db_to_amplitude(max_gain_db.max(0.0)),
+            noise_floor: db_to_amplitude(noise_floor_dbfs),
+            block_len: (sample_rate * AGC_BLOCK_MS / 1000).max(1) as usize,
+            peaks: std::collections::VecDeque::new(),
+            window_blocks: (AGC_WINDOW_MS / AGC_BLOCK_MS) as usize,
+            gain: 1.0,
+            logged_gain_db: 0.0,
+        }
+    }
+
+    /// 当前增益（dB）
+    pub fn gain_db(&self) -> f32 {
+        amplitude_to_db(self.gain)
+    }
+
+    /// 就地处理一段单声道样本
+    pub fn process(&mut self, samples: &mut [f32]) {
+        for block in samples.chunks_mut(self.block_len) {
+            if self.peaks.len() == self.window_blocks {
+                self.peaks.pop_front();
+            }
+            self.peaks.push_back(block.iter().fold(0f32, |peak, s| peak.max(s.abs())));
+            let window_peak = self.peaks.iter().copied().fold(0f32, f32::max);
+
+            if window_peak >= self.noise_floor {
+                let desired = (self.target / window_peak).clamp(1.0, self.max_gain);
+                if desired < self.gain {
+                    self.gain = desired;
+                } else {
+                    self.gain += (desired - self.gain) * AGC_RELEASE;
+                }
+            }
+            apply_gain(block, self.gain);
+        }
+
+        let gain_db = self.gain_db();
+        if (gain_db - self.logged_gain_db).abs() >= AGC_LOG_STEP_DB {
+            tracing::info!("流式自动增益: {:+.1} dB", gain_db);
+            self.logged_gain_db = gain_db;
+        }
+    }
+}
+
+/// 录音时长上限（audio.max_duration_secs）：在录音回调里按设备样本数累计，
+/// 达到上限后不再接收样本，并通知上层按松开按键的流程结束录音（只通知一次）
+pub struct DurationLimit {
+    // 最多接收的交错样本数，None 表示不限制
+    max_samples: Option<usize>,
+    recorded: usize,
+    notify: Option<tokio::sync::mpsc::UnboundedSender<()>>,
+}
+
+impl DurationLimit {
+    pub fn new(max_secs: u32, sample_rate: u32, channels: u16, notify: tokio::sync::mpsc::UnboundedSender<()>) -> Self {
+        Self {
+            max_samples: (max_secs > 0).then(|| max_secs as usize * sample_rate as usize * channels.max(1) as usize),
+            recorded: 0,
+            notify: Some(notify),
+        }
+    }
+
+    /// 本次回调可接收的样本数（回调按整帧交付，上限也是整帧，截断后不会拆开一帧的各声道）
+    pub fn admit(&mut self, incoming: usize) -> usize {
+        let Some(max_samples) = self.max_samples else {
+            return incoming;
+        };
+        let admitted = incoming.min(max_samples - self.recorded);
+        self.recorded += admitted;
+        if self.recorded == max_samples {
+            if let Some(notify) = self.notify.take() {
+                tracing::warn!("录音达到最长时长，停止采集");
+                let _ = notify.send(());
+            }
+        }
+        admitted
+    }
+
+    /// 换设备续录时计入之前已录的时长（按新设备的采样率与声道折算），已到上限时立即通知
+    pub fn skip(&mut self, elapsed: Duration, sample_rate: u32, channels: u16) {
+        let frames = (elapsed.as_millis() as usize * sample_rate as usize / 1000) * channels.max(1) as usize;
+        self.admit(frames);
+    }
+}
+
+// 电平表的最短上报间隔，以及静音时上报的电平下限（dB）
+const LEVEL_MIN_INTERVAL_MS: u32 = 20;
+const LEVEL_FLOOR_DB: f32 = -100.0;
+
+/// 录音中的实时电平（audio_level 事件的载荷）
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct AudioLevel {
+    pub rms_db: f32,
+    pub peak_db: f32,
+}
+
+// 样本绝对值达到该值视为削波（爆音）
+const CLIP_LEVEL: f32 = 0.999;
+
+/// 一整段原始样本的电平（下限同电平表）以及是否出现满幅样本（麦克风测试用）
+pub fn overall_level(samples: &[f32]) -> (AudioLevel, bool) {
+    let peak = samples.iter().fold(0f32, |peak, s| peak.max(s.abs()));
+    let level = AudioLevel {
+        rms_db: rms_dbfs(samples).max(LEVEL_FLOOR_DB),
+        peak_db: amplitude_to_db(peak).max(LEVEL_FLOOR_DB),
+    };
+    (level, peak >= CLIP_LEVEL)
+}
+
+/// 录音电平表（audio.level_interval_ms）：在录音回调里累计样本，每隔固定时长把这段的 RMS 与峰值
+/// 送到异步侧，由上层节流后 emit（音频回调线程里不做 emit）
+pub struct LevelMeter {
+    interval_samples: usize,
+    count: usize,
+    sum_squares: f64,
+    peak: f32,
+    levels: tokio::sync::mpsc::UnboundedSender<AudioLevel>,
+}
+
+impl LevelMeter {
+    /// `interval_ms` 为 0 时不需要电平表，返回 None
+    pub fn new(interval_ms: u32, sample_rate: u32, channels: u16, levels: tokio::sync::mpsc::UnboundedSender<AudioLevel>) -> Option<Self> {
+        if interval_ms == 0 {
+            return None;
+        }
+        let interval_ms = interval_ms.max(LEVEL_MIN_INTERVAL_MS) as usize;
+        Some(Self {
+            interval_samples: (sample_rate as usize * channels.max(1) as usize * interval_ms / 1000).max(1),
+            count: 0,
+            sum_squares: 0.0,
+            peak: 0.0,
+            levels,
+        })
+    }
+
+    /// 累计一段样本（交错多声道按所有声道一起统计），每满一个间隔上报一次
+    pub fn push(&mut self, samples: &[f32]) {
+        for &sample in samples {
+            self.sum_squares += (sample * sample) as f64;
+            self.peak = self.peak.max(sample.abs());
+            self.count += 1;
+            if self.count == self.interval_samples {
+                let rms = (self.sum_squares / self.count as f64).sqrt() as f32;
+                let _ = self.levels.send(AudioLevel {
+                    rms_db: amplitude_to_db(rms).max(LEVEL_FLOOR_DB),
+                    peak_db: amplitude_to_db(self.peak).max(LEVEL_FLOOR_DB),
+                });
+                self.count = 0;
+                self.sum_squares = 0.0;
+                self.peak = 0.0;
+            }
+        }
+    }
+}
+
+// 声道检测统计开始录音后多长的音频，以及判定某个声道"几乎无信号"的 RMS 电平
+const CHANNEL_PROBE_MS: u32 = 500;
+const CHANNEL_SILENT_DBFS: f32 = -60.0;
+
+/// 多声道设备的声道检测：统计开始录音后前 0.5 秒各声道的能量，有声道几乎无信号、
+/// 当前声道选择不合适时记一条提示（如麦克风只接在左声道却在混音，音量减半）。只检测一次
+pub struct ChannelProbe {
+    selection: ChannelSelection,
+    remaining: usize,
+    position: usize,
+    sum_squares: Vec<f64>,
+}
+
+impl ChannelProbe {
+    /// 单声道设备不需要检测，返回 None
+    pub fn new(sample_rate: u32, channels: u16, selection: ChannelSelection) -> Option<Self> {
+        if channels < 2 {
+            return None;
+        }
+        Some(Self {
+            selection,
+            remaining: sample_rate as usize * channels as usize * CHANNEL_PROBE_MS as usize / 1000,
+            position: 0,
+            sum_squares: vec![0.0; channels as usize],
+        })
+    }
+
+    /// 累计一段交错样本（可以不按整帧切分），满 0.5 秒时给出提示
+    pub fn push(&mut self, samples: &[f32]) {
+        if self.remaining == 0 {
+            return;
+        }
+        let channels = self.sum_squares.len();
+        for &sample in &samples[..samples.len().min(self.remaining)] {
+            self.sum_squares[self.position % channels] += (sample * sample) as f64;
+            self.position += 1;
+        }
+        self.remaining -= samples.len().min(self.remaining);
+        if self.remaining > 0 {
+            return;
+        }
+
+        let frames = (self.position / channels).max(1) as f64;
+        let rms_db: Vec<f32> = self
+            .sum_squares
+            .iter()
+            .map(|&sum| amplitude_to_db((sum / frames).sqrt() as f32))
+            .collect();
+        match channel_hint(&rms_db, self.selection) {
+            Some(hint) => tracing::warn!("{}（各声道电平: {:.0?} dBFS）", hint, rms_db),
+            None => tracing::debug!("各声道电平: {:.0?} dBFS", rms_db),
+        }
+    }
+}
+
+/// 按各声道的 RMS 电平给出声道选择建议：所选声道（或混音中的某些声道）几乎无信号、其他声道有信号时提示；
+/// 全部声道都没有信号（还没开口）时不提示
+fn channel_hint(rms_db: &[f32], selection: ChannelSelection) -> Option<String> {
+    let channels = rms_db.len();
+    let silent: Vec<usize> = (0..channels).filter(|&i| rms_db[i] < CHANNEL_SILENT_DBFS).collect();
+    if silent.is_empty() || silent.len() == channels {
+        return None;
+    }
+    let loudest = (0..channels).max_by(|&a, &b| rms_db[a].total_cmp(&rms_db[b]))?;
+    let suggestion = match (channels, loudest) {
+        (2, 0) => "left".to_string(),
+        (2, _) => "right".to_string(),
+        _ => format!("{{\"channel\": {}}}", loudest),
+    };
+    let name = |index: usize| match (channels, index) {
+        (2, 0) => "左声道".to_string(),
+        (2, _) => "右声道".to_string(),
+        _ => format!("声道{}", index),
+    };
+
+    let selected = match selection {
+        ChannelSelection::Left => Some(0),
+        ChannelSelection::Right => Some(1),
+        ChannelSelection::Channel(index) if (index as usize) < channels => Some(index as usize),
+        ChannelSelection::Mix | ChannelSelection::Channel(_) => None,
+    };
+    match selected {
+        Some(index) if silent.contains(&index) => {
+            Some(format!("所选的{}几乎无信号，建议选择 {}", name(index), suggestion))
+        }
+        Some(_) => None,
+        None => {
+            let names: Vec<String> = silent.iter().map(|&index| name(index)).collect();
+            Some(format!("{}几乎无信号，混音会拉低音量，建议选择 {}", names.join("、"), suggestion))
+        }
+    }
+}
+
+/// 单声道化 + 增益
+pub fn process(input: &[f32], channels: u16, processing: &AudioProcessing) -> Vec<f32> {
+    let mut mono = to_mono(input, channels, processing.channel_selection);
+    apply_gain(&mut mono, processing.gain);
+    mono
+}
+
+/// 录音开始时记录当前设备生效的处理参数；指定的声道超出设备声道数时提示将改为混音
+pub fn log_processing(device: &str, channels: u16, processing: &AudioProcessing) {
+    if let ChannelSelection::Channel(index) = processing.channel_selection {
+        if channels > 1 && index >= channels {
+            tracing::warn!("设备 [{}] 只有 {} 个声道，没有声道 {}，改为各声道混音", device, channels, index);
+        }
+    }
+    tracing::info!(
+        "音频处理参数 [{}]: 增益={}, 声道={:?}, 降噪={}, 自动增益={}",
+        device,
+        processing.gain,
+        processing.channel_selection,
+        processing.denoise,
+        processing.agc
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dither_keeps_sub_lsb_detail() {
+        // 0.3 LSB 的直流：直接截断全部变成 0，加抖动后平均值仍保留信号
+        let quiet = vec![0.3 / i16::MAX as f32; 100_000];
+        assert!(quantize(&quiet, false).all(|sample| sample == 0));
+        let mean = quantize(&quiet, true).map(f64::from).sum::<f64>() / quiet.len() as f64;
+        assert!((mean - 0.3).abs() < 0.02, "mean = {}", mean);
+
+        // 抖动误差不超过 ±1.5 LSB，满幅不溢出
+        let ramp: Vec<f32> = (0..10_000).map(|i| (i as f32 / 5000.0 - 1.0) * 0.01).collect();
+        for (sample, quantized) in ramp.iter().zip(quantize(&ramp, true)) {
+            assert!((sample * i16::MAX as f32 - quantized as f32).abs() <= 1.5);
+        }
+        let clipped: Vec<i16> = quantize(&[1.5, -1.5, 1.0], true).collect();
+        assert_eq!(clipped[..2], [i16::MAX, i16::MIN]);
+        assert!(clipped[2] >= i16::MAX - 1);
+    }
+
+    #[test]
+    fn selects_channel_and_applies_gain() {
+        let stereo = [0.25, 0.0, 0.5, 0.0];
+        assert_eq!(to_mono(&stereo, 2, ChannelSelection::Mix), vec![0.125, 0.25]);
+        assert_eq!(to_mono(&stereo, 2, ChannelSelection::Left), vec![0.25, 0.5]);
+        assert_eq!(to_mono(&stereo, 2, ChannelSelection::Right), vec![0.0, 0.0]);
+
+        // 4 声道声卡只取第 3 路；超出声道数时退回混音
+        let quad = [0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8];
+        assert_eq!(to_mono(&quad, 4, ChannelSelection::Channel(2)), vec![0.3, 0.7]);
+        assert_eq!(to_mono(&quad, 4, ChannelSelection::Channel(4)), to_mono(&quad, 4, ChannelSelection::Mix));
+        assert_eq!(to_mono(&stereo, 2, ChannelSelection::Channel(0)), to_mono(&stereo, 2, ChannelSelection::Left));
+
+        let processing = AudioProcessing {
+            gain: 3.0,
+            denoise: false,
+            agc: false,
+            channel_selection: ChannelSelection::Left,
+        };
+        // 放大后超出范围的样本被限幅
+        assert_eq!(process(&stereo, 2, &processing), vec![0.75, 1.0]);
+    }
+
+    #[test]
+    fn hints_channel_selection_from_channel_levels() {
+        // 麦克风只接在左声道：混音时建议 left，已选 left 时不提示，选了 right 时提示改回
+        let left_only = [-20.0, -90.0];
+        assert_eq!(channel_hint(&left_only, ChannelSelection::Mix).unwrap(), "右声道几乎无信号，混音会拉低音量，建议选择 left");
+        assert_eq!(channel_hint(&left_only, ChannelSelection::Left), None);
+        assert_eq!(channel_hint(&left_only, ChannelSelection::Right).unwrap(), "所选的右声道几乎无信号，建议选择 left");
+        // 都有信号或都没有信号（还没开口）时不提示
+        assert_eq!(channel_hint(&[-20.0, -30.0], ChannelSelection::Mix), None);
+        assert_eq!(channel_hint(&[-90.0, -95.0], ChannelSelection::Mix), None);
+        // 多通道声卡按序号建议
+        assert_eq!(
+            channel_hint(&[-90.0, -90.0, -25.0, -90.0], ChannelSelection::Channel(0)).unwrap(),
+            "所选的声道0几乎无信号，建议选择 {\"channel\": 2}"
+        );
+
+        // 统计满 0.5 秒后停止，回调切块不按整帧对齐也不会错位
+        let mut probe = ChannelProbe::new(1000, 2, ChannelSelection::Mix).unwrap();
+        let samples: Vec<f32> = (0..1200).map(|i| if i % 2 == 0 { 0.5 } else { 0.0 }).collect();
+        for chunk in samples.chunks(333) {
+            probe.push(chunk);
+        }
+        assert_eq!((probe.remaining, probe.position), (0, 1000));
+        assert_eq!(probe.sum_squares, vec![125.0, 0.0]);
+        assert!(ChannelProbe::new(48000, 1, ChannelSelection::Mix).is_none());
+    }
+
+    #[test]
+    fn stops_admitting_samples_at_duration_limit() {
+        let (notify, mut reached) = tokio::sync::mpsc::unbounded_channel();
+        // 2 秒、1kHz 双声道 = 4000 个交错样本
+        let mut limit = DurationLimit::new(2, 1000, 2, notify);
+        assert_eq!(limit.admit(1500), 1500);
+        assert_eq!(limit.admit(1500), 1500);
+        assert!(reached.try_recv().is_err());
+        assert_eq!(limit.admit(1500), 1000);
+        assert_eq!(limit.admit(1500), 0);
+        // 只通知一次，之后通道关闭
+        assert!(reached.try_recv().is_ok());
+        assert!(reached.try_recv().is_err());
+
+        let (notify, mut reached) = tokio::sync::mpsc::unbounded_channel();
+        let mut unlimited = DurationLimit::new(0, 48000, 1, notify);
+        assert_eq!(unlimited.admit(usize::MAX / 2), usize::MAX / 2);
+        assert!(reached.try_recv().is_err());
+    }
+
+    #[test]
+    fn clamps_input_gain_to_range() {
+        set_input_gain(20.0);
+        assert_eq!(input_gain(), 8.0);
+        set_input_gain(0.0);
+        assert_eq!(input_gain(), 0.1);
+        set_input_gain(f32::NAN);
+        assert_eq!(input_gain(), 1.0);
+    }
+
+    #[test]
+    fn reports_levels_once_per_interval() {
+        let (levels, mut received) = tokio::sync::mpsc::unbounded_channel();
+        // 1kHz 单声道、50ms 一次 = 每 50 个样本
+        let mut meter = LevelMeter::new(50, 1000, 1, levels).unwrap();
+        meter.push(&[0.5; 30]);
+        assert!(received.try_recv().is_err());
+        meter.push(&[-0.5; 20]);
+        let level = received.try_recv().unwrap();
+        assert!((level.rms_db - -6.02).abs() < 0.01 && (level.peak_db - -6.02).abs() < 0.01, "{:?}", level);
+
+        // 静音按下限上报，跨块累计后每个间隔各报一次
+        meter.push(&[0.0; 120]);
+        assert_eq!(received.try_recv().unwrap(), AudioLevel { rms_db: -100.0, peak_db: -100.0 });
+        assert!(received.try_recv().is_ok());
+        assert!(received.try_recv().is_err());
+
+        let (levels, _) = tokio::sync::mpsc::unbounded_channel();
+        assert!(LevelMeter::new(0, 48000, 2, levels).is_none());
+    }
+
+    #[test]
+    fn measures_wav_duration() {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut cursor = Cursor::new(Vec::new());
+        {
+            let mut writer = hound::WavWriter::new(&mut cursor, spec).unwrap();
+            for _ in 0..4000 {
+                writer.write_sample(0i16).unwrap();
+            }
+            writer.finalize().unwrap();
+        }
+        assert_eq!(wav_duration(cursor.get_ref()), Some(Duration::from_millis(250)));
+        assert_eq!(wav_duration(b"not a wav"), None);
+    }
+
+    #[test]
+    fn trims_trailing_silence_within_limit() {
+        let config = TrailingSilence::default();
+        // 1 秒语音 + 0.5 秒静音（16kHz）
+        let mut samples = vec![0.3f32; 16000];
+        samples.extend(vec![0.0005f32; 8000]);
+        let trimmed = trim_trailing_silence(&mut samples, 16000, &config);
+        // 静音裁掉，保留 100ms 余量
+        assert_eq!(trimmed, 8000 - 1600);
+        assert_eq!(samples.len(), 16000 + 1600);
+
+        // 静音超过最大裁剪长度时最多裁 max_trim_ms（再加回余量）
+        let mut samples = vec![0.3f32; 16000];
+        samples.extend(vec![0.0f32; 48000]);
+        let trimmed = trim_trailing_silence(&mut samples, 16000, &config);
+        assert_eq!(trimmed, 16000 - 1600);
+
+        // 结尾的轻声高于阈值，不裁
+        let mut samples = vec![0.02f32; 16000];
+        assert_eq!(trim_trailing_silence(&mut samples, 16000, &config), 0);
+
+        let disabled = TrailingSilence { enabled: false, ..config };
+        let mut samples = vec![0.0f32; 16000];
+        assert_eq!(trim_trailing_silence(&mut samples, 16000, &disabled), 0);
+    }
+
+    #[test]
+    fn measures_wav_levels() {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let wav = |samples: &[i16]| {
+            let mut cursor = Cursor::new(Vec::new());
+            {
+                let mut writer = hound::WavWriter::new(&mut cursor, spec).unwrap();
+                for &sample in samples {
+                    writer.write_sample(sample).unwrap();
+                }
+                writer.finalize().unwrap();
+            }
+            cursor.into_inner()
+        };
+
+        let levels = wav_levels(&wav(&[i16::MAX, -i16::MAX])).unwrap();
+        assert!(levels.peak_dbfs.abs() < 0.01 && levels.rms_dbfs.abs() < 0.01);
+        assert!(!levels.is_silent(-50.0));
+
+        // 底噪只有几个 LSB：约 -80 dBFS
+        let levels = wav_levels(&wav(&[3, -2, 1, 0])).unwrap();
+        assert!(levels.peak_dbfs < -75.0 && levels.rms_dbfs < levels.peak_dbfs);
+        assert!(levels.is_silent(-50.0));
+
+        // 全零样本为负无穷，仍判为静音
+        assert!(wav_levels(&wav(&[0; 16])).unwrap().is_silent(-50.0));
+        assert_eq!(wav_levels(&wav(&[])), None);
+    }
+
+    #[test]
+    fn normalizes_quiet_recordings_within_gain_limit() {
+        let sine = |peak_dbfs: f32| -> Vec<f32> {
+            let amplitude = db_to_amplitude(peak_dbfs);
+            (0..16000).map(|i| (i as f32 * 0.05).sin() * amplitude).collect()
+        };
+        let peak = |samples: &[f32]| amplitude_to_db(samples.iter().fold(0f32, |p, s| p.max(s.abs())));
+
+        // 峰值 -15dBFS 拉到 -3dBFS
+        let mut quiet = sine(-15.0);
+        let gain = normalize(&mut quiet, -3.0, 20.0, -50.0);
+        assert!((gain - 12.0).abs() < 0.1 && (peak(&quiet) + 3.0).abs() < 0.1);
+
+        // 峰值 -35dBFS 受最大增益限制，只放大 20dB
+        let mut very_quiet = sine(-35.0);
+        assert_eq!(normalize(&mut very_quiet, -3.0, 20.0, -50.0), 20.0);
+        assert!((peak(&very_quiet) + 15.0).abs() < 0.1);
+
+        // 纯底噪与已经够响的录音都不处理
+        let mut noise = sine(-60.0);
+        assert_eq!(normalize(&mut noise, -3.0, 20.0, -50.0), 0.0);
+        let mut loud = sine(-1.0);
+        assert_eq!(normalize(&mut loud, -3.0, 20.0, -50.0), 0.0);
+
+        // 流式：安静段逐步升到目标附近，突然变响时立即压低增益，不削波
+        let mut agc = AutoGain::new(16000, -3.0, 20.0, -50.0);
+        let mut stream = sine(-20.0);
+        for _ in 0..5 {
+            stream.extend(sine(-20.0));
+        }
+        for chunk in stream.chunks_mut(320) {
+            agc.process(chunk);
+        }
+        assert!((agc.gain_db() - 17.0).abs() < 0.5, "{}", agc.gain_db());
+        let mut burst = sine(-6.0);
+        agc.process(&mut burst);
+        assert!(peak(&burst) <= -3.0 + 0.01);
+        assert!(agc.gain_db() < 3.1);
+    }
+}
@@ -1,201 +1,603 @@
 // 音频录制模块
 use hound::{WavSpec, WavWriter};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::io::Cursor;
+use std::time::Duration;
 use anyhow::Result;
-use cpal::Stream;
+use crossbeam_channel::{Receiver, Sender, bounded, unbounded};
+use tokio::sync::mpsc;
+
+use crate::audio_processing::{self, AudioLevel};
+use crate::audio_source::{AudioSource, Capture, SampleSink, StreamInfo};
+use crate::denoise;
+use crate::input_source::{self, ResidentInput};
+use crate::privacy;
+use crate::resampler;
+use crate::vad;
+use crate::config::{AudioConfig, AudioProcessing};
 
 // API 要求的目标采样率
 const TARGET_SAMPLE_RATE: u32 = 16000;
 
+/// 录音器本体只持有录音线程的命令通道，cpal::Stream 不跨线程
 pub struct AudioRecorder {
     device_sample_rate: u32,  // 设备实际采样率
     channels: u16,
     audio_data: Arc<Mutex<Vec<f32>>>,
-    is_recording: Arc<Mutex<bool>>,
-    stream: Option<Stream>,  // 保存 stream 引用
+    is_recording: Arc<AtomicBool>,
+    // 录音线程命令通道；录音器释放时通道关闭，线程随之退出
+    commands: Sender<StreamCommand>,
+    // 音频处理参数（全局默认 + 按设备覆盖），开始录音时按当前设备合并
+    audio: AudioConfig,
+    processing: AudioProcessing,
+    // 录音流错误通道（设备被拔出等），由上层取走后监听
+    device_errors: Option<mpsc::UnboundedReceiver<String>>,
+    // 录音达到最长时长的通知通道，由上层取走后监听
+    duration_limit: Option<mpsc::UnboundedReceiver<()>>,
+    // 录音电平通道（audio.level_interval_ms 为 0 时为 None），由上层取走后转发给前端
+    levels: Option<mpsc::UnboundedReceiver<AudioLevel>>,
+    // 本次录音选择的设备找不到、已回退默认设备时为该设备名
+    missing_device: Option<String>,
+    // 上次录音实际采集的时长（按处理前的样本数换算，不受静音裁剪影响）
+    recorded_duration: std::time::Duration,
+    // 本次录音开头拼接的预滚动时长
+    pre_roll: Duration,
 }
 
-impl AudioRecorder {
-    pub fn new() -> Result<Self> {
-        Ok(Self {
-            device_sample_rate: 48000,  // 默认值，会在 start_recording 时更新
-            channels: 1,
-            audio_data: Arc::new(Mutex::new(Vec::new())),
-            is_recording: Arc::new(Mutex::new(false)),
-            stream: None,
-        })
+/// 当前系统默认输入设备名称（设备出错后用于提示将切回的设备）
+pub fn default_input_device_name() -> Option<String> {
+    use cpal::traits::{DeviceTrait, HostTrait};
+    cpal::default_host()
+        .default_input_device()
+        .and_then(|device| device.name().ok())
+}
+
+// list_audio_devices 列出的常见采样率（设备支持区间内的才返回）
+const COMMON_SAMPLE_RATES: [u32; 9] = [8000, 11025, 16000, 22050, 24000, 32000, 44100, 48000, 96000];
+
+/// 输入设备信息（list_audio_devices 命令返回）
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct InputDeviceInfo {
+    pub name: String,
+    pub is_default: bool,
+    /// 设备支持的常见采样率
+    pub sample_rates: Vec<u32>,
+    /// 最大声道数
+    pub channels: u16,
+}
+
+/// 麦克风测试结果（test_microphone 命令返回）：电平按设备原始样本统计（已乘输入增益，未经降噪 / 自动增益）
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct MicrophoneTest {
+    pub peak_db: f32,
+    pub rms_db: f32,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// 出现满幅样本，可能爆音
+    pub clipped: bool,
+    /// 峰值低于 audio.silence_threshold_dbfs，没有收到声音（权限未开或选错设备）
+    pub silent: bool,
+}
+
+/// 录音结果：16kHz 单声道 WAV 及其时长（提交转录前据此检查各服务商的时长上限）
+#[derive(Debug, Clone)]
+pub struct AudioClip {
+    pub wav: Vec<u8>,
+    pub duration_ms: u64,
+    pub sample_rate: u32,
+}
+
+impl AudioClip {
+    /// 按单声道样本数换算时长
+    pub fn new(wav: Vec<u8>, samples: usize, sample_rate: u32) -> Self {
+        let duration_ms = samples as u64 * 1000 / sample_rate.max(1) as u64;
+        Self { wav, duration_ms, sample_rate }
     }
 
-    /// 将音频从设备采样率降采样到目标采样率 (16kHz)
-    fn resample(&self, input: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
-        if from_rate == to_rate {
-            return input.to_vec();
-        }
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.duration_ms)
+    }
+}
+
+/// 列出所有输入设备
+pub fn list_input_devices() -> Result<Vec<InputDeviceInfo>> {
+    use cpal::traits::{DeviceTrait, HostTrait};
+    let host = cpal::default_host();
+    let default_name = default_input_device_name();
+    let devices = host
+        .input_devices()
+        .map_err(|e| anyhow::anyhow!("枚举音频输入设备失败: {}", e))?;
+    Ok(devices
+        .filter_map(|device| {
+            let name = device.name().ok()?;
+            let configs: Vec<_> = device.supported_input_configs().map(|configs| configs.collect()).unwrap_or_default();
+            let sample_rates = COMMON_SAMPLE_RATES
+                .into_iter()
+                .filter(|&rate| {
+                    configs
+                        .iter()
+                        .any(|config| (config.min_sample_rate().0..=config.max_sample_rate().0).contains(&rate))
+                })
+                .collect();
+            let channels = configs.iter().map(|config| config.channels()).max().unwrap_or_default();
+            Some(InputDeviceInfo {
+                is_default: default_name.as_deref() == Some(name.as_str()),
+                name,
+                sample_rates,
+                channels,
+            })
+        })
+        .collect())
+}
 
-        let ratio = from_rate as f64 / to_rate as f64;
-        let output_len = (input.len() as f64 / ratio) as usize;
-        let mut output = Vec::with_capacity(output_len);
+/// 在设备名列表中查找用户选择的设备：先精确匹配，再忽略首尾空白与大小写匹配
+fn match_device_name<'a>(names: impl IntoIterator<Item = &'a str> + Clone, wanted: &str) -> Option<usize> {
+    let loose = |name: &str| name.trim().to_lowercase();
+    names.clone().into_iter().position(|name| name == wanted).or_else(|| {
+        let wanted = loose(wanted);
+        names.into_iter().position(|name| loose(name) == wanted)
+    })
+}
 
-        for i in 0..output_len {
-            let src_idx = i as f64 * ratio;
-            let idx_floor = src_idx.floor() as usize;
-            let idx_ceil = (idx_floor + 1).min(input.len() - 1);
-            let frac = src_idx - idx_floor as f64;
+/// 按名称选择输入设备，未指定时用系统默认设备；指定的设备找不到时回退默认设备，并返回找不到的设备名
+pub fn select_input_device(name: Option<&str>) -> Result<(cpal::Device, Option<String>)> {
+    use cpal::traits::{DeviceTrait, HostTrait};
+    let host = cpal::default_host();
+    let devices = || {
+        host.input_devices()
+            .map(|devices| devices.filter_map(|device| Some((device.name().ok()?, device))).collect())
+            .unwrap_or_default()
+    };
+    select_device(devices, || host.default_input_device(), name)
+}
+
+/// 录音时实际会打开的输入设备名（与 `select_input_device` 的选择和回退一致），设置页按它读写设备的音频处理参数
+pub fn selected_input_device_name(name: Option<&str>) -> Option<String> {
+    use cpal::traits::DeviceTrait;
+    let (device, _) = select_input_device(name).ok()?;
+    device.name().ok()
+}
 
-            // 线性插值
-            let sample = input[idx_floor] as f64 * (1.0 - frac) + input[idx_ceil] as f64 * frac;
-            output.push(sample as f32);
+/// `select_input_device` 的选择逻辑：只在指定了设备时才枚举设备列表
+fn select_device<D>(
+    devices: impl FnOnce() -> Vec<(String, D)>,
+    default: impl FnOnce() -> Option<D>,
+    name: Option<&str>,
+) -> Result<(D, Option<String>)> {
+    let mut missing = None;
+    if let Some(wanted) = name.filter(|name| !name.trim().is_empty()) {
+        let mut devices = devices();
+        let names: Vec<&str> = devices.iter().map(|(name, _)| name.as_str()).collect();
+        if let Some(index) = match_device_name(names.iter().copied(), wanted) {
+            return Ok((devices.swap_remove(index).1, None));
         }
+        tracing::warn!("找不到输入设备 \"{}\"，改用系统默认设备", wanted);
+        missing = Some(wanted.to_string());
+    }
+    let device = default().ok_or_else(|| anyhow::anyhow!("没有找到默认音频输入设备"))?;
+    Ok((device, missing))
+}
 
-        output
+// 录音设备中途失效时切回默认设备重建采集流的尝试次数与间隔（系统切换默认设备需要一点时间）
+const REBUILD_ATTEMPTS: u32 = 3;
+const REBUILD_RETRY_DELAY: Duration = Duration::from_millis(200);
+// 录音中把采集回调写入环形队列的样本汇总到录音缓冲区的间隔
+const COLLECT_INTERVAL: Duration = Duration::from_millis(10);
+
+/// 录音线程命令：采集流（如 cpal::Stream）不是 Send，只在专用线程中创建、持有和释放
+enum StreamCommand {
+    Start {
+        // 用户选择的输入设备，None 为系统默认设备
+        device: Option<String>,
+        sink: SampleSink,
+        // 采集流错误（sink.error_tx 的接收端），由录音线程处理
+        stream_errors: Receiver<String>,
+        // 重建失败、已无设备可录时通知上层取消本次转录
+        device_lost: mpsc::UnboundedSender<String>,
+        reply: Sender<Result<StreamInfo>>,
+    },
+    Stop {
+        // 回复本次录音按设备分段的全部样本
+        reply: Sender<Vec<Segment>>,
+    },
+    // 音频配置更新：空闲时按 keep_stream_open 建立或释放常驻采集流
+    Standby {
+        device: Option<String>,
+        audio: Arc<AudioConfig>,
+    },
+}
+
+/// 同一设备上连续采集的一段交错样本；录音中换过设备时一次录音有多段
+struct Segment {
+    samples: Vec<f32>,
+    info: StreamInfo,
+}
+
+impl Segment {
+    fn duration(&self) -> Duration {
+        let frames = self.samples.len() as u64 / self.info.channels.max(1) as u64;
+        Duration::from_millis(frames * 1000 / self.info.sample_rate.max(1) as u64)
     }
+}
 
-    /// 将多声道音频转换为单声道
-    fn to_mono(&self, input: &[f32], channels: u16) -> Vec<f32> {
-        if channels == 1 {
-            return input.to_vec();
+/// 录音线程中正在进行的录音
+struct ActiveRecording {
+    // 设备失效且重建失败后为 None
+    capture: Option<Capture>,
+    info: StreamInfo,
+    sink: SampleSink,
+    stream_errors: Receiver<String>,
+    device_lost: mpsc::UnboundedSender<String>,
+    // 换设备之前已采集的各段
+    segments: Vec<Segment>,
+}
+
+impl ActiveRecording {
+    /// 采集流出错（如蓝牙耳机断开）：保留已采集的样本，切回默认设备重建采集流继续录；
+    /// 重建失败则停止采集并通知上层
+    fn recover(&mut self, source: &mut dyn AudioSource, message: String) {
+        if self.capture.is_none() {
+            return;
         }
+        tracing::warn!("录音流出错: {}，尝试切回默认设备继续录音", message);
+        drop(self.capture.take());
+        self.sink.collect();
+        let samples = std::mem::take(&mut *self.sink.audio_data.lock().unwrap());
+        self.segments.push(Segment { samples, info: self.info.clone() });
+
+        let mut sink = self.sink.clone();
+        sink.elapsed = self.segments.iter().map(Segment::duration).sum();
+        for attempt in 1..=REBUILD_ATTEMPTS {
+            match source.start(None, sink.clone()) {
+                Ok((capture, info)) => {
+                    tracing::info!("已切换到输入设备 \"{}\" 继续录音 ({}Hz, {} 声道)",
+                        info.device_name, info.sample_rate, info.channels);
+                    // 旧采集流释放前排队的错误不再处理
+                    while self.stream_errors.try_recv().is_ok() {}
+                    self.capture = Some(capture);
+                    self.info = info;
+                    return;
+                }
+                Err(e) => {
+                    tracing::warn!("第 {} 次重建录音流失败: {}", attempt, e);
+                    if attempt < REBUILD_ATTEMPTS {
+                        std::thread::sleep(REBUILD_RETRY_DELAY);
+                    }
+                }
+            }
+        }
+        tracing::error!("没有可用的输入设备，停止本次录音");
+        let _ = self.device_lost.send(message);
+    }
 
-        let channels = channels as usize;
-        let output_len = input.len() / channels;
-        let mut output = Vec::with_capacity(output_len);
+    /// 结束录音：释放采集流，返回全部分段
+    fn finish(mut self) -> Vec<Segment> {
+        if self.capture.take().is_some() {
+            self.sink.collect();
+            let samples = std::mem::take(&mut *self.sink.audio_data.lock().unwrap());
+            self.segments.push(Segment { samples, info: self.info });
+        }
+        self.segments
+    }
+}
 
-        for i in 0..output_len {
-            let mut sum = 0.0f32;
-            for ch in 0..channels {
-                sum += input[i * channels + ch];
+/// 录音线程：按命令在音源上建立 / 释放采集流，并处理录音中的采集流错误；命令通道关闭时退出
+fn run_stream_thread(commands: Receiver<StreamCommand>, mut source: Box<dyn AudioSource>) {
+    let mut active: Option<ActiveRecording> = None;
+    let ticker = crossbeam_channel::tick(COLLECT_INTERVAL);
+    loop {
+        // 先处理已到达的采集流错误再处理命令，保证停止录音前完成设备切换
+        if let Some(recording) = active.as_mut() {
+            if let Ok(message) = recording.stream_errors.try_recv() {
+                recording.recover(&mut *source, message);
+                continue;
+            }
+        }
+        let never = crossbeam_channel::never();
+        let stream_errors = active.as_ref().map_or(&never, |recording| &recording.stream_errors);
+        let idle = crossbeam_channel::never();
+        let collect = if active.is_some() { &ticker } else { &idle };
+        crossbeam_channel::select! {
+            recv(collect) -> _ => {
+                if let Some(recording) = active.as_ref() {
+                    recording.sink.collect();
+                }
+            }
+            recv(stream_errors) -> message => {
+                if let (Ok(message), Some(recording)) = (message, active.as_mut()) {
+                    recording.recover(&mut *source, message);
+                }
             }
-            output.push(sum / channels as f32);
+            recv(commands) -> command => match command {
+                Ok(StreamCommand::Start { device, sink, stream_errors, device_lost, reply }) => {
+                    drop(active.take());
+                    let result = source.start(device.as_deref(), sink.clone()).map(|(capture, info)| {
+                        active = Some(ActiveRecording {
+                            capture: Some(capture),
+                            info: info.clone(),
+                            sink,
+                            stream_errors,
+                            device_lost,
+                            segments: Vec::new(),
+                        });
+                        info
+                    });
+                    let _ = reply.send(result);
+                }
+                Ok(StreamCommand::Stop { reply }) => {
+                    // 释放采集流，停止音频流（常驻模式下只取下本次录音的写入器）
+                    let segments = active.take().map(ActiveRecording::finish).unwrap_or_default();
+                    let _ = reply.send(segments);
+                }
+                Ok(StreamCommand::Standby { device, audio }) => {
+                    // 录音中不动采集流，下次开始录音时按新配置处理
+                    if active.is_none() {
+                        source.standby(device.as_deref(), &audio);
+                    }
+                }
+                Err(_) => break,
+            },
         }
+    }
+}
+
+/// cpal 输入设备音源：在选择的设备（未指定时为默认设备）上建立并启动录音流，样本统一转成 f32
+/// 开启 audio.keep_stream_open 时采集流常驻，两次录音之间不释放
+#[derive(Default)]
+pub struct CpalSource {
+    resident: Option<ResidentInput>,
+}
 
-        output
+impl AudioSource for CpalSource {
+    fn start(&mut self, device_name: Option<&str>, sink: SampleSink) -> Result<(Capture, StreamInfo)> {
+        // 按 audio.source 选择麦克风、系统回环或两者混合；常驻模式下复用已打开的采集流
+        let input = input_source::open(&mut self.resident, &sink.audio, device_name)?;
+        let info = input.info().clone();
+        let mut writer = sink.writer(&info.device_name, info.sample_rate, info.channels);
+        let errors = writer.errors();
+        let err_fn = move |err: cpal::StreamError| {
+            tracing::error!("录音流错误: {}", err);
+            let _ = errors.send(err.to_string());
+        };
+        let (capture, pre_roll) = input.start(move |data| writer.write(data.iter().copied()), err_fn)?;
+        Ok((capture, StreamInfo { pre_roll, ..info }))
     }
 
-    pub fn start_recording(&mut self) -> Result<()> {
-        use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+    fn standby(&mut self, device: Option<&str>, audio: &AudioConfig) {
+        input_source::standby(&mut self.resident, audio, device);
+    }
+}
 
-        tracing::info!("开始录音...");
+impl AudioRecorder {
+    pub fn new() -> Result<Self> {
+        Self::with_source(|| Box::new(CpalSource::default()))
+    }
 
-        // 清空之前的音频数据
-        self.audio_data.lock().unwrap().clear();
-        *self.is_recording.lock().unwrap() = true;
+    /// 使用指定音源的录音器（测试时注入内存音源）
+    /// 音源在录音线程中由 make_source 创建，常驻采集流（cpal::Stream 不是 Send）始终不离开该线程
+    pub fn with_source<F>(make_source: F) -> Result<Self>
+    where
+        F: FnOnce() -> Box<dyn AudioSource> + Send + 'static,
+    {
+        let audio_data = Arc::new(Mutex::new(Vec::new()));
+        let is_recording = Arc::new(AtomicBool::new(false));
+        let (commands, command_rx) = unbounded();
+
+        std::thread::Builder::new()
+            .name("audio-recorder".to_string())
+            .spawn(move || run_stream_thread(command_rx, make_source()))?;
 
-        let host = cpal::default_host();
-        let device = host
-            .default_input_device()
-            .ok_or_else(|| anyhow::anyhow!("没有找到默认音频输入设备"))?;
+        Ok(Self {
+            device_sample_rate: 48000,  // 默认值，会在 start_recording 时更新
+            channels: 1,
+            audio_data,
+            is_recording,
+            commands,
+            audio: AudioConfig::default(),
+            processing: AudioConfig::default().defaults(),
+            device_errors: None,
+            duration_limit: None,
+            levels: None,
+            missing_device: None,
+            recorded_duration: std::time::Duration::ZERO,
+            pre_roll: Duration::ZERO,
+        })
+    }
+
+    /// 最短录音时长，取自 `audio.min_recording_ms`；录音时长包含开头的预滚动，这里一并加上
+    pub fn min_recording(&self) -> std::time::Duration {
+        self.audio.min_recording() + self.pre_roll
+    }
 
-        // 获取设备支持的配置
-        let supported_config = device
-            .default_input_config()
-            .map_err(|e| anyhow::anyhow!("无法获取默认音频配置: {}", e))?;
+    /// 更新音频处理参数（下次开始录音时生效）；开启常驻录音流时随即打开设备，关闭时释放
+    pub fn set_audio_config(&mut self, audio: AudioConfig) {
+        let _ = self.commands.send(StreamCommand::Standby {
+            device: audio.input_device.clone(),
+            audio: Arc::new(audio.clone()),
+        });
+        self.audio = audio;
+    }
 
-        tracing::info!("设备支持的配置: {:?}", supported_config);
+    pub fn start_recording(&mut self) -> Result<()> {
+        tracing::info!("开始录音...");
 
-        // 使用设备支持的配置
-        let config = supported_config.config();
+        // 清空之前的音频数据
+        self.audio_data.lock().unwrap().clear();
+        self.is_recording.store(true, Ordering::SeqCst);
+
+        // 采集流错误（如录音中拔出麦克风）交给录音线程切换到默认设备续录；
+        // 没有可用设备时才上报到通道，由上层中止录音
+        let (error_tx, stream_errors) = unbounded::<String>();
+        let (device_lost, device_lost_rx) = mpsc::unbounded_channel::<String>();
+        self.device_errors = Some(device_lost_rx);
+        // 达到最长录音时长时停止采集，由上层按松开按键的流程结束录音
+        let (limit_tx, limit_rx) = mpsc::unbounded_channel::<()>();
+        self.duration_limit = Some(limit_rx);
+        // 录音电平转发到上层，供前端画音量条；录音流释放后通道关闭
+        let (level_tx, level_rx) = mpsc::unbounded_channel::<AudioLevel>();
+        self.levels = (self.audio.level_interval_ms > 0).then_some(level_rx);
+
+        let (reply_tx, reply_rx) = bounded(1);
+        self.commands
+            .send(StreamCommand::Start {
+                device: self.audio.input_device.clone(),
+                sink: SampleSink {
+                    audio_data: Arc::clone(&self.audio_data),
+                    is_recording: Arc::clone(&self.is_recording),
+                    pending: Arc::default(),
+                    audio: Arc::new(self.audio.clone()),
+                    elapsed: Duration::ZERO,
+                    limit_tx,
+                    level_tx,
+                    error_tx,
+                },
+                stream_errors,
+                device_lost,
+                reply: reply_tx,
+            })
+            .map_err(|_| anyhow::anyhow!("录音线程已退出"))?;
+        let info = reply_rx
+            .recv()
+            .map_err(|_| anyhow::anyhow!("录音线程已退出"))??;
 
         // 更新采样率和声道为设备实际支持的值
-        self.device_sample_rate = config.sample_rate.0;
-        self.channels = config.channels;
+        self.device_sample_rate = info.sample_rate;
+        self.channels = info.channels;
 
         tracing::info!("设备配置: 采样率={}Hz, 声道={}, 目标采样率={}Hz",
             self.device_sample_rate, self.channels, TARGET_SAMPLE_RATE);
 
-        let audio_data = Arc::clone(&self.audio_data);
-        let is_recording = Arc::clone(&self.is_recording);
-        let err_fn = |err| tracing::error!("录音流错误: {}", err);
-
-        // 根据采样格式创建不同的 stream
-        let stream = match supported_config.sample_format() {
-            cpal::SampleFormat::F32 => device.build_input_stream(
-                &config,
-                move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                    if *is_recording.lock().unwrap() {
-                        let mut buffer = audio_data.lock().unwrap();
-                        buffer.extend_from_slice(data);
-                    }
-                },
-                err_fn,
-                None,
-            )?,
-            cpal::SampleFormat::I16 => {
-                let audio_data_i16 = Arc::clone(&audio_data);
-                let is_recording_i16 = Arc::clone(&is_recording);
-                device.build_input_stream(
-                    &config,
-                    move |data: &[i16], _: &cpal::InputCallbackInfo| {
-                        if *is_recording_i16.lock().unwrap() {
-                            let mut buffer = audio_data_i16.lock().unwrap();
-                            // 转换 i16 到 f32
-                            for &sample in data.iter() {
-                                let normalized = sample as f32 / i16::MAX as f32;
-                                buffer.push(normalized);
-                            }
-                        }
-                    },
-                    err_fn,
-                    None,
-                )?
-            }
-            cpal::SampleFormat::U16 => {
-                let audio_data_u16 = Arc::clone(&audio_data);
-                let is_recording_u16 = Arc::clone(&is_recording);
-                device.build_input_stream(
-                    &config,
-                    move |data: &[u16], _: &cpal::InputCallbackInfo| {
-                        if *is_recording_u16.lock().unwrap() {
-                            let mut buffer = audio_data_u16.lock().unwrap();
-                            // 转换 u16 到 f32
-                            for &sample in data.iter() {
-                                let normalized = (sample as f32 - 32768.0) / 32768.0;
-                                buffer.push(normalized);
-                            }
-                        }
-                    },
-                    err_fn,
-                    None,
-                )?
-            }
-            _ => return Err(anyhow::anyhow!("不支持的采样格式")),
-        };
+        self.processing = self.audio.for_device(&info.device_name);
+        audio_processing::log_processing(&info.device_name, info.channels, &self.processing);
+        self.missing_device = info.missing_device;
+        self.pre_roll = info.pre_roll;
+
+        Ok(())
+    }
 
-        stream.play()?;
+    /// 通知录音线程释放录音流，并等待释放完成（之后不会再有数据写入缓冲区），返回本次录音的各段样本
+    fn release_stream(&self) -> Vec<Segment> {
+        let (reply_tx, reply_rx) = bounded(1);
+        if self.commands.send(StreamCommand::Stop { reply: reply_tx }).is_err() {
+            return Vec::new();
+        }
+        reply_rx.recv().unwrap_or_default()
+    }
 
-        // 保存 stream 引用，保持录音流活跃
-        self.stream = Some(stream);
+    /// 各段按各自设备的参数转单声道、降采样到 16kHz 后拼接，同时记下实际录音时长
+    fn merge_segments(&mut self, segments: Vec<Segment>) -> Vec<f32> {
+        self.recorded_duration = segments.iter().map(Segment::duration).sum();
+        let mut merged = Vec::new();
+        for segment in segments {
+            let info = &segment.info;
+            // 1. 转换为单声道并应用增益
+            let mono_audio = audio_processing::process(&segment.samples, info.channels, &self.audio.for_device(&info.device_name));
+            tracing::info!("转单声道: {} -> {} 样本", segment.samples.len(), mono_audio.len());
+
+            // 2. 降采样到 16kHz
+            let resampled = resampler::resample(&mono_audio, info.sample_rate, TARGET_SAMPLE_RATE);
+            tracing::info!("降采样: {}Hz -> {}Hz, {} -> {} 样本",
+                info.sample_rate, TARGET_SAMPLE_RATE, mono_audio.len(), resampled.len());
+            merged.extend(resampled);
+        }
+        merged
+    }
 
-        Ok(())
+    /// 取走本次录音的设备错误通道（录音流释放后通道随之关闭）
+    pub fn take_device_errors(&mut self) -> Option<mpsc::UnboundedReceiver<String>> {
+        self.device_errors.take()
+    }
+
+    /// 取走本次录音达到最长时长的通知通道（录音流释放后通道随之关闭）
+    pub fn take_duration_limit(&mut self) -> Option<mpsc::UnboundedReceiver<()>> {
+        self.duration_limit.take()
+    }
+
+    /// 取走本次录音的电平通道（录音流释放后通道随之关闭）
+    pub fn take_levels(&mut self) -> Option<mpsc::UnboundedReceiver<AudioLevel>> {
+        self.levels.take()
+    }
+
+    /// 取走本次录音找不到的输入设备名（已回退默认设备）
+    pub fn take_missing_device(&mut self) -> Option<String> {
+        self.missing_device.take()
+    }
+
+    /// 上次录音实际采集的时长（停止录音后有效），用于判断误触
+    pub fn recorded_duration(&self) -> std::time::Duration {
+        self.recorded_duration
+    }
+
+    /// 停止录音，只统计原始样本的电平，不生成 WAV（麦克风测试用）
+    pub fn stop_microphone_test(&mut self) -> MicrophoneTest {
+        self.is_recording.store(false, Ordering::SeqCst);
+        let samples: Vec<f32> = self.release_stream().into_iter().flat_map(|segment| segment.samples).collect();
+        let (level, clipped) = audio_processing::overall_level(&samples);
+        let silent = level.peak_db < self.audio.silence_threshold_dbfs;
+        tracing::info!(
+            "麦克风测试: 峰值 {:.1} dB, RMS {:.1} dB, {}Hz, {} 声道{}{}",
+            level.peak_db,
+            level.rms_db,
+            self.device_sample_rate,
+            self.channels,
+            if clipped { "，可能爆音" } else { "" },
+            if silent { "，没有声音" } else { "" }
+        );
+        MicrophoneTest {
+            peak_db: level.peak_db,
+            rms_db: level.rms_db,
+            sample_rate: self.device_sample_rate,
+            channels: self.channels,
+            clipped,
+            silent,
+        }
     }
 
     /// 停止录音并返回处理后的音频数据（16kHz 单声道 WAV 格式的字节数组）
-    pub fn stop_recording_to_memory(&mut self) -> Result<Vec<u8>> {
+    pub fn stop_recording_to_memory(&mut self) -> Result<AudioClip> {
         tracing::info!("停止录音...");
 
         // 停止录音
-        *self.is_recording.lock().unwrap() = false;
+        self.is_recording.store(false, Ordering::SeqCst);
 
-        // Drop stream，停止音频流
-        self.stream = None;
+        // 释放录音流，停止音频流；录音线程在采集流释放后才取走缓冲区回复，之后不会再有数据写入
+        // （泄漏修复：取走而非克隆，避免长录音的大缓冲区在两次录音之间一直占用内存）
+        let segments = self.release_stream();
+        if segments.len() > 1 {
+            tracing::info!("本次录音中途切换过输入设备，共 {} 段", segments.len());
+        }
 
-        // 等待一小段时间确保所有数据都已写入
-        std::thread::sleep(std::time::Duration::from_millis(100));
+        // 1-2. 各段转单声道、降采样到 16kHz 后拼接
+        let mut resampled_audio = self.merge_segments(segments);
+
+        // 降噪（在 16kHz 上逐帧处理，先于静音裁剪，让裁剪按降噪后的能量判断）
+        if self.processing.denoise {
+            resampled_audio = denoise::denoise(&resampled_audio, TARGET_SAMPLE_RATE);
+            tracing::info!("已降噪");
+        }
 
-        let raw_audio = self.audio_data.lock().unwrap().clone();
-        let original_len = raw_audio.len();
+        // 3. 裁掉松手延迟带来的末尾静音
+        let trimmed = audio_processing::trim_trailing_silence(&mut resampled_audio, TARGET_SAMPLE_RATE, &self.audio.trailing_silence);
+        if trimmed > 0 {
+            tracing::info!("裁剪末尾静音: {}ms", trimmed as u64 * 1000 / TARGET_SAMPLE_RATE as u64);
+        }
 
-        // 1. 转换为单声道
-        let mono_audio = self.to_mono(&raw_audio, self.channels);
-        tracing::info!("转单声道: {} -> {} 样本", original_len, mono_audio.len());
+        // 4. VAD 裁掉首尾静音、压缩中段长停顿
+        let vad_trim = vad::apply(&mut resampled_audio, TARGET_SAMPLE_RATE, &self.audio.vad);
+        if !vad_trim.is_empty() {
+            tracing::info!("VAD 裁剪: 开头 {}ms, 结尾 {}ms, 压缩停顿 {}ms",
+                vad_trim.leading_ms, vad_trim.trailing_ms, vad_trim.compressed_ms);
+        }
 
-        // 2. 降采样到 16kHz
-        let resampled_audio = self.resample(&mono_audio, self.device_sample_rate, TARGET_SAMPLE_RATE);
-        tracing::info!("降采样: {}Hz -> {}Hz, {} -> {} 样本",
-            self.device_sample_rate, TARGET_SAMPLE_RATE, mono_audio.len(), resampled_audio.len());
+        // 5. 自动增益：把峰值拉到目标电平（静音录音不放大）
+        if self.processing.agc {
+            audio_processing::normalize(&mut resampled_audio, self.audio.agc_target_dbfs,
+                self.audio.agc_max_gain_db, self.audio.silence_threshold_dbfs);
+        }
 
-        // 3. 写入内存中的 WAV 格式
+        // 6. 写入内存中的 WAV 格式
         let spec = WavSpec {
             channels: 1,
             sample_rate: TARGET_SAMPLE_RATE,
@@ -206,46 +608,47 @@ impl AudioRecorder {
         let mut cursor = Cursor::new(Vec::new());
         {
             let mut writer = WavWriter::new(&mut cursor, spec)?;
-            for &sample in resampled_audio.iter() {
-                let amplitude = (sample * i16::MAX as f32).clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+            for amplitude in audio_processing::quantize(&resampled_audio, self.audio.dither) {
                 writer.write_sample(amplitude)?;
             }
             writer.finalize()?;
         }
 
-        let wav_data = cursor.into_inner();
-        tracing::info!("音频已转换为内存 WAV: {} bytes, 采样率: {}Hz", wav_data.len(), TARGET_SAMPLE_RATE);
+        let clip = AudioClip::new(cursor.into_inner(), resampled_audio.len(), TARGET_SAMPLE_RATE);
+        tracing::info!("音频已转换为内存 WAV: {} bytes, 采样率: {}Hz, 时长: {}ms",
+            clip.wav.len(), TARGET_SAMPLE_RATE, clip.duration_ms);
 
-        Ok(wav_data)
+        Ok(clip)
     }
 
-    /// 停止录音并保存到文件（保留兼容性）
+    /// 停止录音并保存到文件（保留兼容性）；隐私模式下不落盘，直接返回错误
     pub fn stop_recording(&mut self) -> Result<PathBuf> {
-        tracing::info!("停止录音...");
-
-        // 停止录音
-        *self.is_recording.lock().unwrap() = false;
+        self.stop_recording_in(&std::env::temp_dir())
+    }
 
-        // Drop stream，停止音频流
-        self.stream = None;
+    pub(crate) fn stop_recording_in(&mut self, dir: &Path) -> Result<PathBuf> {
+        tracing::info!("停止录音...");
 
-        // 等待一小段时间确保所有数据都已写入
-        std::thread::sleep(std::time::Duration::from_millis(100));
+        if privacy::enabled() {
+            self.is_recording.store(false, Ordering::SeqCst);
+            self.release_stream();
+            anyhow::bail!("隐私模式下不写入录音文件，请使用 stop_recording_to_memory");
+        }
 
-        let raw_audio = self.audio_data.lock().unwrap().clone();
+        // 停止录音
+        self.is_recording.store(false, Ordering::SeqCst);
 
-        // 1. 转换为单声道
-        let mono_audio = self.to_mono(&raw_audio, self.channels);
+        // 释放录音流，停止音频流
+        let segments = self.release_stream();
 
-        // 2. 降采样到 16kHz
-        let resampled_audio = self.resample(&mono_audio, self.device_sample_rate, TARGET_SAMPLE_RATE);
+        // 1-2. 各段转单声道、降采样到 16kHz 后拼接
+        let resampled_audio = self.merge_segments(segments);
 
         // 保存音频文件
-        let temp_dir = std::env::temp_dir();
         let timestamp = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)?
             .as_secs();
-        let file_path = temp_dir.join(format!("recording_{}.wav", timestamp));
+        let file_path = dir.join(format!("recording_{}.wav", timestamp));
 
         let spec = WavSpec {
             channels: 1,
@@ -256,8 +659,7 @@ impl AudioRecorder {
 
         let mut writer = WavWriter::create(&file_path, spec)?;
 
-        for &sample in resampled_audio.iter() {
-            let amplitude = (sample * i16::MAX as f32).clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+        for amplitude in audio_processing::quantize(&resampled_audio, self.audio.dither) {
             writer.write_sample(amplitude)?;
         }
 
@@ -268,6 +670,223 @@ impl AudioRecorder {
     }
 }
 
-// 实现 Send 和 Sync traits
-unsafe impl Send for AudioRecorder {}
-unsafe impl Sync for AudioRecorder {}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_device_names_exactly_then_loosely() {
+        let names = ["麦克风 (Realtek(R) Audio)", "USB 声卡 - Mic (2)", "Blue Yeti"];
+        let find = |wanted| match_device_name(names.iter().copied(), wanted);
+
+        assert_eq!(find("USB 声卡 - Mic (2)"), Some(1));
+        assert_eq!(find("麦克风 (Realtek(R) Audio)"), Some(0));
+        assert_eq!(find(" blue yeti "), Some(2));
+        assert_eq!(find("USB 声卡"), None);
+    }
+
+    #[test]
+    fn selects_configured_device_and_falls_back_to_default() {
+        let devices = || vec![("麦克风 (Realtek(R) Audio)".to_string(), 0), ("Blue Yeti".to_string(), 1)];
+        let default = || Some(0);
+
+        assert_eq!(select_device(devices, default, Some("blue yeti")).unwrap(), (1, None));
+        assert_eq!(select_device(devices, default, None).unwrap(), (0, None));
+        assert_eq!(select_device(devices, default, Some(" ")).unwrap(), (0, None));
+        assert_eq!(
+            select_device(devices, default, Some("USB 声卡")).unwrap(),
+            (0, Some("USB 声卡".to_string()))
+        );
+        assert!(select_device(devices, || None::<i32>, Some("USB 声卡")).is_err());
+    }
+
+    #[test]
+    fn records_from_injected_source_into_16k_mono_wav() {
+        use crate::audio_source::MemorySource;
+
+        // 48kHz 立体声：左声道 1 秒 440Hz 正弦，右声道静音，末尾再补 0.5 秒静音
+        let spec = WavSpec { channels: 2, sample_rate: 48000, bits_per_sample: 16, sample_format: hound::SampleFormat::Int };
+        let mut cursor = Cursor::new(Vec::new());
+        {
+            let mut writer = WavWriter::new(&mut cursor, spec).unwrap();
+            for i in 0..72000 {
+                let tone = if i < 48000 { (i as f32 / 48000.0 * 440.0 * std::f32::consts::TAU).sin() * 0.5 } else { 0.0 };
+                writer.write_sample((tone * i16::MAX as f32) as i16).unwrap();
+                writer.write_sample(0i16).unwrap();
+            }
+            writer.finalize().unwrap();
+        }
+        let source = MemorySource::from_wav(&cursor.into_inner()).unwrap();
+
+        let mut recorder = AudioRecorder::with_source(move || Box::new(source)).unwrap();
+        let mut audio = AudioConfig::default();
+        audio.vad.enabled = false;
+        recorder.set_audio_config(audio);
+        recorder.start_recording().unwrap();
+        let mut levels = recorder.take_levels().unwrap();
+        let clip = recorder.stop_recording_to_memory().unwrap();
+
+        assert_eq!(recorder.recorded_duration(), std::time::Duration::from_millis(1500));
+        let mut reader = hound::WavReader::new(Cursor::new(clip.wav)).unwrap();
+        assert_eq!((reader.spec().channels, reader.spec().sample_rate), (1, 16000));
+        let samples: Vec<f32> = reader.samples::<i16>().map(|s| s.unwrap() as f32 / i16::MAX as f32).collect();
+        // 末尾静音被裁掉一部分，正弦部分完整保留；时长按裁剪后的样本数计算
+        assert!((16000..24000).contains(&samples.len()), "{}", samples.len());
+        assert_eq!((clip.duration_ms, clip.sample_rate), (samples.len() as u64 / 16, 16000));
+        // 两个声道平均后峰值减半
+        let peak = samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        assert!((peak - 0.25).abs() < 0.02, "{}", peak);
+
+        // 电平按 50ms 上报（统计的是混音前的原始样本）：1.5 秒共 30 次，有声段峰值 -6 dB、RMS -12 dB，静音段为下限
+        let reported: Vec<AudioLevel> = std::iter::from_fn(|| levels.try_recv().ok()).collect();
+        assert_eq!(reported.len(), 30);
+        assert!((reported[0].peak_db + 6.0).abs() < 0.5 && (reported[0].rms_db + 12.0).abs() < 0.5, "{:?}", reported[0]);
+        assert_eq!(reported[29].rms_db, -100.0);
+    }
+
+    #[test]
+    fn microphone_test_reports_levels_clipping_and_silence() {
+        use crate::audio_source::MemorySource;
+
+        let run = |samples: Vec<f32>| {
+            let source = MemorySource { samples, sample_rate: 48000, channels: 2 };
+            let mut recorder = AudioRecorder::with_source(move || Box::new(source)).unwrap();
+            recorder.set_audio_config(AudioConfig::default());
+            recorder.start_recording().unwrap();
+            recorder.stop_microphone_test()
+        };
+
+        // 左声道 -6 dB 正弦、右声道静音：整体 RMS 约 -12 dB
+        let tone: Vec<f32> = (0..96000)
+            .map(|i| if i % 2 == 0 { ((i / 2) as f32 / 48000.0 * 440.0 * std::f32::consts::TAU).sin() * 0.5 } else { 0.0 })
+            .collect();
+        let test = run(tone);
+        assert!((test.peak_db + 6.0).abs() < 0.1 && (test.rms_db + 12.0).abs() < 0.1, "{:?}", test);
+        assert_eq!((test.sample_rate, test.channels, test.clipped, test.silent), (48000, 2, false, false));
+
+        let test = run(vec![0.0; 96000]);
+        assert_eq!((test.peak_db, test.rms_db, test.clipped, test.silent), (-100.0, -100.0, false, true));
+
+        let test = run((0..96000).map(|i| if i % 100 == 0 { 1.0 } else { 0.1 }).collect());
+        assert!(test.clipped && !test.silent);
+    }
+
+    /// 按脚本依次"打开设备"的测试音源：每次 start 取下一项，写完样本后按需模拟采集流出错
+    struct ScriptedSource {
+        // (采样率, 声道, 时长毫秒, 写完后是否出错)；为 None 时该次打开设备失败
+        script: std::collections::VecDeque<Option<(u32, u16, u32, bool)>>,
+        // 每次打开设备（无论成败）都通知测试，便于等录音线程切换完设备再停止
+        started: Sender<()>,
+    }
+
+    impl AudioSource for ScriptedSource {
+        fn start(&mut self, device: Option<&str>, sink: SampleSink) -> Result<(Capture, StreamInfo)> {
+            let Some((sample_rate, channels, ms, fail)) = self.script.pop_front().flatten() else {
+                let _ = self.started.send(());
+                anyhow::bail!("没有找到默认音频输入设备");
+            };
+            let device = device.unwrap_or("default");
+            let mut writer = sink.writer(device, sample_rate, channels);
+            let frames = (sample_rate * ms / 1000) as usize;
+            writer.write((0..frames * channels as usize).map(|i| ((i / channels as usize) as f32 * 0.05).sin() * 0.5));
+            if fail {
+                let _ = writer.errors().send("设备已断开".to_string());
+            }
+            let _ = self.started.send(());
+            let info = StreamInfo {
+                sample_rate,
+                channels,
+                device_name: device.to_string(),
+                missing_device: None,
+                pre_roll: Duration::ZERO,
+            };
+            Ok((Box::new(()), info))
+        }
+    }
+
+    /// 按脚本开始录音，等所有脚本项都被打开过（设备切换已完成）后返回
+    fn scripted_recording(script: Vec<Option<(u32, u16, u32, bool)>>, max_duration_secs: u32) -> AudioRecorder {
+        let attempts = script.len();
+        let (started, starts) = unbounded();
+        let mut recorder = AudioRecorder::with_source(move || Box::new(ScriptedSource { script: script.into(), started })).unwrap();
+        let mut audio = AudioConfig::default();
+        audio.vad.enabled = false;
+        audio.trailing_silence.enabled = false;
+        audio.max_duration_secs = max_duration_secs;
+        recorder.set_audio_config(audio);
+        recorder.start_recording().unwrap();
+        for _ in 0..attempts {
+            starts.recv().unwrap();
+        }
+        recorder
+    }
+
+    #[test]
+    fn stream_error_switches_to_default_device_and_keeps_recording() {
+        // 48kHz 立体声设备录 0.6 秒后断开，切到 16kHz 单声道默认设备再录 0.6 秒；时长上限 1 秒按两段合计
+        let mut recorder = scripted_recording(vec![Some((48000, 2, 600, true)), Some((16000, 1, 600, false))], 1);
+        let mut lost = recorder.take_device_errors().unwrap();
+        let mut limit = recorder.take_duration_limit().unwrap();
+        let clip = recorder.stop_recording_to_memory().unwrap();
+
+        assert_eq!(recorder.recorded_duration(), std::time::Duration::from_millis(1000));
+        assert!(limit.try_recv().is_ok());
+        assert!(lost.try_recv().is_err());
+        let reader = hound::WavReader::new(Cursor::new(clip.wav)).unwrap();
+        assert_eq!((reader.spec().channels, reader.spec().sample_rate), (1, 16000));
+        assert!((15900..=16000).contains(&reader.len()), "{}", reader.len());
+    }
+
+    #[test]
+    fn failed_rebuild_reports_device_lost() {
+        let mut recorder = scripted_recording(vec![Some((44100, 1, 500, true)), None, None, None], 0);
+        let mut lost = recorder.take_device_errors().unwrap();
+        let clip = recorder.stop_recording_to_memory().unwrap();
+
+        assert_eq!(lost.try_recv().unwrap(), "设备已断开");
+        // 断开前录到的部分仍然保留
+        assert_eq!(recorder.recorded_duration(), std::time::Duration::from_millis(500));
+        assert!(hound::WavReader::new(Cursor::new(clip.wav)).unwrap().len() > 7900);
+    }
+
+    /// 在独立线程里按 10ms 一块快速写入的测试音源，模拟声卡回调线程与录音线程并发
+    struct ThreadedSource {
+        secs: u32,
+        done: Sender<()>,
+    }
+
+    impl AudioSource for ThreadedSource {
+        fn start(&mut self, _device: Option<&str>, sink: SampleSink) -> Result<(Capture, StreamInfo)> {
+            let (secs, done) = (self.secs, self.done.clone());
+            let mut writer = sink.writer("stress", 48000, 2);
+            std::thread::spawn(move || {
+                let block = 480 * 2;
+                for index in 0..(secs as usize * 100) {
+                    writer.write((index * block..(index + 1) * block).map(|i| (i % 1000) as f32 / 1000.0));
+                    // 约 20 倍速：环形队列容纳 2 秒音频，相当于录音线程可以 100ms 不汇总
+                    std::thread::sleep(std::time::Duration::from_micros(500));
+                }
+                let _ = done.send(());
+            });
+            let info = StreamInfo { sample_rate: 48000, channels: 2, device_name: "stress".to_string(), missing_device: None, pre_roll: Duration::ZERO };
+            Ok((Box::new(()), info))
+        }
+    }
+
+    #[test]
+    fn long_recording_keeps_every_sample() {
+        let (done, finished) = bounded(1);
+        let mut recorder = AudioRecorder::with_source(move || Box::new(ThreadedSource { secs: 300, done })).unwrap();
+        recorder.set_audio_config(AudioConfig { max_duration_secs: 0, ..Default::default() });
+        recorder.start_recording().unwrap();
+        finished.recv().unwrap();
+
+        // 5 分钟 48kHz 立体声，一个样本不少，顺序不乱
+        let segments = recorder.release_stream();
+        assert_eq!(segments.len(), 1);
+        let samples = &segments[0].samples;
+        assert_eq!(samples.len(), 300 * 48000 * 2);
+        assert!(samples.iter().enumerate().all(|(i, &s)| s == (i % 1000) as f32 / 1000.0));
+        assert_eq!(segments[0].duration(), std::time::Duration::from_secs(300));
+    }
+}
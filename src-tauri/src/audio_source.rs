@@ -0,0 +1,195 @@
+// 录音音源抽象
+// AudioRecorder 通过 AudioSource 建立采集流，cpal 输入设备（audio_recorder::CpalSource）是其中一种；
+// 测试时可注入从内存 / WAV 读取的假音源，离线验证"采集→单声道→降采样→WAV"整条流水线
+
+use anyhow::Result;
+use rtrb::{Consumer, Producer, RingBuffer};
+use std::any::Any;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::audio_processing::{self, AudioLevel, ChannelProbe, DurationLimit, LevelMeter};
+use crate::config::AudioConfig;
+
+// 采集回调与录音线程之间环形队列容纳的音频时长；录音线程每 10ms 汇总一次，留足调度抖动的余量
+const RING_SECS: u32 = 2;
+
+/// 采集流建立后的实际参数
+#[derive(Debug, Clone)]
+pub struct StreamInfo {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub device_name: String,
+    // 选择的设备找不到、已回退默认设备时为该设备名
+    pub missing_device: Option<String>,
+    // 录音开头拼接的预滚动时长（常驻流空闲时保留的样本），不算作按住按键的时长
+    pub pre_roll: Duration,
+}
+
+/// 采集流句柄，释放即停止采集（cpal::Stream 等不是 Send，只在录音线程中持有）
+pub type Capture = Box<dyn Any>;
+
+/// 录音音源：在选择的设备（None 为默认设备）上开始采集，样本统一转成交错的 f32 写入 sink
+/// 音源在录音线程中创建并一直留在该线程（见 `AudioRecorder::with_source`），不要求 Send
+pub trait AudioSource {
+    fn start(&mut self, device: Option<&str>, sink: SampleSink) -> Result<(Capture, StreamInfo)>;
+
+    /// 空闲时按配置准备采集流（常驻模式下提前打开设备、关闭后释放）；默认不做任何事
+    fn standby(&mut self, _device: Option<&str>, _audio: &AudioConfig) {}
+}
+
+/// 本次录音的写入目标：录音缓冲区与状态，以及时长上限、电平、设备错误的通知通道
+/// 设备中途失效、换设备重建采集流时复用同一份
+/// 采集回调只往无锁的 SPSC 环形队列追加，不加锁也不分配内存；录音线程定期 `collect` 到 audio_data
+#[derive(Clone)]
+pub struct SampleSink {
+    pub audio_data: Arc<Mutex<Vec<f32>>>,
+    pub is_recording: Arc<AtomicBool>,
+    // 当前采集流环形队列的读端，每次生成写入器时替换
+    pub pending: Arc<Mutex<Option<Consumer<f32>>>>,
+    // 本次录音的音频配置（最长时长、电平上报间隔、按设备的声道选择）
+    pub audio: Arc<AudioConfig>,
+    // 换设备续录前已录的时长，计入时长上限
+    pub elapsed: Duration,
+    pub limit_tx: mpsc::UnboundedSender<()>,
+    pub level_tx: mpsc::UnboundedSender<AudioLevel>,
+    // 采集流错误交给录音线程处理（切回默认设备重建）
+    pub error_tx: crossbeam_channel::Sender<String>,
+}
+
+impl SampleSink {
+    /// 按设备及其实际的采样率与声道数生成采集回调使用的写入器（环形队列在这里一次分配好）
+    pub fn writer(self, device: &str, sample_rate: u32, channels: u16) -> SampleWriter {
+        let mut limit = DurationLimit::new(self.audio.max_duration_secs, sample_rate, channels, self.limit_tx);
+        limit.skip(self.elapsed, sample_rate, channels);
+        let (producer, consumer) = RingBuffer::new((sample_rate * RING_SECS) as usize * channels.max(1) as usize);
+        *self.pending.lock().unwrap() = Some(consumer);
+        SampleWriter {
+            producer,
+            is_recording: self.is_recording,
+            limit,
+            meter: LevelMeter::new(self.audio.level_interval_ms, sample_rate, channels, self.level_tx),
+            probe: ChannelProbe::new(sample_rate, channels, self.audio.for_device(device).channel_selection),
+            error_tx: self.error_tx,
+            dropped: 0,
+        }
+    }
+
+    /// 把环形队列里已写入的样本汇总到录音缓冲区（录音线程定期调用，释放采集流后再调用一次取完）
+    pub fn collect(&self) {
+        let mut pending = self.pending.lock().unwrap();
+        let Some(consumer) = pending.as_mut() else {
+            return;
+        };
+        let Ok(chunk) = consumer.read_chunk(consumer.slots()) else {
+            return;
+        };
+        let (first, second) = chunk.as_slices();
+        let mut buffer = self.audio_data.lock().unwrap();
+        buffer.extend_from_slice(first);
+        buffer.extend_from_slice(second);
+        chunk.commit_all();
+    }
+}
+
+/// 采集回调中的写入器：录音中才写入，超过时长上限的样本丢弃，写入后应用输入增益并更新电平表
+pub struct SampleWriter {
+    producer: Producer<f32>,
+    is_recording: Arc<AtomicBool>,
+    limit: DurationLimit,
+    meter: Option<LevelMeter>,
+    probe: Option<ChannelProbe>,
+    error_tx: crossbeam_channel::Sender<String>,
+    // 环形队列已满（录音线程长时间没有汇总）时丢弃的样本数，写入器释放时记入日志
+    dropped: usize,
+}
+
+impl SampleWriter {
+    /// 追加一段交错样本
+    pub fn write(&mut self, samples: impl ExactSizeIterator<Item = f32>) {
+        if !self.is_recording.load(Ordering::Relaxed) {
+            return;
+        }
+        let admitted = self.limit.admit(samples.len());
+        let writable = admitted.min(self.producer.slots());
+        self.dropped += admitted - writable;
+        let Ok(mut chunk) = self.producer.write_chunk(writable) else {
+            return;
+        };
+        let mut samples = samples.take(writable);
+        let gain = audio_processing::input_gain();
+        let (first, second) = chunk.as_mut_slices();
+        for slots in [first, second] {
+            for (slot, sample) in slots.iter_mut().zip(&mut samples) {
+                *slot = sample;
+            }
+            audio_processing::apply_gain(slots, gain);
+            if let Some(meter) = self.meter.as_mut() {
+                meter.push(slots);
+            }
+            if let Some(probe) = self.probe.as_mut() {
+                probe.push(slots);
+            }
+        }
+        chunk.commit_all();
+    }
+
+    /// 采集流错误的上报通道（录音线程收到后尝试切回默认设备重建）
+    pub fn errors(&self) -> crossbeam_channel::Sender<String> {
+        self.error_tx.clone()
+    }
+}
+
+impl Drop for SampleWriter {
+    fn drop(&mut self) {
+        if self.dropped > 0 {
+            tracing::warn!("录音缓冲队列已满，丢弃了 {} 个样本", self.dropped);
+        }
+    }
+}
+
+/// 测试用音源：开始采集时把内存中的样本按块一次性写入（每块写完即汇总，代替录音线程）
+#[cfg(test)]
+pub struct MemorySource {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+#[cfg(test)]
+impl MemorySource {
+    /// 从 16 位 PCM WAV 读取
+    pub fn from_wav(wav: &[u8]) -> Result<Self> {
+        let mut reader = hound::WavReader::new(std::io::Cursor::new(wav))?;
+        let spec = reader.spec();
+        let samples = reader
+            .samples::<i16>()
+            .map(|sample| sample.map(|s| s as f32 / i16::MAX as f32))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { samples, sample_rate: spec.sample_rate, channels: spec.channels })
+    }
+}
+
+#[cfg(test)]
+impl AudioSource for MemorySource {
+    fn start(&mut self, device: Option<&str>, sink: SampleSink) -> Result<(Capture, StreamInfo)> {
+        let device = device.unwrap_or("memory");
+        let mut writer = sink.clone().writer(device, self.sample_rate, self.channels);
+        // 模拟声卡按 10ms 一块回调
+        let block = (self.sample_rate as usize / 100 * self.channels as usize).max(1);
+        for chunk in self.samples.chunks(block) {
+            writer.write(chunk.iter().copied());
+            sink.collect();
+        }
+        let info = StreamInfo {
+            sample_rate: self.sample_rate,
+            channels: self.channels,
+            device_name: device.to_string(),
+            missing_device: None,
+            pre_roll: Duration::ZERO,
+        };
+        Ok((Box::new(()), info))
+    }
+}
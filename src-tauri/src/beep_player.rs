@@ -1,21 +1,42 @@
 use rodio::{OutputStream, Sink, Source};
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::time::Duration;
 
+// 音量为 1.0 时的幅度：30% 避免太刺耳
+const BASE_AMPLITUDE: f32 = 0.3;
+
+// 提示音音量系数（f32 的位模式），默认 1.0
+static VOLUME: AtomicU32 = AtomicU32::new(0x3f80_0000);
+
+/// 设置提示音音量（0.0 ~ 1.0，0 为静音）
+pub fn set_volume(volume: f32) {
+    let volume = if volume.is_finite() { volume.clamp(0.0, 1.0) } else { 1.0 };
+    VOLUME.store(volume.to_bits(), Ordering::Relaxed);
+}
+
+fn volume() -> f32 {
+    f32::from_bits(VOLUME.load(Ordering::Relaxed))
+}
+
 /// 播放一个短促的提示音（非阻塞）
 ///
 /// frequency: 音调频率（Hz），建议 800-1200
 /// duration_ms: 持续时间（毫秒），建议 100-200
 pub fn play_beep(frequency: u32, duration_ms: u64) {
+    let amplitude = BASE_AMPLITUDE * volume();
+    if amplitude <= 0.0 {
+        return;
+    }
     // 在新线程中播放，避免阻塞主线程
     std::thread::spawn(move || {
-        if let Err(e) = play_beep_blocking(frequency, duration_ms) {
+        if let Err(e) = play_beep_blocking(frequency, duration_ms, amplitude) {
             tracing::error!("播放提示音失败: {}", e);
         }
     });
 }
 
 /// 阻塞式播放提示音
-fn play_beep_blocking(frequency: u32, duration_ms: u64) -> Result<(), Box<dyn std::error::Error>> {
+fn play_beep_blocking(frequency: u32, duration_ms: u64, amplitude: f32) -> Result<(), Box<dyn std::error::Error>> {
     // 获取音频输出流
     let (_stream, stream_handle) = OutputStream::try_default()?;
     let sink = Sink::try_new(&stream_handle)?;
@@ -23,7 +44,7 @@ fn play_beep_blocking(frequency: u32, duration_ms: u64) -> Result<(), Box<dyn st
     // 生成正弦波音频源
     let source = rodio::source::SineWave::new(frequency as f32)
         .take_duration(Duration::from_millis(duration_ms))
-        .amplify(0.3); // 音量调整为 30% 避免太刺耳
+        .amplify(amplitude);
 
     sink.append(source);
     sink.sleep_until_end(); // 等待播放完成
@@ -40,3 +61,20 @@ pub fn play_start_beep() {
 pub fn play_stop_beep() {
     play_beep(800, 150); // 800 Hz, 150ms
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamps_volume_to_unit_range() {
+        set_volume(0.5);
+        assert_eq!(volume(), 0.5);
+        set_volume(3.0);
+        assert_eq!(volume(), 1.0);
+        set_volume(-1.0);
+        assert_eq!(volume(), 0.0);
+        set_volume(f32::NAN);
+        assert_eq!(volume(), 1.0);
+    }
+}
@@ -0,0 +1,144 @@
+// 本地 HTTP 控制接口（只监听 127.0.0.1）
+// 所有路径共用同一个 token 校验，各路径可在配置中单独关闭；目前提供 /metrics
+
+use crate::config::ControlConfig;
+use crate::metrics;
+use anyhow::Result;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+// 单个请求头的最大长度
+const MAX_REQUEST_BYTES: usize = 8192;
+// 读取请求的超时
+const REQUEST_TIMEOUT_SECS: u64 = 5;
+
+/// 启动控制接口（未启用或未配置 token 时不启动）
+pub fn spawn_server(config: ControlConfig) {
+    if !config.enabled {
+        return;
+    }
+    if config.token.trim().is_empty() {
+        tracing::warn!("控制接口已启用但未配置 token，不会启动");
+        return;
+    }
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = serve(config).await {
+            tracing::error!("控制接口异常退出: {}", e);
+        }
+    });
+}
+
+async fn serve(config: ControlConfig) -> Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", config.port)).await?;
+    tracing::info!("控制接口已启动: http://127.0.0.1:{}", config.port);
+
+    let config = Arc::new(config);
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let config = Arc::clone(&config);
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, &config).await {
+                tracing::debug!("控制接口请求处理失败: {}", e);
+            }
+        });
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, config: &ControlConfig) -> Result<()> {
+    let mut buf = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 1024];
+    let read = async {
+        while !buf.windows(4).any(|w| w == b"\r\n\r\n") && buf.len() < MAX_REQUEST_BYTES {
+            let n = stream.read(&mut chunk).await?;
+            if n == 0 {
+                break;
+            }
+            buf.extend_from_slice(&chunk[..n]);
+        }
+        anyhow::Ok(())
+    };
+    tokio::time::timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS), read)
+        .await
+        .map_err(|_| anyhow::anyhow!("读取请求超时"))??;
+
+    let request = String::from_utf8_lossy(&buf);
+    let (status, body) = route(&request, config);
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// 控制接口提供的路径
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Endpoint {
+    Metrics,
+}
+
+impl Endpoint {
+    /// 按请求路径查找，配置中关闭的路径视为不存在
+    fn find(path: &str, config: &ControlConfig) -> Option<Self> {
+        match path.split('?').next()? {
+            "/metrics" if config.metrics => Some(Endpoint::Metrics),
+            _ => None,
+        }
+    }
+
+    fn respond(self) -> String {
+        match self {
+            Endpoint::Metrics => metrics::render(),
+        }
+    }
+}
+
+/// 根据请求行和 Authorization 头决定响应
+fn route(request: &str, config: &ControlConfig) -> (&'static str, String) {
+    let mut lines = request.lines();
+    let mut parts = lines.next().unwrap_or_default().split_whitespace();
+    let (method, path) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+
+    let Some(endpoint) = Endpoint::find(path, config).filter(|_| method == "GET") else {
+        return ("404 Not Found", "not found\n".to_string());
+    };
+
+    let authorized = lines
+        .filter_map(|line| line.split_once(':'))
+        .filter(|(name, _)| name.trim().eq_ignore_ascii_case("authorization"))
+        .any(|(_, value)| value.trim().strip_prefix("Bearer ") == Some(config.token.as_str()));
+    if !authorized {
+        return ("401 Unauthorized", "unauthorized\n".to_string());
+    }
+
+    ("200 OK", endpoint.respond())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(metrics: bool) -> ControlConfig {
+        ControlConfig { enabled: true, token: "secret".to_string(), metrics, ..Default::default() }
+    }
+
+    #[test]
+    fn route_requires_token() {
+        let get = |auth: &str| format!("GET /metrics HTTP/1.1\r\nHost: localhost\r\n{}\r\n", auth);
+        assert_eq!(route(&get(""), &config(true)).0, "401 Unauthorized");
+        assert_eq!(route(&get("Authorization: Bearer wrong\r\n"), &config(true)).0, "401 Unauthorized");
+        assert_eq!(route(&get("authorization: Bearer secret\r\n"), &config(true)).0, "200 OK");
+        assert_eq!(route("GET / HTTP/1.1\r\n\r\n", &config(true)).0, "404 Not Found");
+    }
+
+    #[test]
+    fn disabled_metrics_path_is_not_found() {
+        let request = "GET /metrics HTTP/1.1\r\nAuthorization: Bearer secret\r\n\r\n";
+        assert_eq!(route(request, &config(false)).0, "404 Not Found");
+    }
+}
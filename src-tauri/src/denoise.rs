@@ -0,0 +1,191 @@
+// 降噪模块（audio.denoise）
+// 机械键盘、空调这类稳态噪声容易被识别成语气词。这里用 nnnoiseless（RNNoise 的 Rust 移植，
+// 频带增益由小型循环神经网络估计）降噪。RNNoise 只处理 48kHz、480 样本一帧的 16-bit 幅度样本：
+// 其他采样率先升到 48kHz、降噪后再降回原采样率；流式录音每来一块就处理凑满的整帧，
+// 不满一帧的样本留到下一块，不需要攒完整段音频
+
+use nnnoiseless::DenoiseState;
+
+use crate::resampler::{self, Resampler};
+
+/// RNNoise 的采样率与帧长
+const MODEL_RATE: u32 = 48000;
+const FRAME: usize = DenoiseState::FRAME_SIZE;
+// RNNoise 的输入输出按 16-bit 整数幅度缩放
+const SCALE: f32 = 32768.0;
+
+/// 有状态的流式降噪器，可按任意大小分块输入
+pub struct Denoiser {
+    state: Box<DenoiseState<'static>>,
+    // 采样率不是 48kHz 时前后各一个重采样器
+    resamplers: Option<(Resampler, Resampler)>,
+    // 48kHz 下尚未凑满一帧的样本
+    pending: Vec<f32>,
+    // 第一帧输出只有淡入（合成窗重叠相加的延迟），丢掉后输出与输入时间对齐
+    warmed_up: bool,
+}
+
+impl Denoiser {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            state: DenoiseState::new(),
+            resamplers: (sample_rate != MODEL_RATE)
+                .then(|| (Resampler::new(sample_rate, MODEL_RATE), Resampler::new(MODEL_RATE, sample_rate))),
+            pending: Vec::with_capacity(FRAME * 2),
+            warmed_up: false,
+        }
+    }
+
+    /// 处理一块输入，返回已完成的整帧输出（末尾不满一帧的部分留到下一块）
+    pub fn process(&mut self, input: &[f32]) -> Vec<f32> {
+        match self.resamplers.as_mut() {
+            Some((up, _)) => {
+                let upsampled = up.process(input);
+                self.pending.extend(upsampled);
+            }
+            None => self.pending.extend_from_slice(input),
+        }
+        let output = self.process_frames();
+        match self.resamplers.as_mut() {
+            Some((_, down)) => down.process(&output),
+            None => output,
+        }
+    }
+
+    /// 处理 `pending` 里凑满的整帧（48kHz）
+    fn process_frames(&mut self) -> Vec<f32> {
+        let frames = self.pending.len() / FRAME;
+        let mut output = Vec::with_capacity(frames * FRAME);
+        let mut input = [0.0; FRAME];
+        let mut denoised = [0.0; FRAME];
+        for frame in self.pending.chunks_exact(FRAME) {
+            for (scaled, &sample) in input.iter_mut().zip(frame) {
+                *scaled = sample * SCALE;
+            }
+            self.state.process_frame(&mut denoised, &input);
+            if self.warmed_up {
+                output.extend(denoised.iter().map(|&sample| sample / SCALE));
+            }
+            self.warmed_up = true;
+        }
+        self.pending.drain(..frames * FRAME);
+        output
+    }
+}
+
+/// 一次性降噪整段音频，输出与输入等长、时间对齐
+pub fn denoise(samples: &[f32], sample_rate: u32) -> Vec<f32> {
+    let mut denoiser = Denoiser::new(MODEL_RATE);
+    denoiser.pending = resampler::resample(samples, sample_rate, MODEL_RATE);
+    let len = denoiser.pending.len();
+    // 补零冲出最后不满一帧的样本和一帧的合成延迟
+    denoiser.pending.resize(len.div_ceil(FRAME) * FRAME + FRAME, 0.0);
+    let mut output = denoiser.process_frames();
+    output.truncate(len);
+    let mut output = resampler::resample(&output, MODEL_RATE, sample_rate);
+    output.resize(samples.len(), 0.0);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const RATE: usize = 16000;
+
+    /// 带噪测试样本：0.5s 只有底噪 → 真实录音（tests/data/speech_16k.wav）→ 0.5s 停顿 → 再一遍录音，
+    /// 全程叠加白噪声
+    fn noisy_clip(noise_level: f32) -> (Vec<f32>, Vec<f32>) {
+        let wav = include_bytes!("../tests/data/speech_16k.wav");
+        let speech: Vec<f32> = hound::WavReader::new(&wav[..])
+            .unwrap()
+            .samples::<i16>()
+            .map(|s| s.unwrap() as f32 / SCALE)
+            .collect();
+        let pause = vec![0.0; RATE / 2];
+        let clean: Vec<f32> = [&pause, &speech, &pause, &speech].into_iter().flatten().copied().collect();
+
+        let mut seed = 11u32;
+        let noisy = clean
+            .iter()
+            .map(|&sample| {
+                seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+                sample + ((seed >> 8) as f32 / (1 << 24) as f32 * 2.0 - 1.0) * noise_level
+            })
+            .collect();
+        (clean, noisy)
+    }
+
+    fn snr_db(clean: &[f32], signal: &[f32]) -> f32 {
+        let power: f32 = clean.iter().map(|s| s * s).sum();
+        let error: f32 = clean.iter().zip(signal).map(|(c, s)| (c - s) * (c - s)).sum();
+        10.0 * (power / error).log10()
+    }
+
+    /// 经过 16-bit WAV 编解码，与实际发送的音频一致
+    fn through_wav(samples: &[f32]) -> Vec<f32> {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: RATE as u32,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut cursor = Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut cursor, spec).unwrap();
+        for &sample in samples {
+            writer.write_sample((sample * i16::MAX as f32) as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+        hound::WavReader::new(Cursor::new(cursor.into_inner()))
+            .unwrap()
+            .samples::<i16>()
+            .map(|s| s.unwrap() as f32 / i16::MAX as f32)
+            .collect()
+    }
+
+    #[test]
+    fn improves_snr_of_noisy_speech() {
+        let (clean, noisy) = noisy_clip(0.05);
+        let denoised = through_wav(&denoise(&noisy, RATE as u32));
+        assert_eq!(denoised.len(), noisy.len());
+
+        let before = snr_db(&clean, &noisy);
+        let after = snr_db(&clean, &denoised);
+        assert!(after > before + 5.0, "降噪前 {:.1} dB, 降噪后 {:.1} dB", before, after);
+
+        // 两遍录音之间只有噪声的停顿被压低 20dB 以上（开头网络状态还在收敛，不计）
+        let pause = |samples: &[f32]| samples[RATE * 2 + RATE / 10..RATE * 2 + RATE / 2].iter().map(|s| s * s).sum::<f32>();
+        assert!(pause(&denoised) < pause(&noisy) * 0.01);
+    }
+
+    #[test]
+    fn streaming_matches_one_shot() {
+        let (_, noisy) = noisy_clip(0.02);
+
+        // 重采样器流式输出最多滞后的输入样本数：未凑满的一块加滤波器长度
+        let resampler_lag = resampler::CHUNK_FRAMES + resampler::SINC_LEN;
+
+        // 不规则分块（模拟声卡回调）：48kHz 直接按帧处理，16kHz 先升采样；都与整段处理的前缀一致
+        for rate in [RATE as u32, MODEL_RATE] {
+            let input = resampler::resample(&noisy, RATE as u32, rate);
+            let expected = denoise(&input, rate);
+            let mut denoiser = Denoiser::new(rate);
+            let mut streamed = Vec::new();
+            for chunk in input.chunks(333) {
+                streamed.extend(denoiser.process(chunk));
+            }
+
+            // 流式输出只比整段处理少末尾尚未确定的部分：凑帧中的样本（不满一帧）与一帧合成延迟，
+            // 非 48kHz 时再加前后两个重采样器的滞后（按本采样率换算）
+            let to_rate = |samples: usize| (samples * rate as usize).div_ceil(MODEL_RATE as usize);
+            let mut max_lag = to_rate(FRAME * 2);
+            if rate != MODEL_RATE {
+                max_lag += resampler_lag + to_rate(resampler_lag) + 2;
+            }
+            let lag = expected.len() - streamed.len();
+            assert!(lag <= max_lag, "{}Hz: 流式输出少 {} 样本，上限 {}", rate, lag, max_lag);
+            assert_eq!(streamed[..], expected[..streamed.len()], "{}Hz", rate);
+        }
+    }
+}
@@ -0,0 +1,79 @@
+// 口述写入文件（output_target = file）
+// 持续口述记笔记时，识别结果逐条追加到指定的 markdown / txt 文件末尾，每条一行
+
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use std::fs::OpenOptions;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// 把一条识别结果追加到文件末尾，`timestamp` 为真时行首加上本地时间
+pub fn append(path: &Path, text: &str, timestamp: bool) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| anyhow::anyhow!("无法打开输出文件 {:?}: {}", path, e))?;
+
+    // 原文件末尾没有换行时先补一个，保证每条结果独占一行
+    let len = file.metadata()?.len();
+    let mut needs_newline = false;
+    if len > 0 {
+        let mut last = [0u8; 1];
+        file.seek(SeekFrom::Start(len - 1))?;
+        file.read_exact(&mut last)?;
+        needs_newline = last[0] != b'\n';
+    }
+
+    let entry = format_entry(text, timestamp.then(Local::now));
+    let prefix = if needs_newline { "\n" } else { "" };
+    file.write_all(format!("{}{}", prefix, entry).as_bytes())?;
+    Ok(())
+}
+
+/// 一条记录：可选的 "[2024-05-01 09:30:00] " 前缀 + 文本 + 换行
+fn format_entry(text: &str, time: Option<DateTime<Local>>) -> String {
+    let text = text.trim_end();
+    match time {
+        Some(time) => format!("[{}] {}\n", time.format("%Y-%m-%d %H:%M:%S"), text),
+        None => format!("{}\n", text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn appends_entries_on_their_own_lines() {
+        let dir = std::env::temp_dir().join(format!("ptt-dictation-{}", std::process::id()));
+        let path = dir.join("notes").join("口述.md");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        // 目录不存在时自动创建
+        append(&path, "第一条", false).unwrap();
+        // 用户手动编辑后末尾没有换行
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all("# 手写标题".as_bytes()).unwrap();
+        drop(file);
+        append(&path, "第二条\n", true).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "第一条");
+        assert_eq!(lines[1], "# 手写标题");
+        assert!(lines[2].starts_with('[') && lines[2].ends_with("] 第二条"));
+        assert!(content.ends_with('\n'));
+
+        let time = Local.with_ymd_and_hms(2024, 5, 1, 9, 30, 0).unwrap();
+        assert_eq!(format_entry("笔记", Some(time)), "[2024-05-01 09:30:00] 笔记\n");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
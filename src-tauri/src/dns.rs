@@ -0,0 +1,165 @@
+// 自定义 DNS 解析模块
+// 公司网络 DNS 污染时，可在配置中把服务域名直接绑定到指定 IP（TLS SNI / Host 仍使用原域名）
+// 配置了 HTTP 代理时一并随覆盖表下发到各客户端，经代理的连接由代理负责解析
+
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, SocketAddr};
+
+use crate::proxy::HttpProxy;
+
+/// 域名 -> IP 的解析覆盖表（以及可选的 HTTP 代理）
+#[derive(Debug, Clone, Default)]
+pub struct DnsOverrides {
+    entries: BTreeMap<String, IpAddr>,
+    proxy: Option<HttpProxy>,
+}
+
+impl DnsOverrides {
+    /// 从配置构建，非法 IP 的条目记录警告后忽略
+    pub fn from_config(overrides: &HashMap<String, String>) -> Self {
+        let mut entries = BTreeMap::new();
+        for (host, ip) in overrides {
+            let host = host.trim().to_ascii_lowercase();
+            match ip.trim().parse::<IpAddr>() {
+                Ok(addr) if !host.is_empty() => {
+                    entries.insert(host, addr);
+                }
+                _ => tracing::warn!("忽略无效的 DNS 覆盖配置: {} -> {}", host, ip),
+            }
+        }
+        Self { entries, proxy: None }
+    }
+
+    /// 设置 HTTP 代理（`http://[user:pass@]host:port`），地址无效时记录警告后直连
+    pub fn with_proxy(mut self, proxy: Option<&str>) -> Self {
+        self.proxy = match proxy.map(HttpProxy::parse).transpose() {
+            Ok(proxy) => proxy.flatten(),
+            Err(e) => {
+                tracing::warn!("忽略代理配置: {}", e);
+                None
+            }
+        };
+        self
+    }
+
+    pub fn proxy(&self) -> Option<&HttpProxy> {
+        self.proxy.as_ref()
+    }
+
+    /// 既没有域名覆盖也没有代理，客户端按系统默认方式连接
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty() && self.proxy.is_none()
+    }
+
+    /// 查询域名的覆盖地址（不区分大小写）
+    pub fn lookup(&self, host: &str) -> Option<IpAddr> {
+        self.entries.get(&host.to_ascii_lowercase()).copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, IpAddr)> {
+        self.entries.iter().map(|(host, ip)| (host.as_str(), *ip))
+    }
+
+    /// 把覆盖表与代理绑定到 reqwest 客户端（reqwest 以 URL 中的端口为准，这里的端口仅占位）
+    pub fn apply(&self, mut builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        if self.is_empty() {
+            return builder;
+        }
+        for (host, ip) in self.iter() {
+            builder = builder.resolve(host, SocketAddr::new(ip, 443));
+        }
+        match self.proxy {
+            Some(ref proxy) => proxy.apply(builder),
+            None => builder,
+        }
+    }
+
+    /// 启动日志中输出生效的覆盖项与代理
+    pub fn log_active(&self) {
+        for (host, ip) in self.iter() {
+            tracing::info!("DNS 覆盖生效: {} -> {}", host, ip);
+        }
+        if let Some(ref proxy) = self.proxy {
+            tracing::info!("HTTP 代理生效: {}", proxy.address());
+        }
+    }
+}
+
+/// 单个服务域名的解析诊断结果
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct HostDiagnostic {
+    pub host: String,
+    /// 配置的覆盖地址（未配置为 None）
+    pub override_ip: Option<String>,
+    /// 系统 DNS 的解析结果
+    pub system_ips: Vec<String>,
+    /// 实际连接使用的地址
+    pub effective_ip: Option<String>,
+    /// 实际地址的 443 端口能否建立 TCP 连接
+    pub reachable: bool,
+    pub error: Option<String>,
+}
+
+impl DnsOverrides {
+    /// 诊断一个域名：对比系统解析与覆盖地址，并测试实际地址的连通性
+    pub async fn diagnose(&self, host: &str) -> HostDiagnostic {
+        let override_ip = self.lookup(host);
+        let mut error = None;
+
+        let system_ips: Vec<IpAddr> = match tokio::net::lookup_host((host, 443)).await {
+            Ok(addrs) => addrs.map(|addr| addr.ip()).collect(),
+            Err(e) => {
+                error = Some(format!("系统 DNS 解析失败: {}", e));
+                Vec::new()
+            }
+        };
+
+        let effective_ip = override_ip.or_else(|| system_ips.first().copied());
+        let reachable = match effective_ip {
+            Some(ip) => {
+                let connect = tokio::net::TcpStream::connect(SocketAddr::new(ip, 443));
+                match tokio::time::timeout(std::time::Duration::from_secs(3), connect).await {
+                    Ok(Ok(_)) => true,
+                    Ok(Err(e)) => {
+                        error = Some(format!("连接 {}:443 失败: {}", ip, e));
+                        false
+                    }
+                    Err(_) => {
+                        error = Some(format!("连接 {}:443 超时", ip));
+                        false
+                    }
+                }
+            }
+            None => false,
+        };
+
+        HostDiagnostic {
+            host: host.to_string(),
+            override_ip: override_ip.map(|ip| ip.to_string()),
+            system_ips: system_ips.iter().map(|ip| ip.to_string()).collect(),
+            effective_ip: effective_ip.map(|ip| ip.to_string()),
+            reachable,
+            error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_entries_leave_overrides_empty() {
+        let config = HashMap::from([
+            ("dashscope.aliyuncs.com".to_string(), "not-an-ip".to_string()),
+            (" ".to_string(), "1.2.3.4".to_string()),
+        ]);
+        assert!(DnsOverrides::from_config(&config).is_empty());
+
+        let config = HashMap::from([("DashScope.aliyuncs.com".to_string(), "1.2.3.4".to_string())]);
+        let overrides = DnsOverrides::from_config(&config);
+        assert!(!overrides.is_empty());
+        assert_eq!(overrides.lookup("dashscope.aliyuncs.com"), Some("1.2.3.4".parse().unwrap()));
+        assert!(!DnsOverrides::default().with_proxy(Some("http://127.0.0.1:7890")).is_empty());
+    }
+}
@@ -0,0 +1,191 @@
+// 口头禅统计模块
+// 基于历史原始转录文本做 n-gram 统计（完全本地），与内置常见口头禅词表交叉得到高频口头禅
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::privacy;
+use crate::text_postprocess;
+
+// 统计的最大 n-gram 长度（字符数）
+const MAX_NGRAM: usize = 4;
+// 后台任务攒批间隔，避免每段转录都立刻落盘
+const BATCH_DELAY_MS: u64 = 2000;
+
+/// 内置常见口头禅词表（只有落在词表内的 n-gram 才会被计入）
+const COMMON_FILLERS: &[&str] = &[
+    "嗯", "啊", "呃", "额", "哦", "哎", "唉", "诶",
+    "那个", "这个", "就是", "然后", "其实", "反正", "所以", "对吧", "是吧", "的话",
+    "就是说", "然后呢", "那么", "怎么说", "那什么", "对对对", "你知道",
+    "怎么说呢", "所以说呢", "基本上", "相当于", "说白了", "一般来说",
+];
+
+/// 一个高频口头禅
+#[derive(Debug, Clone, Serialize)]
+pub struct FillerWord {
+    pub word: String,
+    pub count: u64,
+}
+
+/// 口头禅计数（持久化到配置目录）
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FillerStats {
+    /// 已统计的原始文本条数
+    pub texts: u64,
+    /// 词表内 n-gram 的累计出现次数
+    pub counts: BTreeMap<String, u64>,
+}
+
+impl FillerStats {
+    fn path() -> Result<PathBuf> {
+        let config_dir = dirs::config_dir()
+            .ok_or_else(|| anyhow::anyhow!("无法获取配置目录"))?;
+        let app_dir = config_dir.join("PushToTalk");
+        std::fs::create_dir_all(&app_dir)?;
+        Ok(app_dir.join("filler_stats.json"))
+    }
+
+    pub fn load() -> Self {
+        Self::path()
+            .ok()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<()> {
+        self.save_to(&Self::path()?)
+    }
+
+    pub(crate) fn save_to(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        privacy::write_user_data(path, content)?;
+        Ok(())
+    }
+
+    /// 增量统计一段原始文本
+    pub fn ingest(&mut self, text: &str) {
+        self.texts += 1;
+        for (gram, count) in count_ngrams(text) {
+            if COMMON_FILLERS.contains(&gram.as_str()) {
+                *self.counts.entry(gram).or_insert(0) += count;
+            }
+        }
+    }
+
+    /// 出现次数最多的 top_n 个口头禅（次数相同时较长的词优先）
+    pub fn top(&self, top_n: usize) -> Vec<FillerWord> {
+        let mut words: Vec<FillerWord> = self
+            .counts
+            .iter()
+            .filter(|(_, &count)| count > 0)
+            .map(|(word, &count)| FillerWord { word: word.clone(), count })
+            .collect();
+        words.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| b.word.chars().count().cmp(&a.word.chars().count()))
+                .then_with(|| a.word.cmp(&b.word))
+        });
+        words.truncate(top_n);
+        words
+    }
+}
+
+/// 统计文本中 1..=MAX_NGRAM 字符的 n-gram 出现次数（n-gram 不跨越标点与空白）
+fn count_ngrams(text: &str) -> HashMap<String, u64> {
+    let mut counts = HashMap::new();
+    let runs = text.split(|c: char| c.is_whitespace() || c.is_ascii_punctuation() || text_postprocess::is_punctuation(c));
+    for run in runs {
+        let chars: Vec<char> = run.chars().collect();
+        for n in 1..=MAX_NGRAM.min(chars.len()) {
+            for window in chars.windows(n) {
+                *counts.entry(window.iter().collect::<String>()).or_insert(0) += 1;
+            }
+        }
+    }
+    counts
+}
+
+/// 后台口头禅统计器：转录文本投递到低优先级线程中攒批统计并落盘
+pub struct FillerTracker {
+    sender: mpsc::Sender<String>,
+    stats: Arc<Mutex<FillerStats>>,
+}
+
+impl FillerTracker {
+    pub fn new() -> Self {
+        let stats = Arc::new(Mutex::new(FillerStats::load()));
+        let (sender, receiver) = mpsc::channel::<String>();
+
+        let worker_stats = Arc::clone(&stats);
+        std::thread::Builder::new()
+            .name("filler-stats".to_string())
+            .spawn(move || {
+                // 发送端全部释放（应用退出）时 recv 返回错误，线程结束
+                while let Ok(first) = receiver.recv() {
+                    std::thread::sleep(Duration::from_millis(BATCH_DELAY_MS));
+                    let mut stats = worker_stats.lock().unwrap();
+                    stats.ingest(&first);
+                    for text in receiver.try_iter() {
+                        stats.ingest(&text);
+                    }
+                    if let Err(e) = stats.save() {
+                        tracing::warn!("保存口头禅统计失败: {}", e);
+                    }
+                }
+            })
+            .expect("无法启动口头禅统计线程");
+
+        Self { sender, stats }
+    }
+
+    /// 投递一段原始转录文本（不阻塞调用方）；隐私模式下不统计
+    pub fn record(&self, text: &str) {
+        if text.trim().is_empty() || privacy::enabled() {
+            return;
+        }
+        let _ = self.sender.send(text.to_string());
+    }
+
+    pub fn top(&self, top_n: usize) -> Vec<FillerWord> {
+        self.stats.lock().unwrap().top(top_n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ngrams_do_not_cross_punctuation() {
+        let counts = count_ngrams("那个，个人");
+        assert_eq!(counts.get("那个"), Some(&1));
+        assert_eq!(counts.get("个个"), None);
+        assert_eq!(counts.get("个"), Some(&2));
+    }
+
+    #[test]
+    fn ingest_only_counts_common_fillers() {
+        let mut stats = FillerStats::default();
+        stats.ingest("嗯那个我们就是说然后呢，那个方案就是这样");
+        assert_eq!(stats.texts, 1);
+        assert_eq!(stats.counts.get("那个"), Some(&2));
+        assert_eq!(stats.counts.get("就是"), Some(&2));
+        assert_eq!(stats.counts.get("就是说"), Some(&1));
+        assert_eq!(stats.counts.get("方案"), None);
+    }
+
+    #[test]
+    fn top_sorts_by_count_then_length() {
+        let mut stats = FillerStats::default();
+        stats.ingest("然后然后，那个那个那个，嗯");
+        let top: Vec<String> = stats.top(2).into_iter().map(|w| w.word).collect();
+        assert_eq!(top, vec!["那个", "然后"]);
+    }
+}
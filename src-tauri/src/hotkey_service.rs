@@ -1,34 +1,166 @@
 // 全局快捷键监听模块
 use rdev::{listen, Event, EventType, Key};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use anyhow::Result;
 
+// 监听到的按键按下次数（含非快捷键），撤销插入时据此判断插入后用户是否又输入过
+static KEY_PRESSES: AtomicU64 = AtomicU64::new(0);
+
+/// 到目前为止监听到的按键按下次数（监听未启动时恒为 0）
+pub fn key_press_count() -> u64 {
+    KEY_PRESSES.load(Ordering::SeqCst)
+}
+
+/// 组合键中的一个按键（左右修饰键视为同一个）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HotkeyKey {
+    Ctrl,
+    Win,
+    Alt,
+    Shift,
+    Other(Key),
+}
+
+impl HotkeyKey {
+    fn from_rdev(key: Key) -> Self {
+        match key {
+            Key::ControlLeft | Key::ControlRight => HotkeyKey::Ctrl,
+            Key::MetaLeft | Key::MetaRight => HotkeyKey::Win,
+            Key::Alt | Key::AltGr => HotkeyKey::Alt,
+            Key::ShiftLeft | Key::ShiftRight => HotkeyKey::Shift,
+            other => HotkeyKey::Other(other),
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        let name = name.trim().to_ascii_lowercase();
+        let key = match name.as_str() {
+            "ctrl" | "control" => return Some(HotkeyKey::Ctrl),
+            "win" | "meta" | "super" | "cmd" => return Some(HotkeyKey::Win),
+            "alt" | "option" => return Some(HotkeyKey::Alt),
+            "shift" => return Some(HotkeyKey::Shift),
+            "space" => Key::Space,
+            "enter" | "return" => Key::Return,
+            "tab" => Key::Tab,
+            "esc" | "escape" => Key::Escape,
+            "capslock" => Key::CapsLock,
+            "f1" => Key::F1,
+            "f2" => Key::F2,
+            "f3" => Key::F3,
+            "f4" => Key::F4,
+            "f5" => Key::F5,
+            "f6" => Key::F6,
+            "f7" => Key::F7,
+            "f8" => Key::F8,
+            "f9" => Key::F9,
+            "f10" => Key::F10,
+            "f11" => Key::F11,
+            "f12" => Key::F12,
+            _ => {
+                let mut chars = name.chars();
+                let (Some(c), None) = (chars.next(), chars.next()) else {
+                    return None;
+                };
+                match c {
+                    'a'..='z' => letter_key(c)?,
+                    '0'..='9' => digit_key(c)?,
+                    _ => return None,
+                }
+            }
+        };
+        Some(HotkeyKey::Other(key))
+    }
+}
+
+fn letter_key(c: char) -> Option<Key> {
+    const LETTERS: [Key; 26] = [
+        Key::KeyA, Key::KeyB, Key::KeyC, Key::KeyD, Key::KeyE, Key::KeyF, Key::KeyG,
+        Key::KeyH, Key::KeyI, Key::KeyJ, Key::KeyK, Key::KeyL, Key::KeyM, Key::KeyN,
+        Key::KeyO, Key::KeyP, Key::KeyQ, Key::KeyR, Key::KeyS, Key::KeyT, Key::KeyU,
+        Key::KeyV, Key::KeyW, Key::KeyX, Key::KeyY, Key::KeyZ,
+    ];
+    LETTERS.get((c as u8).checked_sub(b'a')? as usize).copied()
+}
+
+fn digit_key(c: char) -> Option<Key> {
+    const DIGITS: [Key; 10] = [
+        Key::Num0, Key::Num1, Key::Num2, Key::Num3, Key::Num4,
+        Key::Num5, Key::Num6, Key::Num7, Key::Num8, Key::Num9,
+    ];
+    DIGITS.get((c as u8).checked_sub(b'0')? as usize).copied()
+}
+
+/// 一组需要同时按住的快捷键
+#[derive(Debug, Clone)]
+pub struct Hotkey {
+    label: String,
+    keys: Vec<HotkeyKey>,
+}
+
+impl Hotkey {
+    /// 解析 "ctrl+win"、"ctrl+alt+space" 这样的组合键
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut keys = Vec::new();
+        for part in spec.split('+') {
+            let key = HotkeyKey::parse(part)
+                .ok_or_else(|| anyhow::anyhow!("无法识别的按键 \"{}\"（快捷键: {}）", part.trim(), spec))?;
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        if keys.is_empty() {
+            anyhow::bail!("快捷键不能为空");
+        }
+        Ok(Self {
+            label: spec.trim().to_string(),
+            keys,
+        })
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    fn is_held(&self, pressed: &[HotkeyKey]) -> bool {
+        self.keys.iter().all(|key| pressed.contains(key))
+    }
+}
+
 pub struct HotkeyService {
-    is_recording: Arc<Mutex<bool>>,
-    ctrl_pressed: Arc<Mutex<bool>>,
-    win_pressed: Arc<Mutex<bool>>,
+    // 当前正在录音的快捷键组序号
+    active: Arc<Mutex<Option<usize>>>,
+    pressed: Arc<Mutex<Vec<HotkeyKey>>>,
+    // 暂停监听：不再触发新的录音，已在录音的快捷键松开时照常结束
+    paused: Arc<AtomicBool>,
 }
 
 impl HotkeyService {
-    pub fn new() -> Self {
+    /// `paused` 由上层持有，置位即暂停响应快捷键（录音器等资源不受影响）
+    pub fn new(paused: Arc<AtomicBool>) -> Self {
         Self {
-            is_recording: Arc::new(Mutex::new(false)),
-            ctrl_pressed: Arc::new(Mutex::new(false)),
-            win_pressed: Arc::new(Mutex::new(false)),
+            active: Arc::new(Mutex::new(None)),
+            pressed: Arc::new(Mutex::new(Vec::new())),
+            paused,
         }
     }
 
-    pub fn start<F1, F2>(&self, on_start: F1, on_stop: F2) -> Result<()>
+    /// 注册多组快捷键，回调参数为触发的快捷键在 `hotkeys` 中的序号；同一时间只有一组处于录音状态
+    pub fn start<F1, F2>(&self, hotkeys: Vec<Hotkey>, on_start: F1, on_stop: F2) -> Result<()>
     where
-        F1: Fn() + Send + 'static,
-        F2: Fn() + Send + 'static,
+        F1: Fn(usize) + Send + 'static,
+        F2: Fn(usize) + Send + 'static,
     {
-        tracing::info!("启动快捷键监听服务 (Ctrl+Win)");
+        if hotkeys.is_empty() {
+            anyhow::bail!("未配置任何快捷键");
+        }
+        let labels: Vec<&str> = hotkeys.iter().map(|h| h.label()).collect();
+        tracing::info!("启动快捷键监听服务 ({})", labels.join(", "));
 
-        let is_recording = Arc::clone(&self.is_recording);
-        let ctrl_pressed = Arc::clone(&self.ctrl_pressed);
-        let win_pressed = Arc::clone(&self.win_pressed);
+        let active = Arc::clone(&self.active);
+        let pressed = Arc::clone(&self.pressed);
+        let paused = Arc::clone(&self.paused);
 
         thread::spawn(move || {
             tracing::info!("快捷键监听线程已启动");
@@ -43,49 +175,44 @@ impl HotkeyService {
 
                 match event.event_type {
                     EventType::KeyPress(key) => {
-                        match key {
-                            Key::ControlLeft | Key::ControlRight => {
-                                tracing::debug!("检测到 Ctrl 键按下");
-                                *ctrl_pressed.lock().unwrap() = true;
-                            }
-                            Key::MetaLeft | Key::MetaRight => {
-                                tracing::debug!("检测到 Win 键按下");
-                                *win_pressed.lock().unwrap() = true;
-                            }
-                            _ => {}
+                        KEY_PRESSES.fetch_add(1, Ordering::SeqCst);
+                        let key = HotkeyKey::from_rdev(key);
+                        let mut pressed = pressed.lock().unwrap();
+                        if !pressed.contains(&key) {
+                            tracing::debug!("检测到按键按下: {:?}", key);
+                            pressed.push(key);
                         }
 
-                        // 检查是否按下了 Ctrl+Win
-                        let ctrl = *ctrl_pressed.lock().unwrap();
-                        let win = *win_pressed.lock().unwrap();
-                        let mut recording = is_recording.lock().unwrap();
-
-                        if ctrl && win && !*recording {
-                            *recording = true;
-                            tracing::info!("检测到快捷键按下: Ctrl+Win");
-                            on_start();
+                        // 多组同时满足时，按键数最多的一组优先（Ctrl+Win+Alt 优先于 Ctrl+Win）
+                        // 暂停期间仍记录按下的键，恢复后组合键判断不会错位
+                        let mut active = active.lock().unwrap();
+                        if active.is_none() && !paused.load(Ordering::SeqCst) {
+                            let matched = hotkeys
+                                .iter()
+                                .enumerate()
+                                .filter(|(_, hotkey)| hotkey.is_held(&pressed))
+                                .max_by_key(|(_, hotkey)| hotkey.keys.len())
+                                .map(|(index, _)| index);
+                            if let Some(index) = matched {
+                                *active = Some(index);
+                                tracing::info!("检测到快捷键按下: {}", hotkeys[index].label());
+                                on_start(index);
+                            }
                         }
                     }
                     EventType::KeyRelease(key) => {
-                        match key {
-                            Key::ControlLeft | Key::ControlRight => {
-                                *ctrl_pressed.lock().unwrap() = false;
-                            }
-                            Key::MetaLeft | Key::MetaRight => {
-                                *win_pressed.lock().unwrap() = false;
-                            }
-                            _ => {}
-                        }
+                        let key = HotkeyKey::from_rdev(key);
+                        let mut pressed = pressed.lock().unwrap();
+                        pressed.retain(|k| *k != key);
 
-                        // 检查是否松开了快捷键
-                        let ctrl = *ctrl_pressed.lock().unwrap();
-                        let win = *win_pressed.lock().unwrap();
-                        let mut recording = is_recording.lock().unwrap();
-
-                        if *recording && (!ctrl || !win) {
-                            *recording = false;
-                            tracing::info!("检测到快捷键释放");
-                            on_stop();
+                        // 检查是否松开了当前快捷键
+                        let mut active = active.lock().unwrap();
+                        if let Some(index) = *active {
+                            if !hotkeys[index].is_held(&pressed) {
+                                *active = None;
+                                tracing::info!("检测到快捷键释放: {}", hotkeys[index].label());
+                                on_stop(index);
+                            }
                         }
                     }
                     _ => {}
@@ -0,0 +1,745 @@
+// 录音输入源：麦克风、系统回环（默认输出设备正在播放的声音）或两者混合
+// Windows 上 cpal 的 WASAPI 后端在输出设备上建立输入流即以 loopback 方式采集，不必另外引入 wasapi crate。
+// mix 模式以麦克风流为时钟：回环流在自己的回调里转成单声道、重采样到麦克风采样率，经无锁环形队列交给
+// 麦克风回调按增益叠加到每个声道。两个录音器拿到的都是交错的 f32 样本，之后的流程与只录麦克风相同。
+// audio.keep_stream_open 开启时采集流常驻（ResidentInput），每次录音只挂上 / 取下本次的回调；
+// 配置了 audio.pre_roll_ms 时常驻流在空闲期间保留最近一小段样本，挂上回调时先交给本次录音
+
+use anyhow::Result;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{BufferSize, Device, SampleFormat, Stream, StreamConfig, SupportedBufferSize, SupportedStreamConfig};
+use rtrb::{Consumer, Producer, RingBuffer};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::audio_processing;
+use crate::audio_recorder::{default_input_device_name, select_input_device};
+use crate::audio_source::{Capture, StreamInfo};
+use crate::config::{AudioConfig, ChannelSelection, InputSource, SourceMix};
+use crate::resampler::Resampler;
+
+// 回环队列容纳的时长；两路设备时钟不同步，积压超过 LOOPBACK_MAX_LAG_MS 时丢掉最早的样本，回环声音不会越来越滞后
+const LOOPBACK_RING_MS: u32 = 1000;
+const LOOPBACK_MAX_LAG_MS: u32 = 100;
+// 混合增益范围
+const MIX_GAIN_RANGE: (f32, f32) = (0.0, 8.0);
+// 预滚动时长上限
+const MAX_PRE_ROLL_MS: u32 = 2000;
+
+/// 已选好设备、尚未开始采集的输入源
+pub struct PreparedInput {
+    device: Device,
+    config: SupportedStreamConfig,
+    // mix 模式下叠加到麦克风上的系统回环
+    loopback: Option<(Device, SupportedStreamConfig)>,
+    mix: SourceMix,
+    // 固定采集缓冲区（帧），0 为设备默认
+    buffer_size: u32,
+    /// 交给录音器的样本参数：回环模式为输出设备的参数，mix 模式为麦克风的参数
+    pub info: StreamInfo,
+}
+
+/// 按 `audio.source` 选择设备；`device` 为用户选择的输入设备（None 为默认设备），只录系统回环时不使用
+pub fn prepare(audio: &AudioConfig, device: Option<&str>) -> Result<PreparedInput> {
+    let (device, config, missing_device, loopback) = match audio.source {
+        InputSource::Microphone => {
+            let (device, missing_device) = select_input_device(device)?;
+            let config = input_config(&device)?;
+            (device, config, missing_device, None)
+        }
+        InputSource::SystemLoopback => {
+            let (device, config) = loopback_device()?;
+            (device, config, None, None)
+        }
+        InputSource::Mix => {
+            let (device, missing_device) = select_input_device(device)?;
+            let config = input_config(&device)?;
+            (device, config, missing_device, Some(loopback_device()?))
+        }
+    };
+
+    tracing::info!("设备支持的配置: {:?}", config);
+    let info = StreamInfo {
+        sample_rate: config.sample_rate().0,
+        channels: config.channels(),
+        device_name: device.name().unwrap_or_default(),
+        missing_device,
+        pre_roll: Duration::ZERO,
+    };
+    match (audio.source, &loopback) {
+        (InputSource::SystemLoopback, _) => tracing::info!("录音输入源: 系统回环（{}）", info.device_name),
+        (InputSource::Mix, Some((output, _))) => tracing::info!(
+            "录音输入源: 麦克风（{}）+ 系统回环（{}），增益 {:.2} / {:.2}",
+            info.device_name,
+            output.name().unwrap_or_default(),
+            audio.source_mix.microphone_gain,
+            audio.source_mix.loopback_gain
+        ),
+        _ => {}
+    }
+    Ok(PreparedInput { device, config, loopback, mix: audio.source_mix, buffer_size: audio.buffer_size, info })
+}
+
+fn input_config(device: &Device) -> Result<SupportedStreamConfig> {
+    device
+        .default_input_config()
+        .map_err(|e| anyhow::anyhow!("无法获取默认音频配置: {}", e))
+}
+
+/// 系统回环采集的设备：默认输出设备，按它的输出格式采集
+fn loopback_device() -> Result<(Device, SupportedStreamConfig)> {
+    if !cfg!(windows) {
+        return Err(anyhow::anyhow!("系统回环录音仅支持 Windows"));
+    }
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or_else(|| anyhow::anyhow!("没有找到默认音频输出设备"))?;
+    let config = device
+        .default_output_config()
+        .map_err(|e| anyhow::anyhow!("无法获取输出设备的音频配置: {}", e))?;
+    Ok((device, config))
+}
+
+impl PreparedInput {
+    /// 开始采集：样本统一转成交错的 f32 交给 `on_samples`，采集流错误交给 `on_error`（两路流共用）
+    pub fn start<D, E>(self, mut on_samples: D, on_error: E) -> Result<Capture>
+    where
+        D: FnMut(&[f32]) + Send + 'static,
+        E: FnMut(cpal::StreamError) + Clone + Send + 'static,
+    {
+        let Some((output, output_config)) = self.loopback else {
+            let stream = build_stream(&self.device, &self.config, self.buffer_size, on_samples, on_error)?;
+            stream.play()?;
+            return Ok(Box::new(stream));
+        };
+
+        let (mut feed, mut mix) = loopback_mix(
+            self.info.sample_rate,
+            output_config.sample_rate().0,
+            output_config.channels(),
+            self.mix,
+        );
+        let loopback =
+            build_stream(&output, &output_config, self.buffer_size, move |data| feed.push(data), on_error.clone())?;
+        let channels = self.info.channels;
+        let mut buffer = Vec::new();
+        let microphone = build_stream(
+            &self.device,
+            &self.config,
+            self.buffer_size,
+            move |data| {
+                buffer.clear();
+                buffer.extend_from_slice(data);
+                mix.apply(&mut buffer, channels);
+                on_samples(&buffer);
+            },
+            on_error,
+        )?;
+        loopback.play()?;
+        microphone.play()?;
+        Ok(Box::new((microphone, loopback)))
+    }
+}
+
+/// 建立输入流：配置了 buffer_size 时先按固定缓冲区建立，设备不支持时回退默认缓冲区，不会因此打不开流
+fn build_stream<D, E>(
+    device: &Device,
+    supported: &SupportedStreamConfig,
+    buffer_size: u32,
+    on_samples: D,
+    on_error: E,
+) -> Result<Stream>
+where
+    D: FnMut(&[f32]) + Send + 'static,
+    E: FnMut(cpal::StreamError) + Clone + Send + 'static,
+{
+    // 建立失败时 cpal 会丢弃传入的回调，用户回调先放在共享槽里，由真正建立起来的流在首次回调时取走
+    let slot = Arc::new(Mutex::new(Some(on_samples)));
+    let (channels, sample_rate) = (supported.channels(), supported.sample_rate().0);
+    let format = supported.sample_format();
+    if let Some(frames) = fixed_buffer_frames(buffer_size, supported.buffer_size()) {
+        let config = StreamConfig { buffer_size: BufferSize::Fixed(frames), ..supported.config() };
+        match build_with(device, format, &config, handoff(&slot, channels, sample_rate), on_error.clone()) {
+            Ok(stream) => {
+                tracing::info!("采集缓冲区固定为 {} 帧", frames);
+                return Ok(stream);
+            }
+            Err(e) => tracing::warn!("设备不支持 {} 帧的固定缓冲区，改用默认缓冲区: {}", frames, e),
+        }
+    }
+    build_with(device, format, &supported.config(), handoff(&slot, channels, sample_rate), on_error)
+}
+
+/// 请求的固定缓冲区按设备支持的范围钳制；0 表示使用默认缓冲区
+fn fixed_buffer_frames(requested: u32, supported: &SupportedBufferSize) -> Option<u32> {
+    if requested == 0 {
+        return None;
+    }
+    let frames = match *supported {
+        SupportedBufferSize::Range { min, max } => requested.clamp(min, max.max(min)),
+        SupportedBufferSize::Unknown => requested,
+    };
+    if frames != requested {
+        tracing::info!("设备支持的缓冲区范围为 {:?}，{} 帧调整为 {} 帧", supported, requested, frames);
+    }
+    Some(frames)
+}
+
+/// 取出共享槽里的用户回调，并把首次回调的块大小（实际协商到的缓冲区）记入日志
+fn handoff<D>(slot: &Arc<Mutex<Option<D>>>, channels: u16, sample_rate: u32) -> impl FnMut(&[f32]) + Send + 'static
+where
+    D: FnMut(&[f32]) + Send + 'static,
+{
+    let slot = Arc::clone(slot);
+    let mut callback = None;
+    move |data: &[f32]| {
+        if callback.is_none() {
+            callback = slot.lock().unwrap().take();
+            let frames = data.len() / channels.max(1) as usize;
+            tracing::info!("采集回调每块 {} 帧（约 {:.1}ms）", frames, frames as f32 * 1000.0 / sample_rate as f32);
+        }
+        if let Some(callback) = callback.as_mut() {
+            callback(data);
+        }
+    }
+}
+
+/// 本次录音使用的输入：常驻流，或按次建立的采集流
+pub enum Input<'a> {
+    Resident(&'a ResidentInput),
+    Fresh(PreparedInput),
+}
+
+impl Input<'_> {
+    pub fn info(&self) -> &StreamInfo {
+        match self {
+            Input::Resident(resident) => &resident.info,
+            Input::Fresh(input) => &input.info,
+        }
+    }
+
+    /// 开始本次录音的采集，返回的句柄释放即停止（常驻流只取下回调，不释放设备），
+    /// 以及先交给 `on_samples` 的预滚动时长（按次建立的采集流没有预滚动）
+    pub fn start<D, E>(self, on_samples: D, on_error: E) -> Result<(Capture, Duration)>
+    where
+        D: FnMut(&[f32]) + Send + 'static,
+        E: FnMut(cpal::StreamError) + Clone + Send + 'static,
+    {
+        match self {
+            Input::Resident(resident) => {
+                let (capture, samples) = resident.slot.attach(on_samples, on_error);
+                let pre_roll = samples_duration(samples, resident.info.sample_rate, resident.info.channels);
+                if !pre_roll.is_zero() {
+                    tracing::debug!("录音开头拼接预滚动 {}ms", pre_roll.as_millis());
+                }
+                Ok((capture, pre_roll))
+            }
+            Input::Fresh(input) => Ok((input.start(on_samples, on_error)?, Duration::ZERO)),
+        }
+    }
+}
+
+/// 选择本次录音的输入：开启 keep_stream_open 时复用常驻流（不能复用时重建），否则释放常驻流、按次建立
+pub fn open<'a>(resident: &'a mut Option<ResidentInput>, audio: &AudioConfig, device: Option<&str>) -> Result<Input<'a>> {
+    if !audio.keep_stream_open {
+        release(resident);
+        return prepare(audio, device).map(Input::Fresh);
+    }
+    // 不能复用时先释放旧流再打开，独占设备时才不会冲突
+    if !resident.as_ref().is_some_and(|resident| resident.reusable(audio, device)) {
+        release(resident);
+    }
+    let input = match resident.take() {
+        Some(input) => input,
+        None => ResidentInput::open(audio, device)?,
+    };
+    Ok(Input::Resident(resident.insert(input)))
+}
+
+/// 空闲时按配置准备常驻流（启动服务、更新配置时调用）：开启时提前建立，关闭时释放
+pub fn standby(resident: &mut Option<ResidentInput>, audio: &AudioConfig, device: Option<&str>) {
+    if !audio.keep_stream_open {
+        release(resident);
+        return;
+    }
+    if resident.as_ref().is_some_and(|resident| resident.reusable(audio, device)) {
+        return;
+    }
+    release(resident);
+    match ResidentInput::open(audio, device) {
+        Ok(input) => *resident = Some(input),
+        Err(e) => tracing::warn!("建立常驻录音流失败，开始录音时再试: {}", e),
+    }
+}
+
+fn release(resident: &mut Option<ResidentInput>) {
+    if let Some(input) = resident.take() {
+        tracing::info!("释放常驻录音流: {}", input.info.device_name);
+    }
+}
+
+/// 常驻采集流：两次录音之间不释放，回调里只有挂上了本次录音的回调时才写入
+pub struct ResidentInput {
+    _capture: Capture,
+    info: StreamInfo,
+    slot: CallbackSlot,
+    // 建立时的输入配置，变化后重建
+    key: ResidentKey,
+    // 采集流出过错（如设备拔出），下次录音时重建
+    failed: Arc<AtomicBool>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct ResidentKey {
+    device: Option<String>,
+    source: InputSource,
+    source_mix: SourceMix,
+    buffer_size: u32,
+    pre_roll_ms: u32,
+}
+
+impl ResidentKey {
+    fn new(audio: &AudioConfig, device: Option<&str>) -> Self {
+        Self {
+            device: device.map(str::trim).filter(|name| !name.is_empty()).map(str::to_string),
+            source: audio.source,
+            source_mix: audio.source_mix,
+            buffer_size: audio.buffer_size,
+            pre_roll_ms: audio.pre_roll_ms,
+        }
+    }
+}
+
+impl ResidentInput {
+    pub fn info(&self) -> &StreamInfo {
+        &self.info
+    }
+
+    fn open(audio: &AudioConfig, device: Option<&str>) -> Result<Self> {
+        let input = prepare(audio, device)?;
+        let info = input.info.clone();
+        let slot = CallbackSlot::with_pre_roll(pre_roll_samples(audio.pre_roll_ms, info.sample_rate, info.channels));
+        let failed = Arc::new(AtomicBool::new(false));
+        let on_samples = {
+            let slot = slot.clone();
+            move |data: &[f32]| slot.samples(data)
+        };
+        let on_error = {
+            let (slot, failed) = (slot.clone(), Arc::clone(&failed));
+            move |err: cpal::StreamError| {
+                failed.store(true, Ordering::SeqCst);
+                if let Some(err) = slot.error(err) {
+                    tracing::warn!("常驻录音流出错，下次录音时重建: {}", err);
+                }
+            }
+        };
+        let capture = input.start(on_samples, on_error)?;
+        tracing::info!("常驻录音流已建立: {} ({}Hz, {} 声道)", info.device_name, info.sample_rate, info.channels);
+        Ok(Self { _capture: capture, info, slot, key: ResidentKey::new(audio, device), failed })
+    }
+
+    /// 能否继续使用：未出错、输入配置未变、选择的设备没有回退，跟随系统默认设备时默认设备也未变
+    fn reusable(&self, audio: &AudioConfig, device: Option<&str>) -> bool {
+        if self.failed.load(Ordering::SeqCst) || self.info.missing_device.is_some() {
+            return false;
+        }
+        let key = ResidentKey::new(audio, device);
+        if key != self.key {
+            return false;
+        }
+        let follows_default = key.device.is_none() || key.source == InputSource::SystemLoopback;
+        if !follows_default {
+            return true;
+        }
+        let default = match key.source {
+            InputSource::SystemLoopback => cpal::default_host().default_output_device().and_then(|device| device.name().ok()),
+            InputSource::Microphone | InputSource::Mix => default_input_device_name(),
+        };
+        let unchanged = default.as_deref() == Some(self.info.device_name.as_str());
+        if !unchanged {
+            tracing::info!("系统默认设备已变为 {:?}，重建常驻录音流", default);
+        }
+        unchanged
+    }
+}
+
+type SampleCallback = Box<dyn FnMut(&[f32]) + Send>;
+type ErrorCallback = Box<dyn FnMut(cpal::StreamError) + Send>;
+
+/// 预滚动保留的样本数（交错，整帧）；0 表示不保留
+fn pre_roll_samples(pre_roll_ms: u32, sample_rate: u32, channels: u16) -> usize {
+    let frames = sample_rate as u64 * pre_roll_ms.min(MAX_PRE_ROLL_MS) as u64 / 1000;
+    frames as usize * channels.max(1) as usize
+}
+
+fn samples_duration(samples: usize, sample_rate: u32, channels: u16) -> Duration {
+    let frames = (samples / channels.max(1) as usize) as u64;
+    Duration::from_micros(frames * 1_000_000 / sample_rate.max(1) as u64)
+}
+
+/// 常驻流回调转发到的本次录音回调；只在挂上 / 取下时与采集回调竞争锁
+#[derive(Clone, Default)]
+struct CallbackSlot(Arc<Mutex<SlotState>>);
+
+#[derive(Default)]
+struct SlotState {
+    callbacks: Option<(SampleCallback, ErrorCallback)>,
+    // 空闲期间最近的样本（预滚动），容量为 0 时不保留任何样本
+    pre_roll: VecDeque<f32>,
+    pre_roll_capacity: usize,
+}
+
+impl CallbackSlot {
+    fn with_pre_roll(capacity: usize) -> Self {
+        Self(Arc::new(Mutex::new(SlotState {
+            callbacks: None,
+            pre_roll: VecDeque::with_capacity(capacity),
+            pre_roll_capacity: capacity,
+        })))
+    }
+
+    fn samples(&self, data: &[f32]) {
+        let mut state = self.0.lock().unwrap();
+        let state = &mut *state;
+        match state.callbacks.as_mut() {
+            Some((on_samples, _)) => on_samples(data),
+            None if state.pre_roll_capacity > 0 => {
+                // 只保留最近 capacity 个样本；采集回调每块都是整帧，丢掉的也是整帧
+                let data = &data[data.len().saturating_sub(state.pre_roll_capacity)..];
+                let overflow = (state.pre_roll.len() + data.len()).saturating_sub(state.pre_roll_capacity);
+                state.pre_roll.drain(..overflow);
+                state.pre_roll.extend(data);
+            }
+            None => {}
+        }
+    }
+
+    /// 转发给本次录音；没有在录音时交回调用方处理
+    fn error(&self, err: cpal::StreamError) -> Option<cpal::StreamError> {
+        match self.0.lock().unwrap().callbacks.as_mut() {
+            Some((_, on_error)) => {
+                on_error(err);
+                None
+            }
+            None => Some(err),
+        }
+    }
+
+    /// 挂上本次录音的回调，先把预滚动交给它，返回交出的样本数；
+    /// 与采集回调在同一把锁下切换，预滚动与之后的样本首尾相接，中间不缺也不重
+    fn attach<D, E>(&self, mut on_samples: D, on_error: E) -> (Capture, usize)
+    where
+        D: FnMut(&[f32]) + Send + 'static,
+        E: FnMut(cpal::StreamError) + Send + 'static,
+    {
+        let mut state = self.0.lock().unwrap();
+        let pre_roll = state.pre_roll.len();
+        if pre_roll > 0 {
+            on_samples(state.pre_roll.make_contiguous());
+            state.pre_roll.clear();
+        }
+        state.callbacks = Some((Box::new(on_samples), Box::new(on_error)));
+        (Box::new(Attachment(self.clone())), pre_roll)
+    }
+}
+
+/// 挂在常驻流上的本次录音，释放时取下回调（回调持有的通道等随之关闭）
+struct Attachment(CallbackSlot);
+
+impl Drop for Attachment {
+    fn drop(&mut self) {
+        // 在锁外释放回调
+        let callbacks = self.0 .0.lock().unwrap().callbacks.take();
+        drop(callbacks);
+    }
+}
+
+/// 按采样格式建立输入流，样本转成 f32（F32 直接交给回调，其余格式经复用的缓冲区转换）
+fn build_with<D, E>(device: &Device, format: SampleFormat, config: &StreamConfig, mut on_samples: D, on_error: E) -> Result<Stream>
+where
+    D: FnMut(&[f32]) + Send + 'static,
+    E: FnMut(cpal::StreamError) + Send + 'static,
+{
+    let stream = match format {
+        SampleFormat::F32 => device.build_input_stream(
+            config,
+            move |data: &[f32], _: &cpal::InputCallbackInfo| on_samples(data),
+            on_error,
+            None,
+        )?,
+        SampleFormat::F64 => build_converting::<f64, _, _>(device, config, on_samples, on_error)?,
+        SampleFormat::I8 => build_converting::<i8, _, _>(device, config, on_samples, on_error)?,
+        SampleFormat::U8 => build_converting::<u8, _, _>(device, config, on_samples, on_error)?,
+        SampleFormat::I16 => build_converting::<i16, _, _>(device, config, on_samples, on_error)?,
+        SampleFormat::U16 => build_converting::<u16, _, _>(device, config, on_samples, on_error)?,
+        SampleFormat::I32 => build_converting::<i32, _, _>(device, config, on_samples, on_error)?,
+        SampleFormat::U32 => build_converting::<u32, _, _>(device, config, on_samples, on_error)?,
+        format => return Err(anyhow::anyhow!("不支持的采样格式: {}", format)),
+    };
+    Ok(stream)
+}
+
+fn build_converting<T, D, E>(device: &Device, config: &StreamConfig, mut on_samples: D, on_error: E) -> Result<Stream, cpal::BuildStreamError>
+where
+    T: CaptureSample,
+    D: FnMut(&[f32]) + Send + 'static,
+    E: FnMut(cpal::StreamError) + Send + 'static,
+{
+    let mut buffer = Vec::new();
+    device.build_input_stream(
+        config,
+        move |data: &[T], _: &cpal::InputCallbackInfo| {
+            buffer.clear();
+            buffer.extend(data.iter().map(|&sample| sample.to_f32()));
+            on_samples(&buffer);
+        },
+        on_error,
+        None,
+    )
+}
+
+/// 采集样本归一化到 [-1, 1)：有符号整数除以 2^(位数-1)，无符号整数先减去零点偏移 2^(位数-1)
+trait CaptureSample: cpal::SizedSample + Send + 'static {
+    fn to_f32(self) -> f32;
+}
+
+impl CaptureSample for i8 {
+    fn to_f32(self) -> f32 {
+        self as f32 / 128.0
+    }
+}
+
+impl CaptureSample for u8 {
+    fn to_f32(self) -> f32 {
+        (self as f32 - 128.0) / 128.0
+    }
+}
+
+impl CaptureSample for i16 {
+    fn to_f32(self) -> f32 {
+        self as f32 / 32768.0
+    }
+}
+
+impl CaptureSample for u16 {
+    fn to_f32(self) -> f32 {
+        (self as f32 - 32768.0) / 32768.0
+    }
+}
+
+// 32 位整数经 f64 换算，避免 f32 尾数不够时零点附近失真
+impl CaptureSample for i32 {
+    fn to_f32(self) -> f32 {
+        (self as f64 / 2_147_483_648.0) as f32
+    }
+}
+
+impl CaptureSample for u32 {
+    fn to_f32(self) -> f32 {
+        ((self as f64 - 2_147_483_648.0) / 2_147_483_648.0) as f32
+    }
+}
+
+impl CaptureSample for f64 {
+    fn to_f32(self) -> f32 {
+        self as f32
+    }
+}
+
+/// 回环流一侧：转成单声道、重采样到麦克风采样率后写入队列，队列满时丢弃
+struct LoopbackFeed {
+    channels: u16,
+    resampler: Resampler,
+    producer: Producer<f32>,
+}
+
+impl LoopbackFeed {
+    fn push(&mut self, data: &[f32]) {
+        let mono = audio_processing::to_mono(data, self.channels, ChannelSelection::Mix);
+        let samples = self.resampler.process(&mono);
+        let writable = samples.len().min(self.producer.slots());
+        if let Ok(chunk) = self.producer.write_chunk_uninit(writable) {
+            chunk.fill_from_iter(samples);
+        }
+    }
+}
+
+/// 麦克风流一侧：按增益把回环样本叠加到麦克风的每个声道；回环暂时没有数据（系统没在播放声音）时按静音处理
+struct LoopbackMix {
+    consumer: Consumer<f32>,
+    microphone_gain: f32,
+    loopback_gain: f32,
+    max_lag: usize,
+}
+
+impl LoopbackMix {
+    fn apply(&mut self, samples: &mut [f32], channels: u16) {
+        let channels = channels.max(1) as usize;
+        let frames = samples.len() / channels;
+
+        // 回环积压过多（先于麦克风启动或设备时钟偏快）时丢掉最早的部分
+        let excess = self.consumer.slots().saturating_sub(frames + self.max_lag);
+        if let Ok(chunk) = self.consumer.read_chunk(excess) {
+            chunk.commit_all();
+        }
+
+        let available = self.consumer.slots().min(frames);
+        let Ok(chunk) = self.consumer.read_chunk(available) else {
+            return;
+        };
+        let (first, second) = chunk.as_slices();
+        let loopback = first.iter().chain(second).copied().chain(std::iter::repeat(0.0));
+        for (frame, loopback) in samples.chunks_mut(channels).zip(loopback) {
+            for sample in frame {
+                *sample = (*sample * self.microphone_gain + loopback * self.loopback_gain).clamp(-1.0, 1.0);
+            }
+        }
+        chunk.commit_all();
+    }
+}
+
+/// 建立回环与麦克风之间的混合队列
+fn loopback_mix(microphone_rate: u32, loopback_rate: u32, loopback_channels: u16, mix: SourceMix) -> (LoopbackFeed, LoopbackMix) {
+    let (producer, consumer) = RingBuffer::new((microphone_rate * LOOPBACK_RING_MS / 1000) as usize);
+    let gain = |gain: f32| if gain.is_finite() { gain.clamp(MIX_GAIN_RANGE.0, MIX_GAIN_RANGE.1) } else { 1.0 };
+    let feed = LoopbackFeed {
+        channels: loopback_channels,
+        resampler: Resampler::new(loopback_rate, microphone_rate),
+        producer,
+    };
+    let mix = LoopbackMix {
+        consumer,
+        microphone_gain: gain(mix.microphone_gain),
+        loopback_gain: gain(mix.loopback_gain),
+        max_lag: (microphone_rate * LOOPBACK_MAX_LAG_MS / 1000) as usize,
+    };
+    (feed, mix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mixes_loopback_into_every_microphone_channel() {
+        let gains = SourceMix { microphone_gain: 0.5, loopback_gain: 2.0 };
+        let (mut feed, mut mix) = loopback_mix(48000, 48000, 2, gains);
+
+        // 回环立体声 10ms（左右取平均），麦克风立体声 10ms
+        feed.push(&[0.1, 0.3].repeat(480));
+        let mut microphone = [0.4, -0.4].repeat(480);
+        mix.apply(&mut microphone, 2);
+        assert!(microphone.chunks(2).all(|frame| (frame[0] - 0.6).abs() < 1e-6 && (frame[1] - 0.2).abs() < 1e-6));
+
+        // 回环没有数据时只剩麦克风
+        let mut microphone = [0.4, -0.4].repeat(480);
+        mix.apply(&mut microphone, 2);
+        assert!(microphone.chunks(2).all(|frame| (frame[0] - 0.2).abs() < 1e-6 && (frame[1] + 0.2).abs() < 1e-6));
+
+        // 积压超过 100ms 时丢掉最早的样本，只保留最近的
+        for block in 0..50 {
+            feed.push(&[block as f32 / 100.0; 960]);
+        }
+        let mut microphone = vec![0.0; 480];
+        mix.apply(&mut microphone, 1);
+        assert!((microphone[0] - 2.0 * 0.39).abs() < 1e-6);
+        assert_eq!(mix.consumer.slots(), 4800);
+    }
+
+    #[test]
+    fn fixed_buffer_is_clamped_to_device_range() {
+        let range = SupportedBufferSize::Range { min: 64, max: 4096 };
+        assert_eq!(fixed_buffer_frames(0, &range), None);
+        assert_eq!(fixed_buffer_frames(256, &range), Some(256));
+        assert_eq!(fixed_buffer_frames(16, &range), Some(64));
+        assert_eq!(fixed_buffer_frames(8192, &range), Some(4096));
+        assert_eq!(fixed_buffer_frames(256, &SupportedBufferSize::Unknown), Some(256));
+    }
+
+    #[test]
+    fn attachment_forwards_samples_until_released() {
+        let slot = CallbackSlot::default();
+        slot.samples(&[0.5; 4]);
+
+        let (tx, rx) = crossbeam_channel::unbounded::<usize>();
+        let (attachment, pre_roll) = slot.attach(move |data| tx.send(data.len()).unwrap(), |_| {});
+        assert_eq!(pre_roll, 0);
+        slot.samples(&[0.5; 8]);
+        assert_eq!(rx.try_recv(), Ok(8));
+
+        // 取下后回调（连同它持有的发送端）立即释放，之后的样本不再写入
+        drop(attachment);
+        slot.samples(&[0.5; 16]);
+        assert_eq!(rx.try_recv(), Err(crossbeam_channel::TryRecvError::Disconnected));
+        assert!(slot.error(cpal::StreamError::DeviceNotAvailable).is_some());
+    }
+
+    #[test]
+    fn pre_roll_joins_live_samples_without_gap() {
+        // 16kHz 立体声正弦，10ms 一块；预滚动 50ms
+        let signal: Vec<f32> = (0..16000 * 2).map(|i| ((i / 2) as f32 * 0.05).sin() * 0.5).collect();
+        let blocks: Vec<&[f32]> = signal.chunks(320).collect();
+        let capacity = pre_roll_samples(50, 16000, 2);
+        assert_eq!(capacity, 1600);
+
+        let slot = CallbackSlot::with_pre_roll(capacity);
+        blocks[..10].iter().for_each(|block| slot.samples(block));
+        let recorded = Arc::new(Mutex::new(Vec::new()));
+        let (attachment, pre_roll) = {
+            let recorded = Arc::clone(&recorded);
+            slot.attach(move |data| recorded.lock().unwrap().extend_from_slice(data), |_| {})
+        };
+        blocks[10..13].iter().for_each(|block| slot.samples(block));
+        drop(attachment);
+
+        // 录到的是按键前最近 50ms 加按键后的样本，与原信号逐个相同：拼接处不缺不重，也就没有爆音
+        assert_eq!(samples_duration(pre_roll, 16000, 2), Duration::from_millis(50));
+        assert_eq!(*recorded.lock().unwrap(), signal[10 * 320 - 1600..13 * 320]);
+
+        // 常驻流刚建立、预滚动还没攒满时只交出已有的部分；下次录音前重新开始攒
+        let (_attachment, pre_roll) = slot.attach(|_| {}, |_| {});
+        assert_eq!(pre_roll, 0);
+        let slot = CallbackSlot::with_pre_roll(capacity);
+        blocks[..2].iter().for_each(|block| slot.samples(block));
+        assert_eq!(slot.attach(|_| {}, |_| {}).1, 640);
+
+        // 关闭预滚动时空闲期间不保留任何样本
+        let slot = CallbackSlot::with_pre_roll(pre_roll_samples(0, 16000, 2));
+        blocks[..10].iter().for_each(|block| slot.samples(block));
+        assert!(slot.0.lock().unwrap().pre_roll.is_empty());
+        assert_eq!(slot.attach(|_| {}, |_| {}).1, 0);
+    }
+
+    #[test]
+    fn integer_formats_normalize_around_zero() {
+        // 极值映射到 -1 与略小于 1，无符号格式的零点（中点）映射到 0
+        assert_eq!((i8::MIN.to_f32(), 0i8.to_f32(), i8::MAX.to_f32()), (-1.0, 0.0, 127.0 / 128.0));
+        assert_eq!((u8::MIN.to_f32(), 128u8.to_f32(), u8::MAX.to_f32()), (-1.0, 0.0, 127.0 / 128.0));
+        assert_eq!((i16::MIN.to_f32(), 0i16.to_f32(), i16::MAX.to_f32()), (-1.0, 0.0, 32767.0 / 32768.0));
+        assert_eq!((u16::MIN.to_f32(), 32768u16.to_f32(), u16::MAX.to_f32()), (-1.0, 0.0, 32767.0 / 32768.0));
+        assert_eq!((i32::MIN.to_f32(), 0i32.to_f32(), i32::MAX.to_f32()), (-1.0, 0.0, 1.0));
+        assert_eq!((u32::MIN.to_f32(), (1u32 << 31).to_f32(), u32::MAX.to_f32()), (-1.0, 0.0, 1.0));
+        assert_eq!(((-1.0f64).to_f32(), 0.25f64.to_f32()), (-1.0, 0.25));
+
+        // 零点附近的小信号不因偏移换算丢失
+        assert_eq!(1i32.to_f32(), 1.0 / 2_147_483_648.0);
+        assert_eq!(((1u32 << 31) + 1).to_f32(), 1.0 / 2_147_483_648.0);
+        assert_eq!(129u8.to_f32(), 1.0 / 128.0);
+        assert_eq!((-1i16).to_f32(), -1.0 / 32768.0);
+    }
+
+    #[test]
+    fn resamples_loopback_to_microphone_rate() {
+        let (mut feed, mut mix) = loopback_mix(16000, 48000, 1, SourceMix::default());
+        for _ in 0..10 {
+            feed.push(&[0.25; 480]);
+        }
+        let mut microphone = vec![0.0; 1000];
+        mix.apply(&mut microphone, 1);
+        // 48kHz 100ms 重采样后约 1600 个样本（滤波器延迟让末尾滞后一点），中段保持原电平
+        assert!((microphone[800] - 0.25).abs() < 0.01);
+        assert!(mix.consumer.slots() > 400);
+    }
+}
@@ -0,0 +1,206 @@
+// 端到端延迟采集
+// 记录一次录音从按下快捷键到文本插入完成的各个时间点，转录完成后换算成毫秒数，
+// 经由 transcription_metrics 事件上报并追加写入 latency_metrics.jsonl（只有耗时，不含转录内容）
+
+use anyhow::Result;
+use serde::Serialize;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// 一次录音中的时间点
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mark {
+    /// 实时模式：第一块音频发出
+    FirstChunkSent,
+    /// 松开快捷键
+    Released,
+    /// 实时模式：commit 发出
+    CommitSent,
+    /// 实时模式：收到首个增量结果
+    FirstDelta,
+    /// HTTP 识别请求开始（含实时失败后的回退）
+    RequestStarted,
+    /// HTTP 识别请求结束
+    RequestFinished,
+    /// 拿到最终识别结果
+    FinalResult,
+    /// 文本插入完成
+    Inserted,
+}
+
+/// 本次录音的计时（按下快捷键时创建，转录完成后生成报告）
+#[derive(Debug, Clone)]
+pub struct SessionTimeline {
+    pressed: Instant,
+    first_chunk_sent: Option<Instant>,
+    released: Option<Instant>,
+    commit_sent: Option<Instant>,
+    first_delta: Option<Instant>,
+    request_started: Option<Instant>,
+    request_finished: Option<Instant>,
+    final_result: Option<Instant>,
+    inserted: Option<Instant>,
+    audio_duration: Option<Duration>,
+    fallback: bool,
+}
+
+impl SessionTimeline {
+    pub fn new(pressed: Instant) -> Self {
+        Self {
+            pressed,
+            first_chunk_sent: None,
+            released: None,
+            commit_sent: None,
+            first_delta: None,
+            request_started: None,
+            request_finished: None,
+            final_result: None,
+            inserted: None,
+            audio_duration: None,
+            fallback: false,
+        }
+    }
+
+    pub fn mark(&mut self, mark: Mark) {
+        self.mark_at(mark, Instant::now());
+    }
+
+    /// 记录时间点；同一时间点只保留第一次
+    pub fn mark_at(&mut self, mark: Mark, at: Instant) {
+        let slot = match mark {
+            Mark::FirstChunkSent => &mut self.first_chunk_sent,
+            Mark::Released => &mut self.released,
+            Mark::CommitSent => &mut self.commit_sent,
+            Mark::FirstDelta => &mut self.first_delta,
+            Mark::RequestStarted => &mut self.request_started,
+            Mark::RequestFinished => &mut self.request_finished,
+            Mark::FinalResult => &mut self.final_result,
+            Mark::Inserted => &mut self.inserted,
+        };
+        slot.get_or_insert(at);
+    }
+
+    /// 记录音频时长（实时模式取已发送时长，HTTP 模式取 WAV 时长，先记录的为准）
+    pub fn set_audio_duration(&mut self, duration: Duration) {
+        self.audio_duration.get_or_insert(duration);
+    }
+
+    /// 已记录的音频时长（随 transcription_complete 上报）
+    pub fn audio_duration(&self) -> Option<Duration> {
+        self.audio_duration
+    }
+
+    /// 实时识别失败，改走 HTTP 备用方案
+    pub fn set_fallback(&mut self) {
+        self.fallback = true;
+    }
+
+    pub fn report(&self, provider: &str) -> LatencyReport {
+        let since_press = |at: Option<Instant>| at.map(|at| millis(at.saturating_duration_since(self.pressed)));
+        let between = |from: Option<Instant>, to: Option<Instant>| match (from, to) {
+            (Some(from), Some(to)) => Some(millis(to.saturating_duration_since(from))),
+            _ => None,
+        };
+        LatencyReport {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(millis)
+                .unwrap_or_default(),
+            provider: provider.to_string(),
+            fallback: self.fallback,
+            audio_ms: self.audio_duration.map(millis),
+            first_chunk_ms: since_press(self.first_chunk_sent),
+            released_ms: since_press(self.released),
+            commit_ms: since_press(self.commit_sent),
+            first_delta_ms: since_press(self.first_delta),
+            request_start_ms: since_press(self.request_started),
+            request_end_ms: since_press(self.request_finished),
+            final_result_ms: since_press(self.final_result),
+            inserted_ms: since_press(self.inserted),
+            request_ms: between(self.request_started, self.request_finished),
+            release_to_insert_ms: between(self.released, self.inserted),
+        }
+    }
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis() as u64
+}
+
+/// transcription_metrics 事件的 payload，同时也是指标文件中的一行
+/// `*_ms` 时间点均为相对按下快捷键的毫秒数，未经过的阶段为空
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LatencyReport {
+    /// 生成报告的时间（Unix 毫秒）
+    pub timestamp_ms: u64,
+    pub provider: String,
+    /// 是否走了 HTTP 备用方案
+    pub fallback: bool,
+    /// 音频时长
+    pub audio_ms: Option<u64>,
+    pub first_chunk_ms: Option<u64>,
+    pub released_ms: Option<u64>,
+    pub commit_ms: Option<u64>,
+    pub first_delta_ms: Option<u64>,
+    pub request_start_ms: Option<u64>,
+    pub request_end_ms: Option<u64>,
+    pub final_result_ms: Option<u64>,
+    pub inserted_ms: Option<u64>,
+    /// HTTP 请求耗时
+    pub request_ms: Option<u64>,
+    /// 松开快捷键到文字出现的耗时
+    pub release_to_insert_ms: Option<u64>,
+}
+
+fn metrics_path() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir()
+        .ok_or_else(|| anyhow::anyhow!("无法获取配置目录"))?;
+    let app_dir = config_dir.join("PushToTalk");
+    std::fs::create_dir_all(&app_dir)?;
+    Ok(app_dir.join("latency_metrics.jsonl"))
+}
+
+/// 追加一行到本地指标文件
+pub fn append(report: &LatencyReport) -> Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(metrics_path()?)?;
+    writeln!(file, "{}", serde_json::to_string(report)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_offsets_from_key_press() {
+        let pressed = Instant::now();
+        let at = |ms| pressed + Duration::from_millis(ms);
+
+        let mut timeline = SessionTimeline::new(pressed);
+        timeline.mark_at(Mark::FirstChunkSent, at(120));
+        timeline.mark_at(Mark::FirstChunkSent, at(300));
+        timeline.mark_at(Mark::Released, at(2000));
+        timeline.mark_at(Mark::RequestStarted, at(2100));
+        timeline.mark_at(Mark::RequestFinished, at(2600));
+        timeline.mark_at(Mark::FinalResult, at(2600));
+        timeline.mark_at(Mark::Inserted, at(2650));
+        timeline.set_audio_duration(Duration::from_millis(1900));
+        timeline.set_audio_duration(Duration::from_millis(1800));
+        timeline.set_fallback();
+
+        let report = timeline.report("sensevoice");
+        assert_eq!(report.provider, "sensevoice");
+        assert!(report.fallback);
+        assert_eq!(report.audio_ms, Some(1900));
+        assert_eq!(report.first_chunk_ms, Some(120));
+        assert_eq!(report.commit_ms, None);
+        assert_eq!(report.first_delta_ms, None);
+        assert_eq!(report.request_ms, Some(500));
+        assert_eq!(report.inserted_ms, Some(2650));
+        assert_eq!(report.release_to_insert_ms, Some(650));
+    }
+}
@@ -1,26 +1,66 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod active_window;
+mod audio_codec;
+mod audio_file;
+mod audio_frame;
+mod audio_processing;
 mod audio_recorder;
+mod audio_source;
 mod beep_player;
 mod config;
+mod control;
+mod denoise;
+mod dictation_file;
+mod dns;
+mod filler_stats;
 mod hotkey_service;
+mod input_source;
+mod latency;
+mod logging;
 mod llm_post_processor;
+mod metrics;
+mod network_probe;
+mod privacy;
+mod proxy;
+mod quality;
+mod recordings;
+mod resampler;
 mod qwen_asr;
 mod qwen_realtime;
+mod segmentation;
+mod self_test;
+mod stats;
 mod streaming_recorder;
 mod text_inserter;
+mod text_postprocess;
+mod timing;
+mod vad;
+mod volc_realtime;
+mod ws_trace;
 
 use audio_recorder::AudioRecorder;
-use config::AppConfig;
-use hotkey_service::HotkeyService;
+use config::{AppConfig, AppOverrides, AsrProvider, HotkeyBinding, InputMode, OutputTarget, RealtimeProvider, RealtimeVadMode, SplitMessagesConfig};
+use dns::{DnsOverrides, HostDiagnostic};
+use filler_stats::{FillerTracker, FillerWord};
+use hotkey_service::{Hotkey, HotkeyService};
+use latency::{Mark, SessionTimeline};
 use llm_post_processor::LlmPostProcessor;
+use network_probe::NetworkProbe;
 use qwen_asr::{QwenASRClient, SenseVoiceClient};
-use qwen_realtime::QwenRealtimeClient;
+use qwen_realtime::{QwenRealtimeClient, RealtimeEndpoint, RealtimeOptions, SessionOptions, SpeechEvent, StopResult};
+use stats::{StatsTracker, UsageStats};
 use streaming_recorder::StreamingRecorder;
 use text_inserter::TextInserter;
+use volc_realtime::VolcRealtimeClient;
 
-use std::sync::{Arc, Mutex};
+#[cfg(feature = "soak")]
+#[doc(hidden)]
+pub use qwen_realtime::SoakClient;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use tauri::{
     AppHandle, Emitter, Manager,
     tray::{TrayIconBuilder, TrayIconEvent, MouseButton, MouseButtonState},
@@ -28,6 +68,30 @@ use tauri::{
     WindowEvent,
 };
 
+// 口头禅统计默认返回条数
+const DEFAULT_FILLER_TOP_N: usize = 10;
+// 麦克风测试的录音时长
+const MICROPHONE_TEST_DURATION: std::time::Duration = std::time::Duration::from_secs(2);
+
+// 服务端 VAD 分段消费任务句柄（返回已插入段数与错误信息）
+type SegmentTaskHandle = tokio::task::JoinHandle<(usize, Option<String>)>;
+// 音频发送任务句柄（返回收尾结果）
+type AudioSenderHandle = tokio::task::JoinHandle<SenderOutcome>;
+
+/// 音频发送任务的收尾结果：手动断句模式下由发送任务发完残留音频后自动 commit
+enum SenderOutcome {
+    /// 已提交音频，等待转录结果
+    Committed,
+    /// 整段录音未检测到语音，未提交
+    NoSpeech,
+    /// 录音短于最短时长（误触），未提交
+    TooShort(std::time::Duration),
+    /// 服务端 VAD / 连续听写：各段已自动提交，尾段交给 finish_segments 收尾
+    Segmented,
+    /// 发送或提交失败
+    Failed(String),
+}
+
 // 全局应用状态
 struct AppState {
     audio_recorder: Arc<Mutex<Option<AudioRecorder>>>,
@@ -39,16 +103,238 @@ struct AppState {
     enable_post_process: Arc<Mutex<bool>>,
     qwen_client: Arc<Mutex<Option<QwenASRClient>>>,
     sensevoice_client: Arc<Mutex<Option<SenseVoiceClient>>>,
+    // 各组快捷键对应的转录配置（序号与 HotkeyService 注册顺序一致）
+    hotkey_profiles: Arc<Mutex<Vec<Arc<HotkeyProfile>>>>,
     // 活跃的实时转录会话（用于真正的流式传输）
     active_session: Arc<tokio::sync::Mutex<Option<qwen_realtime::RealtimeSession>>>,
+    // 活跃会话的取消句柄（等待结果期间会话被锁住，取消转录时经由它通知服务端）
+    session_canceller: Arc<Mutex<Option<qwen_realtime::SessionCanceller>>>,
     // 音频发送任务句柄
-    audio_sender_handle: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
+    audio_sender_handle: Arc<Mutex<Option<AudioSenderHandle>>>,
+    // 服务端 VAD 模式下的分段结果消费任务句柄
+    segment_task_handle: Arc<Mutex<Option<SegmentTaskHandle>>>,
+    // 当前生效的配置（录音 / 转录时读取最新值，update_config 原子替换）
+    config: Arc<RwLock<AppConfig>>,
+    // 服务启动时使用的配置，用于判断哪些改动需要重启服务；未运行时为空
+    running_config: Arc<Mutex<Option<AppConfig>>>,
+    // 当前录音已因设备错误中止（松开按键时不再走转录流程）
+    recording_aborted: Arc<AtomicBool>,
+    // 当前录音的实时连接建立失败、已降级为录完再传（松开按键时直接走 HTTP 备用方案）
+    realtime_degraded: Arc<AtomicBool>,
+    // 暂停监听（pause_listening）：快捷键不再触发录音，录音器、文本插入器等保留
+    listening_paused: Arc<AtomicBool>,
+    // 口头禅统计（后台线程增量更新）
+    filler_tracker: Arc<FillerTracker>,
+    // 使用统计（录音时长、字数、各 provider 成功/失败次数）
+    stats: Arc<StatsTracker>,
+    // 本次录音开始时间（录音中为 Some，松开按键时清除）
+    recording_started_at: Arc<Mutex<Option<std::time::Instant>>>,
+    // 本次录音的端到端延迟计时（按下快捷键时重置，文本插入后上报）
+    latency: Arc<Mutex<Option<SessionTimeline>>>,
+    // 未通过质量检查、等待用户确认的识别结果（下次按下快捷键时丢弃）
+    pending_transcript: Arc<Mutex<Option<PendingTranscript>>>,
+    // 预览模式下等待用户确认插入的结果（下次按下快捷键时丢弃）
+    pending_preview: Arc<Mutex<Option<TranscriptionResult>>>,
+    // 是否处于连续听写中（input_mode 为 dictation 时按一次进入、再按一次退出）
+    dictation_mode: Arc<AtomicBool>,
+    // 本次录音开始时前台应用匹配到的配置覆盖（app_rules，按下快捷键时更新）
+    app_overrides: Arc<Mutex<AppOverrides>>,
+    // 本次录音保留的文件路径（audio.keep_recordings），随转录结果发给前端历史记录
+    last_recording: Arc<Mutex<Option<std::path::PathBuf>>>,
+}
+
+/// 实时识别客户端，按 realtime_provider 选择服务商，会话类型相同
+enum RealtimeClient {
+    // 千问与 OpenAI 共用同一套事件协议的客户端
+    Qwen(QwenRealtimeClient),
+    Volc(VolcRealtimeClient),
+}
+
+impl RealtimeClient {
+    async fn start_session(&self, sample_rate: u32) -> anyhow::Result<qwen_realtime::RealtimeSession> {
+        match self {
+            RealtimeClient::Qwen(client) => client.start_session(sample_rate).await,
+            RealtimeClient::Volc(client) => client.start_session(sample_rate).await,
+        }
+    }
+
+    /// 归还用完的会话（火山引擎不复用，直接关闭）
+    async fn release(&self, session: qwen_realtime::RealtimeSession) {
+        match self {
+            RealtimeClient::Qwen(client) => client.release(session).await,
+            RealtimeClient::Volc(_) => close_session_in_background(Some(session)),
+        }
+    }
+
+    fn warm_up(&self) {
+        match self {
+            RealtimeClient::Qwen(client) => client.warm_up(),
+            RealtimeClient::Volc(client) => client.warm_up(),
+        }
+    }
+
+    /// 是否有刚收到过服务端消息的预热会话（火山引擎不预热）
+    async fn has_live_session(&self) -> bool {
+        match self {
+            RealtimeClient::Qwen(client) => client.has_live_session().await,
+            RealtimeClient::Volc(_) => false,
+        }
+    }
+
+    fn supported_sample_rates(&self) -> &'static [u32] {
+        match self {
+            RealtimeClient::Qwen(client) => client.supported_sample_rates(),
+            RealtimeClient::Volc(client) => client.supported_sample_rates(),
+        }
+    }
+
+    async fn check_connectivity(&self) -> anyhow::Result<()> {
+        match self {
+            RealtimeClient::Qwen(client) => client.check_connectivity().await,
+            RealtimeClient::Volc(client) => client.check_connectivity().await,
+        }
+    }
+
+    async fn shutdown(&self) {
+        match self {
+            RealtimeClient::Qwen(client) => client.shutdown().await,
+            RealtimeClient::Volc(client) => client.shutdown().await,
+        }
+    }
+}
+
+/// 一组快捷键解析后的转录配置
+struct HotkeyProfile {
+    keys: String,
+    provider: AsrProvider,
+    post_process: bool,
+    // 实时转录客户端（千问持有预热连接池，跨多次录音复用）
+    realtime_client: Option<Arc<RealtimeClient>>,
+    // 按该组语言配置的千问 HTTP 客户端
+    qwen_client: Option<QwenASRClient>,
+    // 该组的识别语言（自动识别时为 None），润色时据此选择预设中对应语言的 prompt
+    language: Option<String>,
+    // 按下快捷键时探测该组识别服务是否可达（地址无效时为 None，不探测）
+    network_probe: Option<NetworkProbe>,
+}
+
+impl HotkeyProfile {
+    /// 识别服务是否可达：有刚收到过消息的预热会话时直接视为在线，否则快速探测（结果缓存几秒）
+    async fn network_available(&self) -> bool {
+        if let Some(ref client) = self.realtime_client {
+            if client.has_live_session().await {
+                return true;
+            }
+        }
+        match self.network_probe {
+            Some(ref probe) => probe.reachable().await,
+            None => true,
+        }
+    }
+}
+
+/// 该组使用的润色处理器：按识别语言选择 prompt，前台应用的覆盖优先于该组的润色开关与预设；关闭润色时为空
+/// 克隆的处理器共享进行中的任务句柄，cancel_transcription 仍能中途取消
+fn profile_post_processor(
+    shared: &Arc<Mutex<Option<LlmPostProcessor>>>,
+    profile: &HotkeyProfile,
+    overrides: &AppOverrides,
+) -> Arc<Mutex<Option<LlmPostProcessor>>> {
+    if !overrides.post_process.unwrap_or(profile.post_process) {
+        return Arc::new(Mutex::new(None));
+    }
+    let processor = shared.lock().unwrap().clone();
+    Arc::new(Mutex::new(processor.map(|p| {
+        p.with_language(profile.language.clone()).with_preset(overrides.llm_preset.as_deref())
+    })))
+}
+
+/// 按前台窗口的进程名 / 窗口类名匹配 app_rules，返回该应用的配置覆盖（未匹配时为空）
+fn app_overrides_for(config: &AppConfig, window: Option<active_window::WindowId>) -> AppOverrides {
+    let Some(window) = window else {
+        return AppOverrides::default();
+    };
+    let process = active_window::process_name(window);
+    let class = active_window::window_class(window);
+    match config.app_rule_for(process.as_deref(), class.as_deref()) {
+        Some(rule) => {
+            tracing::info!("前台应用 {:?} ({:?}) 匹配应用规则: {:?}", process, class, rule.overrides);
+            rule.overrides.clone()
+        }
+        None => AppOverrides::default(),
+    }
+}
+
+fn provider_label(provider: AsrProvider) -> &'static str {
+    match provider {
+        AsrProvider::QwenRealtime => "千问实时",
+        AsrProvider::QwenHttp => "千问 HTTP",
+        AsrProvider::SenseVoice => "SenseVoice",
+    }
+}
+
+/// 更新本次录音的延迟计时（未在计时中时忽略）
+fn update_latency(app: &AppHandle, update: impl FnOnce(&mut SessionTimeline)) {
+    if let Some(timeline) = app.state::<AppState>().latency.lock().unwrap().as_mut() {
+        update(timeline);
+    }
+}
+
+/// 延迟指标中的 provider：实时识别按实际服务商区分，便于对比各家延迟
+fn latency_provider(app: &AppHandle, provider: &'static str) -> &'static str {
+    if provider != stats::QWEN_REALTIME {
+        return provider;
+    }
+    match app.state::<AppState>().config.read().unwrap().realtime_provider {
+        RealtimeProvider::Qwen => stats::QWEN_REALTIME,
+        RealtimeProvider::Volc => "volc_realtime",
+        RealtimeProvider::OpenAi => "openai_realtime",
+    }
+}
+
+/// 按当前配置把 HTTP 上传的录音切段，返回各段与并行请求数
+fn http_segments(app: &AppHandle, audio_data: Vec<u8>) -> (Vec<Vec<u8>>, usize) {
+    let config = app.state::<AppState>().config.read().unwrap().http_segmentation;
+    (segmentation::split_wav(audio_data, &config), config.max_parallel)
+}
+
+/// 按各服务商的单次时长上限筛选 HTTP 客户端：最长一段超限的服务商不提交，改用其它服务商；都超限时返回错误
+fn within_duration_limit(
+    segments: &[Vec<u8>],
+    qwen_client: Option<QwenASRClient>,
+    sensevoice_client: Option<SenseVoiceClient>,
+) -> anyhow::Result<(Option<QwenASRClient>, Option<SenseVoiceClient>)> {
+    let longest = segmentation::longest_segment(segments);
+    let exceeded = [
+        (AsrProvider::QwenHttp, qwen_client.is_some()),
+        (AsrProvider::SenseVoice, sensevoice_client.is_some()),
+    ]
+    .into_iter()
+    .find(|&(provider, configured)| configured && !provider.accepts(longest));
+    let Some((provider, _)) = exceeded else {
+        return Ok((qwen_client, sensevoice_client));
+    };
+
+    let qwen_client = qwen_client.filter(|_| AsrProvider::QwenHttp.accepts(longest));
+    let sensevoice_client = sensevoice_client.filter(|_| AsrProvider::SenseVoice.accepts(longest));
+    let max_secs = provider.max_audio_duration().unwrap_or_default().as_secs();
+    if qwen_client.is_none() && sensevoice_client.is_none() {
+        anyhow::bail!(
+            "录音 {:.0} 秒超过 {} 单次 {} 秒的上限，请开启长录音分段 (http_segmentation) 或配置硅基流动 API Key",
+            longest.as_secs_f32(),
+            provider_label(provider),
+            max_secs
+        );
+    }
+    tracing::info!("录音 {:.1} 秒超过 {} 单次 {} 秒的上限，改用其它服务商", longest.as_secs_f32(), provider_label(provider), max_secs);
+    Ok((qwen_client, sensevoice_client))
 }
 
 // Tauri Commands
 
 #[tauri::command]
 async fn save_config(
+    app_handle: AppHandle,
     api_key: String,
     fallback_api_key: String,
     use_realtime: Option<bool>,
@@ -57,6 +343,8 @@ async fn save_config(
     close_action: Option<String>,
 ) -> Result<String, String> {
     tracing::info!("保存配置...");
+    // 前端未传入的设置项沿用已保存的值
+    let existing = AppConfig::load().unwrap_or_else(|_| AppConfig::new());
     let config = AppConfig {
         dashscope_api_key: api_key,
         siliconflow_api_key: fallback_api_key,
@@ -64,15 +352,86 @@ async fn save_config(
         enable_llm_post_process: enable_post_process.unwrap_or(false),
         llm_config: llm_config.unwrap_or_default(),
         close_action,
+        ..existing
     };
 
     config
         .save()
         .map_err(|e| format!("保存配置失败: {}", e))?;
+    publish_config(&app_handle, config);
 
     Ok("配置已保存".to_string())
 }
 
+/// 已写入磁盘的配置同步到共享配置
+fn publish_config(app: &AppHandle, config: AppConfig) {
+    *app.state::<AppState>().config.write().unwrap() = config;
+}
+
+/// update_config 的结果
+#[derive(serde::Serialize)]
+struct ConfigUpdate {
+    /// 需要停止并重新启动服务才会生效的配置项（服务未运行时为空）
+    restart_required: Vec<&'static str>,
+}
+
+/// 运行中更新配置：保存后原子替换共享配置。结果后处理、按应用规则、隐私模式、音频处理参数、
+/// LLM 润色配置下次录音即生效；识别服务、快捷键、API Key 等需要重建资源的改动在返回值中列出
+#[tauri::command]
+async fn update_config(app_handle: AppHandle, config: AppConfig) -> Result<ConfigUpdate, String> {
+    text_postprocess::validate_replacements(&config.text_replacements)?;
+    config.save().map_err(|e| format!("保存配置失败: {}", e))?;
+
+    let state = app_handle.state::<AppState>();
+    let running = state.running_config.lock().unwrap().clone();
+    let restart_required = running
+        .as_ref()
+        .map(|running| config.restart_required_changes(running))
+        .unwrap_or_default();
+
+    privacy::set_enabled(config.privacy_mode);
+    beep_player::set_volume(config.beep_volume);
+    audio_processing::set_input_gain(config.input_gain);
+    logging::set_level(&config.log_level);
+    audio_codec::set_upload_codec(config.audio.upload_codec);
+    if let Some(ref mut recorder) = *state.audio_recorder.lock().unwrap() {
+        recorder.set_audio_config(config.audio.clone());
+    }
+    if let Some(ref mut recorder) = *state.streaming_recorder.lock().unwrap() {
+        recorder.set_audio_config(config.audio.clone());
+    }
+    if let Some(ref running) = running {
+        // DNS 覆盖属于需重启的配置，润色处理器沿用启动时的解析
+        let any_post_process = state.hotkey_profiles.lock().unwrap().iter().any(|p| p.post_process);
+        let dns_overrides = DnsOverrides::from_config(&running.dns_overrides).with_proxy(running.proxy.as_deref());
+        *state.post_processor.lock().unwrap() = build_post_processor(&config, any_post_process, &dns_overrides);
+    }
+    *state.config.write().unwrap() = config;
+
+    if restart_required.is_empty() {
+        tracing::info!("配置已更新并立即生效");
+    } else {
+        tracing::info!("配置已更新，以下改动需重启服务后生效: {}", restart_required.join(", "));
+    }
+    Ok(ConfigUpdate { restart_required })
+}
+
+/// 按配置构建 LLM 润色处理器（复用连接）；没有任何一组快捷键或应用规则启用润色、或未配置 API Key 时为空
+fn build_post_processor(config: &AppConfig, any_post_process: bool, dns_overrides: &DnsOverrides) -> Option<LlmPostProcessor> {
+    let any_post_process =
+        any_post_process || config.app_rules.iter().any(|rule| rule.overrides.post_process == Some(true));
+    let llm_cfg = &config.llm_config;
+    if any_post_process && !llm_cfg.api_key.trim().is_empty() {
+        tracing::info!("LLM 后处理器配置: endpoint={}, model={}", llm_cfg.endpoint, llm_cfg.model);
+        Some(LlmPostProcessor::new(llm_cfg.clone(), dns_overrides).with_filler_words(config.filler_words.clone()))
+    } else {
+        if any_post_process {
+            tracing::warn!("LLM 后处理已启用但未配置 API Key，将跳过后处理");
+        }
+        None
+    }
+}
+
 #[tauri::command]
 async fn load_config() -> Result<AppConfig, String> {
     tracing::info!("加载配置...");
@@ -87,31 +446,238 @@ async fn start_app(
     use_realtime: Option<bool>,
     enable_post_process: Option<bool>,
     llm_config: Option<config::LlmConfig>,
+    hotkey_bindings: Option<Vec<HotkeyBinding>>,
 ) -> Result<String, String> {
     tracing::info!("启动应用...");
 
     // 获取应用状态
     let state = app_handle.state::<AppState>();
 
-    let mut is_running = state.is_running.lock().unwrap();
-    if *is_running {
+    if *state.is_running.lock().unwrap() {
         return Err("应用已在运行中".to_string());
     }
 
     // 确定是否使用实时模式
     let use_realtime_mode = use_realtime.unwrap_or(true);
-    *state.use_realtime_asr.lock().unwrap() = use_realtime_mode;
 
     // 确定是否启用 LLM 后处理
     let enable_post_process_mode = enable_post_process.unwrap_or(false);
-    *state.enable_post_process.lock().unwrap() = enable_post_process_mode;
+
+    // 前端未传入的设置项从已保存的配置中读取
+    let saved_config = AppConfig::load().unwrap_or_else(|_| AppConfig::new());
+    privacy::set_enabled(saved_config.privacy_mode);
+    beep_player::set_volume(saved_config.beep_volume);
+    audio_processing::set_input_gain(saved_config.input_gain);
+    logging::set_level(&saved_config.log_level);
+    audio_codec::set_upload_codec(saved_config.audio.upload_codec);
+    let dns_overrides = DnsOverrides::from_config(&saved_config.dns_overrides).with_proxy(saved_config.proxy.as_deref());
+    if !dns_overrides.is_empty() {
+        dns_overrides.log_active();
+    }
+    let realtime_options = SessionOptions {
+        vad_mode: saved_config.realtime_vad,
+        max_reconnect_attempts: saved_config.realtime_max_reconnects,
+        batch_ms: saved_config.realtime_batch_ms,
+        backlog_limit: saved_config.realtime_backlog_limit,
+        backlog_policy: saved_config.realtime_backlog_policy,
+        sample_rate: saved_config.realtime_sample_rate,
+        ping_interval_secs: saved_config.realtime_ping_interval_secs,
+        pong_timeout: std::time::Duration::from_secs(saved_config.realtime_pong_timeout_secs),
+        stale_after: std::time::Duration::from_secs(saved_config.realtime_stale_secs),
+        dns_overrides: dns_overrides.clone(),
+        timeout_factor: saved_config.realtime_timeout_factor,
+        speech_threshold_dbfs: saved_config.realtime_speech_threshold_dbfs,
+        auto_commit: (saved_config.input_mode == InputMode::Dictation)
+            .then(|| std::time::Duration::from_secs(saved_config.dictation_commit_secs.max(1))),
+        timings: timing::Timings::default(),
+        ws_trace: saved_config.debug.ws_trace,
+    };
+
+    // 快捷键绑定：前端未传入时读取已保存的配置，均为空时使用默认的 Ctrl+Win
+    let bindings = hotkey_bindings
+        .filter(|bindings| !bindings.is_empty())
+        .unwrap_or_else(|| saved_config.hotkey_bindings.clone());
+    // 本次启动实际使用的配置（前端传入的设置项覆盖已保存的值）
+    let running_config = AppConfig {
+        dashscope_api_key: api_key.clone(),
+        siliconflow_api_key: fallback_api_key.clone(),
+        use_realtime_asr: use_realtime_mode,
+        enable_llm_post_process: enable_post_process_mode,
+        llm_config: llm_config.unwrap_or_default(),
+        hotkey_bindings: bindings.clone(),
+        ..saved_config.clone()
+    };
+    let bindings = if bindings.is_empty() {
+        vec![HotkeyBinding::default_binding()]
+    } else {
+        bindings
+    };
+    let hotkeys = bindings
+        .iter()
+        .map(|binding| Hotkey::parse(&binding.keys))
+        .collect::<anyhow::Result<Vec<_>>>()
+        .map_err(|e| format!("快捷键配置无效: {}", e))?;
+    // 未指定 provider 的快捷键沿用全局的识别模式
+    let default_provider = if use_realtime_mode {
+        AsrProvider::QwenRealtime
+    } else {
+        AsrProvider::QwenHttp
+    };
 
     tracing::info!("ASR 模式: {}", if use_realtime_mode { "实时 WebSocket" } else { "HTTP" });
+    if bindings.iter().any(|b| b.provider.unwrap_or(default_provider) == AsrProvider::QwenRealtime) {
+        tracing::info!("实时识别服务: {:?}, 会话参数: {:?}", saved_config.realtime_provider, realtime_options);
+    }
     tracing::info!("LLM 后处理: {}", if enable_post_process_mode { "启用" } else { "禁用" });
 
+    // 启动前校验：key 非空并对要用的 provider 做一次连通性测试，失败则不进入监听状态
+    let uses_dashscope = bindings
+        .iter()
+        .any(|b| b.provider.unwrap_or(default_provider) != AsrProvider::SenseVoice);
+    if uses_dashscope && api_key.trim().is_empty() {
+        return Err("请先填写 DashScope API Key".to_string());
+    }
+    if bindings.iter().any(|b| b.provider == Some(AsrProvider::SenseVoice))
+        && fallback_api_key.trim().is_empty()
+    {
+        return Err("使用 SenseVoice 的快捷键需要填写硅基流动 API Key".to_string());
+    }
+
+    let mut profiles = Vec::with_capacity(bindings.len());
+    let mut api_key_checked = false;
+    for binding in &bindings {
+        let provider = binding.provider.unwrap_or(default_provider);
+        let post_process = binding.post_process.unwrap_or(enable_post_process_mode);
+        tracing::info!(
+            "快捷键 {}: 识别服务={}, 语言={}, LLM 后处理={}",
+            binding.keys,
+            provider_label(provider),
+            binding.language.as_deref().unwrap_or("默认"),
+            post_process
+        );
+
+        let mut realtime_client = None;
+        let mut qwen_client = None;
+        let language = match provider {
+            AsrProvider::QwenRealtime => Some(
+                binding
+                    .language
+                    .clone()
+                    .unwrap_or_else(|| saved_config.realtime_language.clone()),
+            ),
+            AsrProvider::QwenHttp => Some(binding.language.clone().unwrap_or_else(|| "zh".to_string())),
+            // SenseVoice 自动识别语种
+            AsrProvider::SenseVoice => binding.language.clone(),
+        };
+        match provider {
+            AsrProvider::QwenRealtime => {
+                let realtime = RealtimeOptions {
+                    language: language.clone().unwrap_or_default(),
+                    vocabulary: saved_config.realtime_vocabulary.clone(),
+                    model: saved_config.realtime_model.clone(),
+                    punctuation: saved_config.realtime_punctuation,
+                    extra: saved_config.realtime_session_extra.clone(),
+                };
+                let client = Arc::new(match saved_config.realtime_provider {
+                    RealtimeProvider::Qwen => {
+                        let endpoint = RealtimeEndpoint::dashscope(
+                            api_key.clone(),
+                            &saved_config.dashscope_ws_url,
+                            &saved_config.realtime_model,
+                        );
+                        RealtimeClient::Qwen(QwenRealtimeClient::with_endpoint(endpoint, realtime_options.clone(), realtime))
+                    }
+                    RealtimeProvider::OpenAi => {
+                        if saved_config.openai.api_key.trim().is_empty() {
+                            return Err("请先填写 OpenAI API Key".to_string());
+                        }
+                        let endpoint = RealtimeEndpoint::openai(
+                            saved_config.openai.api_key.clone(),
+                            saved_config.openai.url.clone(),
+                        );
+                        let realtime = RealtimeOptions {
+                            model: saved_config.openai.model.clone(),
+                            ..realtime
+                        };
+                        RealtimeClient::Qwen(QwenRealtimeClient::with_endpoint(endpoint, realtime_options.clone(), realtime))
+                    }
+                    RealtimeProvider::Volc if saved_config.input_mode == InputMode::Dictation => {
+                        return Err("火山引擎流式识别不支持连续听写，请改用千问或 OpenAI 实时识别".to_string());
+                    }
+                    RealtimeProvider::Volc => RealtimeClient::Volc(VolcRealtimeClient::new(
+                        saved_config.volc.clone(),
+                        realtime_options.clone(),
+                        realtime.vocabulary,
+                    )),
+                });
+                if let Some(rate) = saved_config.realtime_sample_rate {
+                    let model = match saved_config.realtime_provider {
+                        RealtimeProvider::Qwen => saved_config.realtime_model.as_str(),
+                        RealtimeProvider::OpenAi => saved_config.openai.model.as_str(),
+                        RealtimeProvider::Volc => saved_config.volc.resource_id.as_str(),
+                    };
+                    qwen_realtime::check_sample_rate(rate, client.supported_sample_rates(), model)
+                        .map_err(|e| e.to_string())?;
+                }
+                // 握手成功的千问连接直接留作预热会话，按下快捷键时取用
+                client
+                    .check_connectivity()
+                    .await
+                    .map_err(|e| format!("实时识别服务连接失败，请检查 API Key 与网络: {}", e))?;
+                realtime_client = Some(client);
+            }
+            AsrProvider::QwenHttp => {
+                let client = QwenASRClient::new(api_key.clone(), &dns_overrides)
+                    .with_base_url(&saved_config.dashscope_base_url)
+                    .with_language(language.as_deref().unwrap_or("zh"));
+                if !api_key_checked {
+                    client
+                        .check_api_key()
+                        .await
+                        .map_err(|e| format!("识别服务校验失败: {}", e))?;
+                    api_key_checked = true;
+                }
+                qwen_client = Some(client);
+            }
+            AsrProvider::SenseVoice => {}
+        }
+
+        let probe_url = match provider {
+            AsrProvider::QwenRealtime => match saved_config.realtime_provider {
+                RealtimeProvider::Qwen => &saved_config.dashscope_ws_url,
+                RealtimeProvider::OpenAi => &saved_config.openai.url,
+                RealtimeProvider::Volc => &saved_config.volc.url,
+            },
+            AsrProvider::QwenHttp => &saved_config.dashscope_base_url,
+            AsrProvider::SenseVoice => &saved_config.siliconflow_base_url,
+        };
+        let network_probe = NetworkProbe::new(probe_url, dns_overrides.clone(), &realtime_options.timings)
+            .map_err(|e| tracing::warn!("不做按键时的网络探测: {}", e))
+            .ok();
+
+        profiles.push(Arc::new(HotkeyProfile {
+            keys: binding.keys.clone(),
+            provider,
+            post_process,
+            realtime_client,
+            qwen_client,
+            language,
+            network_probe,
+        }));
+    }
+
+    let mut is_running = state.is_running.lock().unwrap();
+    if *is_running {
+        return Err("应用已在运行中".to_string());
+    }
+    *state.use_realtime_asr.lock().unwrap() = use_realtime_mode;
+    *state.enable_post_process.lock().unwrap() = enable_post_process_mode;
+
     {
         let mut qwen_guard = state.qwen_client.lock().unwrap();
-        *qwen_guard = Some(QwenASRClient::new(api_key.clone()));
+        *qwen_guard = Some(
+            QwenASRClient::new(api_key.clone(), &dns_overrides).with_base_url(&saved_config.dashscope_base_url),
+        );
     }
 
     {
@@ -119,44 +685,41 @@ async fn start_app(
         if fallback_api_key.trim().is_empty() {
             *sensevoice_guard = None;
         } else {
-            *sensevoice_guard = Some(SenseVoiceClient::new(fallback_api_key.clone()));
+            *sensevoice_guard = Some(
+                SenseVoiceClient::new(fallback_api_key.clone(), &dns_overrides)
+                    .with_base_url(&saved_config.siliconflow_base_url),
+            );
         }
     }
 
-    // 初始化 LLM 后处理器（复用连接）
-    {
-        let mut processor_guard = state.post_processor.lock().unwrap();
-        let llm_cfg = llm_config.unwrap_or_default();
-        if enable_post_process_mode && !llm_cfg.api_key.trim().is_empty() {
-            tracing::info!("LLM 后处理器配置: endpoint={}, model={}", llm_cfg.endpoint, llm_cfg.model);
-            *processor_guard = Some(LlmPostProcessor::new(llm_cfg));
-            tracing::info!("LLM 后处理器已初始化");
-        } else {
-            *processor_guard = None;
-            if enable_post_process_mode {
-                tracing::warn!("LLM 后处理已启用但未配置 API Key，将跳过后处理");
-            }
-        }
-    }
+    // 初始化 LLM 后处理器
+    let any_post_process = profiles.iter().any(|p| p.post_process);
+    *state.post_processor.lock().unwrap() = build_post_processor(&running_config, any_post_process, &dns_overrides);
 
     // 初始化文本插入器
     let text_inserter = TextInserter::new()
         .map_err(|e| format!("初始化文本插入器失败: {}", e))?;
     *state.text_inserter.lock().unwrap() = Some(text_inserter);
+    *state.running_config.lock().unwrap() = Some(running_config.clone());
+    *state.config.write().unwrap() = running_config;
 
-    // 根据模式初始化录音器
-    if use_realtime_mode {
-        let streaming_recorder = StreamingRecorder::new()
+    // 根据各组快捷键用到的模式初始化录音器
+    if profiles.iter().any(|p| p.provider == AsrProvider::QwenRealtime) {
+        let mut streaming_recorder = StreamingRecorder::new()
             .map_err(|e| format!("初始化流式录音器失败: {}", e))?;
+        streaming_recorder.set_audio_config(saved_config.audio.clone());
         *state.streaming_recorder.lock().unwrap() = Some(streaming_recorder);
-    } else {
-        let audio_recorder = AudioRecorder::new()
+    }
+    if profiles.iter().any(|p| p.provider != AsrProvider::QwenRealtime) {
+        let mut audio_recorder = AudioRecorder::new()
             .map_err(|e| format!("初始化音频录制器失败: {}", e))?;
+        audio_recorder.set_audio_config(saved_config.audio.clone());
         *state.audio_recorder.lock().unwrap() = Some(audio_recorder);
     }
 
     // 启动全局快捷键监听
-    let hotkey_service = HotkeyService::new();
+    state.listening_paused.store(false, Ordering::SeqCst);
+    let hotkey_service = HotkeyService::new(Arc::clone(&state.listening_paused));
 
     // 克隆状态用于回调
     let app_handle_start = app_handle.clone();
@@ -164,64 +727,182 @@ async fn start_app(
     let streaming_recorder_start = Arc::clone(&state.streaming_recorder);
     let active_session_start = Arc::clone(&state.active_session);
     let audio_sender_handle_start = Arc::clone(&state.audio_sender_handle);
-    let use_realtime_start = use_realtime_mode;
-    let api_key_start = api_key.clone();
+    let segment_task_handle_start = Arc::clone(&state.segment_task_handle);
+    let text_inserter_start = Arc::clone(&state.text_inserter);
+    let post_processor_start = Arc::clone(&state.post_processor);
+    let app_overrides_start = Arc::clone(&state.app_overrides);
+    let hotkey_profiles_start = Arc::clone(&state.hotkey_profiles);
     let is_running_start = Arc::clone(&state.is_running);
+    let recording_aborted_start = Arc::clone(&state.recording_aborted);
+    let realtime_degraded_start = Arc::clone(&state.realtime_degraded);
+    let recording_started_at_start = Arc::clone(&state.recording_started_at);
+    let latency_start = Arc::clone(&state.latency);
+    let pending_transcript_start = Arc::clone(&state.pending_transcript);
+    let pending_preview_start = Arc::clone(&state.pending_preview);
 
     let app_handle_stop = app_handle.clone();
     let audio_recorder_stop = Arc::clone(&state.audio_recorder);
     let streaming_recorder_stop = Arc::clone(&state.streaming_recorder);
     let active_session_stop = Arc::clone(&state.active_session);
     let audio_sender_handle_stop = Arc::clone(&state.audio_sender_handle);
+    let segment_task_handle_stop = Arc::clone(&state.segment_task_handle);
+    let hotkey_profiles_stop = Arc::clone(&state.hotkey_profiles);
     let text_inserter_stop = Arc::clone(&state.text_inserter);
     let post_processor_stop = Arc::clone(&state.post_processor);
+    let app_overrides_stop = Arc::clone(&state.app_overrides);
     let qwen_client_stop = Arc::clone(&state.qwen_client);
     let sensevoice_client_stop = Arc::clone(&state.sensevoice_client);
-    let use_realtime_stop = use_realtime_mode;
     let is_running_stop = Arc::clone(&state.is_running);
+    let recording_aborted_stop = Arc::clone(&state.recording_aborted);
+    let realtime_degraded_stop = Arc::clone(&state.realtime_degraded);
+    let recording_started_at_stop = Arc::clone(&state.recording_started_at);
 
     // 按键按下回调
-    let on_start = move || {
+    // 录音达到最长时长时，由监听任务把快捷键组序号送回来，按松开按键的流程结束录音
+    let (auto_stop_tx, mut auto_stop_rx) = tokio::sync::mpsc::unbounded_channel::<usize>();
+
+    let on_start = move |index: usize| {
         // 检查服务是否仍在运行
         if !*is_running_start.lock().unwrap() {
             tracing::debug!("服务已停止，忽略快捷键按下事件");
             return;
         }
+        let Some(profile) = hotkey_profiles_start.lock().unwrap().get(index).cloned() else {
+            return;
+        };
+        recording_aborted_start.store(false, Ordering::SeqCst);
+        realtime_degraded_start.store(false, Ordering::SeqCst);
+        let pressed_at = std::time::Instant::now();
+        *recording_started_at_start.lock().unwrap() = Some(pressed_at);
+        *latency_start.lock().unwrap() = Some(SessionTimeline::new(pressed_at));
+        pending_transcript_start.lock().unwrap().take();
+        pending_preview_start.lock().unwrap().take();
 
         let app = app_handle_start.clone();
         let recorder = Arc::clone(&audio_recorder_start);
         let streaming_recorder = Arc::clone(&streaming_recorder_start);
         let active_session = Arc::clone(&active_session_start);
         let audio_sender_handle = Arc::clone(&audio_sender_handle_start);
-        let use_realtime = use_realtime_start;
-        let api_key = api_key_start.clone();
+        let segment_task_handle = Arc::clone(&segment_task_handle_start);
+        let inserter = Arc::clone(&text_inserter_start);
+        let use_realtime = profile.provider == AsrProvider::QwenRealtime;
+        let realtime_client = profile.realtime_client.clone();
+        let auto_stop = auto_stop_tx.clone();
+
+        // 记录当前前台窗口，转录完成后插入到该窗口（期间切走也不会插错地方）
+        let target_window = match *inserter.lock().unwrap() {
+            Some(ref mut ins) => {
+                ins.capture_target();
+                ins.target_window()
+            }
+            None => active_window::foreground_window(),
+        };
+
+        // 按前台应用匹配配置覆盖（如代码编辑器里不润色、微信里润色）
+        let overrides = app_overrides_for(&app.state::<AppState>().config.read().unwrap(), target_window);
+        let post_processor = profile_post_processor(&post_processor_start, &profile, &overrides);
+        *app_overrides_start.lock().unwrap() = overrides;
 
         // 播放开始录音提示音
         beep_player::play_start_beep();
 
         tauri::async_runtime::spawn(async move {
-            tracing::info!("检测到快捷键按下");
+            tracing::info!("检测到快捷键按下: {} ({})", profile.keys, provider_label(profile.provider));
+
+            // 断网时不再白等连接超时：直接提示并中止本次录音，松开按键时不再转录
+            if !profile.network_available().await {
+                let target = profile.network_probe.as_ref().map(|probe| probe.target()).unwrap_or_default();
+                tracing::warn!("识别服务 {} 不可达，中止本次录音", target);
+                let _ = app.emit("network_offline", NetworkOfflineEvent { target });
+                app.state::<AppState>().recording_aborted.store(true, Ordering::SeqCst);
+                end_dictation(&app);
+                return;
+            }
             let _ = app.emit("recording_started", ());
 
             if use_realtime {
                 // 实时模式：建立 WebSocket 连接 + 启动流式录音 + 启动发送任务
                 tracing::info!("启动真正的实时流式转录...");
 
-                // 1. 建立 WebSocket 连接
-                let realtime_client = QwenRealtimeClient::new(api_key);
-                match realtime_client.start_session().await {
-                    Ok(session) => {
+                // 1. 按设备采样率协商会话采样率（协议支持时直传原始 PCM，省去重采样）
+                let device_rate = streaming_recorder
+                    .lock()
+                    .unwrap()
+                    .as_ref()
+                    .map(|rec| rec.device_sample_rate());
+                let sample_rate = match device_rate {
+                    Some(Ok(rate)) => qwen_realtime::session_sample_rate(rate),
+                    Some(Err(e)) => {
+                        tracing::warn!("查询设备采样率失败: {}，使用 {}Hz", e, qwen_realtime::DEFAULT_SAMPLE_RATE);
+                        qwen_realtime::DEFAULT_SAMPLE_RATE
+                    }
+                    None => qwen_realtime::DEFAULT_SAMPLE_RATE,
+                };
+
+                // 取用预热连接（不可用时即时建立）
+                let connect_started = std::time::Instant::now();
+                let session_result = match realtime_client {
+                    Some(ref client) => client.start_session(sample_rate).await,
+                    None => Err(anyhow::anyhow!("实时转录客户端未初始化")),
+                };
+                match session_result {
+                    Ok(mut session) => {
                         tracing::info!("WebSocket 连接已建立");
+                        let _ = app.emit("realtime_session", RealtimeSessionReady {
+                            origin: session.origin(),
+                            connect_ms: connect_started.elapsed().as_millis() as u64,
+                        });
+
+                        // 转发断线 / 重连状态给前端；发送积压单独发 network_slow 事件
+                        if let Some(mut event_rx) = session.take_event_receiver() {
+                            let app_events = app.clone();
+                            tokio::spawn(async move {
+                                while let Some(event) = event_rx.recv().await {
+                                    tracing::info!("实时连接状态: {:?}", event);
+                                    let name = match event {
+                                        qwen_realtime::ConnectionEvent::NetworkSlow { .. } => "network_slow",
+                                        _ => "realtime_connection",
+                                    };
+                                    let _ = app_events.emit(name, event);
+                                }
+                            });
+                        }
+
+                        // 转发服务端 VAD 的说话状态（悬浮窗显示"正在说话"）
+                        if let Some(mut speech_rx) = session.take_speech_receiver() {
+                            let app_events = app.clone();
+                            tokio::spawn(async move {
+                                while let Some(event) = speech_rx.recv().await {
+                                    let name = match event {
+                                        SpeechEvent::Started => "speech_detected",
+                                        SpeechEvent::Stopped => "speech_ended",
+                                    };
+                                    let _ = app_events.emit(name, ());
+                                }
+                            });
+                        }
 
                         // 2. 启动流式录音
                         let chunk_rx = {
                             let mut streaming_guard = streaming_recorder.lock().unwrap();
                             if let Some(ref mut rec) = *streaming_guard {
-                                match rec.start_streaming() {
-                                    Ok(rx) => Some(rx),
+                                match rec.start_streaming(session.sample_rate()) {
+                                    Ok(rx) => {
+                                        if let Some(errors) = rec.take_device_errors() {
+                                            spawn_device_error_watcher(app.clone(), errors);
+                                        }
+                                        if let Some(reached) = rec.take_duration_limit() {
+                                            spawn_duration_limit_watcher(reached, auto_stop.clone(), index);
+                                        }
+                                        if let Some(levels) = rec.take_levels() {
+                                            spawn_level_forwarder(app.clone(), levels);
+                                        }
+                                        warn_missing_device(&app, rec.take_missing_device());
+                                        Some((rx, rec.chunk_ms(), rec.min_recording()))
+                                    }
                                     Err(e) => {
                                         tracing::error!("开始流式录音失败: {}", e);
-                                        let _ = app.emit("error", format!("录音失败: {}", e));
+                                        emit_error(&app, "recording", format!("录音失败: {}", e));
                                         None
                                     }
                                 }
@@ -230,48 +911,119 @@ async fn start_app(
                             }
                         };
 
-                        if let Some(chunk_rx) = chunk_rx {
+                        if let Some((chunk_rx, chunk_ms, min_recording)) = chunk_rx {
+                            // 服务端 VAD / 连续听写：录音期间每段结果都立即插入
+                            if session.vad_mode() == RealtimeVadMode::Server || session.is_dictation() {
+                                if let Some(segment_rx) = session.take_segment_receiver() {
+                                    let segment_handle = tokio::spawn(consume_vad_segments(
+                                        app.clone(),
+                                        segment_rx,
+                                        inserter,
+                                        post_processor,
+                                        session.is_dictation(),
+                                    ));
+                                    *segment_task_handle.lock().unwrap() = Some(segment_handle);
+                                }
+                            }
+
                             // 保存会话
+                            *app.state::<AppState>().session_canceller.lock().unwrap() = Some(session.canceller());
                             *active_session.lock().await = Some(session);
 
                             // 3. 启动音频发送任务
                             let session_for_sender = Arc::clone(&active_session);
+                            let app_sender = app.clone();
                             let sender_handle = tokio::spawn(async move {
-                                tracing::info!("音频发送任务启动");
+                                tracing::info!("音频发送任务启动（块时长 {}ms）", chunk_ms);
                                 let mut chunk_count = 0;
+                                // 约每 2 秒音频记录一次进度
+                                let log_every = (2000 / chunk_ms).max(1);
 
+                                // 停止录音后通道关闭，此前残留的块仍会全部取出发送
                                 while let Ok(chunk) = chunk_rx.recv() {
                                     let session_guard = session_for_sender.lock().await;
-                                    if let Some(ref session) = *session_guard {
-                                        if let Err(e) = session.send_audio_chunk(&chunk).await {
-                                            tracing::error!("发送音频块失败: {}", e);
-                                            break;
-                                        }
-                                        chunk_count += 1;
-                                        if chunk_count % 10 == 0 {
-                                            tracing::debug!("已发送 {} 个音频块", chunk_count);
-                                        }
-                                    } else {
-                                        break;
+                                    let Some(ref session) = *session_guard else {
+                                        return SenderOutcome::Failed("会话已关闭".to_string());
+                                    };
+                                    if let Err(e) = session.send_audio_chunk(&chunk).await {
+                                        tracing::error!("发送音频块失败: {}", e);
+                                        return SenderOutcome::Failed(e.to_string());
+                                    }
+                                    if chunk_count == 0 {
+                                        update_latency(&app_sender, |t| t.mark(Mark::FirstChunkSent));
+                                    }
+                                    chunk_count += 1;
+                                    if chunk_count % log_every == 0 {
+                                        tracing::debug!("已发送 {} 个音频块", chunk_count);
                                     }
-                                    drop(session_guard);
                                 }
-
                                 tracing::info!("音频发送任务结束，共发送 {} 个块", chunk_count);
+
+                                // 所有音频发完后再提交，保证最后一块不会落在 commit 之后
+                                let session_guard = session_for_sender.lock().await;
+                                let Some(ref session) = *session_guard else {
+                                    return SenderOutcome::Failed("会话已关闭".to_string());
+                                };
+                                // 录音过短多为误触，不提交识别
+                                let duration = session.audio_duration();
+                                if duration < min_recording {
+                                    return SenderOutcome::TooShort(duration);
+                                }
+                                if session.vad_mode() == RealtimeVadMode::Server || session.is_dictation() {
+                                    return SenderOutcome::Segmented;
+                                }
+                                if !session.speech_detected() {
+                                    return SenderOutcome::NoSpeech;
+                                }
+                                tracing::info!("发送 commit...");
+                                match session.commit_audio().await {
+                                    Ok(()) => {
+                                        update_latency(&app_sender, |t| t.mark(Mark::CommitSent));
+                                        SenderOutcome::Committed
+                                    }
+                                    Err(e) => {
+                                        tracing::error!("发送 commit 失败: {}", e);
+                                        SenderOutcome::Failed(e.to_string())
+                                    }
+                                }
                             });
 
-                            *audio_sender_handle.lock().unwrap() = Some(sender_handle);
+                            // 泄漏修复：上一次的发送任务若未被 join（例如未收到释放事件），先终止再替换
+                            if let Some(previous) = audio_sender_handle.lock().unwrap().replace(sender_handle) {
+                                previous.abort();
+                            }
                         }
                     }
+                    Err(e) if e.is::<qwen_realtime::AuthRejected>() => {
+                        // HTTP 回退使用同一个 Key，同样会被拒绝：中止本次录音，松开按键时不再转录
+                        report_auth_error(&app, &e);
+                        app.state::<AppState>().recording_aborted.store(true, Ordering::SeqCst);
+                        end_dictation(&app);
+                    }
                     Err(e) => {
                         tracing::error!("建立 WebSocket 连接失败: {}，回退到普通录音", e);
-                        let _ = app.emit("error", format!("实时连接失败: {}", e));
+                        metrics::record_provider(stats::QWEN_REALTIME, false);
+                        emit_error(&app, "realtime_connect", format!("实时连接失败: {}", e));
+                        // 松开按键时据此直接走 HTTP 备用方案
+                        app.state::<AppState>().realtime_degraded.store(true, Ordering::SeqCst);
 
                         // 回退到普通流式录音（录完再传）
                         let mut streaming_guard = streaming_recorder.lock().unwrap();
                         if let Some(ref mut rec) = *streaming_guard {
-                            if let Err(e) = rec.start_streaming() {
-                                tracing::error!("开始流式录音失败: {}", e);
+                            match rec.start_streaming(qwen_realtime::DEFAULT_SAMPLE_RATE) {
+                                Ok(_) => {
+                                    if let Some(errors) = rec.take_device_errors() {
+                                        spawn_device_error_watcher(app.clone(), errors);
+                                    }
+                                    if let Some(reached) = rec.take_duration_limit() {
+                                        spawn_duration_limit_watcher(reached, auto_stop.clone(), index);
+                                    }
+                                    if let Some(levels) = rec.take_levels() {
+                                        spawn_level_forwarder(app.clone(), levels);
+                                    }
+                                    warn_missing_device(&app, rec.take_missing_device());
+                                }
+                                Err(e) => tracing::error!("开始流式录音失败: {}", e),
                             }
                         }
                     }
@@ -280,9 +1032,23 @@ async fn start_app(
                 // HTTP 模式：使用原有录音器
                 let mut recorder_guard = recorder.lock().unwrap();
                 if let Some(ref mut rec) = *recorder_guard {
-                    if let Err(e) = rec.start_recording() {
-                        tracing::error!("开始录音失败: {}", e);
-                        let _ = app.emit("error", format!("录音失败: {}", e));
+                    match rec.start_recording() {
+                        Ok(()) => {
+                            if let Some(errors) = rec.take_device_errors() {
+                                spawn_device_error_watcher(app.clone(), errors);
+                            }
+                            if let Some(reached) = rec.take_duration_limit() {
+                                spawn_duration_limit_watcher(reached, auto_stop.clone(), index);
+                            }
+                            if let Some(levels) = rec.take_levels() {
+                                spawn_level_forwarder(app.clone(), levels);
+                            }
+                            warn_missing_device(&app, rec.take_missing_device());
+                        }
+                        Err(e) => {
+                            tracing::error!("开始录音失败: {}", e);
+                            emit_error(&app, "recording", format!("录音失败: {}", e));
+                        }
                     }
                 }
             }
@@ -290,23 +1056,33 @@ async fn start_app(
     };
 
     // 按键释放回调
-    let on_stop = move || {
+    let on_stop = move |index: usize| {
         // 检查服务是否仍在运行
         if !*is_running_stop.lock().unwrap() {
             tracing::debug!("服务已停止，忽略快捷键释放事件");
             return;
         }
+        if recording_aborted_stop.swap(false, Ordering::SeqCst) {
+            tracing::info!("本次录音已中止或已自动停止（设备错误 / 网络不可用 / 鉴权失败 / 达到最长时长），忽略快捷键释放事件");
+            return;
+        }
+        let Some(profile) = hotkey_profiles_stop.lock().unwrap().get(index).cloned() else {
+            return;
+        };
+        let realtime_degraded = realtime_degraded_stop.swap(false, Ordering::SeqCst);
+        recording_started_at_stop.lock().unwrap().take();
+        update_latency(&app_handle_stop, |t| t.mark(Mark::Released));
 
         let app = app_handle_stop.clone();
         let recorder = Arc::clone(&audio_recorder_stop);
         let streaming_recorder = Arc::clone(&streaming_recorder_stop);
         let active_session = Arc::clone(&active_session_stop);
         let audio_sender_handle = Arc::clone(&audio_sender_handle_stop);
+        let segment_task_handle = Arc::clone(&segment_task_handle_stop);
         let inserter = Arc::clone(&text_inserter_stop);
-        let post_processor = Arc::clone(&post_processor_stop);
+        let post_processor = profile_post_processor(&post_processor_stop, &profile, &app_overrides_stop.lock().unwrap());
         let qwen_client_state = Arc::clone(&qwen_client_stop);
         let sensevoice_client_state = Arc::clone(&sensevoice_client_stop);
-        let use_realtime = use_realtime_stop;
 
         // 播放停止录音提示音
         beep_player::play_stop_beep();
@@ -315,39 +1091,118 @@ async fn start_app(
             tracing::info!("检测到快捷键释放");
             let _ = app.emit("recording_stopped", ());
 
-            if use_realtime {
+            if profile.provider == AsrProvider::QwenRealtime {
                 // 实时模式：停止录音 + commit + 等待结果
-                handle_realtime_stop(
-                    app,
+                let stop = RealtimeStop {
+                    realtime_client: profile.realtime_client.clone(),
+                    degraded: realtime_degraded,
                     streaming_recorder,
                     active_session,
                     audio_sender_handle,
+                    segment_task_handle,
                     inserter,
                     post_processor,
                     qwen_client_state,
                     sensevoice_client_state,
-                ).await;
+                };
+                handle_realtime_stop(app, stop).await;
+
+                // 为下一次录音预热连接
+                if let Some(ref client) = profile.realtime_client {
+                    client.warm_up();
+                }
             } else {
-                // HTTP 模式：使用原有逻辑
+                // HTTP 模式：千问按该组语言转录，SenseVoice 作为备用；SenseVoice 组只走 SenseVoice
+                let qwen_client = match profile.provider {
+                    AsrProvider::SenseVoice => None,
+                    _ => profile.qwen_client.clone(),
+                };
+                let sensevoice_client = sensevoice_client_state.lock().unwrap().clone();
                 handle_http_transcription(
                     app,
                     recorder,
                     inserter,
                     post_processor,
-                    qwen_client_state,
-                    sensevoice_client_state,
+                    qwen_client,
+                    sensevoice_client,
                 ).await;
             }
         });
     };
 
+    // 连续听写：实时识别的快捷键按一次进入、再按一次退出，松开按键不再触发
+    let dictation = saved_config.input_mode == InputMode::Dictation;
+    let toggles = {
+        let hotkey_profiles = Arc::clone(&state.hotkey_profiles);
+        move |index: usize| {
+            dictation
+                && hotkey_profiles
+                    .lock()
+                    .unwrap()
+                    .get(index)
+                    .is_some_and(|p| p.provider == AsrProvider::QwenRealtime)
+        }
+    };
+    let on_stop = Arc::new(on_stop);
+
+    // 录音达到 audio.max_duration_secs：与松开按键相同的流程停止采集并转录（实时模式停止发送并 commit）
+    {
+        let on_stop = Arc::clone(&on_stop);
+        let app = app_handle.clone();
+        tauri::async_runtime::spawn(async move {
+            while let Some(index) = auto_stop_rx.recv().await {
+                let state = app.state::<AppState>();
+                // 通知到达前已经松开按键
+                if state.recording_started_at.lock().unwrap().is_none() {
+                    continue;
+                }
+                let max_duration_secs = state.config.read().unwrap().audio.max_duration_secs;
+                tracing::warn!("录音达到最长时长 {} 秒，自动停止", max_duration_secs);
+                let _ = app.emit("recording_auto_stopped", RecordingAutoStopped { reason: "max_duration", max_duration_secs });
+                on_stop(index);
+                // 快捷键可能仍按着（或卡住），之后的释放事件不再重复转录
+                state.recording_aborted.store(true, Ordering::SeqCst);
+                end_dictation(&app);
+            }
+        });
+    }
+    let on_press = {
+        let on_stop = Arc::clone(&on_stop);
+        let toggles = toggles.clone();
+        let app = app_handle.clone();
+        let dictation_mode = Arc::clone(&state.dictation_mode);
+        move |index: usize| {
+            if !toggles(index) {
+                on_start(index);
+            } else if dictation_mode.swap(false, Ordering::SeqCst) {
+                tracing::info!("再次按下快捷键，退出连续听写");
+                on_stop(index);
+                let _ = app.emit("dictation_stopped", ());
+            } else {
+                tracing::info!("进入连续听写");
+                dictation_mode.store(true, Ordering::SeqCst);
+                on_start(index);
+                let _ = app.emit("dictation_started", ());
+            }
+        }
+    };
+    let on_release = move |index: usize| {
+        if !toggles(index) {
+            on_stop(index);
+        }
+    };
+
     hotkey_service
-        .start(on_start, on_stop)
+        .start(hotkeys, on_press, on_release)
         .map_err(|e| format!("启动快捷键监听失败: {}", e))?;
 
+    let summary: Vec<String> = profiles
+        .iter()
+        .map(|p| format!("{} ({})", p.keys, provider_label(p.provider)))
+        .collect();
+    *state.hotkey_profiles.lock().unwrap() = profiles;
     *is_running = true;
-    let mode_str = if use_realtime_mode { "实时模式" } else { "HTTP 模式" };
-    Ok(format!("应用已启动 ({})，按 Ctrl+Win 开始录音", mode_str))
+    Ok(format!("应用已启动，按住 {} 开始录音", summary.join("、")))
 }
 
 /// HTTP 模式转录处理（原有逻辑）
@@ -356,18 +1211,18 @@ async fn handle_http_transcription(
     recorder: Arc<Mutex<Option<AudioRecorder>>>,
     inserter: Arc<Mutex<Option<TextInserter>>>,
     post_processor: Arc<Mutex<Option<LlmPostProcessor>>>,
-    qwen_client_state: Arc<Mutex<Option<QwenASRClient>>>,
-    sensevoice_client_state: Arc<Mutex<Option<SenseVoiceClient>>>,
+    qwen_client: Option<QwenASRClient>,
+    sensevoice_client: Option<SenseVoiceClient>,
 ) {
     // 停止录音并直接获取内存中的音频数据
     let audio_data = {
         let mut recorder_guard = recorder.lock().unwrap();
         if let Some(ref mut rec) = *recorder_guard {
             match rec.stop_recording_to_memory() {
-                Ok(data) => Some(data),
+                Ok(clip) => Some((clip, rec.recorded_duration(), rec.min_recording())),
                 Err(e) => {
                     tracing::error!("停止录音失败: {}", e);
-                    let _ = app.emit("error", format!("停止录音失败: {}", e));
+                    emit_error(&app, "recording", format!("停止录音失败: {}", e));
                     None
                 }
             }
@@ -376,115 +1231,341 @@ async fn handle_http_transcription(
         }
     };
 
-    if let Some(audio_data) = audio_data {
-        let _ = app.emit("transcribing", ());
+    if let Some((clip, recorded_duration, min_recording)) = audio_data {
+        // 按实际采集的样本数判断误触（静音裁剪后的时长会偏短）
+        if reject_too_short(&app, Some(recorded_duration), min_recording)
+            || reject_silent(&app, &clip.wav)
+        {
+            return;
+        }
+        keep_recording(&app, &clip.wav);
+
+        // 提交前按服务商的单次时长上限筛选客户端，都超限时直接提示
+        let duration = clip.duration();
+        tracing::info!("提交 HTTP 转录: 音频 {}ms, {}Hz", clip.duration_ms, clip.sample_rate);
+        let (segments, max_parallel) = http_segments(&app, clip.wav);
+        let (qwen_client, sensevoice_client) = match within_duration_limit(&segments, qwen_client, sensevoice_client) {
+            Ok(clients) => clients,
+            Err(e) => {
+                tracing::warn!("{}", e);
+                app.state::<AppState>().latency.lock().unwrap().take();
+                emit_error(&app, "too_long", format!("录音过长: {}", e));
+                return;
+            }
+        };
 
-        let qwen_client = { qwen_client_state.lock().unwrap().clone() };
-        let sensevoice_client = { sensevoice_client_state.lock().unwrap().clone() };
+        let _ = app.emit("transcribing", TranscribingPayload { duration_ms: Some(clip.duration_ms) });
+        record_submitted(&app, duration);
+        update_latency(&app, |t| {
+            t.set_audio_duration(duration);
+            t.mark(Mark::RequestStarted);
+        });
 
         let asr_start = std::time::Instant::now();
-        let result = match (qwen_client, sensevoice_client) {
+        let (provider, result) = match (qwen_client, sensevoice_client) {
             (Some(qwen), Some(sensevoice)) => {
                 tracing::info!("使用主备并行转录模式 (HTTP)");
-                qwen_asr::transcribe_with_fallback_clients(qwen, sensevoice, audio_data).await
+                let result = segmentation::transcribe_segments(segments, max_parallel, |wav| {
+                    qwen_asr::transcribe_with_fallback_clients(qwen.clone(), sensevoice.clone(), wav)
+                })
+                .await;
+                (stats::QWEN_HTTP_WITH_FALLBACK, result)
             }
             (Some(qwen), None) => {
                 tracing::info!("仅使用千问 ASR (HTTP)");
-                qwen.transcribe_bytes(&audio_data).await
+                let result = segmentation::transcribe_segments(segments, max_parallel, |wav| {
+                    let qwen = &qwen;
+                    async move { qwen.transcribe_bytes(&wav).await }
+                })
+                .await;
+                (stats::QWEN_HTTP, result)
             }
             (None, Some(sensevoice)) => {
                 tracing::warn!("千问客户端未初始化，回退到 SenseVoice");
-                sensevoice.transcribe_bytes(&audio_data).await
+                let result = segmentation::transcribe_segments(segments, max_parallel, |wav| {
+                    let sensevoice = &sensevoice;
+                    async move { sensevoice.transcribe_bytes(&wav).await }
+                })
+                .await;
+                (stats::SENSEVOICE, result)
             }
             (None, None) => {
                 tracing::error!("未找到可用的 ASR 客户端");
-                Err(anyhow::anyhow!("ASR 客户端未初始化"))
+                (stats::QWEN_HTTP, Err(anyhow::anyhow!("ASR 客户端未初始化")))
             }
         };
         let asr_time_ms = asr_start.elapsed().as_millis() as u64;
+        update_latency(&app, |t| t.mark(Mark::RequestFinished));
 
-        handle_transcription_result(app, inserter, post_processor, result, asr_time_ms).await;
+        handle_transcription_result(app, inserter, post_processor, provider, result, asr_time_ms).await;
     }
 }
 
-/// 真正的实时模式停止处理（边录边传后的 commit + 等待结果）
-async fn handle_realtime_stop(
-    app: AppHandle,
+/// 实时模式松开按键时收尾所需的状态（本次录音的连接、录音器、发送任务与备用客户端）
+struct RealtimeStop {
+    realtime_client: Option<Arc<RealtimeClient>>,
+    // 按下时实时连接建立失败、只做了录完再传的录音
+    degraded: bool,
     streaming_recorder: Arc<Mutex<Option<StreamingRecorder>>>,
     active_session: Arc<tokio::sync::Mutex<Option<qwen_realtime::RealtimeSession>>>,
-    audio_sender_handle: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
+    audio_sender_handle: Arc<Mutex<Option<AudioSenderHandle>>>,
+    segment_task_handle: Arc<Mutex<Option<SegmentTaskHandle>>>,
     inserter: Arc<Mutex<Option<TextInserter>>>,
     post_processor: Arc<Mutex<Option<LlmPostProcessor>>>,
     qwen_client_state: Arc<Mutex<Option<QwenASRClient>>>,
     sensevoice_client_state: Arc<Mutex<Option<SenseVoiceClient>>>,
-) {
-    let _ = app.emit("transcribing", ());
+}
+
+/// 本次录音的会话取消句柄：收尾从任一出口结束时清除，abort_recording 不会再取消已关闭或已归还连接池的会话
+struct CancellerGuard(Option<Arc<Mutex<Option<qwen_realtime::SessionCanceller>>>>);
+
+impl CancellerGuard {
+    fn clear(&mut self) {
+        if let Some(slot) = self.0.take() {
+            slot.lock().unwrap().take();
+        }
+    }
+}
+
+impl Drop for CancellerGuard {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// 真正的实时模式停止处理（边录边传后的 commit + 等待结果）
+async fn handle_realtime_stop(app: AppHandle, stop: RealtimeStop) {
+    let RealtimeStop {
+        realtime_client,
+        degraded,
+        streaming_recorder,
+        active_session,
+        audio_sender_handle,
+        segment_task_handle,
+        inserter,
+        post_processor,
+        qwen_client_state,
+        sensevoice_client_state,
+    } = stop;
+    let mut canceller = CancellerGuard(Some(Arc::clone(&app.state::<AppState>().session_canceller)));
     let asr_start = std::time::Instant::now();
 
     // 1. 停止流式录音，获取完整音频数据（用于备用方案）
-    let audio_data = {
+    let (clip, min_recording) = {
         let mut recorder_guard = streaming_recorder.lock().unwrap();
         if let Some(ref mut rec) = *recorder_guard {
+            let min_recording = rec.min_recording();
             match rec.stop_streaming() {
-                Ok(data) => Some(data),
+                Ok(clip) => (Some(clip), min_recording),
+                Err(_) if rec.full_audio_released() => {
+                    tracing::info!("完整录音已在分段送达后释放，不再保留");
+                    (None, min_recording)
+                }
                 Err(e) => {
                     tracing::error!("停止流式录音失败: {}", e);
-                    None
+                    (None, min_recording)
                 }
             }
         } else {
-            None
+            (None, std::time::Duration::ZERO)
         }
     };
+    let _ = app.emit("transcribing", TranscribingPayload { duration_ms: clip.as_ref().map(|clip| clip.duration_ms) });
+    let audio_data = clip.map(|clip| clip.wav);
 
-    // 2. 等待音频发送任务完成
-    {
+    // 连接失败已降级为录完再传：没有会话也没有发送任务，明确走 HTTP 备用方案
+    if degraded {
+        tracing::info!("本次录音实时连接失败，使用 HTTP 备用方案转录");
+        transcribe_recorded_audio(app, audio_data, min_recording, inserter, post_processor, qwen_client_state, sensevoice_client_state)
+            .await;
+        return;
+    }
+
+    // 2. 等待音频发送任务发完残留音频并自动 commit
+    let sender_outcome = {
         let handle = audio_sender_handle.lock().unwrap().take();
-        if let Some(h) = handle {
-            tracing::info!("等待音频发送任务完成...");
-            let _ = h.await;
+        match handle {
+            Some(h) => {
+                tracing::info!("等待音频发送任务完成...");
+                h.await.unwrap_or_else(|e| SenderOutcome::Failed(format!("发送任务异常: {}", e)))
+            }
+            None => SenderOutcome::Failed("音频发送任务不存在".to_string()),
         }
-    }
+    };
 
     // 3. 检查是否有活跃的 WebSocket 会话
     let mut session_guard = active_session.lock().await;
     if let Some(ref mut session) = *session_guard {
-        tracing::info!("发送 commit 并等待转录结果...");
+        // 用户已取消：不再收尾，也不回退
+        if session.is_cancelled() {
+            tracing::info!("实时转录已取消，跳过收尾");
+            close_session_in_background(session_guard.take());
+            return;
+        }
 
-        // 发送 commit
-        if let Err(e) = session.commit_audio().await {
-            tracing::error!("发送 commit 失败: {}", e);
-            drop(session_guard);
-            // 回退到备用方案
-            if let Some(audio_data) = audio_data {
-                fallback_transcription(
-                    app,
-                    inserter,
-                    post_processor,
-                    Arc::clone(&qwen_client_state),
-                    Arc::clone(&sensevoice_client_state),
-                    audio_data,
-                )
-                .await;
+        let audio_duration = session.audio_duration();
+        update_latency(&app, |t| t.set_audio_duration(audio_duration));
+
+        // 误触 / 全程静音：音频已流式发出但未提交，取消会话清空服务端缓冲区
+        let rejected = match sender_outcome {
+            SenderOutcome::TooShort(duration) => reject_too_short(&app, Some(duration), min_recording),
+            _ => audio_data.as_deref().is_some_and(|audio_data| reject_silent(&app, audio_data)),
+        };
+        if rejected {
+            if let Some(session) = session_guard.take() {
+                tokio::spawn(async move { session.cancel().await });
+            }
+            if let Some(h) = segment_task_handle.lock().unwrap().take() {
+                h.abort();
             }
             return;
         }
+        // 手动断句下整段没有检测到语音的录音不会提交，不计入录音时长
+        if !matches!(sender_outcome, SenderOutcome::NoSpeech) {
+            record_submitted(&app, audio_duration);
+        }
+        if let Some(audio_data) = audio_data.as_deref() {
+            keep_recording(&app, audio_data);
+        }
+
+        if session.vad_mode() == RealtimeVadMode::Server || session.is_dictation() {
+            // 服务端 VAD / 连续听写：各段已自动提交，只需收尾尾段并等待分段插入完成
+            tracing::info!("等待分段结果收尾...");
+            let finish_result = session.finish_segments().await;
+            let segment_task_timeout = session.timings().segment_task_timeout;
+            let _ = session.close().await;
+            *session_guard = None;
+            drop(session_guard);
+
+            let handle = segment_task_handle.lock().unwrap().take();
+            let (delivered, segment_error) = match handle {
+                Some(h) => match tokio::time::timeout(segment_task_timeout, h).await {
+                    Ok(Ok(outcome)) => outcome,
+                    Ok(Err(e)) => (0, Some(format!("分段任务异常: {}", e))),
+                    Err(_) => (0, Some("等待分段任务结束超时".to_string())),
+                },
+                None => (0, None),
+            };
+            let error = finish_result.err().map(|e| e.to_string()).or(segment_error);
+
+            if delivered == 0 {
+                // 一段结果都没拿到，整段回退到备用方案
+                tracing::warn!("服务端 VAD 未产出任何分段 (错误: {:?})，尝试备用方案", error);
+                if let Some(audio_data) = audio_data {
+                    fallback_transcription(
+                        app,
+                        inserter,
+                        post_processor,
+                        Arc::clone(&qwen_client_state),
+                        Arc::clone(&sensevoice_client_state),
+                        audio_data,
+                    )
+                    .await;
+                } else {
+                    emit_error(&app, "no_audio", "没有录制到音频数据");
+                }
+            } else if let Some(e) = error {
+                // 已插入部分分段，不再整段回退以免重复插入
+                tracing::error!("服务端 VAD 收尾失败（已插入 {} 段）: {}", delivered, e);
+                emit_error(&app, "partial_transcription", format!("部分转录失败: {}", e));
+            } else {
+                tracing::info!("服务端 VAD 会话结束，共插入 {} 段", delivered);
+            }
+            return;
+        }
+
+        match sender_outcome {
+            SenderOutcome::Committed => {}
+            // 整段录音都没有检测到语音：发送任务未提交，直接提示
+            SenderOutcome::NoSpeech => {
+                tracing::info!("整段录音未检测到语音 (音频 {:.1} 秒)，跳过转录", session.audio_duration().as_secs_f32());
+                let _ = session.close().await;
+                *session_guard = None;
+                drop(session_guard);
+                emit_error(&app, "no_speech", "没有检测到语音");
+                return;
+            }
+            SenderOutcome::Failed(_) | SenderOutcome::Segmented | SenderOutcome::TooShort(_) => {
+                let reason = match sender_outcome {
+                    SenderOutcome::Failed(e) => e,
+                    _ => "发送任务未提交音频".to_string(),
+                };
+                tracing::error!("音频发送 / 提交失败: {}", reason);
+                app.state::<AppState>().stats.record_failure(stats::QWEN_REALTIME);
+                // 泄漏修复：失败路径同样关闭并清理会话，避免会话一直挂在 active_session 中
+                let _ = session.close().await;
+                *session_guard = None;
+                drop(session_guard);
+                // 回退到备用方案
+                if let Some(audio_data) = audio_data {
+                    fallback_transcription(
+                        app,
+                        inserter,
+                        post_processor,
+                        Arc::clone(&qwen_client_state),
+                        Arc::clone(&sensevoice_client_state),
+                        audio_data,
+                    )
+                    .await;
+                }
+                return;
+            }
+        }
 
         // 等待转录结果
-        match session.wait_for_result().await {
-            Ok(text) => {
+        let wait = session.result_timeout();
+        tracing::info!(
+            "等待转录结果 (音频 {:.1} 秒, 超时 {:.1} 秒)",
+            session.audio_duration().as_secs_f32(),
+            wait.as_secs_f32()
+        );
+        let result = session.wait_for_stop_result().await;
+        if session.is_cancelled() {
+            tracing::info!("等待结果期间转录已取消，丢弃结果");
+            close_session_in_background(session_guard.take());
+            return;
+        }
+        match result {
+            StopResult::Text(text) => {
                 let asr_time_ms = asr_start.elapsed().as_millis() as u64;
-                tracing::info!("实时转录成功: {} (ASR 耗时: {}ms)", text, asr_time_ms);
-                let _ = session.close().await;
+                let first_transcript = session.first_transcript_at();
+                update_latency(&app, |t| {
+                    if let Some(at) = first_transcript {
+                        t.mark_at(Mark::FirstDelta, at);
+                    }
+                    t.mark(Mark::FinalResult);
+                });
+                tracing::info!("实时转录成功: {} (ASR 耗时: {}ms)", privacy::redact(&text), asr_time_ms);
+                // 会话归还连接池后不再属于本次录音，取消句柄一并清除
+                canceller.clear();
+                let session = session_guard.take();
                 drop(session_guard);
-                *active_session.lock().await = None;
-                handle_transcription_result(app, inserter, post_processor, Ok(text), asr_time_ms).await;
+                match (realtime_client, session) {
+                    (Some(client), Some(session)) => client.release(session).await,
+                    (None, session) => close_session_in_background(session),
+                    _ => {}
+                }
+                let (provider, text) = match audio_data {
+                    Some(audio_data) => {
+                        recheck_truncated(&app, &qwen_client_state, &sensevoice_client_state, text, audio_duration, audio_data)
+                            .await
+                    }
+                    None => (stats::QWEN_REALTIME, text),
+                };
+                let asr_time_ms = asr_start.elapsed().as_millis() as u64;
+                handle_transcription_result(app, inserter, post_processor, provider, Ok(text), asr_time_ms).await;
             }
-            Err(e) => {
+            StopResult::AuthRejected(e) => {
+                app.state::<AppState>().stats.record_failure(stats::QWEN_REALTIME);
+                close_session_in_background(session_guard.take());
+                drop(session_guard);
+                report_auth_error(&app, &e);
+            }
+            StopResult::Fallback(e) => {
                 tracing::warn!("等待转录结果失败: {}，尝试备用方案", e);
-                let _ = session.close().await;
+                app.state::<AppState>().stats.record_failure(stats::QWEN_REALTIME);
+                close_session_in_background(session_guard.take());
                 drop(session_guard);
-                *active_session.lock().await = None;
 
                 // 回退到备用方案
                 if let Some(audio_data) = audio_data {
@@ -498,29 +1579,142 @@ async fn handle_realtime_stop(
                     )
                     .await;
                 } else {
-                    let _ = app.emit("error", format!("转录失败: {}", e));
+                    emit_error(&app, "transcription", format!("转录失败: {}", e));
                 }
             }
         }
     } else {
-        // 没有活跃会话，使用备用方案（可能是连接失败时的回退）
+        // 未降级却没有活跃会话（不应出现），同样用已录的音频走备用方案，不丢录音
         tracing::warn!("没有活跃的 WebSocket 会话，使用备用方案");
         drop(session_guard);
-
-        if let Some(audio_data) = audio_data {
-            fallback_transcription(
-                app,
-                inserter,
-                post_processor,
-                Arc::clone(&qwen_client_state),
-                Arc::clone(&sensevoice_client_state),
-                audio_data,
-            )
+        transcribe_recorded_audio(app, audio_data, min_recording, inserter, post_processor, qwen_client_state, sensevoice_client_state)
             .await;
-        } else {
-            let _ = app.emit("error", "没有录制到音频数据".to_string());
+    }
+}
+
+/// 不经实时会话，把流式录音保留的完整音频交给 HTTP 备用方案（误触、静音照常拦下）
+async fn transcribe_recorded_audio(
+    app: AppHandle,
+    audio_data: Option<Vec<u8>>,
+    min_recording: std::time::Duration,
+    inserter: Arc<Mutex<Option<TextInserter>>>,
+    post_processor: Arc<Mutex<Option<LlmPostProcessor>>>,
+    qwen_client_state: Arc<Mutex<Option<QwenASRClient>>>,
+    sensevoice_client_state: Arc<Mutex<Option<SenseVoiceClient>>>,
+) {
+    let Some(audio_data) = audio_data else {
+        emit_error(&app, "no_audio", "没有录制到音频数据");
+        return;
+    };
+    if reject_too_short(&app, audio_processing::wav_duration(&audio_data), min_recording) || reject_silent(&app, &audio_data) {
+        return;
+    }
+    keep_recording(&app, &audio_data);
+    if let Some(duration) = audio_processing::wav_duration(&audio_data) {
+        record_submitted(&app, duration);
+    }
+    fallback_transcription(app, inserter, post_processor, qwen_client_state, sensevoice_client_state, audio_data).await;
+}
+
+/// 服务端 VAD / 连续听写：录音期间持续消费每段转录结果并立即插入
+/// 连续听写中某一段失败不影响后续段，只提示错误
+async fn consume_vad_segments(
+    app: AppHandle,
+    mut segment_rx: tokio::sync::mpsc::Receiver<anyhow::Result<String>>,
+    inserter: Arc<Mutex<Option<TextInserter>>>,
+    post_processor: Arc<Mutex<Option<LlmPostProcessor>>>,
+    dictation: bool,
+) -> (usize, Option<String>) {
+    let mut delivered = 0;
+    let mut last_error = None;
+    let mut segment_start = std::time::Instant::now();
+
+    while let Some(result) = segment_rx.recv().await {
+        match result {
+            Ok(text) => {
+                let asr_time_ms = segment_start.elapsed().as_millis() as u64;
+                handle_transcription_result(
+                    app.clone(),
+                    Arc::clone(&inserter),
+                    Arc::clone(&post_processor),
+                    stats::QWEN_REALTIME,
+                    Ok(text),
+                    asr_time_ms,
+                )
+                .await;
+                delivered += 1;
+                if delivered == 1 {
+                    release_fallback_audio(&app);
+                }
+                segment_start = std::time::Instant::now();
+                if dictation {
+                    let _ = app.emit("dictation_segment", DictationSegment { index: delivered });
+                }
+            }
+            Err(e) => {
+                tracing::error!("分段转录失败: {}", e);
+                app.state::<AppState>().stats.record_failure(stats::QWEN_REALTIME);
+                last_error = Some(e.to_string());
+                if !dictation {
+                    break;
+                }
+                emit_error(&app, "transcription", format!("听写分段失败: {}", e));
+                segment_start = std::time::Instant::now();
+            }
         }
     }
+
+    // 仍在听写中结果通道就关闭了：会话已断开，后续录音只能在退出时整段回退
+    if dictation && app.state::<AppState>().dictation_mode.load(Ordering::SeqCst) {
+        tracing::warn!("连续听写会话已断开（已插入 {} 段）", delivered);
+        emit_error(&app, "realtime_connect", "连续听写连接已断开，请再按一次快捷键结束");
+    }
+
+    (delivered, last_error)
+}
+
+/// 已插入分段后不会再整段回退：除非要保留录音，释放录音器中的完整音频缓冲
+fn release_fallback_audio(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    if state.config.read().unwrap().audio.keep_recordings && !privacy::enabled() {
+        return;
+    }
+    if let Some(recorder) = state.streaming_recorder.lock().unwrap().as_ref() {
+        recorder.release_full_audio();
+    };
+}
+
+/// realtime_session 事件的 payload：本次按键取得会话的方式与耗时
+#[derive(Clone, serde::Serialize)]
+struct RealtimeSessionReady {
+    origin: qwen_realtime::SessionOrigin,
+    connect_ms: u64,
+}
+
+/// dictation_segment 事件的 payload（文本随 transcription_complete 发出）
+#[derive(Clone, serde::Serialize)]
+struct DictationSegment {
+    /// 本次听写中的第几段（从 1 开始）
+    index: usize,
+}
+
+/// 退出连续听写状态（设备错误、取消转录、停止服务时），原本在听写中才发出 dictation_stopped
+fn end_dictation(app: &AppHandle) {
+    if app.state::<AppState>().dictation_mode.swap(false, Ordering::SeqCst) {
+        let _ = app.emit("dictation_stopped", ());
+    }
+}
+
+/// 录音通过误触 / 静音检查、实际提交识别时累计使用统计中的录音时长
+fn record_submitted(app: &AppHandle, duration: std::time::Duration) {
+    app.state::<AppState>().stats.record_recording(duration);
+}
+
+/// 开启 audio.keep_recordings 时在后台把本次录音落盘（不等待写入完成），路径随转录结果发给前端
+fn keep_recording(app: &AppHandle, wav: &[u8]) {
+    let state = app.state::<AppState>();
+    let keep = state.config.read().unwrap().audio.keep_recordings;
+    *state.last_recording.lock().unwrap() = if keep { recordings::keep(wav.to_vec()) } else { None };
 }
 
 /// 备用转录方案（HTTP 模式）
@@ -532,23 +1726,110 @@ async fn fallback_transcription(
     sensevoice_client_state: Arc<Mutex<Option<SenseVoiceClient>>>,
     audio_data: Vec<u8>,
 ) {
-    let qwen_client = { qwen_client_state.lock().unwrap().clone() };
-    let sensevoice_client = { sensevoice_client_state.lock().unwrap().clone() };
+    metrics::record_fallback(stats::QWEN_REALTIME);
+    update_latency(&app, |t| {
+        t.set_fallback();
+        if let Some(duration) = audio_processing::wav_duration(&audio_data) {
+            t.set_audio_duration(duration);
+        }
+        t.mark(Mark::RequestStarted);
+    });
 
     let asr_start = std::time::Instant::now();
-    let result = if let Some(sensevoice) = sensevoice_client {
+    let (provider, result) = http_transcribe(&app, &qwen_client_state, &sensevoice_client_state, audio_data).await;
+    let asr_time_ms = asr_start.elapsed().as_millis() as u64;
+    update_latency(&app, |t| t.mark(Mark::RequestFinished));
+
+    handle_transcription_result(app, inserter, post_processor, provider, result, asr_time_ms).await;
+}
+
+/// 用 HTTP 接口转录完整录音（优先 SenseVoice，其次千问 HTTP），长录音按配置分段并行识别；
+/// 超出服务商单次时长上限的不提交
+async fn http_transcribe(
+    app: &AppHandle,
+    qwen_client_state: &Arc<Mutex<Option<QwenASRClient>>>,
+    sensevoice_client_state: &Arc<Mutex<Option<SenseVoiceClient>>>,
+    audio_data: Vec<u8>,
+) -> (&'static str, anyhow::Result<String>) {
+    let qwen_client = { qwen_client_state.lock().unwrap().clone() };
+    let sensevoice_client = { sensevoice_client_state.lock().unwrap().clone() };
+    let (segments, max_parallel) = http_segments(app, audio_data);
+    let (qwen_client, sensevoice_client) = match within_duration_limit(&segments, qwen_client, sensevoice_client) {
+        Ok(clients) => clients,
+        Err(e) => return (stats::QWEN_HTTP, Err(e)),
+    };
+    if let Some(sensevoice) = sensevoice_client {
         tracing::info!("使用 SenseVoice 备用方案");
-        sensevoice.transcribe_bytes(&audio_data).await
+        let result = segmentation::transcribe_segments(segments, max_parallel, |wav| {
+            let sensevoice = &sensevoice;
+            async move { sensevoice.transcribe_bytes(&wav).await }
+        })
+        .await;
+        (stats::SENSEVOICE, result)
     } else if let Some(qwen) = qwen_client {
         tracing::info!("使用 HTTP 模式千问 ASR 备用");
-        qwen.transcribe_bytes(&audio_data).await
+        let result = segmentation::transcribe_segments(segments, max_parallel, |wav| {
+            let qwen = &qwen;
+            async move { qwen.transcribe_bytes(&wav).await }
+        })
+        .await;
+        (stats::QWEN_HTTP, result)
     } else {
         tracing::error!("未找到可用的 ASR 客户端以处理备用方案");
-        Err(anyhow::anyhow!("ASR 客户端未初始化"))
-    };
-    let asr_time_ms = asr_start.elapsed().as_millis() as u64;
+        (stats::QWEN_HTTP, Err(anyhow::anyhow!("ASR 客户端未初始化")))
+    }
+}
 
-    handle_transcription_result(app, inserter, post_processor, result, asr_time_ms).await;
+/// realtime_recheck 事件的 payload
+#[derive(Clone, serde::Serialize)]
+struct RecheckEvent {
+    audio_secs: f32,
+    realtime_chars: usize,
+    // HTTP 复核结果的字数（复核失败时为 None）
+    http_chars: Option<usize>,
+    // 最终采用的结果："realtime" / "http"
+    used: &'static str,
+    error: Option<String>,
+}
+
+/// 实时结果疑似截断（中途丢了音频块）时用完整录音走 HTTP 复核，返回字数更多的结果及其来源
+async fn recheck_truncated(
+    app: &AppHandle,
+    qwen_client_state: &Arc<Mutex<Option<QwenASRClient>>>,
+    sensevoice_client_state: &Arc<Mutex<Option<SenseVoiceClient>>>,
+    text: String,
+    audio_duration: std::time::Duration,
+    audio_data: Vec<u8>,
+) -> (&'static str, String) {
+    let config = app.state::<AppState>().config.read().unwrap().truncation_check;
+    let Some(rate) = quality::truncated(&text, audio_duration, &config) else {
+        return (stats::QWEN_REALTIME, text);
+    };
+    let audio_secs = audio_duration.as_secs_f32();
+    tracing::warn!("实时结果疑似截断（音频 {:.1} 秒，每秒 {:.2} 字），用完整录音走 HTTP 复核", audio_secs, rate);
+
+    let realtime_chars = quality::char_count(&text);
+    let (provider, result) = http_transcribe(app, qwen_client_state, sensevoice_client_state, audio_data).await;
+    let (chosen, http_chars, error) = match result {
+        Ok(http_text) => {
+            let http_chars = quality::char_count(&http_text);
+            tracing::info!("HTTP 复核完成: 实时 {} 字, HTTP {} 字", realtime_chars, http_chars);
+            let chosen = if http_chars > realtime_chars { (provider, http_text) } else { (stats::QWEN_REALTIME, text) };
+            (chosen, Some(http_chars), None)
+        }
+        Err(e) => {
+            tracing::warn!("HTTP 复核失败，沿用实时结果: {}", e);
+            ((stats::QWEN_REALTIME, text), None, Some(e.to_string()))
+        }
+    };
+    let _ = app.emit("realtime_recheck", RecheckEvent {
+        audio_secs,
+        realtime_chars,
+        http_chars,
+        used: if chosen.0 == stats::QWEN_REALTIME { "realtime" } else { "http" },
+        error,
+    });
+    chosen
 }
 
 /// 实时模式转录处理（WebSocket）- 录完再传的回退模式
@@ -562,17 +1843,15 @@ async fn handle_realtime_transcription(
     qwen_client_state: Arc<Mutex<Option<QwenASRClient>>>,
     sensevoice_client_state: Arc<Mutex<Option<SenseVoiceClient>>>,
 ) {
-    let _ = app.emit("transcribing", ());
-
     // 停止流式录音，获取完整音频数据
     let audio_data = {
         let mut recorder_guard = streaming_recorder.lock().unwrap();
         if let Some(ref mut rec) = *recorder_guard {
             match rec.stop_streaming() {
-                Ok(data) => Some(data),
+                Ok(clip) => Some((clip, rec.chunk_ms())),
                 Err(e) => {
                     tracing::error!("停止流式录音失败: {}", e);
-                    let _ = app.emit("error", format!("停止录音失败: {}", e));
+                    emit_error(&app, "recording", format!("停止录音失败: {}", e));
                     None
                 }
             }
@@ -585,20 +1864,23 @@ async fn handle_realtime_transcription(
         return;
     }
 
-    let audio_data = audio_data.unwrap();
+    let (clip, chunk_ms) = audio_data.unwrap();
+    let _ = app.emit("transcribing", TranscribingPayload { duration_ms: Some(clip.duration_ms) });
+    record_submitted(&app, clip.duration());
+    let audio_data = clip.wav;
 
     // 尝试使用 WebSocket 实时 API
     tracing::info!("尝试使用 WebSocket 实时 API 转录...");
 
     let asr_start = std::time::Instant::now();
-    let realtime_client = QwenRealtimeClient::new(key.clone());
-    let ws_result = realtime_transcribe_audio(&realtime_client, &audio_data).await;
+    let realtime_client = QwenRealtimeClient::new(key.clone(), SessionOptions::default(), RealtimeOptions::default());
+    let ws_result = realtime_transcribe_audio(&realtime_client, &audio_data, chunk_ms).await;
     let asr_time_ms = asr_start.elapsed().as_millis() as u64;
 
     match ws_result {
         Ok(text) => {
-            tracing::info!("WebSocket 实时转录成功: {} (ASR 耗时: {}ms)", text, asr_time_ms);
-            handle_transcription_result(app, inserter, post_processor, Ok(text), asr_time_ms).await;
+            tracing::info!("WebSocket 实时转录成功: {} (ASR 耗时: {}ms)", privacy::redact(&text), asr_time_ms);
+            handle_transcription_result(app, inserter, post_processor, stats::QWEN_REALTIME, Ok(text), asr_time_ms).await;
         }
         Err(e) => {
             tracing::warn!("WebSocket 实时转录失败: {}，尝试备用方案", e);
@@ -619,16 +1901,17 @@ async fn handle_realtime_transcription(
 async fn realtime_transcribe_audio(
     client: &QwenRealtimeClient,
     wav_data: &[u8],
+    chunk_ms: u32,
 ) -> anyhow::Result<String> {
     // 创建 WebSocket 会话
-    let mut session = client.start_session().await?;
+    let mut session = client.start_session(qwen_realtime::DEFAULT_SAMPLE_RATE).await?;
 
     // 从 WAV 数据中提取 PCM 样本
     let pcm_samples = extract_pcm_from_wav(wav_data)?;
 
-    // 分块发送音频数据（每块 3200 样本 = 0.2秒 @ 16kHz）
-    const CHUNK_SIZE: usize = 3200;
-    for chunk in pcm_samples.chunks(CHUNK_SIZE) {
+    // 按配置的块时长分块发送（默认 200ms = 3200 样本 @ 16kHz）
+    let chunk_size = streaming_recorder::chunk_samples(chunk_ms, qwen_realtime::DEFAULT_SAMPLE_RATE);
+    for chunk in pcm_samples.chunks(chunk_size) {
         session.send_audio_chunk(chunk).await?;
         // 模拟实时发送的间隔（可选，用于更真实的流式体验）
         // tokio::time::sleep(std::time::Duration::from_millis(50)).await;
@@ -638,7 +1921,8 @@ async fn realtime_transcribe_audio(
     session.commit_audio().await?;
 
     // 等待转录结果
-    let result = session.wait_for_result().await?;
+    let wait = session.result_timeout();
+    let result = session.wait_for_result(wait).await?;
 
     // 关闭会话
     let _ = session.close().await;
@@ -660,6 +1944,23 @@ fn extract_pcm_from_wav(wav_data: &[u8]) -> anyhow::Result<Vec<i16>> {
     Ok(samples)
 }
 
+/// 错误事件的 payload：code 为稳定的错误码（指标按它分类），message 为界面提示文案
+#[derive(Clone, serde::Serialize)]
+struct ErrorPayload {
+    code: &'static str,
+    message: String,
+}
+
+fn emit_error(app: &AppHandle, code: &'static str, message: impl Into<String>) {
+    let _ = app.emit("error", ErrorPayload { code, message: message.into() });
+}
+
+/// 开始转录事件的 payload
+#[derive(Clone, serde::Serialize)]
+struct TranscribingPayload {
+    duration_ms: Option<u64>, // 提交识别的音频时长（停止录音失败时为 None）
+}
+
 /// 转录完成事件的 payload
 #[derive(Clone, serde::Serialize)]
 struct TranscriptionResult {
@@ -668,6 +1969,25 @@ struct TranscriptionResult {
     asr_time_ms: u64,
     llm_time_ms: Option<u64>,
     total_time_ms: u64,
+    duration_ms: Option<u64>, // 识别的音频时长（服务端 VAD 分段结果只有第一段带）
+    recording_path: Option<String>, // 保留的录音文件（仅开启 audio.keep_recordings 时有值）
+    privacy_mode: bool, // 隐私模式下前端不把识别文本写入历史记录
+}
+
+/// 未通过质量检查、等待用户确认的识别结果
+struct PendingTranscript {
+    text: String,
+    provider: &'static str,
+    asr_time_ms: u64,
+    post_processor: Arc<Mutex<Option<LlmPostProcessor>>>,
+}
+
+/// low_confidence 事件的 payload
+#[derive(Clone, serde::Serialize)]
+struct LowConfidencePayload {
+    text: String,
+    #[serde(flatten)]
+    reason: quality::LowConfidence,
 }
 
 /// 处理转录结果
@@ -675,59 +1995,229 @@ async fn handle_transcription_result(
     app: AppHandle,
     inserter: Arc<Mutex<Option<TextInserter>>>,
     post_processor: Arc<Mutex<Option<LlmPostProcessor>>>,
+    provider: &'static str,
     result: anyhow::Result<String>,
     asr_time_ms: u64,
 ) {
     match result {
         Ok(text) => {
-            tracing::info!("转录结果: {} (ASR 耗时: {}ms)", text, asr_time_ms);
-
-            // 如果启用了 LLM 后处理，则进行润色
-            let (final_text, original_text, llm_time_ms) = {
-                let processor = post_processor.lock().unwrap().clone();
-                if let Some(processor) = processor {
-                    tracing::info!("开始 LLM 后处理...");
-                    let _ = app.emit("post_processing", ());
-                    let llm_start = std::time::Instant::now();
-                    match processor.polish_transcript(&text).await {
-                        Ok(polished) => {
-                            let llm_elapsed = llm_start.elapsed().as_millis() as u64;
-                            tracing::info!("LLM 后处理完成: {} (耗时: {}ms)", polished, llm_elapsed);
-                            (polished, Some(text), Some(llm_elapsed))
-                        }
-                        Err(e) => {
-                            tracing::warn!("LLM 后处理失败，使用原文: {}", e);
-                            (text, None, None)
-                        }
-                    }
-                } else {
+            tracing::info!("转录结果: {} (ASR 耗时: {}ms)", privacy::redact(&text), asr_time_ms);
+            update_latency(&app, |t| t.mark(Mark::FinalResult));
+
+            // 明显不可靠的结果先不插入，交给用户决定
+            let quality_check = app.state::<AppState>().config.read().unwrap().quality_check;
+            if let Some(reason) = quality::check(&text, &quality_check) {
+                tracing::warn!("识别结果未通过质量检查 ({:?})，等待用户确认: {}", reason, privacy::redact(&text));
+                let state = app.state::<AppState>();
+                state.latency.lock().unwrap().take();
+                *state.pending_transcript.lock().unwrap() = Some(PendingTranscript {
+                    text: text.clone(),
+                    provider,
+                    asr_time_ms,
+                    post_processor,
+                });
+                let _ = app.emit("low_confidence", LowConfidencePayload { text, reason });
+                return;
+            }
+
+            deliver_transcript(app, inserter, post_processor, provider, text, asr_time_ms).await;
+        }
+        Err(e) => {
+            tracing::error!("转录失败: {}", e);
+            app.state::<AppState>().stats.record_failure(provider);
+            emit_error(&app, "transcription", format!("转录失败: {}", e));
+        }
+    }
+}
+
+/// 清理、润色并插入识别结果，随后上报转录完成事件
+async fn deliver_transcript(
+    app: AppHandle,
+    inserter: Arc<Mutex<Option<TextInserter>>>,
+    post_processor: Arc<Mutex<Option<LlmPostProcessor>>>,
+    provider: &'static str,
+    text: String,
+    asr_time_ms: u64,
+) {
+    // 原始文本投递给后台口头禅统计（不阻塞插入）
+    app.state::<AppState>().filler_tracker.record(&text);
+
+    // 末尾标点删除 / 口头禅清理（统计用的是清理前的原文），前台应用有覆盖时按应用规则；
+    // 自定义替换在润色之前执行，LLM 拿到的已是改正后的人名术语
+    let (cleanup, replacements) = {
+        let state = app.state::<AppState>();
+        let overrides = state.app_overrides.lock().unwrap().transcript_cleanup;
+        let config = state.config.read().unwrap();
+        (overrides.unwrap_or(config.transcript_cleanup), config.text_replacements.clone())
+    };
+    // 按句拆分发送依赖句末标点，先断句再逐句清理，否则标点删除后整段无法拆分
+    let clean = |text: &str| text_postprocess::apply_replacements(&text_postprocess::apply(text, &cleanup), &replacements);
+    let text = match split_config(&app, &inserter) {
+        Some(split) => text_inserter::split_then_clean(&text, &split, clean),
+        None => clean(&text),
+    };
+
+    // 如果启用了 LLM 后处理，则进行润色
+    let (final_text, original_text, llm_time_ms) = {
+        let processor = post_processor.lock().unwrap().clone();
+        if let Some(processor) = processor {
+            tracing::info!("开始 LLM 后处理...");
+            let _ = app.emit("post_processing", ());
+            let llm_start = std::time::Instant::now();
+            match processor.polish_transcript_cancellable(&text).await {
+                Ok(Some(polished)) => {
+                    let llm_elapsed = llm_start.elapsed().as_millis() as u64;
+                    tracing::info!("LLM 后处理完成: {} (耗时: {}ms)", privacy::redact(&polished), llm_elapsed);
+                    (polished, Some(text), Some(llm_elapsed))
+                }
+                Ok(None) => {
+                    tracing::info!("LLM 后处理已取消，使用原文");
                     (text, None, None)
                 }
-            };
+                Err(e) => {
+                    tracing::warn!("LLM 后处理失败，使用原文: {}", e);
+                    (text, None, None)
+                }
+            }
+        } else {
+            (text, None, None)
+        }
+    };
 
-            let total_time_ms = asr_time_ms + llm_time_ms.unwrap_or(0);
+    let total_time_ms = asr_time_ms + llm_time_ms.unwrap_or(0);
+    app.state::<AppState>().stats.record_success(provider, &final_text);
+
+    let duration_ms = app
+        .state::<AppState>()
+        .latency
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|t| t.audio_duration())
+        .map(|duration| duration.as_millis() as u64);
+    let result = TranscriptionResult {
+        text: final_text,
+        original_text,
+        asr_time_ms,
+        llm_time_ms,
+        total_time_ms,
+        duration_ms,
+        recording_path: app
+            .state::<AppState>()
+            .last_recording
+            .lock()
+            .unwrap()
+            .take()
+            .map(|path| path.display().to_string()),
+        privacy_mode: privacy::enabled(),
+    };
 
-            // 插入文本
-            let mut inserter_guard = inserter.lock().unwrap();
-            if let Some(ref mut ins) = *inserter_guard {
-                if let Err(e) = ins.insert_text(&final_text) {
-                    tracing::error!("插入文本失败: {}", e);
-                    let _ = app.emit("error", format!("插入文本失败: {}", e));
+    // 预览模式：先交给前端确认，等待 confirm_insert（等待确认的时间不计入端到端延迟）
+    let preview = app.state::<AppState>().config.read().unwrap().preview_before_insert;
+    if preview {
+        tracing::info!("预览模式，等待用户确认插入: {}", privacy::redact(&result.text));
+        let state = app.state::<AppState>();
+        state.latency.lock().unwrap().take();
+        *state.pending_preview.lock().unwrap() = Some(result.clone());
+        let _ = app.emit("transcription_preview", result);
+        return;
+    }
+
+    insert_final_text(&app, &inserter, &result.text).await;
+    update_latency(&app, |t| t.mark(Mark::Inserted));
+    // 服务端 VAD 模式下只有第一段结果带计时
+    let timeline = app.state::<AppState>().latency.lock().unwrap().take();
+
+    let _ = app.emit("transcription_complete", result);
+
+    if let Some(timeline) = timeline {
+        let report = timeline.report(latency_provider(&app, provider));
+        tracing::info!(
+            "端到端延迟 ({}): 松开到插入 {:?}ms, 音频 {:?}ms, 回退 {}",
+            report.provider,
+            report.release_to_insert_ms,
+            report.audio_ms,
+            report.fallback
+        );
+        if let Err(e) = latency::append(&report) {
+            tracing::warn!("写入延迟指标失败: {}", e);
+        }
+        let _ = app.emit("transcription_metrics", report);
+    }
+}
+
+/// 目标窗口所属应用启用了拆分发送时返回拆分规则（输出到文件 / 剪贴板时不拆分）
+fn split_config(app: &AppHandle, inserter: &Arc<Mutex<Option<TextInserter>>>) -> Option<SplitMessagesConfig> {
+    if app.state::<AppState>().config.read().unwrap().output_target != OutputTarget::ActiveWindow {
+        return None;
+    }
+    let target_window = inserter
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|ins| ins.target_window())
+        .or_else(active_window::foreground_window)?;
+    let process = active_window::process_name(target_window);
+    let class = active_window::window_class(target_window);
+    let state = app.state::<AppState>();
+    let config = state.config.read().unwrap();
+    config
+        .app_rule_for(process.as_deref(), class.as_deref())
+        .map(|rule| rule.split_messages.clone())
+        .filter(|split| split.enabled)
+}
+
+/// 把最终文本插入录音开始时的窗口，按目标应用规则决定是否拆分为多条消息发送
+async fn insert_final_text(app: &AppHandle, inserter: &Arc<Mutex<Option<TextInserter>>>, final_text: &str) {
+    // 输出目标不是当前窗口时，写文件或复制到剪贴板后返回
+    let output_target = app.state::<AppState>().config.read().unwrap().output_target.clone();
+    match output_target {
+        OutputTarget::ActiveWindow => {}
+        OutputTarget::File { path, timestamp } => {
+            match dictation_file::append(&path, final_text, timestamp) {
+                Ok(()) => tracing::info!("识别结果已追加到文件: {:?}", path),
+                Err(e) => {
+                    tracing::error!("写入输出文件失败: {}", e);
+                    emit_error(app, "output", format!("写入输出文件失败: {}", e));
                 }
             }
+            return;
+        }
+        OutputTarget::Clipboard => {
+            if let Some(ref mut ins) = *inserter.lock().unwrap() {
+                if let Err(e) = ins.copy_to_clipboard(final_text) {
+                    tracing::error!("复制到剪贴板失败: {}", e);
+                    emit_error(app, "output", format!("复制到剪贴板失败: {}", e));
+                }
+            }
+            return;
+        }
+    }
 
-            let result = TranscriptionResult {
-                text: final_text,
-                original_text,
-                asr_time_ms,
-                llm_time_ms,
-                total_time_ms,
-            };
-            let _ = app.emit("transcription_complete", result);
+    if let Some(split) = split_config(app, inserter) {
+        // 逐条发送之间有间隔等待，放到阻塞线程执行，不占用异步运行时
+        let messages = text_inserter::split_messages(final_text, split.max_chars, split.delimiter);
+        let inserter = Arc::clone(inserter);
+        let sent = tokio::task::spawn_blocking(move || {
+            inserter.lock().unwrap().as_mut().map(|ins| ins.insert_messages(&messages))
+        })
+        .await;
+        match sent {
+            Ok(Some(Err(e))) => {
+                tracing::warn!("拆分发送中断: {}", e);
+                emit_error(app, "insert", format!("拆分发送中断: {}", e));
+            }
+            Err(e) => tracing::error!("拆分发送任务异常: {}", e),
+            _ => {}
         }
-        Err(e) => {
-            tracing::error!("转录失败: {}", e);
-            let _ = app.emit("error", format!("转录失败: {}", e));
+        return;
+    }
+
+    // 插入文本
+    if let Some(ref mut ins) = *inserter.lock().unwrap() {
+        if let Err(e) = ins.insert_text(final_text) {
+            tracing::error!("插入文本失败: {}", e);
+            emit_error(app, "insert", format!("插入文本失败: {}", e));
         }
     }
 }
@@ -738,22 +2228,383 @@ async fn stop_app(app_handle: AppHandle) -> Result<String, String> {
 
     let state = app_handle.state::<AppState>();
 
-    let mut is_running = state.is_running.lock().unwrap();
-    if !*is_running {
+    if !*state.is_running.lock().unwrap() {
         return Err("应用未在运行".to_string());
     }
 
+    release_realtime_resources(&state).await;
+    end_dictation(&app_handle);
+
+    let mut is_running = state.is_running.lock().unwrap();
+
     *state.audio_recorder.lock().unwrap() = None;
     *state.streaming_recorder.lock().unwrap() = None;
     *state.text_inserter.lock().unwrap() = None;
     *state.post_processor.lock().unwrap() = None;
     *state.qwen_client.lock().unwrap() = None;
     *state.sensevoice_client.lock().unwrap() = None;
+    *state.running_config.lock().unwrap() = None;
+    state.listening_paused.store(false, Ordering::SeqCst);
     *is_running = false;
 
     Ok("应用已停止".to_string())
 }
 
+/// 暂停快捷键监听：只让快捷键不再触发录音，录音器、文本插入器、预热连接都保留，恢复时无需重新初始化
+#[tauri::command]
+async fn pause_listening(app_handle: AppHandle) -> Result<String, String> {
+    let state = app_handle.state::<AppState>();
+    if !*state.is_running.lock().unwrap() {
+        return Err("应用未在运行".to_string());
+    }
+    // 连续听写靠再次按下快捷键退出，暂停后将无法退出
+    if state.dictation_mode.load(Ordering::SeqCst) {
+        return Err("连续听写进行中，请先退出听写".to_string());
+    }
+    if !state.listening_paused.swap(true, Ordering::SeqCst) {
+        tracing::info!("已暂停快捷键监听");
+        let _ = app_handle.emit("listening_paused", ());
+    }
+    Ok("已暂停监听".to_string())
+}
+
+/// 恢复快捷键监听
+#[tauri::command]
+async fn resume_listening(app_handle: AppHandle) -> Result<String, String> {
+    let state = app_handle.state::<AppState>();
+    if !*state.is_running.lock().unwrap() {
+        return Err("应用未在运行".to_string());
+    }
+    if state.listening_paused.swap(false, Ordering::SeqCst) {
+        tracing::info!("已恢复快捷键监听");
+        let _ = app_handle.emit("listening_resumed", ());
+    }
+    Ok("已恢复监听".to_string())
+}
+
+/// 释放实时转录遗留的任务与连接（停止服务时调用）
+async fn release_realtime_resources(state: &AppState) {
+    // 泄漏修复：停止服务时终止发送任务 / 分段任务并关闭会话，避免任务与 WebSocket 连接常驻
+    let sender_handle = state.audio_sender_handle.lock().unwrap().take();
+    if let Some(h) = sender_handle {
+        h.abort();
+    }
+    let segment_handle = state.segment_task_handle.lock().unwrap().take();
+    if let Some(h) = segment_handle {
+        h.abort();
+    }
+    if let Some(session) = state.active_session.lock().await.take() {
+        let _ = session.close().await;
+    }
+    // 先关闭池中的预热会话，再释放客户端（连接池的清理任务随之取消）
+    let profiles = std::mem::take(&mut *state.hotkey_profiles.lock().unwrap());
+    for client in profiles.iter().filter_map(|profile| profile.realtime_client.as_ref()) {
+        client.shutdown().await;
+    }
+}
+
+/// 累计使用统计（录音时长、转录字数、各 provider 成功/失败次数）
+#[tauri::command]
+async fn get_stats(app_handle: AppHandle) -> Result<UsageStats, String> {
+    let state = app_handle.state::<AppState>();
+    Ok(state.stats.snapshot())
+}
+
+/// 当前输入设备及其生效的音频处理参数（全局默认 + 设备覆盖）
+#[derive(serde::Serialize)]
+struct DeviceAudioProcessing {
+    device: Option<String>,
+    processing: config::AudioProcessing,
+}
+
+#[tauri::command]
+async fn get_audio_processing() -> Result<DeviceAudioProcessing, String> {
+    let config = AppConfig::load().map_err(|e| format!("加载配置失败: {}", e))?;
+    let device = audio_recorder::selected_input_device_name(config.audio.input_device.as_deref());
+    let processing = match device {
+        Some(ref name) => config.audio.for_device(name),
+        None => config.audio.defaults(),
+    };
+    Ok(DeviceAudioProcessing { device, processing })
+}
+
+/// 设置页修改音频处理参数：写入当前输入设备（audio.input_device，找不到时为默认设备）的覆盖条目，服务运行中则下次录音即生效
+#[tauri::command]
+async fn save_audio_processing(app_handle: AppHandle, processing: config::AudioProcessing) -> Result<String, String> {
+    let mut config = AppConfig::load().map_err(|e| format!("加载配置失败: {}", e))?;
+    let device = audio_recorder::selected_input_device_name(config.audio.input_device.as_deref())
+        .ok_or_else(|| "没有找到音频输入设备".to_string())?;
+    config.audio.set_for_device(&device, processing);
+    config.save().map_err(|e| format!("保存配置失败: {}", e))?;
+    let audio = config.audio.clone();
+    publish_config(&app_handle, config);
+
+    let state = app_handle.state::<AppState>();
+    if let Some(ref mut recorder) = *state.audio_recorder.lock().unwrap() {
+        recorder.set_audio_config(audio.clone());
+    }
+    if let Some(ref mut recorder) = *state.streaming_recorder.lock().unwrap() {
+        recorder.set_audio_config(audio);
+    }
+
+    tracing::info!("已保存设备 {} 的音频处理参数: {:?}", device, processing);
+    Ok(format!("已保存到设备: {}", device))
+}
+
+/// 离线转录进度（每识别完一个片段发送一次）
+#[derive(Clone, serde::Serialize)]
+struct FileTranscriptionProgress {
+    done: usize,
+    total: usize,
+}
+
+/// 离线转录音频文件（wav / mp3 / m4a 等）：按片段依次识别后拼接返回，不插入文本
+#[tauri::command]
+async fn transcribe_file(app_handle: AppHandle, path: String) -> Result<String, String> {
+    let config = AppConfig::load().map_err(|e| format!("加载配置失败: {}", e))?;
+    let dns_overrides = DnsOverrides::from_config(&config.dns_overrides).with_proxy(config.proxy.as_deref());
+    let qwen = (!config.dashscope_api_key.trim().is_empty())
+        .then(|| QwenASRClient::new(config.dashscope_api_key.clone(), &dns_overrides).with_base_url(&config.dashscope_base_url));
+    let sensevoice = (!config.siliconflow_api_key.trim().is_empty())
+        .then(|| SenseVoiceClient::new(config.siliconflow_api_key.clone(), &dns_overrides).with_base_url(&config.siliconflow_base_url));
+    if qwen.is_none() && sensevoice.is_none() {
+        return Err("请先配置 DashScope 或硅基流动 API Key".to_string());
+    }
+
+    tracing::info!("开始离线转录文件: {}", path);
+    let file_path = std::path::PathBuf::from(&path);
+    let segments = tokio::task::spawn_blocking(move || audio_file::load_segments(&file_path))
+        .await
+        .map_err(|e| format!("解码任务异常: {}", e))?
+        .map_err(|e| format!("解码音频文件失败: {}", e))?;
+
+    let total = segments.len();
+    let mut text = String::new();
+    for (index, wav) in segments.into_iter().enumerate() {
+        let result = match (&qwen, &sensevoice) {
+            (Some(qwen), Some(sensevoice)) => {
+                qwen_asr::transcribe_with_fallback_clients(qwen.clone(), sensevoice.clone(), wav).await
+            }
+            (Some(qwen), None) => qwen.transcribe_bytes(&wav).await,
+            (None, Some(sensevoice)) => sensevoice.transcribe_bytes(&wav).await,
+            (None, None) => Err(anyhow::anyhow!("ASR 客户端未初始化")),
+        };
+        let segment = result.map_err(|e| format!("第 {}/{} 段识别失败: {}", index + 1, total, e))?;
+        text.push_str(&segment);
+        tracing::info!("离线转录进度 {}/{}: {}", index + 1, total, privacy::redact(&segment));
+        let _ = app_handle.emit("file_transcription_progress", FileTranscriptionProgress { done: index + 1, total });
+    }
+
+    let text = text_postprocess::apply(&text, &config.transcript_cleanup);
+    Ok(text_postprocess::apply_replacements(&text, &config.text_replacements))
+}
+
+/// 历史原始转录中出现最多的疑似口头禅
+#[tauri::command]
+async fn get_filler_words(app_handle: AppHandle, top_n: Option<usize>) -> Result<Vec<FillerWord>, String> {
+    let state = app_handle.state::<AppState>();
+    Ok(state.filler_tracker.top(top_n.unwrap_or(DEFAULT_FILLER_TOP_N)))
+}
+
+/// 把高频口头禅注入指定预设：写入模板变量 {filler_words}，预设中没有该变量时自动追加一句，
+/// 返回更新后的 system prompt
+#[tauri::command]
+async fn apply_to_preset(app_handle: AppHandle, preset_id: String, top_n: Option<usize>) -> Result<String, String> {
+    let state = app_handle.state::<AppState>();
+    let filler_words: Vec<String> = state
+        .filler_tracker
+        .top(top_n.unwrap_or(DEFAULT_FILLER_TOP_N))
+        .into_iter()
+        .map(|w| w.word)
+        .collect();
+    if filler_words.is_empty() {
+        return Err("暂无口头禅统计数据，请先多录几段".to_string());
+    }
+
+    let mut config = AppConfig::load().map_err(|e| format!("加载配置失败: {}", e))?;
+    let preset = config
+        .llm_config
+        .presets
+        .iter_mut()
+        .find(|p| p.id == preset_id)
+        .ok_or_else(|| format!("未找到预设: {}", preset_id))?;
+    if !preset.system_prompt.contains(llm_post_processor::FILLER_WORDS_VAR) {
+        preset.system_prompt.push_str(&format!(
+            "\n请删除以下口头禅：{}",
+            llm_post_processor::FILLER_WORDS_VAR
+        ));
+    }
+    let system_prompt = preset.system_prompt.clone();
+    config.filler_words = filler_words.clone();
+    config.save().map_err(|e| format!("保存配置失败: {}", e))?;
+    publish_config(&app_handle, config);
+
+    // 服务运行中则立即生效
+    if let Some(ref mut processor) = *state.post_processor.lock().unwrap() {
+        processor.update_preset_prompt(&preset_id, &system_prompt, filler_words.clone());
+    }
+
+    tracing::info!("已将口头禅注入预设 {}: {}", preset_id, filler_words.join("、"));
+    Ok(system_prompt)
+}
+
+/// 连通性诊断：逐个检查识别 / 润色服务域名的解析结果（含 DNS 覆盖是否生效）与可达性
+#[tauri::command]
+async fn run_diagnostics() -> Result<Vec<HostDiagnostic>, String> {
+    let config = AppConfig::load().map_err(|e| format!("加载配置失败: {}", e))?;
+    let dns_overrides = DnsOverrides::from_config(&config.dns_overrides).with_proxy(config.proxy.as_deref());
+
+    let mut hosts: Vec<String> = [
+        &config.dashscope_base_url,
+        &config.dashscope_ws_url,
+        &config.siliconflow_base_url,
+        &config.llm_config.endpoint,
+    ]
+    .into_iter()
+    .filter_map(|url| reqwest::Url::parse(url).ok()?.host_str().map(str::to_string))
+    .collect();
+    hosts.extend(dns_overrides.iter().map(|(host, _)| host.to_string()));
+    hosts.sort();
+    hosts.dedup();
+
+    let mut report = Vec::with_capacity(hosts.len());
+    for host in &hosts {
+        let diagnostic = dns_overrides.diagnose(host).await;
+        tracing::info!(
+            "诊断 {}: 覆盖={:?}, 系统解析={:?}, 实际={:?}, 可达={}",
+            diagnostic.host,
+            diagnostic.override_ip,
+            diagnostic.system_ips,
+            diagnostic.effective_ip,
+            diagnostic.reachable
+        );
+        report.push(diagnostic);
+    }
+    Ok(report)
+}
+
+/// 端到端延迟自测：用内置测试音频（或指定音频文件的前 60 秒）依次跑已配置 Key 的 HTTP、实时、SenseVoice 识别，
+/// 返回各路径的耗时与识别文本
+#[tauri::command]
+async fn run_self_test(audio_path: Option<String>) -> Result<self_test::SelfTestReport, String> {
+    let config = AppConfig::load().map_err(|e| format!("加载配置失败: {}", e))?;
+    let dashscope_key = config.dashscope_api_key.trim().to_string();
+    let siliconflow_key = config.siliconflow_api_key.trim().to_string();
+    if dashscope_key.is_empty() && siliconflow_key.is_empty() {
+        return Err("请先配置 DashScope 或硅基流动 API Key".to_string());
+    }
+    let dns_overrides = DnsOverrides::from_config(&config.dns_overrides).with_proxy(config.proxy.as_deref());
+
+    let builtin_audio = audio_path.is_none();
+    let wav = match audio_path {
+        Some(path) => {
+            let file_path = std::path::PathBuf::from(&path);
+            let segments = tokio::task::spawn_blocking(move || audio_file::load_segments(&file_path))
+                .await
+                .map_err(|e| format!("解码任务异常: {}", e))?
+                .map_err(|e| format!("解码音频文件失败: {}", e))?;
+            segments.into_iter().next().ok_or_else(|| "音频文件为空".to_string())?
+        }
+        None => self_test::builtin_clip().map_err(|e| format!("生成测试音频失败: {}", e))?,
+    };
+    let audio_ms = audio_processing::wav_duration(&wav).unwrap_or_default().as_millis() as u64;
+    tracing::info!("开始端到端自测，测试音频 {}ms{}", audio_ms, if builtin_audio { "（内置）" } else { "" });
+
+    let mut results = Vec::new();
+    if !dashscope_key.is_empty() {
+        let qwen = QwenASRClient::new(dashscope_key.clone(), &dns_overrides).with_base_url(&config.dashscope_base_url);
+        results.push(self_test::measure(stats::QWEN_HTTP, qwen.transcribe_bytes(&wav)).await);
+
+        let endpoint = RealtimeEndpoint::dashscope(dashscope_key, &config.dashscope_ws_url, &config.realtime_model);
+        let realtime = RealtimeOptions {
+            vocabulary: config.realtime_vocabulary.clone(),
+            model: config.realtime_model.clone(),
+            punctuation: config.realtime_punctuation,
+            extra: config.realtime_session_extra.clone(),
+            ..RealtimeOptions::default()
+        };
+        let client = QwenRealtimeClient::with_endpoint(endpoint, SessionOptions::default(), realtime);
+        let chunk_ms = config.audio.effective_chunk_ms();
+        results.push(self_test::measure(stats::QWEN_REALTIME, realtime_transcribe_audio(&client, &wav, chunk_ms)).await);
+        client.shutdown().await;
+    }
+    if !siliconflow_key.is_empty() {
+        let sensevoice = SenseVoiceClient::new(siliconflow_key, &dns_overrides).with_base_url(&config.siliconflow_base_url);
+        results.push(self_test::measure(stats::SENSEVOICE, sensevoice.transcribe_bytes(&wav)).await);
+    }
+    Ok(self_test::SelfTestReport { audio_ms, builtin_audio, results })
+}
+
+/// 列出可用的音频输入设备，供设置页选择 `audio.input_device`
+#[tauri::command]
+async fn list_audio_devices() -> Result<Vec<audio_recorder::InputDeviceInfo>, String> {
+    tokio::task::spawn_blocking(audio_recorder::list_input_devices)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// 麦克风测试：在指定设备（为空时用配置中的设备或系统默认设备）上录 2 秒并返回电平统计，
+/// 供设置页提示"检测到声音 / 完全静音 / 可能爆音"。使用独立的录音器，不转录，也不要求服务已启动
+#[tauri::command]
+async fn test_microphone(device: Option<String>) -> Result<audio_recorder::MicrophoneTest, String> {
+    let config = AppConfig::load().map_err(|e| format!("加载配置失败: {}", e))?;
+    let mut audio = config.audio;
+    if let Some(device) = device.filter(|name| !name.trim().is_empty()) {
+        audio.input_device = Some(device);
+    }
+    // 独立录音器只用这一次，不建立常驻流、没有预滚动
+    audio.keep_stream_open = false;
+    tokio::task::spawn_blocking(move || {
+        let mut recorder = AudioRecorder::new()?;
+        recorder.set_audio_config(audio);
+        recorder.start_recording()?;
+        std::thread::sleep(MICROPHONE_TEST_DURATION);
+        Ok::<_, anyhow::Error>(recorder.stop_microphone_test())
+    })
+    .await
+    .map_err(|e| format!("麦克风测试任务异常: {}", e))?
+    .map_err(|e| format!("麦克风测试失败: {}", e))
+}
+
+/// 拖动输入增益滑块时实时预览（只改当前生效值，不写配置；松手后由 update_config 保存 input_gain）
+#[tauri::command]
+async fn set_input_gain(gain: f32) -> Result<f32, String> {
+    audio_processing::set_input_gain(gain);
+    Ok(audio_processing::input_gain())
+}
+
+/// 用系统文件管理器打开日志目录（运行日志、WebSocket 事件留痕等排障文件）
+#[tauri::command]
+async fn open_log_dir() -> Result<String, String> {
+    let dir = logging::log_dir().map_err(|e| e.to_string())?;
+    open_dir(&dir, "日志目录")
+}
+
+/// 用系统文件管理器打开保留的录音目录（audio.keep_recordings）
+#[tauri::command]
+async fn open_recordings_dir() -> Result<String, String> {
+    let dir = recordings::recordings_dir().map_err(|e| e.to_string())?;
+    open_dir(&dir, "录音目录")
+}
+
+/// 创建目录（如不存在）并用系统文件管理器打开，返回目录路径
+fn open_dir(dir: &std::path::Path, label: &str) -> Result<String, String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("创建{}失败: {}", label, e))?;
+    let opener = if cfg!(target_os = "windows") {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    std::process::Command::new(opener)
+        .arg(dir)
+        .spawn()
+        .map_err(|e| format!("打开{}失败: {}", label, e))?;
+    Ok(dir.display().to_string())
+}
+
 #[tauri::command]
 async fn hide_to_tray(app_handle: AppHandle) -> Result<String, String> {
     if let Some(window) = app_handle.get_webview_window("main") {
@@ -766,6 +2617,7 @@ async fn hide_to_tray(app_handle: AppHandle) -> Result<String, String> {
 async fn quit_app(app_handle: AppHandle) -> Result<(), String> {
     // 先停止服务
     let state = app_handle.state::<AppState>();
+    release_realtime_resources(&state).await;
     {
         let mut is_running = state.is_running.lock().unwrap();
         if *is_running {
@@ -788,6 +2640,142 @@ async fn cancel_transcription(app_handle: AppHandle) -> Result<String, String> {
 
     let state = app_handle.state::<AppState>();
 
+    // 1. 中止录音、发送任务、分段任务与 WebSocket 会话
+    abort_recording(&state).await;
+    end_dictation(&app_handle);
+
+    // 2. 取消进行中的 LLM 后处理（已转录的原文仍会插入）
+    {
+        let processor = state.post_processor.lock().unwrap().clone();
+        if let Some(processor) = processor {
+            if processor.cancel() {
+                tracing::info!("已取消 LLM 后处理，回退到原始转录文本");
+            }
+        }
+    }
+
+    // 3. 发送取消事件
+    let _ = app_handle.emit("transcription_cancelled", ());
+
+    Ok("已取消转录".to_string())
+}
+
+/// 用户确认插入未通过质量检查的识别结果（按原流程清理、润色后插入）
+#[tauri::command]
+async fn insert_low_confidence(app_handle: AppHandle) -> Result<String, String> {
+    let state = app_handle.state::<AppState>();
+    let pending = state
+        .pending_transcript
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or_else(|| "没有待确认的识别结果".to_string())?;
+    let inserter = Arc::clone(&state.text_inserter);
+
+    tracing::info!("用户确认插入低置信度结果");
+    deliver_transcript(
+        app_handle.clone(),
+        inserter,
+        pending.post_processor,
+        pending.provider,
+        pending.text,
+        pending.asr_time_ms,
+    )
+    .await;
+    Ok("已插入".to_string())
+}
+
+/// 预览模式下用户确认插入（text 为用户在浮窗中确认 / 修改后的文本）
+#[tauri::command]
+async fn confirm_insert(app_handle: AppHandle, text: String) -> Result<String, String> {
+    let state = app_handle.state::<AppState>();
+    let mut result = state
+        .pending_preview
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or_else(|| "没有待确认的预览结果".to_string())?;
+    let inserter = Arc::clone(&state.text_inserter);
+
+    tracing::info!("用户确认插入预览结果: {}", privacy::redact(&text));
+    insert_final_text(&app_handle, &inserter, &text).await;
+    result.text = text;
+    let _ = app_handle.emit("transcription_complete", result);
+    Ok("已插入".to_string())
+}
+
+/// 撤销最近一次插入的识别结果（切回插入的窗口后模拟退格删除）
+/// 插入后又有键盘输入、窗口已关闭或结果是拆分发送的消息时放弃撤销，返回原因供前端提示
+#[tauri::command]
+async fn undo_last_insert(app_handle: AppHandle) -> Result<String, String> {
+    let state = app_handle.state::<AppState>();
+    let mut inserter = state.text_inserter.lock().unwrap();
+    let Some(ref mut ins) = *inserter else {
+        return Err("服务未启动".to_string());
+    };
+    match ins.undo_last_insert() {
+        Ok(count) => Ok(format!("已撤销 {} 个字符", count)),
+        Err(e) => {
+            tracing::warn!("撤销插入失败: {}", e);
+            Err(e.to_string())
+        }
+    }
+}
+
+/// recording_too_short 事件的 payload
+#[derive(Clone, serde::Serialize)]
+struct RecordingTooShort {
+    duration_ms: u64,
+    min_recording_ms: u64,
+}
+
+/// 录音短于 audio.min_recording_ms（多为误触）时丢弃不发送任何请求，清理本次的延迟计时，
+/// 并发送 recording_too_short 事件
+fn reject_too_short(app: &AppHandle, duration: Option<std::time::Duration>, min_recording: std::time::Duration) -> bool {
+    let Some(duration) = duration.filter(|duration| *duration < min_recording) else {
+        return false;
+    };
+    tracing::info!(
+        "录音时长 {}ms 短于最短时长 {}ms，视为误触丢弃",
+        duration.as_millis(),
+        min_recording.as_millis()
+    );
+    app.state::<AppState>().latency.lock().unwrap().take();
+    let _ = app.emit("recording_too_short", RecordingTooShort {
+        duration_ms: duration.as_millis() as u64,
+        min_recording_ms: min_recording.as_millis() as u64,
+    });
+    true
+}
+
+/// 整段录音峰值电平低于 audio.silence_threshold_dbfs（麦克风选错或被静音）时跳过转录，
+/// 发送 no_audio_detected 事件（载荷为峰值 / 平均电平）
+fn reject_silent(app: &AppHandle, audio_data: &[u8]) -> bool {
+    let threshold = app.state::<AppState>().config.read().unwrap().audio.silence_threshold_dbfs;
+    let Some(levels) = audio_processing::wav_levels(audio_data).filter(|levels| levels.is_silent(threshold)) else {
+        return false;
+    };
+    tracing::warn!(
+        "整段录音没有有效音量 (峰值 {:.1} dBFS, 平均 {:.1} dBFS, 阈值 {:.1} dBFS)，跳过转录",
+        levels.peak_dbfs,
+        levels.rms_dbfs,
+        threshold
+    );
+    let _ = app.emit("no_audio_detected", levels);
+    true
+}
+
+/// 在后台完成会话的关闭握手（等待服务端回执），不阻塞后续的插入 / 回退流程
+fn close_session_in_background(session: Option<qwen_realtime::RealtimeSession>) {
+    if let Some(session) = session {
+        tokio::spawn(async move {
+            let _ = session.close().await;
+        });
+    }
+}
+
+/// 中止当前录音：丢弃录音数据，终止发送 / 分段任务并关闭 WebSocket 会话
+async fn abort_recording(state: &AppState) {
     // 1. 停止流式录音
     {
         let mut recorder_guard = state.streaming_recorder.lock().unwrap();
@@ -804,7 +2792,15 @@ async fn cancel_transcription(app_handle: AppHandle) -> Result<String, String> {
         }
     }
 
-    // 3. 取消音频发送任务
+    // 3. 通知服务端取消转写（会话可能正被等待结果的任务锁住，经由取消句柄发送），
+    //    须在终止发送任务之前标记取消，收尾流程据此放弃回退
+    let canceller = state.session_canceller.lock().unwrap().take();
+    if let Some(canceller) = canceller {
+        canceller.cancel().await;
+        tracing::info!("已取消实时转录会话");
+    }
+
+    // 4. 取消音频发送任务
     {
         let handle = state.audio_sender_handle.lock().unwrap().take();
         if let Some(h) = handle {
@@ -813,29 +2809,140 @@ async fn cancel_transcription(app_handle: AppHandle) -> Result<String, String> {
         }
     }
 
-    // 4. 关闭 WebSocket 会话
+    // 5. 取消服务端 VAD 分段消费任务
     {
-        let mut session_guard = state.active_session.lock().await;
-        if let Some(ref session) = *session_guard {
-            let _ = session.close().await;
+        let handle = state.segment_task_handle.lock().unwrap().take();
+        if let Some(h) = handle {
+            h.abort();
+            tracing::info!("已取消分段消费任务");
+        }
+    }
+
+    // 6. 释放 WebSocket 会话（已取消的会话直接返回）
+    {
+        let session = state.active_session.lock().await.take();
+        if let Some(session) = session {
+            session.cancel().await;
             tracing::info!("已关闭 WebSocket 会话");
         }
-        *session_guard = None;
     }
+}
 
-    // 5. 发送取消事件
-    let _ = app_handle.emit("transcription_cancelled", ());
+/// service_error 事件的 payload：需要用户处理的服务端错误
+#[derive(Clone, serde::Serialize)]
+struct ServiceErrorEvent {
+    /// 错误码，目前只有 "auth"（API Key 无效或无权限）
+    error_code: &'static str,
+    message: String,
+}
 
-    Ok("已取消转录".to_string())
+/// 鉴权失败：提示检查 API Key，不再回退到同样会被拒绝的 HTTP 识别
+fn report_auth_error(app: &AppHandle, error: &anyhow::Error) {
+    tracing::error!("实时识别鉴权失败，跳过回退: {}", error);
+    emit_error(app, "auth", format!("认证失败: {}", error));
+    let _ = app.emit("service_error", ServiceErrorEvent {
+        error_code: "auth",
+        message: error.to_string(),
+    });
+}
+
+/// network_offline 事件的 payload
+#[derive(Clone, serde::Serialize)]
+struct NetworkOfflineEvent {
+    // 探测的服务地址（host:port）
+    target: String,
+}
+
+/// 所选输入设备找不到事件的 payload
+#[derive(Clone, serde::Serialize)]
+struct InputDeviceMissingEvent {
+    requested: String,
+    // 本次录音实际使用的默认输入设备
+    fallback_device: Option<String>,
+}
+
+/// 配置的输入设备找不到（已回退默认设备）时通知前端
+fn warn_missing_device(app: &AppHandle, missing: Option<String>) {
+    let Some(requested) = missing else {
+        return;
+    };
+    let fallback_device = audio_recorder::default_input_device_name();
+    let _ = app.emit("input_device_missing", InputDeviceMissingEvent { requested, fallback_device });
+}
+
+/// audio_device_lost 事件的 payload
+#[derive(Clone, serde::Serialize)]
+struct DeviceLostEvent {
+    message: String,
+    // 下次录音将使用的默认输入设备（没有可用设备时为 None）
+    fallback_device: Option<String>,
+}
+
+/// recording_auto_stopped 事件的 payload
+#[derive(Clone, serde::Serialize)]
+struct RecordingAutoStopped {
+    // 自动停止的原因，目前只有 "max_duration"
+    reason: &'static str,
+    max_duration_secs: u32,
+}
+
+/// 监听本次录音是否达到最长时长，达到时把快捷键组序号交给自动停止任务；录音正常停止后通道关闭，任务随之结束
+fn spawn_duration_limit_watcher(
+    mut reached: tokio::sync::mpsc::UnboundedReceiver<()>,
+    auto_stop: tokio::sync::mpsc::UnboundedSender<usize>,
+    index: usize,
+) {
+    tokio::spawn(async move {
+        if reached.recv().await.is_some() {
+            let _ = auto_stop.send(index);
+        }
+    });
+}
+
+/// 把录音回调送来的电平转发为 audio_level 事件；录音流释放后通道关闭，发送 audio_level_stopped 让前端收起音量条
+fn spawn_level_forwarder(app: AppHandle, mut levels: tokio::sync::mpsc::UnboundedReceiver<audio_processing::AudioLevel>) {
+    tokio::spawn(async move {
+        while let Some(level) = levels.recv().await {
+            let _ = app.emit("audio_level", level);
+        }
+        let _ = app.emit("audio_level_stopped", ());
+    });
+}
+
+/// 监听本次录音的设备丢失（录音器切换默认设备失败，或流式录音的采集流出错）；
+/// 录音正常停止后录音流释放，通道关闭，任务随之结束
+fn spawn_device_error_watcher(app: AppHandle, mut errors: tokio::sync::mpsc::UnboundedReceiver<String>) {
+    tokio::spawn(async move {
+        let Some(message) = errors.recv().await else {
+            return;
+        };
+        tracing::error!("录音设备丢失，取消当前录音: {}", message);
+
+        let state = app.state::<AppState>();
+        state.recording_aborted.store(true, Ordering::SeqCst);
+        abort_recording(&state).await;
+        end_dictation(&app);
+
+        // 每次开始录音都会重新获取默认设备，这里提示下次将切换到的设备
+        let fallback_device = audio_recorder::default_input_device_name();
+        match fallback_device {
+            Some(ref name) => tracing::info!("下次录音将切回默认输入设备: {}", name),
+            None => tracing::warn!("当前没有可用的音频输入设备"),
+        }
+
+        let _ = app.emit("audio_device_lost", DeviceLostEvent { message, fallback_device });
+    });
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    // 初始化日志
-    tracing_subscriber::fmt::init();
+    // 初始化日志（控制台 + 日志文件），guard 持有到退出，保证缓冲的日志写完
+    let _log_guard = logging::init();
 
     tauri::Builder::default()
         .setup(|app| {
+            let startup_config = AppConfig::load().unwrap_or_else(|_| AppConfig::new());
+
             // 初始化应用状态
             let app_state = AppState {
                 audio_recorder: Arc::new(Mutex::new(None)),
@@ -847,11 +2954,40 @@ pub fn run() {
                 enable_post_process: Arc::new(Mutex::new(false)),
                 qwen_client: Arc::new(Mutex::new(None)),
                 sensevoice_client: Arc::new(Mutex::new(None)),
+                hotkey_profiles: Arc::new(Mutex::new(Vec::new())),
                 active_session: Arc::new(tokio::sync::Mutex::new(None)),
+                session_canceller: Arc::new(Mutex::new(None)),
                 audio_sender_handle: Arc::new(Mutex::new(None)),
+                segment_task_handle: Arc::new(Mutex::new(None)),
+                config: Arc::new(RwLock::new(startup_config.clone())),
+                running_config: Arc::new(Mutex::new(None)),
+                recording_aborted: Arc::new(AtomicBool::new(false)),
+                realtime_degraded: Arc::new(AtomicBool::new(false)),
+                listening_paused: Arc::new(AtomicBool::new(false)),
+                filler_tracker: Arc::new(FillerTracker::new()),
+                stats: Arc::new(StatsTracker::new()),
+                recording_started_at: Arc::new(Mutex::new(None)),
+                latency: Arc::new(Mutex::new(None)),
+                pending_transcript: Arc::new(Mutex::new(None)),
+                pending_preview: Arc::new(Mutex::new(None)),
+                dictation_mode: Arc::new(AtomicBool::new(false)),
+                app_overrides: Arc::new(Mutex::new(AppOverrides::default())),
+                last_recording: Arc::new(Mutex::new(None)),
             };
             app.manage(app_state);
 
+            // 运行指标：订阅应用事件；按配置启动本地控制接口（含 /metrics）
+            metrics::subscribe(app.handle());
+            control::spawn_server(startup_config.control);
+
+            // 隐私模式在启动时即生效，避免首次 start_app 之前的日志带出内容
+            privacy::set_enabled(startup_config.privacy_mode);
+            beep_player::set_volume(startup_config.beep_volume);
+            audio_processing::set_input_gain(startup_config.input_gain);
+            logging::set_level(&startup_config.log_level);
+            audio_codec::set_upload_codec(startup_config.audio.upload_codec);
+            recordings::prune_expired(startup_config.audio.keep_recordings_days);
+
             // 创建托盘菜单
             let show_item = MenuItem::with_id(app, "show", "显示窗口", true, None::<&str>)?;
             let quit_item = MenuItem::with_id(app, "quit", "退出程序", true, None::<&str>)?;
@@ -896,10 +3032,29 @@ pub fn run() {
         })
         .invoke_handler(tauri::generate_handler![
             save_config,
+            update_config,
             load_config,
             start_app,
             stop_app,
+            pause_listening,
+            resume_listening,
             cancel_transcription,
+            insert_low_confidence,
+            confirm_insert,
+            undo_last_insert,
+            run_diagnostics,
+            run_self_test,
+            get_filler_words,
+            apply_to_preset,
+            get_stats,
+            get_audio_processing,
+            save_audio_processing,
+            transcribe_file,
+            list_audio_devices,
+            test_microphone,
+            open_log_dir,
+            open_recordings_dir,
+            set_input_gain,
             hide_to_tray,
             quit_app,
         ])
@@ -3,37 +3,146 @@
 use anyhow::Result;
 use reqwest::Client;
 use serde_json::Value;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use tokio::task::AbortHandle;
 
 use crate::config::LlmConfig;
+use crate::dns::DnsOverrides;
+
+/// 预设 system prompt 中的口头禅模板变量
+pub const FILLER_WORDS_VAR: &str = "{filler_words}";
+// 尚无统计结果时代入模板变量的默认口头禅
+const DEFAULT_FILLER_WORDS: &str = "嗯、啊、呃";
+// 超时或 5xx 后重试前的等待
+const RETRY_DELAY: Duration = Duration::from_millis(300);
+
+/// 单次请求的失败，区分是否值得重试（超时 / 连接失败 / 5xx）
+struct AttemptError {
+    error: anyhow::Error,
+    retryable: bool,
+}
+
+impl From<reqwest::Error> for AttemptError {
+    fn from(e: reqwest::Error) -> Self {
+        Self {
+            retryable: e.is_timeout() || e.is_connect(),
+            error: e.into(),
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct LlmPostProcessor {
     config: LlmConfig,
     client: Client,
+    // 进行中的润色任务（各个克隆共享，便于 cancel_transcription 中途取消）
+    active_task: Arc<Mutex<Option<AbortHandle>>>,
+    // 代入模板变量 {filler_words} 的口头禅
+    filler_words: Vec<String>,
+    // 当前识别语言，用于选择预设中对应语言的 prompt
+    language: Option<String>,
 }
 
 impl LlmPostProcessor {
-    pub fn new(config: LlmConfig) -> Self {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(15))
+    pub fn new(config: LlmConfig, dns: &DnsOverrides) -> Self {
+        let builder = Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs.max(1)))
             .connect_timeout(Duration::from_secs(5))
             .pool_idle_timeout(Duration::from_secs(30))
             .pool_max_idle_per_host(10)
-            .no_proxy()
+            .no_proxy();
+        let client = dns
+            .apply(builder)
             .build()
             .unwrap_or_else(|_| Client::new());
 
-        Self { config, client }
+        Self {
+            config,
+            client,
+            active_task: Arc::new(Mutex::new(None)),
+            filler_words: Vec::new(),
+            language: None,
+        }
+    }
+
+    /// 设置代入模板变量 {filler_words} 的口头禅
+    pub fn with_filler_words(mut self, filler_words: Vec<String>) -> Self {
+        self.filler_words = filler_words;
+        self
+    }
+
+    /// 设置识别语言（"auto" 或 None 时使用预设的默认 prompt）
+    pub fn with_language(mut self, language: Option<String>) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// 改用指定的预设（按应用覆盖时），预设不存在时沿用当前预设
+    pub fn with_preset(mut self, preset_id: Option<&str>) -> Self {
+        if let Some(preset_id) = preset_id {
+            if self.config.presets.iter().any(|p| p.id == preset_id) {
+                self.config.active_preset_id = preset_id.to_string();
+            } else {
+                tracing::warn!("润色预设 {} 不存在，沿用 {}", preset_id, self.config.active_preset_id);
+            }
+        }
+        self
+    }
+
+    /// 运行中更新某个预设的 prompt 与口头禅（apply_to_preset 后立即生效）
+    pub fn update_preset_prompt(&mut self, preset_id: &str, system_prompt: &str, filler_words: Vec<String>) {
+        if let Some(preset) = self.config.presets.iter_mut().find(|p| p.id == preset_id) {
+            preset.system_prompt = system_prompt.to_string();
+        }
+        self.filler_words = filler_words;
+    }
+
+    /// 以可取消的任务执行润色，返回 Ok(None) 表示请求已被 cancel() 取消
+    pub async fn polish_transcript_cancellable(&self, raw_text: &str) -> Result<Option<String>> {
+        let processor = self.clone();
+        let text = raw_text.to_string();
+        let handle = tokio::spawn(async move { processor.polish_transcript(&text).await });
+        *self.active_task.lock().unwrap() = Some(handle.abort_handle());
+
+        let result = handle.await;
+        self.active_task.lock().unwrap().take();
+
+        match result {
+            Ok(polished) => polished.map(Some),
+            Err(e) if e.is_cancelled() => Ok(None),
+            Err(e) => Err(anyhow::anyhow!("LLM 后处理任务异常: {}", e)),
+        }
     }
 
-    // 辅助函数：获取当前激活的 Prompt
+    /// 取消进行中的润色请求，返回是否确实有请求被取消
+    pub fn cancel(&self) -> bool {
+        match self.active_task.lock().unwrap().take() {
+            Some(handle) => {
+                handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    // 辅助函数：获取当前激活的 Prompt（按识别语言选择，代入 {filler_words} 模板变量）
     fn get_active_system_prompt(&self) -> String {
-        self.config.presets
+        let prompt = self.config.presets
             .iter()
             .find(|p| p.id == self.config.active_preset_id)
-            .map(|p| p.system_prompt.clone())
-            .unwrap_or_else(|| "You are a helpful assistant.".to_string())
+            .map(|p| p.prompt_for(self.language.as_deref()).to_string())
+            .unwrap_or_else(|| "You are a helpful assistant.".to_string());
+
+        if !prompt.contains(FILLER_WORDS_VAR) {
+            return prompt;
+        }
+        let filler_words = if self.filler_words.is_empty() {
+            DEFAULT_FILLER_WORDS.to_string()
+        } else {
+            self.filler_words.join("、")
+        };
+        prompt.replace(FILLER_WORDS_VAR, &filler_words)
     }
 
     pub async fn polish_transcript(&self, raw_text: &str) -> Result<String> {
@@ -42,7 +151,11 @@ impl LlmPostProcessor {
         }
 
         let system_prompt = self.get_active_system_prompt();
-        tracing::info!("LLM 使用预设 ID: {}", self.config.active_preset_id);
+        tracing::info!(
+            "LLM 使用预设 ID: {} (语言: {})",
+            self.config.active_preset_id,
+            self.language.as_deref().unwrap_or("auto")
+        );
 
         // 使用 OpenAI 兼容格式
         let request_body = serde_json::json!({
@@ -61,22 +174,38 @@ impl LlmPostProcessor {
             "temperature": 0.3
         });
 
-        // ... (其余代码保持不变)
         tracing::debug!("LLM 请求: endpoint={}, model={}", self.config.endpoint, self.config.model);
 
+        // 超时 / 5xx 重试一次，仍失败则返回错误，由调用方回退到原文
+        match self.request_once(&request_body).await {
+            Ok(refined) => Ok(refined),
+            Err(AttemptError { error, retryable: true }) => {
+                tracing::warn!("LLM 请求失败，{}ms 后重试: {}", RETRY_DELAY.as_millis(), error);
+                tokio::time::sleep(RETRY_DELAY).await;
+                self.request_once(&request_body).await.map_err(|e| e.error)
+            }
+            Err(AttemptError { error, .. }) => Err(error),
+        }
+    }
+
+    /// 发送一次润色请求
+    async fn request_once(&self, request_body: &Value) -> std::result::Result<String, AttemptError> {
         let response = self
             .client
             .post(&self.config.endpoint)
             .header("Authorization", format!("Bearer {}", self.config.api_key))
             .header("Content-Type", "application/json")
-            .json(&request_body)
+            .json(request_body)
             .send()
             .await?;
 
         let status = response.status();
         if !status.is_success() {
             let text = response.text().await.unwrap_or_default();
-            anyhow::bail!("LLM 处理失败 ({}): {}", status, text);
+            return Err(AttemptError {
+                error: anyhow::anyhow!("LLM 处理失败 ({}): {}", status, text),
+                retryable: status.is_server_error(),
+            });
         }
 
         let payload: Value = response.json().await?;
@@ -86,8 +215,82 @@ impl LlmPostProcessor {
             .as_array()
             .and_then(|arr| arr.first())
             .and_then(|choice| choice["message"]["content"].as_str())
-            .ok_or_else(|| anyhow::anyhow!("LLM 返回格式不可解析: {:?}", payload))?;
+            .ok_or_else(|| AttemptError {
+                error: anyhow::anyhow!("LLM 返回格式不可解析: {:?}", payload),
+                retryable: false,
+            })?;
 
         Ok(refined.trim().to_string())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// 本地 HTTP 服务：第 n 个请求（从 0 开始）按 `respond(n)` 返回状态码与 body，None 表示不响应（模拟超时）
+    async fn spawn_llm_server(respond: fn(usize) -> Option<(u16, &'static str)>) -> (String, Arc<AtomicUsize>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let count = Arc::new(AtomicUsize::new(0));
+        let seen = Arc::clone(&count);
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let n = seen.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    // 请求很小，读一次即可（不校验内容）
+                    let mut buf = [0u8; 8192];
+                    let _ = stream.read(&mut buf).await;
+                    let Some((status, body)) = respond(n) else {
+                        tokio::time::sleep(Duration::from_secs(30)).await;
+                        return;
+                    };
+                    let response = format!(
+                        "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        status,
+                        body.len(),
+                        body
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        (format!("http://{}/v1/chat/completions", addr), count)
+    }
+
+    fn processor(endpoint: String) -> LlmPostProcessor {
+        let config = LlmConfig {
+            endpoint,
+            timeout_secs: 1,
+            ..LlmConfig::default()
+        };
+        LlmPostProcessor::new(config, &DnsOverrides::default())
+    }
+
+    const POLISHED: &str = r#"{"choices":[{"message":{"content":" 润色结果 "}}]}"#;
+
+    #[tokio::test]
+    async fn retries_once_on_server_error_or_timeout() {
+        // 5xx 后重试成功
+        let (endpoint, count) = spawn_llm_server(|n| Some(if n == 0 { (503, "busy") } else { (200, POLISHED) })).await;
+        assert_eq!(processor(endpoint).polish_transcript("原文").await.unwrap(), "润色结果");
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+
+        // 超时后重试成功
+        let (endpoint, count) = spawn_llm_server(|n| if n == 0 { None } else { Some((200, POLISHED)) }).await;
+        assert_eq!(processor(endpoint).polish_transcript("原文").await.unwrap(), "润色结果");
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+
+        // 连续两次 5xx：只重试一次，返回错误由调用方回退原文
+        let (endpoint, count) = spawn_llm_server(|_| Some((502, "bad gateway"))).await;
+        assert!(processor(endpoint).polish_transcript("原文").await.is_err());
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+
+        // 4xx 不重试
+        let (endpoint, count) = spawn_llm_server(|_| Some((401, "unauthorized"))).await;
+        assert!(processor(endpoint).polish_transcript("原文").await.is_err());
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+}
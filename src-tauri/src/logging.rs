@@ -0,0 +1,116 @@
+// 日志输出：控制台 + 日志目录下按天滚动的日志文件
+// release 版以 windows_subsystem = "windows" 运行，没有控制台，用户反馈问题时从日志文件取信息。
+// 级别取自 log_level（如 "debug"，也可写 "info,push_to_talk_lib=debug" 这样的过滤规则），
+// 设置了环境变量 RUST_LOG 时以环境变量为准，配置不再调整
+
+use anyhow::Result;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, EnvFilter, Registry};
+
+pub const DEFAULT_LEVEL: &str = "info";
+// 日志文件名为 push-to-talk.{日期}.log，按天滚动，最多保留 14 个
+const LOG_FILE_PREFIX: &str = "push-to-talk";
+const LOG_FILE_SUFFIX: &str = "log";
+const MAX_LOG_FILES: usize = 14;
+
+// 运行中调整级别用的句柄；RUST_LOG 生效时不设置
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+/// 初始化日志（程序启动时调用一次），级别先用默认值，加载配置后由 `set_level` 调整
+/// 返回的 guard 需持有到程序退出，释放时把缓冲中的日志写入文件
+pub fn init() -> Option<WorkerGuard> {
+    let env_filter = std::env::var(EnvFilter::DEFAULT_ENV)
+        .ok()
+        .and_then(|filter| EnvFilter::try_new(filter).ok());
+    let from_env = env_filter.is_some();
+    let (filter, handle) = reload::Layer::new(env_filter.unwrap_or_else(|| EnvFilter::new(DEFAULT_LEVEL)));
+
+    let (file_writer, guard, file_error) = match file_appender() {
+        Ok(appender) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            (Some(writer), Some(guard), None)
+        }
+        Err(e) => (None, None, Some(e)),
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer())
+        .with(file_writer.map(|writer| fmt::layer().with_ansi(false).with_writer(writer)))
+        .init();
+
+    if from_env {
+        tracing::info!("日志级别由环境变量 {} 指定", EnvFilter::DEFAULT_ENV);
+    } else {
+        let _ = FILTER.set(handle);
+    }
+    if let Some(e) = file_error {
+        tracing::warn!("无法写入日志文件，只输出到控制台: {}", e);
+    }
+    guard
+}
+
+/// 日志目录（配置目录下的 PushToTalk/logs）
+pub fn log_dir() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir()
+        .ok_or_else(|| anyhow::anyhow!("无法获取配置目录"))?;
+    Ok(config_dir.join("PushToTalk").join("logs"))
+}
+
+/// 日志目录下按天滚动的文件
+fn file_appender() -> Result<RollingFileAppender> {
+    let dir = log_dir()?;
+    std::fs::create_dir_all(&dir)?;
+    Ok(RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(dir)?)
+}
+
+/// 按配置调整日志级别（启动与配置更新时调用，立即生效）；RUST_LOG 生效时忽略，无效的值保持原级别
+pub fn set_level(level: &str) {
+    let Some(handle) = FILTER.get() else {
+        return;
+    };
+    match parse_filter(level) {
+        Ok(filter) => {
+            let _ = handle.reload(filter);
+        }
+        Err(e) => tracing::warn!("日志级别 \"{}\" 无效，保持原级别: {}", level, e),
+    }
+}
+
+/// 解析日志级别：空串为默认级别；单个词须是级别名（避免把拼错的级别当成模块名，结果什么都不输出）
+fn parse_filter(level: &str) -> Result<EnvFilter> {
+    let level = level.trim();
+    if level.is_empty() {
+        return Ok(EnvFilter::new(DEFAULT_LEVEL));
+    }
+    if !level.contains(['=', ',']) {
+        level.parse::<LevelFilter>()?;
+    }
+    Ok(EnvFilter::try_new(level)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_levels_and_filter_directives() {
+        assert_eq!(parse_filter("").unwrap().to_string(), "info");
+        assert_eq!(parse_filter(" DEBUG ").unwrap().to_string(), "debug");
+        assert!(parse_filter("info,push_to_talk_lib::qwen_realtime=trace").is_ok());
+        // 拼错的级别不会被当成模块名
+        assert!(parse_filter("verbose").is_err());
+        assert!(parse_filter("info,=[").is_err());
+    }
+}
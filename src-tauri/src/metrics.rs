@@ -0,0 +1,389 @@
+// 运行指标模块（Prometheus 文本格式）
+// 指标集中注册在这里，通过订阅应用已有的 Tauri 事件更新，由本地控制接口的 /metrics 路径输出
+// 识别服务的可用状态由各 provider 的请求、回退路径直接上报（HTTP 客户端拿不到 AppHandle）
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Listener};
+
+use crate::stats;
+
+// 各阶段延迟直方图的桶上界（秒）
+const LATENCY_BUCKETS_SECS: &[f64] = &[0.1, 0.25, 0.5, 1.0, 2.0, 5.0, 10.0, 30.0];
+
+static METRICS: Mutex<Metrics> = Mutex::new(Metrics::new());
+
+#[derive(Debug, Clone)]
+struct Histogram {
+    // 与 LATENCY_BUCKETS_SECS 一一对应的非累计计数
+    buckets: [u64; LATENCY_BUCKETS_SECS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    const fn new() -> Self {
+        Self {
+            buckets: [0; LATENCY_BUCKETS_SECS.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, secs: f64) {
+        if let Some(index) = LATENCY_BUCKETS_SECS.iter().position(|&le| secs <= le) {
+            self.buckets[index] += 1;
+        }
+        self.sum += secs;
+        self.count += 1;
+    }
+}
+
+/// 单个识别服务的可用状态：最近一次调用的结果与连续失败次数（达到一定次数即可视为熔断）
+#[derive(Debug, Clone, Default)]
+struct ProviderHealth {
+    up: bool,
+    consecutive_failures: u64,
+    // 因该服务不可用而改走备用方案的次数
+    fallbacks: u64,
+}
+
+impl ProviderHealth {
+    fn record(&mut self, ok: bool) {
+        self.up = ok;
+        self.consecutive_failures = if ok { 0 } else { self.consecutive_failures + 1 };
+    }
+}
+
+/// 进程内的全部指标
+#[derive(Debug)]
+pub struct Metrics {
+    transcriptions: u64,
+    // 成功转录的音频总时长（毫秒）
+    audio_ms: u64,
+    failures: BTreeMap<String, u64>,
+    latencies: BTreeMap<&'static str, Histogram>,
+    realtime_reconnects: u64,
+    // 各识别服务的可用状态（qwen_realtime / qwen_http / sensevoice，未调用过的不输出）
+    providers: BTreeMap<&'static str, ProviderHealth>,
+}
+
+impl Metrics {
+    const fn new() -> Self {
+        Self {
+            transcriptions: 0,
+            audio_ms: 0,
+            failures: BTreeMap::new(),
+            latencies: BTreeMap::new(),
+            realtime_reconnects: 0,
+            providers: BTreeMap::new(),
+        }
+    }
+
+    fn observe(&mut self, stage: &'static str, elapsed: Duration) {
+        self.latencies
+            .entry(stage)
+            .or_insert_with(Histogram::new)
+            .observe(elapsed.as_secs_f64());
+    }
+
+    fn record_failure(&mut self, code: &str) {
+        *self.failures.entry(code.to_string()).or_insert(0) += 1;
+    }
+
+    fn record_completed(&mut self, payload: &CompletedPayload) {
+        self.transcriptions += 1;
+        self.audio_ms += payload.duration_ms.unwrap_or(0);
+        self.observe("asr", Duration::from_millis(payload.asr_time_ms));
+        if let Some(llm_time_ms) = payload.llm_time_ms {
+            self.observe("llm", Duration::from_millis(llm_time_ms));
+        }
+        self.observe("total", Duration::from_millis(payload.total_time_ms));
+    }
+
+    /// 按键到实时会话就绪的耗时，按会话来源分别统计（复用连接应明显快于新建）
+    fn record_session(&mut self, payload: &SessionPayload) {
+        let stage = match payload.origin.as_str() {
+            "reused" => "session_reused",
+            "warm" => "session_warm",
+            _ => "session_new",
+        };
+        self.observe(stage, Duration::from_millis(payload.connect_ms));
+        self.record_provider(stats::QWEN_REALTIME, true);
+    }
+
+    fn record_connection(&mut self, event_type: &str) {
+        match event_type {
+            "reconnecting" => {
+                self.realtime_reconnects += 1;
+                self.record_provider(stats::QWEN_REALTIME, false);
+            }
+            "reconnected" => self.record_provider(stats::QWEN_REALTIME, true),
+            "disconnected" => self.record_provider(stats::QWEN_REALTIME, false),
+            _ => {}
+        }
+    }
+
+    fn record_provider(&mut self, provider: &'static str, ok: bool) {
+        self.providers.entry(provider).or_default().record(ok);
+    }
+
+    fn record_fallback(&mut self, provider: &'static str) {
+        let health = self.providers.entry(provider).or_default();
+        health.record(false);
+        health.fallbacks += 1;
+    }
+
+    /// 输出 Prometheus 文本格式
+    fn render(&self, resident_memory_bytes: Option<u64>) -> String {
+        let mut out = String::new();
+
+        let _ = writeln!(out, "# HELP push2talk_transcriptions_total 成功完成的转录次数");
+        let _ = writeln!(out, "# TYPE push2talk_transcriptions_total counter");
+        let _ = writeln!(out, "push2talk_transcriptions_total {}", self.transcriptions);
+
+        let _ = writeln!(out, "# HELP push2talk_audio_seconds_total 成功转录的音频总时长");
+        let _ = writeln!(out, "# TYPE push2talk_audio_seconds_total counter");
+        let _ = writeln!(out, "push2talk_audio_seconds_total {}", self.audio_ms as f64 / 1000.0);
+
+        let _ = writeln!(out, "# HELP push2talk_failures_total 失败次数（按错误码）");
+        let _ = writeln!(out, "# TYPE push2talk_failures_total counter");
+        for (code, count) in &self.failures {
+            let _ = writeln!(out, "push2talk_failures_total{{code=\"{}\"}} {}", code, count);
+        }
+
+        let _ = writeln!(out, "# HELP push2talk_stage_duration_seconds 各阶段耗时");
+        let _ = writeln!(out, "# TYPE push2talk_stage_duration_seconds histogram");
+        for (stage, histogram) in &self.latencies {
+            let mut cumulative = 0;
+            for (le, count) in LATENCY_BUCKETS_SECS.iter().zip(histogram.buckets.iter()) {
+                cumulative += count;
+                let _ = writeln!(
+                    out,
+                    "push2talk_stage_duration_seconds_bucket{{stage=\"{}\",le=\"{}\"}} {}",
+                    stage, le, cumulative
+                );
+            }
+            let _ = writeln!(
+                out,
+                "push2talk_stage_duration_seconds_bucket{{stage=\"{}\",le=\"+Inf\"}} {}",
+                stage, histogram.count
+            );
+            let _ = writeln!(out, "push2talk_stage_duration_seconds_sum{{stage=\"{}\"}} {}", stage, histogram.sum);
+            let _ = writeln!(out, "push2talk_stage_duration_seconds_count{{stage=\"{}\"}} {}", stage, histogram.count);
+        }
+
+        let _ = writeln!(out, "# HELP push2talk_realtime_reconnects_total 实时识别连接的重连次数");
+        let _ = writeln!(out, "# TYPE push2talk_realtime_reconnects_total counter");
+        let _ = writeln!(out, "push2talk_realtime_reconnects_total {}", self.realtime_reconnects);
+
+        if !self.providers.is_empty() {
+            let _ = writeln!(out, "# HELP push2talk_provider_up 识别服务最近一次调用是否成功（1 = 可用）");
+            let _ = writeln!(out, "# TYPE push2talk_provider_up gauge");
+            for (provider, health) in &self.providers {
+                let _ = writeln!(out, "push2talk_provider_up{{provider=\"{}\"}} {}", provider, health.up as u8);
+            }
+
+            let _ = writeln!(out, "# HELP push2talk_provider_consecutive_failures 识别服务的连续失败次数（熔断判据）");
+            let _ = writeln!(out, "# TYPE push2talk_provider_consecutive_failures gauge");
+            for (provider, health) in &self.providers {
+                let _ = writeln!(
+                    out,
+                    "push2talk_provider_consecutive_failures{{provider=\"{}\"}} {}",
+                    provider, health.consecutive_failures
+                );
+            }
+
+            let _ = writeln!(out, "# HELP push2talk_provider_fallbacks_total 因识别服务不可用改走备用方案的次数");
+            let _ = writeln!(out, "# TYPE push2talk_provider_fallbacks_total counter");
+            for (provider, health) in &self.providers {
+                let _ = writeln!(out, "push2talk_provider_fallbacks_total{{provider=\"{}\"}} {}", provider, health.fallbacks);
+            }
+        }
+
+        if let Some(bytes) = resident_memory_bytes {
+            let _ = writeln!(out, "# HELP process_resident_memory_bytes 进程常驻内存");
+            let _ = writeln!(out, "# TYPE process_resident_memory_bytes gauge");
+            let _ = writeln!(out, "process_resident_memory_bytes {}", bytes);
+        }
+
+        out
+    }
+}
+
+/// transcription_complete 事件中用到的字段
+#[derive(Debug, Deserialize)]
+struct CompletedPayload {
+    asr_time_ms: u64,
+    llm_time_ms: Option<u64>,
+    total_time_ms: u64,
+    #[serde(default)]
+    duration_ms: Option<u64>,
+}
+
+/// realtime_session 事件中用到的字段
+#[derive(Debug, Deserialize)]
+struct SessionPayload {
+    origin: String,
+    connect_ms: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConnectionPayload {
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+/// error 事件中用到的字段（错误码由发出方给出，不解析提示文案）
+#[derive(Debug, Deserialize)]
+struct ErrorPayload {
+    code: String,
+}
+
+/// 订阅应用事件更新指标（应用启动时调用一次）
+pub fn subscribe(app: &AppHandle) {
+    app.listen_any("transcription_complete", |event| {
+        match serde_json::from_str::<CompletedPayload>(event.payload()) {
+            Ok(payload) => METRICS.lock().unwrap().record_completed(&payload),
+            Err(e) => tracing::debug!("无法解析 transcription_complete 事件: {}", e),
+        }
+    });
+    app.listen_any("error", |event| {
+        match serde_json::from_str::<ErrorPayload>(event.payload()) {
+            Ok(payload) => METRICS.lock().unwrap().record_failure(&payload.code),
+            Err(e) => tracing::debug!("无法解析 error 事件: {}", e),
+        }
+    });
+    app.listen_any("audio_device_lost", |_| {
+        METRICS.lock().unwrap().record_failure("device");
+    });
+    app.listen_any("realtime_session", |event| {
+        if let Ok(payload) = serde_json::from_str::<SessionPayload>(event.payload()) {
+            METRICS.lock().unwrap().record_session(&payload);
+        }
+    });
+    app.listen_any("realtime_connection", |event| {
+        if let Ok(payload) = serde_json::from_str::<ConnectionPayload>(event.payload()) {
+            METRICS.lock().unwrap().record_connection(&payload.kind);
+        }
+    });
+}
+
+/// 上报一次识别服务调用的结果（`provider` 为 stats 中的 provider 标签）
+pub fn record_provider(provider: &'static str, ok: bool) {
+    METRICS.lock().unwrap().record_provider(provider, ok);
+}
+
+/// 上报一次因识别服务不可用而改走备用方案
+pub fn record_fallback(provider: &'static str) {
+    METRICS.lock().unwrap().record_fallback(provider);
+}
+
+/// Prometheus 文本格式的当前指标（由控制接口的 /metrics 路径输出）
+pub fn render() -> String {
+    METRICS.lock().unwrap().render(resident_memory_bytes())
+}
+
+#[cfg(windows)]
+fn resident_memory_bytes() -> Option<u64> {
+    use windows_sys::Win32::System::ProcessStatus::{K32GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS};
+    use windows_sys::Win32::System::Threading::GetCurrentProcess;
+
+    let mut counters: PROCESS_MEMORY_COUNTERS = unsafe { std::mem::zeroed() };
+    let size = std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+    let ok = unsafe { K32GetProcessMemoryInfo(GetCurrentProcess(), &mut counters, size) };
+    (ok != 0).then_some(counters.WorkingSetSize as u64)
+}
+
+#[cfg(target_os = "linux")]
+fn resident_memory_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn resident_memory_bytes() -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        let mut metrics = Metrics::new();
+        metrics.record_completed(&CompletedPayload { asr_time_ms: 300, llm_time_ms: None, total_time_ms: 300, duration_ms: None });
+        metrics.record_completed(&CompletedPayload {
+            asr_time_ms: 1500,
+            llm_time_ms: Some(800),
+            total_time_ms: 2300,
+            duration_ms: Some(4250),
+        });
+
+        let text = metrics.render(None);
+        assert!(text.contains("push2talk_transcriptions_total 2\n"));
+        assert!(text.contains("push2talk_audio_seconds_total 4.25\n"));
+        assert!(text.contains("push2talk_stage_duration_seconds_bucket{stage=\"asr\",le=\"0.5\"} 1\n"));
+        assert!(text.contains("push2talk_stage_duration_seconds_bucket{stage=\"asr\",le=\"2\"} 2\n"));
+        assert!(text.contains("push2talk_stage_duration_seconds_bucket{stage=\"asr\",le=\"+Inf\"} 2\n"));
+        assert!(text.contains("push2talk_stage_duration_seconds_count{stage=\"llm\"} 1\n"));
+        assert!(!text.contains("process_resident_memory_bytes"));
+        assert!(!text.contains("push2talk_provider_up"));
+    }
+
+    #[test]
+    fn provider_health_follows_failures_and_fallbacks() {
+        let mut metrics = Metrics::new();
+        // 实时连接建立后断线，回退到 HTTP：千问 HTTP 重试一次后成功，SenseVoice 并行请求失败
+        metrics.record_session(&SessionPayload { origin: "new".to_string(), connect_ms: 120 });
+        metrics.record_connection("reconnecting");
+        metrics.record_fallback(stats::QWEN_REALTIME);
+        metrics.record_provider(stats::QWEN_HTTP, false);
+        metrics.record_provider(stats::QWEN_HTTP, true);
+        metrics.record_provider(stats::SENSEVOICE, false);
+        metrics.record_provider(stats::SENSEVOICE, false);
+
+        let text = metrics.render(None);
+        assert!(text.contains("# TYPE push2talk_provider_up gauge\n"));
+        assert!(text.contains("push2talk_provider_up{provider=\"qwen_realtime\"} 0\n"));
+        assert!(text.contains("push2talk_provider_up{provider=\"qwen_http\"} 1\n"));
+        assert!(text.contains("push2talk_provider_up{provider=\"sensevoice\"} 0\n"));
+        assert!(text.contains("push2talk_provider_consecutive_failures{provider=\"qwen_realtime\"} 2\n"));
+        assert!(text.contains("push2talk_provider_consecutive_failures{provider=\"qwen_http\"} 0\n"));
+        assert!(text.contains("push2talk_provider_consecutive_failures{provider=\"sensevoice\"} 2\n"));
+        assert!(text.contains("push2talk_provider_fallbacks_total{provider=\"qwen_realtime\"} 1\n"));
+        assert!(text.contains("push2talk_provider_fallbacks_total{provider=\"sensevoice\"} 0\n"));
+        assert!(text.contains("push2talk_realtime_reconnects_total 1\n"));
+
+        // 重连成功后恢复可用、连续失败清零
+        metrics.record_connection("reconnected");
+        let text = metrics.render(None);
+        assert!(text.contains("push2talk_provider_up{provider=\"qwen_realtime\"} 1\n"));
+        assert!(text.contains("push2talk_provider_consecutive_failures{provider=\"qwen_realtime\"} 0\n"));
+    }
+
+    #[test]
+    fn failures_are_grouped_by_code() {
+        let mut metrics = Metrics::new();
+        for payload in [
+            r#"{"code":"transcription","message":"转录失败: 超时"}"#,
+            r#"{"code":"transcription","message":"转录失败: 401"}"#,
+            r#"{"code":"insert","message":"拆分发送中断: 焦点变化"}"#,
+            r#"{"code":"too_long","message":"录音过长: 超过 180 秒上限"}"#,
+        ] {
+            let payload: ErrorPayload = serde_json::from_str(payload).unwrap();
+            metrics.record_failure(&payload.code);
+        }
+
+        let text = metrics.render(None);
+        assert!(text.contains("push2talk_failures_total{code=\"transcription\"} 2\n"));
+        assert!(text.contains("push2talk_failures_total{code=\"insert\"} 1\n"));
+        assert!(text.contains("push2talk_failures_total{code=\"too_long\"} 1\n"));
+    }
+}
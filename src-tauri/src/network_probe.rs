@@ -0,0 +1,118 @@
+// 按键时的网络快速探测
+// 断网时建立 WebSocket / HTTP 连接要等十几秒才超时，按下快捷键时先在短超时内探测服务地址能否建立 TCP 连接
+// （含 DNS 解析；配置了代理时探测代理），探测结果缓存几秒，连续按键不重复探测
+
+use anyhow::Result;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+use crate::dns::DnsOverrides;
+use crate::timing::Timings;
+
+pub struct NetworkProbe {
+    host: String,
+    port: u16,
+    dns_overrides: DnsOverrides,
+    timeout: Duration,
+    cache_for: Duration,
+    // 最近一次探测的时间与结果
+    last: Mutex<Option<(Instant, bool)>>,
+}
+
+impl NetworkProbe {
+    /// 探测 `url` 所在的服务（http / https / ws / wss）
+    pub fn new(url: &str, dns_overrides: DnsOverrides, timings: &Timings) -> Result<Self> {
+        let parsed = reqwest::Url::parse(url).map_err(|e| anyhow::anyhow!("服务地址无效 ({}): {}", url, e))?;
+        let host = parsed
+            .host_str()
+            .ok_or_else(|| anyhow::anyhow!("服务地址缺少主机名: {}", url))?
+            .to_string();
+        let port = parsed.port_or_known_default().unwrap_or(match parsed.scheme() {
+            "ws" => 80,
+            _ => 443,
+        });
+        Ok(Self {
+            host,
+            port,
+            dns_overrides,
+            timeout: timings.network_probe_timeout,
+            cache_for: timings.network_probe_cache,
+            last: Mutex::new(None),
+        })
+    }
+
+    /// 被探测的服务地址（host:port）
+    pub fn target(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+
+    /// 服务是否可达；缓存期内直接返回上次的结果
+    pub async fn reachable(&self) -> bool {
+        if let Some((at, reachable)) = *self.last.lock().unwrap() {
+            if at.elapsed() < self.cache_for {
+                return reachable;
+            }
+        }
+        let started = Instant::now();
+        let reachable = match timeout(self.timeout, self.connect()).await {
+            Ok(Ok(())) => true,
+            Ok(Err(e)) => {
+                tracing::warn!("网络探测失败 ({}): {}", self.target(), e);
+                false
+            }
+            Err(_) => {
+                tracing::warn!("网络探测 {}ms 内未连上 {}", self.timeout.as_millis(), self.target());
+                false
+            }
+        };
+        if reachable {
+            tracing::debug!("网络探测成功 ({}), 耗时 {}ms", self.target(), started.elapsed().as_millis());
+        }
+        *self.last.lock().unwrap() = Some((Instant::now(), reachable));
+        reachable
+    }
+
+    /// 建立一次 TCP 连接后立即断开；配置了代理时只探测代理本身
+    async fn connect(&self) -> Result<()> {
+        if let Some(proxy) = self.dns_overrides.proxy() {
+            TcpStream::connect(proxy.address()).await?;
+        } else if let Some(ip) = self.dns_overrides.lookup(&self.host) {
+            TcpStream::connect((ip, self.port)).await?;
+        } else {
+            TcpStream::connect((self.host.as_str(), self.port)).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn caches_probe_result() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let timings = Timings {
+            network_probe_cache: Duration::from_secs(60),
+            ..Timings::default()
+        };
+        let probe = NetworkProbe::new(&format!("ws://{}/realtime", addr), DnsOverrides::default(), &timings).unwrap();
+        assert_eq!(probe.target(), addr.to_string());
+        assert!(probe.reachable().await);
+
+        // 服务关闭后仍在缓存期内，直接返回上次结果
+        drop(listener);
+        assert!(probe.reachable().await);
+
+        let uncached = Timings {
+            network_probe_cache: Duration::ZERO,
+            ..timings
+        };
+        let probe = NetworkProbe::new(&format!("http://{}", addr), DnsOverrides::default(), &uncached).unwrap();
+        assert!(!probe.reachable().await);
+    }
+}
@@ -0,0 +1,144 @@
+// 隐私模式
+// 开启后不保留任何由语音内容派生的数据，日志中的识别文本只记录长度
+//
+// 各类数据的保留策略：
+// - 录音样本：只在内存中缓冲，停止录音时 mem::take 取出，识别请求结束即释放；不落盘
+// - 内存 WAV（HTTP 识别 / 回退用）：随识别请求一起释放；不落盘
+// - 流式录音的完整缓冲（实时识别失败时整段回退用）：只在录音器中保存到本次录音结束；
+//   服务端 VAD / 连续听写送达首段结果后即停止累积并释放（开启 audio.keep_recordings 且非隐私模式时除外）
+// - 保留录音 audio.keep_recordings：隐私模式下不落盘
+// - WebSocket 调试留痕 debug.ws_trace（logs/ws-trace-*.jsonl）：默认关闭；音频帧只记录字节数，不含音频内容；
+//   隐私模式下不建留痕文件（会话中途开启隐私模式时，之后的识别文本只记字数）
+// - 临时 WAV 文件（AudioRecorder::stop_recording，仅兼容旧接口）：隐私模式下拒绝写入
+// - 识别文本 / 润色文本：插入目标窗口后释放；隐私模式下日志只记录字数
+// - 前端历史记录（localStorage 的 pushtotalk_history，最多 50 条识别原文 / 润色文本）：
+//   transcription_complete 带上 privacy_mode，隐私模式下不写入
+// - 口头禅统计 filler_stats.json（由识别文本派生的 n-gram 计数）：隐私模式下不统计、不写盘
+// - 使用统计 usage_stats.json：只有时长、字数和成功/失败次数，不含内容，隐私模式下照常保留
+// - config.json：只保存用户设置，不含语音内容
+
+use anyhow::Result;
+#[cfg(test)]
+use std::cell::Cell;
+use std::fmt;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static PRIVACY_MODE: AtomicBool = AtomicBool::new(false);
+
+// 测试中只对当前线程生效的开关，不影响并发运行的其他测试
+#[cfg(test)]
+thread_local! {
+    static THREAD_OVERRIDE: Cell<Option<bool>> = const { Cell::new(None) };
+}
+
+pub fn set_enabled(enabled: bool) {
+    PRIVACY_MODE.store(enabled, Ordering::SeqCst);
+    tracing::info!("隐私模式: {}", if enabled { "开启" } else { "关闭" });
+}
+
+pub fn enabled() -> bool {
+    #[cfg(test)]
+    if let Some(enabled) = THREAD_OVERRIDE.with(Cell::get) {
+        return enabled;
+    }
+    PRIVACY_MODE.load(Ordering::SeqCst)
+}
+
+/// 在当前线程按指定的隐私模式运行 `f`（测试用）
+#[cfg(test)]
+pub fn with_enabled<R>(enabled: bool, f: impl FnOnce() -> R) -> R {
+    let previous = THREAD_OVERRIDE.with(|cell| cell.replace(Some(enabled)));
+    let result = f();
+    THREAD_OVERRIDE.with(|cell| cell.set(previous));
+    result
+}
+
+/// 日志中输出识别内容用：隐私模式下只显示字数
+pub fn redact(text: &str) -> Redacted<'_> {
+    redact_if(enabled(), text)
+}
+
+fn redact_if(hide: bool, text: &str) -> Redacted<'_> {
+    Redacted { text, hide }
+}
+
+pub struct Redacted<'a> {
+    text: &'a str,
+    hide: bool,
+}
+
+impl fmt::Display for Redacted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.hide {
+            write!(f, "[{} 字]", self.text.chars().count())
+        } else {
+            f.write_str(self.text)
+        }
+    }
+}
+
+/// 写入由语音内容派生的数据；隐私模式下不写入并返回 false
+pub fn write_user_data(path: &Path, contents: impl AsRef<[u8]>) -> Result<bool> {
+    write_unless(enabled(), path, contents)
+}
+
+fn write_unless(private: bool, path: &Path, contents: impl AsRef<[u8]>) -> Result<bool> {
+    if private {
+        tracing::debug!("隐私模式：跳过写入 {:?}", path);
+        return Ok(false);
+    }
+    std::fs::write(path, contents)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_recorder::AudioRecorder;
+    use crate::audio_source::MemorySource;
+    use crate::filler_stats::FillerStats;
+    use crate::recordings;
+    use crate::ws_trace::WsTrace;
+
+    #[test]
+    fn privacy_mode_leaves_nothing_on_disk() {
+        let dir = std::env::temp_dir().join(format!("ptt-privacy-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        with_enabled(true, || {
+            // 保留录音
+            assert_eq!(recordings::keep_in(&dir, b"wav".to_vec()), None);
+
+            // 旧接口的临时 WAV
+            let source = MemorySource { samples: vec![0.1; 4800], sample_rate: 48000, channels: 1 };
+            let mut recorder = AudioRecorder::with_source(move || Box::new(source)).unwrap();
+            recorder.start_recording().unwrap();
+            assert!(recorder.stop_recording_in(&dir).is_err());
+
+            // 口头禅统计
+            let mut stats = FillerStats::default();
+            stats.ingest("那个，就是说");
+            stats.save_to(&dir.join("filler_stats.json")).unwrap();
+
+            // WebSocket 留痕
+            assert!(WsTrace::start_in(true, || Ok(dir.clone())).is_none());
+
+            assert_eq!(redact("你好，世界").to_string(), "[5 字]");
+        });
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0, "隐私模式下不应写入任何文件");
+
+        // 关闭隐私模式时同一组写入照常落盘
+        with_enabled(false, || {
+            let mut stats = FillerStats::default();
+            stats.ingest("那个");
+            stats.save_to(&dir.join("filler_stats.json")).unwrap();
+            assert!(WsTrace::start_in(true, || Ok(dir.clone())).is_some());
+            assert_eq!(redact("你好").to_string(), "你好");
+        });
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,149 @@
+// HTTP 代理模块
+// reqwest 客户端直接使用 Proxy；WebSocket 连接先向代理发 HTTP CONNECT 建立隧道，再在隧道上做 TLS 与握手
+
+use anyhow::Result;
+use base64::{engine::general_purpose, Engine as _};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+// CONNECT 响应头的最大长度
+const MAX_RESPONSE_BYTES: usize = 8192;
+
+/// HTTP 代理（`http://[user:pass@]host:port`）
+#[derive(Debug, Clone)]
+pub struct HttpProxy {
+    url: String,
+    host: String,
+    port: u16,
+    // Proxy-Authorization 头的值（URL 中带用户名时）
+    authorization: Option<String>,
+}
+
+impl HttpProxy {
+    /// 解析代理地址；只支持 http:// 代理，空字符串视为未配置
+    pub fn parse(url: &str) -> Result<Option<Self>> {
+        let url = url.trim();
+        if url.is_empty() {
+            return Ok(None);
+        }
+        let parsed = reqwest::Url::parse(url).map_err(|e| anyhow::anyhow!("代理地址无效 ({}): {}", url, e))?;
+        if parsed.scheme() != "http" {
+            anyhow::bail!("只支持 http:// 代理: {}", url);
+        }
+        let host = parsed
+            .host_str()
+            .ok_or_else(|| anyhow::anyhow!("代理地址缺少主机名: {}", url))?
+            .to_string();
+        let authorization = (!parsed.username().is_empty()).then(|| {
+            let credentials = format!("{}:{}", parsed.username(), parsed.password().unwrap_or_default());
+            format!("Basic {}", general_purpose::STANDARD.encode(credentials))
+        });
+        Ok(Some(Self {
+            url: url.to_string(),
+            host,
+            port: parsed.port_or_known_default().unwrap_or(80),
+            authorization,
+        }))
+    }
+
+    /// 代理地址（host:port，不含凭证，用于日志）
+    pub fn address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+
+    /// 让 reqwest 客户端的所有请求都经过代理
+    pub fn apply(&self, builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        match reqwest::Proxy::all(&self.url) {
+            Ok(proxy) => builder.proxy(proxy),
+            Err(e) => {
+                tracing::warn!("代理配置无效，改为直连: {}", e);
+                builder
+            }
+        }
+    }
+
+    /// 通过 HTTP CONNECT 建立到 `host:port` 的隧道，返回可直接做 TLS / WebSocket 握手的连接
+    pub async fn tunnel(&self, host: &str, port: u16) -> Result<TcpStream> {
+        let mut stream = TcpStream::connect((self.host.as_str(), self.port))
+            .await
+            .map_err(|e| anyhow::anyhow!("连接代理 {}:{} 失败: {}", self.host, self.port, e))?;
+        stream.set_nodelay(true)?;
+
+        let mut request = format!("CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n");
+        if let Some(ref authorization) = self.authorization {
+            request.push_str(&format!("Proxy-Authorization: {}\r\n", authorization));
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes()).await?;
+
+        // 逐字节读到空行为止，避免把隧道里的后续数据读进缓冲区
+        let mut response = Vec::new();
+        let mut byte = [0u8; 1];
+        while !response.ends_with(b"\r\n\r\n") {
+            if response.len() >= MAX_RESPONSE_BYTES {
+                anyhow::bail!("代理 CONNECT 响应过长");
+            }
+            if stream.read(&mut byte).await? == 0 {
+                anyhow::bail!("代理在 CONNECT 响应前关闭了连接");
+            }
+            response.push(byte[0]);
+        }
+        let status_line = String::from_utf8_lossy(&response);
+        let status_line = status_line.lines().next().unwrap_or_default();
+        if status_line.split_whitespace().nth(1) != Some("200") {
+            anyhow::bail!("代理拒绝建立隧道: {}", status_line);
+        }
+        tracing::debug!("已通过代理 {}:{} 建立到 {}:{} 的隧道", self.host, self.port, host, port);
+        Ok(stream)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn tunnels_through_connect_proxy() {
+        // 目标服务：回显收到的数据
+        let target = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target_addr = target.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = target.accept().await.unwrap();
+            let mut buf = [0u8; 5];
+            stream.read_exact(&mut buf).await.unwrap();
+            stream.write_all(&buf).await.unwrap();
+        });
+
+        // 代理：校验 CONNECT 请求与鉴权后转发
+        let proxy = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_addr = proxy.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut client, _) = proxy.accept().await.unwrap();
+            let mut head = Vec::new();
+            let mut byte = [0u8; 1];
+            while !head.ends_with(b"\r\n\r\n") {
+                client.read_exact(&mut byte).await.unwrap();
+                head.push(byte[0]);
+            }
+            let head = String::from_utf8(head).unwrap();
+            assert!(head.starts_with(&format!("CONNECT {} HTTP/1.1", target_addr)));
+            // user:secret
+            assert!(head.contains("Proxy-Authorization: Basic dXNlcjpzZWNyZXQ="));
+            let mut upstream = TcpStream::connect(target_addr).await.unwrap();
+            client.write_all(b"HTTP/1.1 200 Connection established\r\n\r\n").await.unwrap();
+            tokio::io::copy_bidirectional(&mut client, &mut upstream).await.ok();
+        });
+
+        let url = format!("http://user:secret@{}", proxy_addr);
+        let proxy = HttpProxy::parse(&url).unwrap().unwrap();
+        let mut stream = proxy.tunnel("127.0.0.1", target_addr.port()).await.unwrap();
+        stream.write_all(b"hello").await.unwrap();
+        let mut echo = [0u8; 5];
+        stream.read_exact(&mut echo).await.unwrap();
+        assert_eq!(&echo, b"hello");
+
+        assert!(HttpProxy::parse("  ").unwrap().is_none());
+        assert!(HttpProxy::parse("socks5://127.0.0.1:1080").is_err());
+    }
+}
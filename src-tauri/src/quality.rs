@@ -0,0 +1,149 @@
+// 识别结果质量检查
+// 用启发式规则找出明显不可靠的结果（只有标点、过短、大量重复、含乱码），不直接插入，交给用户决定
+
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::config::{QualityCheck, TruncationCheck};
+
+// 有效字数少于该值时不做重复检查（"好的好的"之类的短句很常见）
+const MIN_TOKENS_FOR_REPEAT: usize = 6;
+
+/// 结果不可靠的原因
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum LowConfidence {
+    /// 只有标点或空白
+    OnlyPunctuation,
+    /// 有效字数少于 min_chars
+    TooShort { chars: usize },
+    /// 重复内容占比过高（如"谢谢观看谢谢观看谢谢观看"）
+    Repetitive { ratio: f32 },
+    /// 乱码字符（替换符、控制字符、私用区字符）占比过高
+    Garbled { ratio: f32 },
+}
+
+/// 检查识别结果，不达标时返回原因
+pub fn check(text: &str, config: &QualityCheck) -> Option<LowConfidence> {
+    if !config.enabled {
+        return None;
+    }
+
+    let visible = text.chars().filter(|c| !c.is_whitespace()).count();
+    let garbled = text.chars().filter(|&c| is_garbled(c)).count();
+    if garbled > 0 {
+        let ratio = garbled as f32 / visible.max(1) as f32;
+        if ratio > config.max_garbled_ratio {
+            return Some(LowConfidence::Garbled { ratio });
+        }
+    }
+
+    let chars = text.chars().filter(|c| c.is_alphanumeric()).count();
+    if chars == 0 {
+        return Some(LowConfidence::OnlyPunctuation);
+    }
+    if chars < config.min_chars {
+        return Some(LowConfidence::TooShort { chars });
+    }
+
+    // 按相邻两字统计重复：正常长句里相邻组合很少重复，循环输出则几乎全是重复
+    let tokens = tokens(text);
+    if tokens.len() >= MIN_TOKENS_FOR_REPEAT {
+        let mut pairs: Vec<_> = tokens.windows(2).collect();
+        let total = pairs.len();
+        pairs.sort_unstable();
+        pairs.dedup();
+        let ratio = 1.0 - pairs.len() as f32 / total as f32;
+        if ratio > config.max_repeat_ratio {
+            return Some(LowConfidence::Repetitive { ratio });
+        }
+    }
+    None
+}
+
+/// 实时结果是否疑似截断（相对音频时长字数过少），是则返回每秒字数
+pub fn truncated(text: &str, audio: Duration, config: &TruncationCheck) -> Option<f32> {
+    let secs = audio.as_secs_f32();
+    if !config.enabled || secs < config.min_audio_secs.max(f32::EPSILON) {
+        return None;
+    }
+    let rate = char_count(text) as f32 / secs;
+    (rate < config.min_chars_per_sec).then_some(rate)
+}
+
+/// 有效字数：中日韩等文字按字、英文按词
+pub fn char_count(text: &str) -> usize {
+    tokens(text).len()
+}
+
+/// 按"字"切分：中日韩等文字每个字符算一个，ASCII 字母数字按单词算（不区分大小写）
+fn tokens(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() {
+            word.push(c.to_ascii_lowercase());
+            continue;
+        }
+        if !word.is_empty() {
+            tokens.push(std::mem::take(&mut word));
+        }
+        if c.is_alphanumeric() {
+            tokens.push(c.to_string());
+        }
+    }
+    if !word.is_empty() {
+        tokens.push(word);
+    }
+    tokens
+}
+
+fn is_garbled(c: char) -> bool {
+    c == '\u{FFFD}' || (c.is_control() && !c.is_whitespace()) || ('\u{E000}'..='\u{F8FF}').contains(&c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_unreliable_transcripts() {
+        let config = QualityCheck { min_chars: 2, ..QualityCheck::default() };
+
+        assert_eq!(check("今天下午三点开会", &config), None);
+        assert_eq!(check("好的好的", &config), None);
+        assert_eq!(
+            check("The quick brown fox jumps over the lazy dog and the cat", &config),
+            None
+        );
+
+        assert_eq!(check("。？", &config), Some(LowConfidence::OnlyPunctuation));
+        assert_eq!(check("", &config), Some(LowConfidence::OnlyPunctuation));
+        assert_eq!(check("嗯。", &config), Some(LowConfidence::TooShort { chars: 1 }));
+        assert!(matches!(
+            check("谢谢观看谢谢观看谢谢观看谢谢观看", &config),
+            Some(LowConfidence::Repetitive { .. })
+        ));
+        assert!(matches!(check("会议\u{FFFD}纪要", &config), Some(LowConfidence::Garbled { .. })));
+
+        let disabled = QualityCheck { enabled: false, ..config };
+        assert_eq!(check("。", &disabled), None);
+    }
+
+    #[test]
+    fn detects_truncated_realtime_results() {
+        let config = TruncationCheck::default();
+        let twenty_secs = Duration::from_secs(20);
+
+        // 说了 20 秒只回了 5 个字
+        assert_eq!(truncated("今天开会。", twenty_secs, &config), Some(0.2));
+        assert_eq!(truncated("今天下午三点在三楼会议室开会，讨论下个季度的预算安排和人员调整", twenty_secs, &config), None);
+        // 英文按词计数
+        assert!(truncated("hello world", twenty_secs, &config).is_some());
+        // 短录音不检查
+        assert_eq!(truncated("好", Duration::from_secs(3), &config), None);
+
+        let disabled = TruncationCheck { enabled: false, ..config };
+        assert_eq!(truncated("好", twenty_secs, &disabled), None);
+    }
+}
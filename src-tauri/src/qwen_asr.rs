@@ -1,36 +1,97 @@
 // ASR 客户端模块（支持千问和 SenseVoice）
+use std::future::Future;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use anyhow::Result;
 use base64::{Engine as _, engine::general_purpose};
 
+use crate::audio_codec;
+use crate::dns::DnsOverrides;
+use crate::metrics;
+use crate::privacy;
+use crate::stats;
+use crate::timing::Timings;
+
+const DASHSCOPE_BASE_URL: &str = "https://dashscope.aliyuncs.com";
+const SILICONFLOW_BASE_URL: &str = "https://api.siliconflow.cn/v1";
+
 #[derive(Clone)]
 pub struct QwenASRClient {
     api_key: String,
+    // DashScope 接口地址（不含末尾斜杠），可指向企业网关或自建兼容服务
+    base_url: String,
     client: reqwest::Client,
     max_retries: u32,
+    language: String,
+    timings: Timings,
+}
+
+/// 创建带超时配置的 HTTP 客户端（不读取系统代理，只使用配置的 `proxy`）
+fn build_http_client(dns: &DnsOverrides, timings: &Timings) -> reqwest::Client {
+    let builder = reqwest::Client::builder()
+        .timeout(timings.http_request_timeout)
+        .connect_timeout(timings.http_connect_timeout)
+        .pool_idle_timeout(timings.http_pool_idle_timeout)
+        .pool_max_idle_per_host(10)
+        .no_proxy();
+    dns.apply(builder)
+        .build()
+        .unwrap_or_else(|_| reqwest::Client::new())
 }
 
 impl QwenASRClient {
-    pub fn new(api_key: String) -> Self {
-        // 创建带超时配置的HTTP客户端（禁用代理，始终直连）
-        let client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(30))
-            .connect_timeout(Duration::from_secs(10))
-            .pool_idle_timeout(Duration::from_secs(30))
-            .pool_max_idle_per_host(10)
-            .no_proxy()
-            .build()
-            .unwrap_or_else(|_| reqwest::Client::new());
+    pub fn new(api_key: String, dns: &DnsOverrides) -> Self {
+        Self::with_timings(api_key, dns, Timings::default())
+    }
 
+    pub fn with_timings(api_key: String, dns: &DnsOverrides, timings: Timings) -> Self {
         Self {
             api_key,
-            client,
+            base_url: DASHSCOPE_BASE_URL.to_string(),
+            client: build_http_client(dns, &timings),
             max_retries: 2,  // 最多重试2次
+            language: "zh".to_string(),
+            timings,
         }
     }
 
+    /// 指定识别语言（默认中文）
+    pub fn with_language(mut self, language: &str) -> Self {
+        self.language = language.to_string();
+        self
+    }
+
+    /// 指定 DashScope 接口地址（默认 https://dashscope.aliyuncs.com）
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// 启动前的轻量校验：请求模型列表接口确认 API Key 有效（不产生识别调用）
+    pub async fn check_api_key(&self) -> Result<()> {
+        let url = format!("{}/compatible-mode/v1/models", self.base_url);
+        let response = self
+            .client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .timeout(self.timings.api_key_check_timeout)
+            .send()
+            .await
+            .map_err(|e| anyhow::anyhow!("无法连接 DashScope 服务: {}", e))?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+            anyhow::bail!("DashScope API Key 无效或无权限 ({})", status);
+        }
+        if !status.is_success() {
+            // 服务可达即可，其他状态不阻止启动
+            tracing::warn!("API Key 校验接口返回非预期状态: {}", status);
+        }
+        tracing::info!("DashScope API Key 校验通过");
+        Ok(())
+    }
+
     // 带重试逻辑的转录（用于单独使用千问时）- 文件版本
     pub async fn transcribe(&self, audio_path: &Path) -> Result<String> {
         let audio_data = tokio::fs::read(audio_path).await?;
@@ -55,7 +116,7 @@ impl QwenASRClient {
 
                     // 如果不是最后一次尝试，等待一小段时间再重试
                     if attempt < self.max_retries {
-                        tokio::time::sleep(Duration::from_millis(500)).await;
+                        tokio::time::sleep(self.timings.http_retry_delay).await;
                     }
                 }
             }
@@ -76,9 +137,17 @@ impl QwenASRClient {
 
     /// 从内存中的 WAV 数据直接转录（跳过文件 I/O）
     pub async fn transcribe_from_memory(&self, audio_data: &[u8]) -> Result<String> {
-        let audio_base64 = general_purpose::STANDARD.encode(audio_data);
+        let result = self.request(audio_data).await;
+        metrics::record_provider(stats::QWEN_HTTP, result.is_ok());
+        result
+    }
+
+    /// 单次转录请求（每次调用的成败计入 qwen_http 的可用状态）
+    async fn request(&self, audio_data: &[u8]) -> Result<String> {
+        let upload = audio_codec::for_upload(audio_data, true);
+        let audio_base64 = general_purpose::STANDARD.encode(&upload.data);
 
-        tracing::info!("音频数据大小: {} bytes", audio_data.len());
+        tracing::info!("音频数据大小: {} bytes ({})", upload.data.len(), upload.mime);
 
         // 构建请求体 - 使用 qwen3-asr-flash 的多模态对话 API
         let request_body = serde_json::json!({
@@ -95,7 +164,7 @@ impl QwenASRClient {
                         "role": "user",
                         "content": [
                             {
-                                "audio": format!("data:audio/wav;base64,{}", audio_base64)
+                                "audio": format!("data:{};base64,{}", upload.mime, audio_base64)
                             }
                         ]
                     }
@@ -105,19 +174,19 @@ impl QwenASRClient {
                 "result_format": "message",
                 "enable_itn": false,
                 "disfluency_removal": true,
-                "language": "zh"
+                "language": self.language
             }
         });
 
-        // 正确的 qwen3-asr-flash API endpoint
-        let url = "https://dashscope.aliyuncs.com/api/v1/services/aigc/multimodal-generation/generation";
+        // qwen3-asr-flash 的多模态对话接口
+        let url = format!("{}/api/v1/services/aigc/multimodal-generation/generation", self.base_url);
 
         tracing::info!("发送请求到: {}", url);
 
         // 发送请求到 DashScope API
         let response = self
             .client
-            .post(url)
+            .post(&url)
             .header("Authorization", format!("Bearer {}", self.api_key))
             .header("Content-Type", "application/json")
             .json(&request_body)
@@ -134,29 +203,19 @@ impl QwenASRClient {
         }
 
         let result: serde_json::Value = response.json().await?;
-        tracing::info!("API 响应: {}", serde_json::to_string_pretty(&result)?);
+        tracing::info!("API 响应: {}", privacy::redact(&serde_json::to_string_pretty(&result)?));
 
         // 解析响应 - qwen3-asr-flash 的响应格式
-        let mut text = result["output"]["choices"]
+        let text = result["output"]["choices"]
             .as_array()
             .and_then(|arr| arr.first())
             .and_then(|choice| choice["message"]["content"].as_array())
             .and_then(|content| content.first())
             .and_then(|item| item["text"].as_str())
-            .ok_or_else(|| anyhow::anyhow!("无法解析转录结果，响应格式: {:?}", result))?
+            .ok_or_else(|| anyhow::anyhow!("无法解析转录结果，响应格式: {}", privacy::redact(&result.to_string())))?
             .to_string();
 
-        // 去除末尾的标点符号
-        let punctuation = ['。', '，', '！', '？', '、', '；', '：', '"', '"', '\'', '\'', '.', ',', '!', '?', ';', ':'];
-        while let Some(last_char) = text.chars().last() {
-            if punctuation.contains(&last_char) {
-                text.pop();
-            } else {
-                break;
-            }
-        }
-
-        tracing::info!("转录完成: {}", text);
+        tracing::info!("转录完成: {}", privacy::redact(&text));
         Ok(text)
     }
 }
@@ -165,21 +224,28 @@ impl QwenASRClient {
 #[derive(Clone)]
 pub struct SenseVoiceClient {
     api_key: String,
+    // 硅基流动接口地址（不含末尾斜杠）
+    base_url: String,
     client: reqwest::Client,
 }
 
 impl SenseVoiceClient {
-    pub fn new(api_key: String) -> Self {
-        let client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(30))
-            .connect_timeout(Duration::from_secs(10))
-            .pool_idle_timeout(Duration::from_secs(30))
-            .pool_max_idle_per_host(10)
-            .no_proxy()
-            .build()
-            .unwrap_or_else(|_| reqwest::Client::new());
-
-        Self { api_key, client }
+    pub fn new(api_key: String, dns: &DnsOverrides) -> Self {
+        Self::with_timings(api_key, dns, Timings::default())
+    }
+
+    pub fn with_timings(api_key: String, dns: &DnsOverrides, timings: Timings) -> Self {
+        Self {
+            api_key,
+            base_url: SILICONFLOW_BASE_URL.to_string(),
+            client: build_http_client(dns, &timings),
+        }
+    }
+
+    /// 指定硅基流动接口地址（默认 https://api.siliconflow.cn/v1）
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
     }
 
     pub async fn transcribe(&self, audio_path: &Path) -> Result<String> {
@@ -189,25 +255,33 @@ impl SenseVoiceClient {
 
     /// 从内存中的 WAV 数据直接转录
     pub async fn transcribe_bytes(&self, audio_data: &[u8]) -> Result<String> {
+        let result = self.request(audio_data).await;
+        metrics::record_provider(stats::SENSEVOICE, result.is_ok());
+        result
+    }
+
+    async fn request(&self, audio_data: &[u8]) -> Result<String> {
         tracing::info!("开始使用 SenseVoice 转录音频数据: {} bytes", audio_data.len());
 
+        let upload = audio_codec::for_upload(audio_data, true);
+
         // 构建 multipart/form-data 请求
         let form = reqwest::multipart::Form::new()
             .text("model", "FunAudioLLM/SenseVoiceSmall")
             .part(
                 "file",
-                reqwest::multipart::Part::bytes(audio_data.to_vec())
-                    .file_name("audio.wav")
-                    .mime_str("audio/wav")?,
+                reqwest::multipart::Part::bytes(upload.data.into_owned())
+                    .file_name(format!("audio.{}", upload.extension))
+                    .mime_str(upload.mime)?,
             );
 
-        let url = "https://api.siliconflow.cn/v1/audio/transcriptions";
+        let url = format!("{}/audio/transcriptions", self.base_url);
         tracing::info!("发送请求到 SenseVoice: {}", url);
 
         // 发送请求
         let response = self
             .client
-            .post(url)
+            .post(&url)
             .header("Authorization", format!("Bearer {}", self.api_key))
             .multipart(form)
             .send()
@@ -223,25 +297,15 @@ impl SenseVoiceClient {
         }
 
         let result: serde_json::Value = response.json().await?;
-        tracing::info!("SenseVoice API 响应: {}", serde_json::to_string_pretty(&result)?);
+        tracing::info!("SenseVoice API 响应: {}", privacy::redact(&serde_json::to_string_pretty(&result)?));
 
         // 解析响应
-        let mut text = result["text"]
+        let text = result["text"]
             .as_str()
             .ok_or_else(|| anyhow::anyhow!("无法解析 SenseVoice 转录结果"))?
             .to_string();
 
-        // 去除末尾的标点符号
-        let punctuation = ['。', '，', '！', '？', '、', '；', '：', '"', '"', '\'', '\'', '.', ',', '!', '?', ';', ':'];
-        while let Some(last_char) = text.chars().last() {
-            if punctuation.contains(&last_char) {
-                text.pop();
-            } else {
-                break;
-            }
-        }
-
-        tracing::info!("SenseVoice 转录完成: {}", text);
+        tracing::info!("SenseVoice 转录完成: {}", privacy::redact(&text));
         Ok(text)
     }
 }
@@ -262,8 +326,9 @@ pub async fn transcribe_with_fallback_bytes(
     sensevoice_api_key: String,
     audio_data: Vec<u8>,
 ) -> Result<String> {
-    let qwen_client = QwenASRClient::new(qwen_api_key);
-    let sensevoice_client = SenseVoiceClient::new(sensevoice_api_key);
+    let dns = DnsOverrides::default();
+    let qwen_client = QwenASRClient::new(qwen_api_key, &dns);
+    let sensevoice_client = SenseVoiceClient::new(sensevoice_api_key, &dns);
     transcribe_with_fallback_clients(qwen_client, sensevoice_client, audio_data).await
 }
 
@@ -275,20 +340,45 @@ pub async fn transcribe_with_fallback_clients(
     tracing::info!("启动主备并行转录 (内存模式), 音频大小: {} bytes", audio_data.len());
 
     let audio_data_sensevoice = audio_data.clone();
+    let sensevoice = async move { sensevoice_client.transcribe_bytes(&audio_data_sensevoice).await };
+    let max_retries = qwen_client.max_retries;
+    let retry_delay = qwen_client.timings.http_retry_delay;
+
+    race_with_fallback(
+        || qwen_client.transcribe_from_memory(&audio_data),
+        sensevoice,
+        max_retries,
+        retry_delay,
+    )
+    .await
+}
+
+/// 主备并行的调度逻辑：备用请求在后台同时进行，主请求每次重试前先检查备用是否已成功，
+/// 主请求全部失败后再等待备用的最终结果
+async fn race_with_fallback<P, PF, F>(
+    mut primary: P,
+    fallback: F,
+    max_retries: u32,
+    retry_delay: Duration,
+) -> Result<String>
+where
+    P: FnMut() -> PF,
+    PF: Future<Output = Result<String>>,
+    F: Future<Output = Result<String>> + Send + 'static,
+{
     let sensevoice_result: Arc<Mutex<Option<Result<String>>>> = Arc::new(Mutex::new(None));
     let sensevoice_result_clone = Arc::clone(&sensevoice_result);
 
     let sensevoice_handle = tokio::spawn(async move {
         tracing::info!("🚀 SenseVoice 任务启动");
-        let result = sensevoice_client.transcribe_bytes(&audio_data_sensevoice).await;
+        let result = fallback.await;
         match &result {
-            Ok(text) => tracing::info!("✅SenseVoice 转录成功: {}", text),
+            Ok(text) => tracing::info!("✅SenseVoice 转录成功: {}", privacy::redact(text)),
             Err(e) => tracing::error!("❌SenseVoice 转录失败: {}", e),
         }
         *sensevoice_result_clone.lock().unwrap() = Some(result);
     });
 
-    let max_retries = 2;
     let mut qwen_last_error = None;
 
     for attempt in 0..=max_retries {
@@ -298,7 +388,7 @@ pub async fn transcribe_with_fallback_clients(
             if let Some(sv_result) = sensevoice_result.lock().unwrap().as_ref() {
                 match sv_result {
                     Ok(text) => {
-                        tracing::info!("✅千问重试前发现 SenseVoice 已成功，立即使用: {}", text);
+                        tracing::info!("✅千问重试前发现 SenseVoice 已成功，立即使用: {}", privacy::redact(text));
                         return Ok(text.clone());
                     }
                     Err(e) => {
@@ -307,13 +397,13 @@ pub async fn transcribe_with_fallback_clients(
                 }
             }
 
-            tokio::time::sleep(Duration::from_millis(500)).await;
+            tokio::time::sleep(retry_delay).await;
         }
 
         tracing::info!("🔄 千问第{} 次尝试(共{} 次)", attempt + 1, max_retries + 1);
-        match qwen_client.transcribe_from_memory(&audio_data).await {
+        match primary().await {
             Ok(text) => {
-                tracing::info!("✅千问转录成功: {}", text);
+                tracing::info!("✅千问转录成功: {}", privacy::redact(&text));
                 return Ok(text);
             }
             Err(e) => {
@@ -329,7 +419,7 @@ pub async fn transcribe_with_fallback_clients(
     if let Some(result) = sensevoice_result.lock().unwrap().take() {
         match result {
             Ok(text) => {
-                tracing::info!("✅使用 SenseVoice 备用结果: {}", text);
+                tracing::info!("✅使用 SenseVoice 备用结果: {}", privacy::redact(&text));
                 return Ok(text);
             }
             Err(sensevoice_error) => {
@@ -348,3 +438,150 @@ pub async fn transcribe_with_fallback_clients(
     Err(anyhow::anyhow!("所有API都失败"))
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// 模拟一次卡住直到请求超时的千问调用
+    async fn timed_out_request(timings: Timings) -> Result<String> {
+        let _ = tokio::time::timeout(timings.http_request_timeout, std::future::pending::<()>()).await;
+        Err(anyhow::anyhow!("operation timed out"))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn falls_back_to_sensevoice_after_three_qwen_timeouts() {
+        let timings = Timings::default();
+        let attempts = AtomicU32::new(0);
+        let started = tokio::time::Instant::now();
+
+        // SenseVoice 比第三次千问超时早返回，但晚于第二次重试前的检查
+        let sensevoice = async {
+            tokio::time::sleep(Duration::from_secs(80)).await;
+            Ok("备用结果".to_string())
+        };
+        let result = race_with_fallback(
+            || {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                timed_out_request(timings)
+            },
+            sensevoice,
+            2,
+            timings.http_retry_delay,
+        )
+        .await;
+
+        assert_eq!(result.unwrap(), "备用结果");
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+        // 3 次请求超时 + 2 次重试间隔
+        assert_eq!(
+            started.elapsed(),
+            timings.http_request_timeout * 3 + timings.http_retry_delay * 2
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn uses_sensevoice_before_retry_when_already_succeeded() {
+        let timings = Timings::default();
+        let attempts = AtomicU32::new(0);
+        let started = tokio::time::Instant::now();
+
+        let sensevoice = async { Ok("备用结果".to_string()) };
+        let result = race_with_fallback(
+            || {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                timed_out_request(timings)
+            },
+            sensevoice,
+            2,
+            timings.http_retry_delay,
+        )
+        .await;
+
+        assert_eq!(result.unwrap(), "备用结果");
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+        assert_eq!(started.elapsed(), timings.http_request_timeout);
+    }
+
+    /// 本地 HTTP 服务：记录每个请求的方法与路径，按路径返回固定的 JSON
+    async fn spawn_http_server() -> (String, Arc<Mutex<Vec<String>>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&requests);
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let seen = Arc::clone(&seen);
+                tokio::spawn(async move {
+                    // 读完请求头与 Content-Length 指定的请求体
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 4096];
+                    let (head, body_len) = loop {
+                        let n = stream.read(&mut chunk).await.unwrap_or(0);
+                        if n == 0 {
+                            return;
+                        }
+                        buf.extend_from_slice(&chunk[..n]);
+                        if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                            let head = String::from_utf8_lossy(&buf[..end]).to_string();
+                            let body_len = head
+                                .lines()
+                                .find_map(|line| line.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse().unwrap_or(0)))
+                                .unwrap_or(0usize);
+                            break (head, end + 4 + body_len);
+                        }
+                    };
+                    while buf.len() < body_len {
+                        let n = stream.read(&mut chunk).await.unwrap_or(0);
+                        if n == 0 {
+                            break;
+                        }
+                        buf.extend_from_slice(&chunk[..n]);
+                    }
+
+                    let request_line = head.lines().next().unwrap_or_default();
+                    let path = request_line.split(' ').take(2).collect::<Vec<_>>().join(" ");
+                    let body = if path.ends_with("/generation") {
+                        r#"{"output":{"choices":[{"message":{"content":[{"text":"千问结果"}]}}]}}"#
+                    } else if path.ends_with("/audio/transcriptions") {
+                        r#"{"text":"备用结果"}"#
+                    } else {
+                        r#"{"data":[]}"#
+                    };
+                    seen.lock().unwrap().push(path);
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        (format!("http://{}", addr), requests)
+    }
+
+    #[tokio::test]
+    async fn requests_go_to_configured_base_url() {
+        let (url, requests) = spawn_http_server().await;
+        let dns = DnsOverrides::default();
+
+        let qwen = QwenASRClient::new("key".to_string(), &dns).with_base_url(&format!("{}/gateway/", url));
+        qwen.check_api_key().await.unwrap();
+        assert_eq!(qwen.transcribe_from_memory(b"RIFF").await.unwrap(), "千问结果");
+
+        let sensevoice = SenseVoiceClient::new("key".to_string(), &dns).with_base_url(&format!("{}/sf/v1", url));
+        assert_eq!(sensevoice.transcribe_bytes(b"RIFF").await.unwrap(), "备用结果");
+
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec![
+                "GET /gateway/compatible-mode/v1/models",
+                "POST /gateway/api/v1/services/aigc/multimodal-generation/generation",
+                "POST /sf/v1/audio/transcriptions",
+            ]
+        );
+    }
+}
@@ -2,45 +2,594 @@
 // 实时流式语音识别，边录音边发送
 
 use anyhow::Result;
-use base64::{Engine as _, engine::general_purpose};
-use futures_util::{SinkExt, StreamExt, stream::SplitSink};
-use std::sync::Arc;
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, OnceLock, Weak};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
-use tokio::sync::{Mutex, mpsc};
+use tokio::sync::{Mutex, Notify, mpsc, oneshot};
 use tokio::time::timeout;
-use tokio_tungstenite::{connect_async, tungstenite::Message, tungstenite::http, MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::{client_async_tls_with_config, connect_async_with_config, tungstenite::Message, tungstenite::handshake::client::Response, tungstenite::http, tungstenite::protocol::{CloseFrame, frame::coding::CloseCode}, MaybeTlsStream, WebSocketStream};
 use tokio::net::TcpStream;
 
-// WebSocket 写入端类型别名
-type WsSink = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
+use crate::audio_frame::{AudioFrameEncoder, Base64AppendEncoder};
+use crate::config::{BacklogPolicy, RealtimeVadMode};
+use crate::dns::DnsOverrides;
+use crate::privacy;
+use crate::timing::Timings;
+use crate::ws_trace::{self, WsTrace};
 
-const WEBSOCKET_URL: &str = "wss://dashscope.aliyuncs.com/api-ws/v1/realtime";
-const MODEL: &str = "qwen3-asr-flash-realtime";
-const IDLE_TIMEOUT_SECS: u64 = 180; // 3 分钟空闲超时
-const TRANSCRIPTION_TIMEOUT_SECS: u64 = 10; // 转录结果等待超时（秒）
+// WebSocket 读写端类型别名（开启留痕时记录经过的每条消息）
+pub(crate) type WsSink = ws_trace::TracedSink;
+pub(crate) type WsSource = ws_trace::TracedSource;
+
+pub const DEFAULT_WEBSOCKET_URL: &str = "wss://dashscope.aliyuncs.com/api-ws/v1/realtime";
+pub const DEFAULT_MODEL: &str = "qwen3-asr-flash-realtime";
+/// 默认采样率；设备采样率不在协议支持列表中时重采样到该值
+pub const DEFAULT_SAMPLE_RATE: u32 = 16000;
+/// 协议支持直接上传的 PCM 采样率
+const SUPPORTED_SAMPLE_RATES: [u32; 3] = [16000, 24000, 48000];
+const DEAD_AFTER_PINGS: u32 = 3; // 连续这么多个 ping 周期未收到任何消息视为死连接
+const RESULT_CHANNEL_CAPACITY: usize = 64; // 每轮 / 每段结果一条，调用方拉取不及时也不阻塞驱动任务
+// OpenAI 实时转录的 pcm16 固定为 24kHz
+const OPENAI_SAMPLE_RATE: u32 = 24000;
+
+/// 会话使用的采样率：设备采样率受协议支持时直传，否则回退到 16kHz（由录音端重采样）
+pub fn session_sample_rate(device_rate: u32) -> u32 {
+    if SUPPORTED_SAMPLE_RATES.contains(&device_rate) {
+        device_rate
+    } else {
+        DEFAULT_SAMPLE_RATE
+    }
+}
+
+/// 校验配置的采样率是否受该服务 / 模型支持
+pub fn check_sample_rate(rate: u32, supported: &[u32], model: &str) -> Result<()> {
+    if supported.contains(&rate) {
+        return Ok(());
+    }
+    let supported: Vec<String> = supported.iter().map(|rate| rate.to_string()).collect();
+    anyhow::bail!("实时识别模型 {} 不支持 {}Hz 采样率（可选: {}）", model, rate, supported.join(" / "))
+}
+
+/// 实时识别服务的协议差异：事件模型相同，session 配置事件与个别控制事件不同
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RealtimeProtocol {
+    DashScope,
+    OpenAi,
+}
+
+/// 实时识别服务端点：WebSocket 地址、鉴权与协议
+#[derive(Clone)]
+pub struct RealtimeEndpoint {
+    protocol: RealtimeProtocol,
+    url: String,
+    api_key: String,
+}
+
+impl RealtimeEndpoint {
+    /// 千问实时识别（DashScope），`url` 为 WebSocket 地址（默认 `DEFAULT_WEBSOCKET_URL`），模型通过 URL 参数追加
+    pub fn dashscope(api_key: String, url: &str, model: &str) -> Self {
+        let separator = if url.contains('?') { '&' } else { '?' };
+        Self {
+            protocol: RealtimeProtocol::DashScope,
+            url: format!("{}{}model={}", url, separator, model),
+            api_key,
+        }
+    }
+
+    /// OpenAI Realtime API 转录会话（`intent=transcription`），模型随 transcription_session.update 下发
+    pub fn openai(api_key: String, url: String) -> Self {
+        Self {
+            protocol: RealtimeProtocol::OpenAi,
+            url,
+            api_key,
+        }
+    }
+
+    /// 上传音频的编码方式：两种协议都只接受 JSON 事件，按 base64 嵌入 append
+    fn audio_encoder(&self) -> Box<dyn AudioFrameEncoder> {
+        match self.protocol {
+            RealtimeProtocol::DashScope | RealtimeProtocol::OpenAi => Box::new(Base64AppendEncoder),
+        }
+    }
+
+    /// 该服务支持直接上传的 PCM 采样率
+    pub fn supported_sample_rates(&self) -> &'static [u32] {
+        match self.protocol {
+            RealtimeProtocol::DashScope => &SUPPORTED_SAMPLE_RATES,
+            RealtimeProtocol::OpenAi => &[OPENAI_SAMPLE_RATE],
+        }
+    }
+
+    /// 该服务实际使用的上传采样率（OpenAI 固定 24kHz，由录音端重采样）
+    fn sample_rate(&self, requested: u32) -> u32 {
+        match self.protocol {
+            RealtimeProtocol::DashScope => requested,
+            RealtimeProtocol::OpenAi => OPENAI_SAMPLE_RATE,
+        }
+    }
+
+    /// 会话配置事件
+    fn session_update(&self, vad_mode: RealtimeVadMode, realtime: &RealtimeOptions, sample_rate: u32) -> serde_json::Value {
+        build_session_update(self.protocol, vad_mode, realtime, sample_rate)
+    }
+
+    /// 是否有 response 生命周期（response.cancel / response.done）；
+    /// OpenAI 转录会话没有 response，一次 commit 对应一条 completed
+    fn has_response(&self) -> bool {
+        self.protocol == RealtimeProtocol::DashScope
+    }
+
+    /// 关闭前是否发送 session.finish
+    fn has_session_finish(&self) -> bool {
+        self.protocol == RealtimeProtocol::DashScope
+    }
+}
+
+/// 16-bit 单声道 PCM 每毫秒的字节数
+fn bytes_per_ms(sample_rate: u32) -> usize {
+    sample_rate as usize / 1000 * 2
+}
+
+const MAX_ROUND_INTERVALS: u32 = 3; // 连续听写一直不停顿时，满该倍数的提交间隔后强制提交
+const MAX_RESEND_SECS: usize = 120; // 断线重发缓存最多保留的音频时长，超出后断线不再续传、改走 HTTP 回退
+
+/// 实时会话参数
+#[derive(Debug, Clone)]
+pub struct SessionOptions {
+    /// 断句方式（手动 commit / 服务端 VAD）
+    pub vad_mode: RealtimeVadMode,
+    /// 会话中途断线后的最大重连次数
+    pub max_reconnect_attempts: u32,
+    /// 合并发送阈值（毫秒音频）：连续的音频块累积到该时长后合并为一条 append 消息
+    pub batch_ms: u32,
+    /// 待发送音频的积压上限（块数）
+    pub backlog_limit: usize,
+    /// 积压超过上限时丢弃最旧块还是继续缓冲
+    pub backlog_policy: BacklogPolicy,
+    /// 固定的上传采样率；None 时按设备采样率协商（见 `session_sample_rate`）
+    pub sample_rate: Option<u32>,
+    /// 保活 ping 间隔（秒），0 表示不发送 ping、不做死连接检测
+    pub ping_interval_secs: u64,
+    /// 发送 ping 后超过该时长未收到 pong 即判定连接失效并重建，0 表示不检查
+    pub pong_timeout: Duration,
+    /// 等待结果期间（commit 之后）超过该时长未收到任何服务端消息即判定连接失效，0 表示关闭
+    pub stale_after: Duration,
+    /// 自定义 DNS 解析（命中时直连指定 IP，SNI / Host 保持原域名）
+    pub dns_overrides: DnsOverrides,
+    /// 结果等待超时系数：超时 = max(10 秒, 音频秒数 × 系数)
+    pub timeout_factor: f32,
+    /// 本地语音检测阈值（dBFS）：整段录音的音量都低于它时视为没有说话；None 关闭检测，总是视为说过话
+    pub speech_threshold_dbfs: Option<f32>,
+    /// 连续听写（手动断句）：每轮录满该时长后在停顿处自动 commit，一次会话产出多轮结果，
+    /// 出完结果后连接保持、断线照常重连；None 为按住说话（松开时 commit 一次）
+    pub auto_commit: Option<Duration>,
+    /// 结果等待、分段收尾、重连退避等时长
+    pub timings: Timings,
+    /// 把收发的每条事件写入 logs/ws-trace-*.jsonl（排障用）
+    pub ws_trace: bool,
+}
+
+impl SessionOptions {
+    /// 超过该时长未收到任何服务端消息即判定为死连接（未启用 ping 时为 None）
+    fn dead_after(&self) -> Option<Duration> {
+        (self.ping_interval_secs > 0)
+            .then(|| Duration::from_secs(self.ping_interval_secs * DEAD_AFTER_PINGS as u64))
+    }
+
+    /// 是否为连续听写会话（服务端 VAD 本身就逐段出结果，无需自动 commit）
+    fn dictation(&self) -> bool {
+        self.auto_commit.is_some() && self.vad_mode == RealtimeVadMode::Manual
+    }
+
+    /// 死连接判定阈值：等待结果时用较短的 stale_after，尽快回退 HTTP；空闲时沿用保活 ping 的判定
+    pub(crate) fn stale_threshold(&self, awaiting_result: bool) -> Option<Duration> {
+        if awaiting_result {
+            (!self.stale_after.is_zero()).then_some(self.stale_after)
+        } else {
+            self.dead_after()
+        }
+    }
+}
+
+/// 等待结果期间连接失效（commit 之后长时间收不到任何消息），上层据此立即走 HTTP 回退
+#[derive(Debug)]
+pub struct ConnectionStale {
+    pub silence: Duration,
+}
+
+impl std::fmt::Display for ConnectionStale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "连接已失效：等待结果期间 {}ms 未收到任何消息", self.silence.as_millis())
+    }
+}
+
+impl std::error::Error for ConnectionStale {}
+
+/// 握手被服务端拒绝（401 / 403）：API Key 无效或未开通实时识别权限，重连与 HTTP 回退都没有意义
+#[derive(Debug)]
+pub struct AuthRejected {
+    pub status: u16,
+}
+
+impl std::fmt::Display for AuthRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "API Key 无效或未开通实时识别权限（HTTP {}）", self.status)
+    }
+}
+
+impl std::error::Error for AuthRejected {}
+
+/// 把握手错误转换为 anyhow 错误，401 / 403 转为 `AuthRejected` 供上层识别
+fn handshake_error(error: tokio_tungstenite::tungstenite::Error) -> anyhow::Error {
+    if let tokio_tungstenite::tungstenite::Error::Http(ref response) = error {
+        let status = response.status().as_u16();
+        if matches!(status, 401 | 403) {
+            return AuthRejected { status }.into();
+        }
+    }
+    anyhow::anyhow!("WebSocket 连接失败: {}", error)
+}
+
+/// 等待结果期间的静默时长：从 commit 发出与最近一次收到消息中较晚的一个算起
+pub(crate) fn awaiting_silence(liveness: &Liveness, committed_at: Instant) -> Duration {
+    liveness.silence().min(committed_at.elapsed())
+}
+
+impl Default for SessionOptions {
+    fn default() -> Self {
+        Self {
+            vad_mode: RealtimeVadMode::Manual,
+            max_reconnect_attempts: 2,
+            batch_ms: 200,
+            backlog_limit: 100,
+            backlog_policy: BacklogPolicy::Unbounded,
+            sample_rate: None,
+            ping_interval_secs: 15,
+            pong_timeout: Duration::from_secs(10),
+            stale_after: Duration::from_secs(5),
+            dns_overrides: DnsOverrides::default(),
+            timeout_factor: 0.5,
+            speech_threshold_dbfs: Some(-40.0),
+            auto_commit: None,
+            timings: Timings::default(),
+            ws_trace: false,
+        }
+    }
+}
+
+/// 识别参数（随 session.update 下发）
+#[derive(Debug, Clone)]
+pub struct RealtimeOptions {
+    /// 识别语言，"auto" 或空表示由模型自动判断
+    pub language: String,
+    /// 热词表，作为上下文提示提高专有名词的识别率
+    pub vocabulary: Vec<String>,
+    /// 模型名称
+    pub model: String,
+    /// 是否输出标点（false 时请求服务端不加标点，服务端不支持时忽略）
+    pub punctuation: bool,
+    /// 合并进 session 配置的额外参数，用于下发服务端新增的识别参数
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Default for RealtimeOptions {
+    fn default() -> Self {
+        Self {
+            language: "auto".to_string(),
+            vocabulary: Vec::new(),
+            model: DEFAULT_MODEL.to_string(),
+            punctuation: true,
+            extra: serde_json::Map::new(),
+        }
+    }
+}
+
+/// 会话连接状态事件（断线 / 重连），供上层转发给前端提示
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ConnectionEvent {
+    Reconnecting { attempt: u32, max_attempts: u32 },
+    Reconnected { attempt: u32 },
+    Disconnected { reason: String },
+    /// 待发送音频积压达到上限的 80%（网络变慢）
+    NetworkSlow { backlog: usize, limit: usize },
+}
+
+/// 会话生命周期状态，调用方据此区分"还在等结果"与"已经出错"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionStatus {
+    /// WebSocket 已建立（含重连成功）
+    Connected,
+    /// 服务端已确认会话配置（session.updated）
+    Configured,
+    /// 服务端已确认提交音频（input_audio_buffer.committed）
+    Committed,
+    /// 服务端 error 事件或重连彻底失败
+    Error { code: Option<String>, message: String },
+    /// 会话驱动任务已结束，不会再有任何结果
+    Closed,
+}
+
+impl SessionStatus {
+    /// 出现后不可能再拿到结果的状态
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, SessionStatus::Error { .. } | SessionStatus::Closed)
+    }
+}
+
+/// 松开按键后等待实时结果的结局，决定是否回退到 HTTP 识别
+#[derive(Debug)]
+pub enum StopResult {
+    /// 拿到实时结果（含超时后用已收到增量兜底的结果）
+    Text(String),
+    /// 鉴权失败：HTTP 回退同样会被拒绝，直接提示
+    AuthRejected(anyhow::Error),
+    /// 超时、会话出错或已关闭：改用完整录音走 HTTP 回退
+    Fallback(anyhow::Error),
+}
+
+/// 服务端 VAD 检测到的说话状态变化，供上层转发给前端
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpeechEvent {
+    Started,
+    Stopped,
+}
 
 /// WebSocket 实时 ASR 会话
 pub struct RealtimeSession {
     sender: mpsc::Sender<SessionCommand>,
-    result_receiver: mpsc::Receiver<Result<String>>,
+    audio_queue: Arc<AudioQueue>,
+    result_receiver: Option<mpsc::Receiver<Result<String>>>,
+    event_receiver: Option<mpsc::UnboundedReceiver<ConnectionEvent>>,
+    speech_receiver: Option<mpsc::UnboundedReceiver<SpeechEvent>>,
+    status_receiver: Option<mpsc::UnboundedReceiver<SessionStatus>>,
+    vad_mode: RealtimeVadMode,
+    // 本次录音是否检测到过语音（服务端 speech_started 或本地音量检测）
+    speech_seen: Arc<AtomicBool>,
+    // 本地语音检测的 RMS 阈值，None 表示不检测
+    speech_threshold: Option<f64>,
+    // 服务端 VAD 模式下的断句状态：是否正在说话、已提交但尚未出结果的段数
+    speech_active: Arc<AtomicBool>,
+    pending_segments: Arc<AtomicUsize>,
+    // 连续听写会话：由驱动任务定时 commit，结果逐轮从分段通道发出
+    dictation: bool,
+    liveness: Arc<Liveness>,
+    // 收到首个识别结果（增量或整段）的时间，用于延迟统计
+    first_transcript: Arc<OnceLock<Instant>>,
+    // 本次提交已收到的识别文本（已完成的分段 + 当前增量），等待结果超时时作兜底
+    received_text: Arc<std::sync::Mutex<String>>,
+    // 已发送的音频样本数，用于按音频时长计算结果等待超时
+    sent_samples: AtomicU64,
+    // 上传 PCM 的采样率（随 session.update 下发）
+    sample_rate: u32,
+    timeout_factor: f32,
+    timings: Timings,
+    // 是否已被用户取消
+    cancelled: Arc<AtomicBool>,
+    origin: SessionOrigin,
+}
+
+/// 会话取消句柄：等待结果期间会话本体被占用时，也能从别处取消本次转录
+#[derive(Clone)]
+pub struct SessionCanceller {
+    sender: mpsc::Sender<SessionCommand>,
+    cancelled: Arc<AtomicBool>,
+    close_timeout: Duration,
+}
+
+impl SessionCanceller {
+    /// 见 `RealtimeSession::cancel`
+    pub async fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        request_shutdown(&self.sender, SessionCommand::Cancel, self.close_timeout).await;
+    }
+}
+
+/// 连接活性计时（驱动任务写入，连接池读取）
+pub(crate) struct Liveness {
+    epoch: Instant,
+    // 最近一次收到服务端任何消息（含 pong）的时间，相对 epoch 的毫秒数
+    last_received_ms: AtomicU64,
+}
+
+impl Liveness {
+    fn new() -> Self {
+        Self {
+            epoch: Instant::now(),
+            last_received_ms: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn mark_received(&self) {
+        let now = self.epoch.elapsed().as_millis() as u64;
+        self.last_received_ms.store(now, Ordering::SeqCst);
+    }
+
+    /// 距最近一次收到消息的时长
+    fn silence(&self) -> Duration {
+        let last = Duration::from_millis(self.last_received_ms.load(Ordering::SeqCst));
+        self.epoch.elapsed().saturating_sub(last)
+    }
+}
+
+/// 待发送音频队列：录音侧只入队，不会被慢网络阻塞，由驱动任务取出合并发送
+/// 积压达到上限的 80% 时告警并上报 NetworkSlow；超过上限时按 BacklogPolicy 丢弃最旧块或继续缓冲
+pub(crate) struct AudioQueue {
+    chunks: std::sync::Mutex<VecDeque<Vec<u8>>>,
+    notify: Notify,
+    limit: usize,
+    policy: BacklogPolicy,
+    // 会话归还连接池时随连接状态通道一起换新
+    event_tx: std::sync::Mutex<mpsc::UnboundedSender<ConnectionEvent>>,
+    // 是否处于积压告警中（队列清空后解除，避免每个块都告警）
+    slow: AtomicBool,
+    // 会话期间的积压统计，会话结束时汇总到日志
+    peak: AtomicUsize,
+    slow_count: AtomicUsize,
+    dropped: AtomicUsize,
+}
+
+impl AudioQueue {
+    fn new(limit: usize, policy: BacklogPolicy, event_tx: mpsc::UnboundedSender<ConnectionEvent>) -> Self {
+        Self {
+            chunks: std::sync::Mutex::new(VecDeque::new()),
+            notify: Notify::new(),
+            limit: limit.max(1),
+            policy,
+            event_tx: std::sync::Mutex::new(event_tx),
+            slow: AtomicBool::new(false),
+            peak: AtomicUsize::new(0),
+            slow_count: AtomicUsize::new(0),
+            dropped: AtomicUsize::new(0),
+        }
+    }
+
+    fn push(&self, chunk: Vec<u8>) {
+        let (backlog, dropped) = {
+            let mut chunks = self.chunks.lock().unwrap();
+            chunks.push_back(chunk);
+            let mut dropped = 0;
+            if self.policy == BacklogPolicy::DropOldest {
+                while chunks.len() > self.limit {
+                    chunks.pop_front();
+                    dropped += 1;
+                }
+            }
+            (chunks.len(), dropped)
+        };
+        self.notify.notify_one();
+
+        self.peak.fetch_max(backlog, Ordering::Relaxed);
+        if dropped > 0 && self.dropped.fetch_add(dropped, Ordering::Relaxed) == 0 {
+            tracing::warn!("音频发送积压超过上限 {} 块，开始丢弃最旧的音频块", self.limit);
+        }
+        if backlog * 5 >= self.limit * 4 && !self.slow.swap(true, Ordering::Relaxed) {
+            self.slow_count.fetch_add(1, Ordering::Relaxed);
+            tracing::warn!("音频发送积压 {} 块（上限 {}），网络可能较慢", backlog, self.limit);
+            let _ = self.event_tx.lock().unwrap().send(ConnectionEvent::NetworkSlow { backlog, limit: self.limit });
+        }
+    }
+
+    /// 等待新的音频块入队
+    pub(crate) async fn notified(&self) {
+        self.notify.notified().await;
+    }
+
+    /// 取出当前积压的全部音频块
+    pub(crate) fn drain(&self) -> VecDeque<Vec<u8>> {
+        let chunks = std::mem::take(&mut *self.chunks.lock().unwrap());
+        self.slow.store(false, Ordering::Relaxed);
+        chunks
+    }
+
+    pub(crate) fn summary(&self) -> String {
+        format!(
+            "峰值 {} 块 / 上限 {}，积压告警 {} 次，丢弃 {} 块",
+            self.peak.load(Ordering::Relaxed),
+            self.limit,
+            self.slow_count.load(Ordering::Relaxed),
+            self.dropped.load(Ordering::Relaxed)
+        )
+    }
 }
 
-enum SessionCommand {
-    SendAudio(Vec<u8>),  // PCM 数据（已 Base64 编码）
+pub(crate) enum SessionCommand {
     Commit,              // 提交音频缓冲区
-    Close,               // 关闭连接
+    Close(oneshot::Sender<()>),  // 关闭连接，驱动任务释放连接后回执
+    Cancel(oneshot::Sender<()>), // 取消转写并清空服务端缓冲区，随后关闭连接
+    // 归还连接池：清空上一次录音的状态，换上新的首字计时与连接状态 / 说话状态通道，完成后回执
+    Reset {
+        first_transcript: Arc<OnceLock<Instant>>,
+        event_tx: mpsc::UnboundedSender<ConnectionEvent>,
+        speech_tx: mpsc::UnboundedSender<SpeechEvent>,
+        ack: oneshot::Sender<()>,
+    },
+    Probe(oneshot::Sender<()>), // 发送 ping，收到 pong 后回执
+}
+
+/// 会话的来源，用于统计按键到会话就绪的耗时
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionOrigin {
+    /// 按键时即时建立的连接
+    New,
+    /// 连接池中预热好、尚未用过的会话
+    Warm,
+    /// 上一次录音结束后归还连接池的会话
+    Reused,
+}
+
+impl SessionOrigin {
+    pub fn label(self) -> &'static str {
+        match self {
+            SessionOrigin::New => "新建",
+            SessionOrigin::Warm => "预热",
+            SessionOrigin::Reused => "复用",
+        }
+    }
+}
+
+/// 发送关闭 / 取消命令并等待驱动任务回执（带超时）
+async fn request_shutdown(
+    sender: &mpsc::Sender<SessionCommand>,
+    command: fn(oneshot::Sender<()>) -> SessionCommand,
+    wait: Duration,
+) {
+    let (ack_tx, ack_rx) = oneshot::channel();
+    if sender.send(command(ack_tx)).await.is_err() {
+        // 驱动任务已退出，连接早已释放
+        return;
+    }
+    if timeout(wait, ack_rx).await.is_err() {
+        tracing::warn!("等待会话关闭超时");
+    }
 }
 
 impl RealtimeSession {
-    /// 发送音频块（PCM 16-bit, 16kHz, 单声道）
+    /// 发送音频块（PCM 16-bit 单声道，采样率为会话协商的 `sample_rate()`）
     pub async fn send_audio_chunk(&self, pcm_data: &[i16]) -> Result<()> {
         // 转换为字节数组
         let bytes: Vec<u8> = pcm_data.iter()
             .flat_map(|&sample| sample.to_le_bytes())
             .collect();
 
-        self.sender.send(SessionCommand::SendAudio(bytes)).await
-            .map_err(|_| anyhow::anyhow!("发送音频块失败：通道已关闭"))
+        if self.sender.is_closed() {
+            anyhow::bail!("发送音频块失败：通道已关闭");
+        }
+        self.audio_queue.push(bytes);
+        self.sent_samples.fetch_add(pcm_data.len() as u64, Ordering::Relaxed);
+        // 手动模式下服务端不下发 VAD 事件，用本地音量检测补充
+        if let Some(threshold) = self.speech_threshold {
+            if !self.speech_seen.load(Ordering::Relaxed) && has_voice(pcm_data, threshold) {
+                self.speech_seen.store(true, Ordering::Relaxed);
+            }
+        }
+        Ok(())
+    }
+
+    /// 本次录音是否检测到过语音（关闭本地检测时总是 true）
+    pub fn speech_detected(&self) -> bool {
+        self.speech_threshold.is_none() || self.speech_seen.load(Ordering::Relaxed)
+    }
+
+    /// 已发送音频的时长
+    pub fn audio_duration(&self) -> Duration {
+        Duration::from_millis(self.sent_samples.load(Ordering::Relaxed) * 1000 / self.sample_rate as u64)
+    }
+
+    /// 收到首个识别结果（增量或整段）的时间
+    pub fn first_transcript_at(&self) -> Option<Instant> {
+        self.first_transcript.get().copied()
+    }
+
+    /// 按已发送音频时长计算的结果等待超时：max(10 秒, 音频秒数 × 系数)
+    pub fn result_timeout(&self) -> Duration {
+        let floor = self.timings.realtime_result_floor;
+        let factor = self.timeout_factor.max(0.0);
+        floor.max(self.audio_duration().mul_f32(factor))
     }
 
     /// 提交音频缓冲区（手动 commit 模式）
@@ -49,284 +598,2922 @@ impl RealtimeSession {
             .map_err(|_| anyhow::anyhow!("提交音频失败：通道已关闭"))
     }
 
-    /// 等待最终转录结果（带超时）
-    pub async fn wait_for_result(&mut self) -> Result<String> {
-        match timeout(
-            Duration::from_secs(TRANSCRIPTION_TIMEOUT_SECS),
-            self.result_receiver.recv()
-        ).await {
+    /// 等待最终转录结果（带超时，通常传入 `result_timeout()`）
+    pub async fn wait_for_result(&mut self, wait: Duration) -> Result<String> {
+        let audio_duration = self.audio_duration();
+        if self.result_receiver.is_none() {
+            anyhow::bail!("等待结果失败：结果通道已被取走");
+        }
+        let started = tokio::time::Instant::now();
+        match timeout(wait, self.next_result()).await {
             Ok(Some(result)) => result,
             Ok(None) => Err(anyhow::anyhow!("等待结果失败：通道已关闭")),
-            Err(_) => Err(anyhow::anyhow!("转录超时：{}秒内未收到结果", TRANSCRIPTION_TIMEOUT_SECS)),
+            Err(_) => {
+                // 已收到部分增量时用它兜底，不再整段回退重传
+                let received = self.received_text.lock().unwrap().clone();
+                if has_content(&received) {
+                    tracing::warn!(
+                        "转录超时：已等待 {:.1} 秒未收到最终结果，使用已收到的增量兜底: {}",
+                        started.elapsed().as_secs_f32(),
+                        privacy::redact(&received)
+                    );
+                    return Ok(received);
+                }
+                Err(anyhow::anyhow!(
+                    "转录超时：已等待 {:.1} 秒未收到结果（音频时长 {:.1} 秒）",
+                    started.elapsed().as_secs_f32(),
+                    audio_duration.as_secs_f32()
+                ))
+            }
+        }
+    }
+
+    /// 拉取下一条结果：每次 commit（连续听写为每个自动提交的轮次，服务端 VAD 为每个分段）产出一条。
+    /// 接收任务在出结果后继续运行，同一连接上可以一边发送音频、commit 下一轮，一边拉取上一轮的结果；
+    /// 驱动任务结束且结果已取完（或结果通道已被取走）时返回 None
+    pub async fn next_result(&mut self) -> Option<Result<String>> {
+        self.result_receiver.as_mut()?.recv().await
+    }
+
+    /// 会话上传 PCM 的采样率
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// 会话使用的时长配置
+    pub fn timings(&self) -> Timings {
+        self.timings
+    }
+
+    /// 当前会话的断句方式
+    pub fn vad_mode(&self) -> RealtimeVadMode {
+        self.vad_mode
+    }
+
+    /// 是否为连续听写会话
+    pub fn is_dictation(&self) -> bool {
+        self.dictation
+    }
+
+    /// 取走分段结果通道（服务端 VAD 模式下由调用方持续消费每一段 transcript）
+    pub fn take_segment_receiver(&mut self) -> Option<mpsc::Receiver<Result<String>>> {
+        self.result_receiver.take()
+    }
+
+    /// 会话驱动任务是否仍在运行（重连彻底失败后会退出）
+    pub fn is_alive(&self) -> bool {
+        !self.sender.is_closed()
+    }
+
+    /// 丢弃已积压的连接状态事件（预热会话空闲期间的静默重连）
+    fn discard_connection_events(&mut self) {
+        if let Some(rx) = self.event_receiver.as_mut() {
+            while rx.try_recv().is_ok() {}
+        }
+        if let Some(rx) = self.status_receiver.as_mut() {
+            while rx.try_recv().is_ok() {}
+        }
+    }
+
+    /// 取走连接状态事件通道（断线 / 重连通知）
+    pub fn take_event_receiver(&mut self) -> Option<mpsc::UnboundedReceiver<ConnectionEvent>> {
+        self.event_receiver.take()
+    }
+
+    /// 取走说话状态事件通道（服务端 VAD 的 speech_started / speech_stopped）
+    pub fn take_speech_receiver(&mut self) -> Option<mpsc::UnboundedReceiver<SpeechEvent>> {
+        self.speech_receiver.take()
+    }
+
+    /// 取走会话状态通道（Connected / Configured / Committed / Error / Closed）
+    pub fn take_status_receiver(&mut self) -> Option<mpsc::UnboundedReceiver<SessionStatus>> {
+        self.status_receiver.take()
+    }
+
+    /// 手动断句提交后等待最终结果（超时为 `result_timeout()`），同时监听会话状态：
+    /// 出现致命错误或会话已关闭时立即结束，不再干等到超时
+    pub async fn wait_for_stop_result(&mut self) -> StopResult {
+        let wait = self.result_timeout();
+        // 只借用会话状态通道，等待结束后放回，会话归还连接池后下次录音仍能及早发现致命错误
+        let mut status_receiver = self.status_receiver.take();
+        let result = match status_receiver.as_mut() {
+            Some(status_rx) => {
+                let fatal_status = async {
+                    while let Some(status) = status_rx.recv().await {
+                        if status.is_fatal() {
+                            return status;
+                        }
+                        tracing::debug!("实时会话状态: {:?}", status);
+                    }
+                    SessionStatus::Closed
+                };
+                tokio::select! {
+                    // 结果与关闭同时到达时优先取结果
+                    biased;
+                    result = self.wait_for_result(wait) => result,
+                    status = fatal_status => match status {
+                        SessionStatus::Error { code, message } => {
+                            Err(anyhow::anyhow!("实时会话出错: {} (code: {:?})", message, code))
+                        }
+                        _ => Err(anyhow::anyhow!("实时会话已关闭，未收到转录结果")),
+                    },
+                }
+            }
+            None => self.wait_for_result(wait).await,
+        };
+        self.status_receiver = status_receiver;
+        match result {
+            Ok(text) => StopResult::Text(text),
+            Err(e) if e.is::<AuthRejected>() => StopResult::AuthRejected(e),
+            Err(e) => StopResult::Fallback(e),
+        }
+    }
+
+    /// 服务端 VAD / 连续听写收尾：松开按键时若仍在说话（连续听写总是）提交尾段，
+    /// 然后等待所有已提交的段都返回结果（带超时）
+    pub async fn finish_segments(&self) -> Result<()> {
+        if self.dictation {
+            // 先计数再提交，尾段没有语音时由驱动任务跳过提交并扣回
+            self.pending_segments.fetch_add(1, Ordering::SeqCst);
+            self.commit_audio().await?;
+        } else if self.speech_active.load(Ordering::SeqCst) {
+            tracing::info!("松开按键时仍在说话，手动提交尾段");
+            self.pending_segments.fetch_add(1, Ordering::SeqCst);
+            self.speech_active.store(false, Ordering::SeqCst);
+            self.commit_audio().await?;
+        }
+
+        let wait = self.timings.realtime_result_floor;
+        let deadline = tokio::time::Instant::now() + wait;
+        while self.pending_segments.load(Ordering::SeqCst) > 0 {
+            if tokio::time::Instant::now() >= deadline {
+                return Err(anyhow::anyhow!("转录超时：{}秒内仍有分段未返回结果", wait.as_secs()));
+            }
+            tokio::time::sleep(self.timings.segment_poll_interval).await;
         }
+        Ok(())
     }
 
-    /// 关闭会话
+    /// 优雅关闭会话：停止发送音频，发送会话结束事件与 Close 帧（1000），
+    /// 等待服务端回执或超时后再释放连接
     pub async fn close(&self) -> Result<()> {
-        let _ = self.sender.send(SessionCommand::Close).await;
+        request_shutdown(&self.sender, SessionCommand::Close, self.timings.session_close_timeout).await;
+        Ok(())
+    }
+
+    /// 取消本次转录：已 commit 时发送 response.cancel 让服务端停止转写（不再计费），
+    /// 清空服务端的音频缓冲区，然后关闭连接。等待中的 `wait_for_result` 随之返回错误
+    pub async fn cancel(&self) {
+        self.canceller().cancel().await;
+    }
+
+    /// 取消句柄（可在不持有会话的情况下取消）
+    pub fn canceller(&self) -> SessionCanceller {
+        SessionCanceller {
+            sender: self.sender.clone(),
+            cancelled: Arc::clone(&self.cancelled),
+            close_timeout: self.timings.session_close_timeout,
+        }
+    }
+
+    /// 会话是否已被取消
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// 会话的来源（新建 / 预热 / 复用）
+    pub fn origin(&self) -> SessionOrigin {
+        self.origin
+    }
+
+    /// 能否归还连接池：连接仍可用、未被取消、结果通道与会话状态通道都还在的手动断句会话
+    /// （服务端 VAD 与连续听写的会话状态跨段累积，用完即关闭；连接状态 / 说话状态通道在 `reset` 时换新）
+    fn reusable(&self) -> bool {
+        self.is_alive()
+            && !self.is_cancelled()
+            && self.result_receiver.is_some()
+            && self.status_receiver.is_some()
+            && self.vad_mode == RealtimeVadMode::Manual
+            && !self.dictation
+    }
+
+    /// 清空本次录音的状态，供下一次录音复用同一条连接
+    async fn reset(&mut self) -> Result<()> {
+        self.first_transcript = Arc::new(OnceLock::new());
+        // 连接状态与说话状态通道每次录音都会被取走交给转发任务，复用时换一组新的
+        let (event_tx, event_rx) = mpsc::unbounded_channel::<ConnectionEvent>();
+        let (speech_tx, speech_rx) = mpsc::unbounded_channel::<SpeechEvent>();
+        let (ack_tx, ack_rx) = oneshot::channel();
+        let command = SessionCommand::Reset {
+            first_transcript: Arc::clone(&self.first_transcript),
+            event_tx: event_tx.clone(),
+            speech_tx,
+            ack: ack_tx,
+        };
+        self.sender.send(command).await
+            .map_err(|_| anyhow::anyhow!("重置会话失败：通道已关闭"))?;
+        timeout(self.timings.session_close_timeout, ack_rx).await
+            .map_err(|_| anyhow::anyhow!("重置会话超时"))?
+            .map_err(|_| anyhow::anyhow!("重置会话失败：驱动任务已退出"))?;
+
+        // 驱动任务回执之后不会再产出上一次录音的结果，丢弃残留
+        if let Some(rx) = self.result_receiver.as_mut() {
+            while rx.try_recv().is_ok() {}
+        }
+        *self.audio_queue.event_tx.lock().unwrap() = event_tx;
+        self.event_receiver = Some(event_rx);
+        self.speech_receiver = Some(speech_rx);
+        self.discard_connection_events();
+        self.speech_seen.store(false, Ordering::SeqCst);
+        self.speech_active.store(false, Ordering::SeqCst);
+        self.pending_segments.store(0, Ordering::SeqCst);
+        self.sent_samples.store(0, Ordering::Relaxed);
         Ok(())
     }
+
+    /// 发送 ping 并等待 pong，确认连接仍然可用
+    async fn probe(&self, wait: Duration) -> bool {
+        let (ack_tx, ack_rx) = oneshot::channel();
+        if self.sender.send(SessionCommand::Probe(ack_tx)).await.is_err() {
+            return false;
+        }
+        matches!(timeout(wait, ack_rx).await, Ok(Ok(())))
+    }
 }
 
 /// WebSocket 连接池（智能连接管理）
+/// 预先建立一条已完成 session.update 的空闲会话，按键按下时直接取用
 pub struct ConnectionPool {
-    api_key: String,
+    endpoint: RealtimeEndpoint,
+    options: SessionOptions,
+    realtime: RealtimeOptions,
+    // 最近一次取用会话时的采样率，预热连接按此建立
+    sample_rate: Arc<AtomicU32>,
     connection: Arc<Mutex<Option<PooledConnection>>>,
+    // 空闲清理任务，随连接池释放而取消
+    cleanup: Option<tokio::task::AbortHandle>,
+}
+
+impl Drop for ConnectionPool {
+    fn drop(&mut self) {
+        if let Some(cleanup) = self.cleanup.take() {
+            cleanup.abort();
+        }
+    }
+}
+
+/// 池内连接需要被回收的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EvictReason {
+    /// 空闲超过 `Timings::pool_idle_timeout`
+    IdleExpired,
+    /// 驱动任务已结束或长时间未收到服务端消息
+    Unhealthy,
+}
+
+/// 根据空闲时长与心跳情况判定连接是否应回收
+fn evict_reason(
+    idle: Duration,
+    silence: Duration,
+    alive: bool,
+    idle_limit: Duration,
+    dead_after: Option<Duration>,
+) -> Option<EvictReason> {
+    if idle >= idle_limit {
+        Some(EvictReason::IdleExpired)
+    } else if !alive || dead_after.is_some_and(|limit| silence >= limit) {
+        Some(EvictReason::Unhealthy)
+    } else {
+        None
+    }
 }
 
 struct PooledConnection {
-    sender: mpsc::Sender<SessionCommand>,
+    id: u64,
+    session: RealtimeSession,
+    // 最近一次放入 / 取出连接池的时间，空闲回收以此为准
     last_used: Instant,
+    // 与驱动任务共享的收包计时，用于判定死连接
+    liveness: Arc<Liveness>,
+    // 是否为用过后归还的会话（否则为预热会话）
+    reused: bool,
 }
 
-impl ConnectionPool {
-    pub fn new(api_key: String) -> Self {
+impl PooledConnection {
+    fn new(id: u64, session: RealtimeSession) -> Self {
+        let liveness = Arc::clone(&session.liveness);
         Self {
-            api_key,
-            connection: Arc::new(Mutex::new(None)),
+            id,
+            session,
+            last_used: Instant::now(),
+            liveness,
+            reused: false,
         }
     }
 
-    /// 获取或创建会话
-    pub async fn get_session(&self) -> Result<RealtimeSession> {
-        let mut conn_guard = self.connection.lock().await;
+    fn evict_reason(&self, options: &SessionOptions) -> Option<EvictReason> {
+        evict_reason(
+            self.last_used.elapsed(),
+            self.liveness.silence(),
+            self.session.is_alive(),
+            options.timings.pool_idle_timeout,
+            options.dead_after(),
+        )
+    }
+}
 
-        // 检查现有连接是否可用且未超时
-        if let Some(ref conn) = *conn_guard {
-            if conn.last_used.elapsed() < Duration::from_secs(IDLE_TIMEOUT_SECS) {
-                // 复用现有连接 - 但实际上每次转录需要新会话
-                // WebSocket realtime API 每次转录是独立的会话
-                tracing::info!("连接池中有活跃连接，但 realtime API 需要新会话");
-            }
-        }
+// 预热连接编号，用于判断定时重建时池中是否仍是同一条连接
+static NEXT_POOLED_ID: AtomicU64 = AtomicU64::new(1);
 
-        // 创建新会话
-        *conn_guard = None; // 清理旧连接
-        drop(conn_guard);
+impl ConnectionPool {
+    pub fn new(api_key: String, options: SessionOptions, realtime: RealtimeOptions) -> Self {
+        let endpoint = RealtimeEndpoint::dashscope(api_key, DEFAULT_WEBSOCKET_URL, &realtime.model);
+        Self::with_endpoint(endpoint, options, realtime)
+    }
 
-        self.create_new_session().await
+    /// 连接指定服务端点的连接池
+    pub fn with_endpoint(endpoint: RealtimeEndpoint, options: SessionOptions, realtime: RealtimeOptions) -> Self {
+        let sample_rate = endpoint.sample_rate(options.sample_rate.unwrap_or(DEFAULT_SAMPLE_RATE));
+        let mut pool = Self {
+            endpoint,
+            options,
+            realtime,
+            sample_rate: Arc::new(AtomicU32::new(sample_rate)),
+            connection: Arc::new(Mutex::new(None)),
+            cleanup: None,
+        };
+        pool.restart_cleanup();
+        pool
     }
 
-    async fn create_new_session(&self) -> Result<RealtimeSession> {
-        let url = format!("{}?model={}", WEBSOCKET_URL, MODEL);
-        tracing::info!("创建 WebSocket 连接: {}", url);
+    /// 指定 WebSocket 地址（测试中指向本地 mock 服务）
+    #[cfg(test)]
+    fn with_url(mut self, url: String) -> Self {
+        self.endpoint.url = url;
+        self.restart_cleanup();
+        self
+    }
 
-        // 构建请求
-        let request = http::Request::builder()
-            .uri(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("OpenAI-Beta", "realtime=v1")
-            .header("Host", "dashscope.aliyuncs.com")
-            .header("Connection", "Upgrade")
-            .header("Upgrade", "websocket")
-            .header("Sec-WebSocket-Version", "13")
-            .header("Sec-WebSocket-Key", tokio_tungstenite::tungstenite::handshake::client::generate_key())
-            .body(())?;
+    /// （重新）启动空闲清理任务；不在 tokio 运行时中时不启动（取用时仍会检查连接是否可用）
+    fn restart_cleanup(&mut self) {
+        if let Some(cleanup) = self.cleanup.take() {
+            cleanup.abort();
+        }
+        self.cleanup = tokio::runtime::Handle::try_current().ok().map(|runtime| {
+            runtime
+                .spawn(run_cleanup(self.session_config(), Arc::downgrade(&self.connection)))
+                .abort_handle()
+        });
+    }
 
-        let (ws_stream, _) = connect_async(request).await
-            .map_err(|e| anyhow::anyhow!("WebSocket 连接失败: {}", e))?;
+    /// 关闭并移除池内的预热会话（停止服务时调用）
+    pub async fn clear(&self) {
+        if let Some(conn) = self.connection.lock().await.take() {
+            tracing::info!("清空连接池，关闭预热会话 #{}", conn.id);
+            let _ = conn.session.close().await;
+        }
+    }
 
-        tracing::info!("WebSocket 连接成功");
+    fn session_config(&self) -> SessionConfig {
+        SessionConfig {
+            endpoint: self.endpoint.clone(),
+            options: self.options.clone(),
+            realtime: self.realtime.clone(),
+            sample_rate: self.sample_rate.load(Ordering::Relaxed),
+        }
+    }
 
-        let (mut write, mut read) = ws_stream.split();
+    /// 池中是否有刚收到过服务端消息的会话（可据此跳过按键时的网络探测）
+    pub async fn has_live_session(&self) -> bool {
+        let guard = self.connection.lock().await;
+        guard.as_ref().is_some_and(|conn| {
+            conn.evict_reason(&self.options).is_none()
+                && conn.liveness.silence() < self.options.timings.network_probe_cache
+        })
+    }
 
-        // 创建命令通道
-        let (cmd_tx, mut cmd_rx) = mpsc::channel::<SessionCommand>(100);
-        // 创建结果通道
-        let (result_tx, result_rx) = mpsc::channel::<Result<String>>(1);
+    /// 获取或创建指定采样率的会话：优先取用预热好的空闲会话，不可用或采样率不一致时即时新建
+    /// 配置了固定采样率时忽略设备协商的值
+    pub async fn get_session(&self, sample_rate: u32) -> Result<RealtimeSession> {
+        let started = Instant::now();
+        let sample_rate = self.endpoint.sample_rate(self.options.sample_rate.unwrap_or(sample_rate));
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+        let pooled = self.connection.lock().await.take();
 
-        // 发送 session.update 配置会话
-        let session_update = serde_json::json!({
-            "event_id": format!("event_{}", std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_millis()),
-            "type": "session.update",
-            "session": {
-                "modalities": ["text"],
-                "input_audio_format": "pcm",
-                "sample_rate": 16000,
-                "input_audio_transcription": {
-                    "language": "zh"
-                },
-                "turn_detection": serde_json::Value::Null  // 禁用 VAD，使用手动 commit
+        if let Some(mut conn) = pooled {
+            let timings = self.options.timings;
+            if conn.session.sample_rate != sample_rate {
+                tracing::info!(
+                    "预热会话采样率 {}Hz 与设备 {}Hz 不一致，重新建立连接",
+                    conn.session.sample_rate,
+                    sample_rate
+                );
+            } else if conn.evict_reason(&self.options).is_some() {
+                tracing::info!("预热会话已失效或空闲超时，重新建立连接");
+            } else if conn.liveness.silence() >= timings.pool_probe_after
+                && !conn.session.probe(timings.pool_probe_timeout).await
+            {
+                tracing::info!(
+                    "会话 #{} 探活 {}ms 内未收到 pong，重新建立连接",
+                    conn.id,
+                    timings.pool_probe_timeout.as_millis()
+                );
+            } else {
+                conn.session.origin = if conn.reused { SessionOrigin::Reused } else { SessionOrigin::Warm };
+                tracing::info!(
+                    "{}连接池中的 WebSocket 会话 #{}，就绪耗时 {}ms (空闲 {}ms, 距上次收包 {}ms)",
+                    conn.session.origin.label(),
+                    conn.id,
+                    started.elapsed().as_millis(),
+                    conn.last_used.elapsed().as_millis(),
+                    conn.liveness.silence().as_millis()
+                );
+                // 空闲期间的静默重连不需要通知前端
+                conn.session.discard_connection_events();
+                return Ok(conn.session);
             }
-        });
+            // 旧会话在后台完成关闭握手，不拖慢本次按键
+            tokio::spawn(async move {
+                let _ = conn.session.close().await;
+            });
+        }
 
-        write.send(Message::Text(session_update.to_string())).await
-            .map_err(|e| anyhow::anyhow!("发送 session.update 失败: {}", e))?;
+        let session = open_session(self.session_config()).await?;
+        tracing::info!("新建 WebSocket 会话，就绪耗时 {}ms", started.elapsed().as_millis());
+        Ok(session)
+    }
 
-        tracing::info!("已发送 session.update 配置");
+    /// 归还用完的会话：清空本次录音的状态后放回连接池并刷新 last_used，
+    /// 下次按键省去 TCP/TLS 握手与 session.update；不可复用或池中已有会话时在后台关闭
+    pub async fn release(&self, mut session: RealtimeSession) {
+        if !session.reusable() {
+            tokio::spawn(async move {
+                let _ = session.close().await;
+            });
+            return;
+        }
+        if let Err(e) = session.reset().await {
+            tracing::warn!("会话无法复用，关闭连接: {}", e);
+            tokio::spawn(async move {
+                let _ = session.close().await;
+            });
+            return;
+        }
 
-        // 启动发送任务
-        let write: Arc<Mutex<WsSink>> = Arc::new(Mutex::new(write));
-        let write_clone = Arc::clone(&write);
+        let mut guard = self.connection.lock().await;
+        if guard.is_some() {
+            drop(guard);
+            tokio::spawn(async move {
+                let _ = session.close().await;
+            });
+            return;
+        }
+        let id = NEXT_POOLED_ID.fetch_add(1, Ordering::SeqCst);
+        *guard = Some(PooledConnection { reused: true, ..PooledConnection::new(id, session) });
+        tracing::info!("WebSocket 会话已归还连接池 (#{})", id);
+    }
 
-        tokio::spawn(async move {
-            while let Some(cmd) = cmd_rx.recv().await {
-                match cmd {
-                    SessionCommand::SendAudio(pcm_bytes) => {
-                        let encoded = general_purpose::STANDARD.encode(&pcm_bytes);
-                        let event = serde_json::json!({
-                            "event_id": format!("event_{}", std::time::SystemTime::now()
-                                .duration_since(std::time::UNIX_EPOCH)
-                                .unwrap()
-                                .as_millis()),
-                            "type": "input_audio_buffer.append",
-                            "audio": encoded
-                        });
-
-                        let mut w = write_clone.lock().await;
-                        if let Err(e) = w.send(Message::Text(event.to_string())).await {
-                            tracing::error!("发送音频块失败: {}", e);
-                            break;
-                        }
-                    }
-                    SessionCommand::Commit => {
-                        let event = serde_json::json!({
-                            "event_id": format!("event_{}", std::time::SystemTime::now()
-                                .duration_since(std::time::UNIX_EPOCH)
-                                .unwrap()
-                                .as_millis()),
-                            "type": "input_audio_buffer.commit"
-                        });
-
-                        let mut w = write_clone.lock().await;
-                        if let Err(e) = w.send(Message::Text(event.to_string())).await {
-                            tracing::error!("发送 commit 失败: {}", e);
-                        }
-                        tracing::info!("已发送 input_audio_buffer.commit");
+    /// 后台预热一条空闲会话放入连接池（已有则跳过）
+    pub fn warm_up(&self) {
+        spawn_warm_up(self.session_config(), Arc::downgrade(&self.connection), None);
+    }
+
+    /// 立即完成一次握手并把该会话留作预热连接，握手失败直接返回错误（用于启动前校验）
+    pub async fn connect_now(&self) -> Result<()> {
+        let session = open_session(self.session_config()).await?;
+        spawn_warm_up(self.session_config(), Arc::downgrade(&self.connection), Some(session));
+        Ok(())
+    }
+}
+
+/// 后台建立预热会话（或放入已建立好的 `preopened`），空闲回收由清理任务负责
+fn spawn_warm_up(
+    config: SessionConfig,
+    connection: Weak<Mutex<Option<PooledConnection>>>,
+    preopened: Option<RealtimeSession>,
+) {
+    tokio::spawn(async move {
+        let session = match preopened {
+            Some(session) => session,
+            None => {
+                if let Some(pool) = connection.upgrade() {
+                    if pool.lock().await.is_some() {
+                        return;
                     }
-                    SessionCommand::Close => {
-                        let mut w = write_clone.lock().await;
-                        let _ = w.close().await;
-                        break;
+                }
+                match open_session(config).await {
+                    Ok(session) => session,
+                    Err(e) => {
+                        tracing::warn!("预热 WebSocket 连接失败: {}", e);
+                        return;
                     }
                 }
             }
+        };
+
+        let Some(pool) = connection.upgrade() else {
+            // 连接池已释放（服务已停止）
+            let _ = session.close().await;
+            return;
+        };
+        let mut guard = pool.lock().await;
+        if guard.is_some() {
+            drop(guard);
+            let _ = session.close().await;
+            return;
+        }
+        let id = NEXT_POOLED_ID.fetch_add(1, Ordering::SeqCst);
+        *guard = Some(PooledConnection::new(id, session));
+        tracing::info!("已预热 WebSocket 会话 #{}", id);
+    });
+}
+
+/// 连接池清理任务：每隔 `Timings::pool_cleanup_interval` 检查一次池内连接，
+/// 空闲超时的关闭并移除；心跳异常的关闭后重新预热
+async fn run_cleanup(config: SessionConfig, connection: Weak<Mutex<Option<PooledConnection>>>) {
+    let period = config.options.timings.pool_cleanup_interval;
+    let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+
+    loop {
+        ticker.tick().await;
+
+        let Some(pool) = connection.upgrade() else {
+            return;
+        };
+        let evicted = {
+            let mut guard = pool.lock().await;
+            match guard.as_ref().and_then(|conn| conn.evict_reason(&config.options)) {
+                Some(reason) => guard.take().map(|conn| (conn, reason)),
+                None => None,
+            }
+        };
+        drop(pool);
+
+        if let Some((conn, reason)) = evicted {
+            let _ = conn.session.close().await;
+            match reason {
+                EvictReason::IdleExpired => {
+                    tracing::info!(
+                        "预热会话 #{} 空闲超过 {} 秒，已关闭",
+                        conn.id,
+                        config.options.timings.pool_idle_timeout.as_secs()
+                    );
+                }
+                EvictReason::Unhealthy => {
+                    tracing::info!("预热会话 #{} 已失效，关闭并重新预热", conn.id);
+                    let config = SessionConfig {
+                        sample_rate: conn.session.sample_rate,
+                        ..config.clone()
+                    };
+                    spawn_warm_up(config, connection.clone(), None);
+                }
+            }
+        }
+    }
+}
+
+/// 会话驱动任务一侧持有的通道与共享状态（各家实时识别协议的驱动任务共用）
+pub(crate) struct SessionEndpoints {
+    pub(crate) cmd_rx: mpsc::Receiver<SessionCommand>,
+    pub(crate) audio_queue: Arc<AudioQueue>,
+    pub(crate) result_tx: mpsc::Sender<Result<String>>,
+    pub(crate) event_tx: mpsc::UnboundedSender<ConnectionEvent>,
+    pub(crate) speech_tx: mpsc::UnboundedSender<SpeechEvent>,
+    pub(crate) status_tx: mpsc::UnboundedSender<SessionStatus>,
+    pub(crate) speech_seen: Arc<AtomicBool>,
+    pub(crate) speech_active: Arc<AtomicBool>,
+    pub(crate) pending_segments: Arc<AtomicUsize>,
+    pub(crate) liveness: Arc<Liveness>,
+    pub(crate) first_transcript: Arc<OnceLock<Instant>>,
+    pub(crate) received_text: Arc<std::sync::Mutex<String>>,
+}
+
+/// 创建会话本体及驱动任务一侧的端点
+pub(crate) fn new_session(options: &SessionOptions, sample_rate: u32) -> (RealtimeSession, SessionEndpoints) {
+    // 创建命令通道
+    let (cmd_tx, cmd_rx) = mpsc::channel::<SessionCommand>(100);
+    // 创建结果通道（多轮 commit、连续听写、服务端 VAD 分段都会依次产出多条结果）
+    let (result_tx, result_rx) = mpsc::channel::<Result<String>>(RESULT_CHANNEL_CAPACITY);
+    // 创建连接状态事件通道（断线/重连）
+    let (event_tx, event_rx) = mpsc::unbounded_channel::<ConnectionEvent>();
+    // 创建说话状态事件通道
+    let (speech_tx, speech_rx) = mpsc::unbounded_channel::<SpeechEvent>();
+    // 创建会话状态通道
+    let (status_tx, status_rx) = mpsc::unbounded_channel::<SessionStatus>();
+    // 待发送音频队列（积压告警经由连接状态事件上报）
+    let audio_queue = Arc::new(AudioQueue::new(options.backlog_limit, options.backlog_policy, event_tx.clone()));
+
+    let endpoints = SessionEndpoints {
+        cmd_rx,
+        audio_queue: Arc::clone(&audio_queue),
+        result_tx,
+        event_tx,
+        speech_tx,
+        status_tx,
+        speech_seen: Arc::new(AtomicBool::new(false)),
+        speech_active: Arc::new(AtomicBool::new(false)),
+        pending_segments: Arc::new(AtomicUsize::new(0)),
+        liveness: Arc::new(Liveness::new()),
+        first_transcript: Arc::new(OnceLock::new()),
+        received_text: Arc::new(std::sync::Mutex::new(String::new())),
+    };
+    let session = RealtimeSession {
+        sender: cmd_tx,
+        audio_queue,
+        result_receiver: Some(result_rx),
+        event_receiver: Some(event_rx),
+        speech_receiver: Some(speech_rx),
+        status_receiver: Some(status_rx),
+        vad_mode: options.vad_mode,
+        speech_seen: Arc::clone(&endpoints.speech_seen),
+        speech_threshold: options.speech_threshold_dbfs.map(dbfs_to_rms),
+        speech_active: Arc::clone(&endpoints.speech_active),
+        pending_segments: Arc::clone(&endpoints.pending_segments),
+        dictation: options.dictation(),
+        liveness: Arc::clone(&endpoints.liveness),
+        first_transcript: Arc::clone(&endpoints.first_transcript),
+        received_text: Arc::clone(&endpoints.received_text),
+        sent_samples: AtomicU64::new(0),
+        sample_rate,
+        timeout_factor: options.timeout_factor,
+        timings: options.timings,
+        cancelled: Arc::new(AtomicBool::new(false)),
+        origin: SessionOrigin::New,
+    };
+    (session, endpoints)
+}
+
+/// 建立连接并启动会话驱动任务
+async fn open_session(config: SessionConfig) -> Result<RealtimeSession> {
+    let trace = WsTrace::start(config.options.ws_trace);
+    let (write, read) = connect(&config, trace.clone()).await?;
+
+    let (session, endpoints) = new_session(&config.options, config.sample_rate);
+    let _ = endpoints.status_tx.send(SessionStatus::Connected);
+    let driver = SessionDriver {
+        config,
+        cmd_rx: endpoints.cmd_rx,
+        audio_queue: endpoints.audio_queue,
+        result_tx: endpoints.result_tx,
+        event_tx: endpoints.event_tx,
+        speech_tx: endpoints.speech_tx,
+        status_tx: endpoints.status_tx,
+        speech_seen: endpoints.speech_seen,
+        speech_active: endpoints.speech_active,
+        pending_segments: endpoints.pending_segments,
+        liveness: endpoints.liveness,
+        first_transcript: endpoints.first_transcript,
+        received_text: endpoints.received_text,
+        trace,
+    };
+    tokio::spawn(driver.run(write, read));
+    Ok(session)
+}
+
+/// 会话连接参数（断线重连时复用）
+#[derive(Clone)]
+struct SessionConfig {
+    endpoint: RealtimeEndpoint,
+    options: SessionOptions,
+    realtime: RealtimeOptions,
+    sample_rate: u32,
+}
+
+/// 建立 WebSocket 连接并发送 session.update 配置会话
+async fn connect(config: &SessionConfig, trace: Option<Arc<WsTrace>>) -> Result<(WsSink, WsSource)> {
+    let endpoint = &config.endpoint;
+    tracing::info!("创建 WebSocket 连接: {}", endpoint.url);
+
+    // 构建请求（DashScope 与 OpenAI 同为 Bearer 鉴权 + OpenAI-Beta 头）
+    let uri: http::Uri = endpoint.url.parse()
+        .map_err(|e| anyhow::anyhow!("WebSocket 地址无效 ({}): {}", endpoint.url, e))?;
+    let request = http::Request::builder()
+        .uri(&uri)
+        .header("Authorization", format!("Bearer {}", endpoint.api_key))
+        .header("OpenAI-Beta", "realtime=v1")
+        .header("Host", uri.host().unwrap_or_default())
+        .header("Connection", "Upgrade")
+        .header("Upgrade", "websocket")
+        .header("Sec-WebSocket-Version", "13")
+        .header("Sec-WebSocket-Key", tokio_tungstenite::tungstenite::handshake::client::generate_key())
+        .body(())?;
+
+    let (ws_stream, _) = open_websocket(request, &config.options.dns_overrides).await?;
+    tracing::info!("WebSocket 连接成功");
+
+    let (mut write, read) = ws_trace::split(ws_stream, trace);
+
+    // 发送 session.update 配置会话
+    let session_update = endpoint.session_update(config.options.vad_mode, &config.realtime, config.sample_rate);
+    write.send(Message::Text(session_update.to_string())).await
+        .map_err(|e| anyhow::anyhow!("发送 session.update 失败: {}", e))?;
+
+    tracing::info!(
+        "已发送 session.update 配置 (断句方式: {:?}, 语言: {}, 热词 {} 个)",
+        config.options.vad_mode,
+        config.realtime.language,
+        config.realtime.vocabulary.len()
+    );
+
+    Ok((write, read))
+}
+
+/// 建立 WebSocket 连接；配置了代理时经 HTTP CONNECT 隧道连接，命中 DNS 覆盖时手动连到指定 IP，
+/// 两种情况下 TLS 握手都仍以 URL 中的域名作为 SNI
+pub(crate) async fn open_websocket(
+    request: http::Request<()>,
+    dns_overrides: &DnsOverrides,
+) -> Result<(WebSocketStream<MaybeTlsStream<TcpStream>>, Response)> {
+    let uri = request.uri().clone();
+    let host = uri.host().unwrap_or_default();
+    let port = uri.port_u16().unwrap_or(if uri.scheme_str() == Some("ws") { 80 } else { 443 });
+    if let Some(proxy) = dns_overrides.proxy() {
+        tracing::info!("WebSocket 经代理 {} 连接 {}:{}", proxy.address(), host, port);
+        let stream = proxy.tunnel(host, port).await?;
+        return client_async_tls_with_config(request, stream, None, None)
+            .await
+            .map_err(handshake_error);
+    }
+    match dns_overrides.lookup(host) {
+        Some(ip) => {
+            tracing::info!("WebSocket 使用 DNS 覆盖: {} -> {}:{}", host, ip, port);
+            let stream = TcpStream::connect((ip, port)).await
+                .map_err(|e| anyhow::anyhow!("WebSocket 连接失败 ({}:{}): {}", ip, port, e))?;
+            stream.set_nodelay(true)?;
+            client_async_tls_with_config(request, stream, None, None).await
+        }
+        // 实时音频小包频繁，关闭 Nagle 避免与延迟 ACK 叠加产生几十毫秒的停顿
+        None => connect_async_with_config(request, None, true).await,
+    }
+    .map_err(handshake_error)
+}
+
+/// 断句配置：手动模式下禁用 VAD，使用手动 commit
+fn turn_detection(vad_mode: RealtimeVadMode) -> serde_json::Value {
+    match vad_mode {
+        RealtimeVadMode::Manual => serde_json::Value::Null,
+        RealtimeVadMode::Server => serde_json::json!({
+            "type": "server_vad",
+            "threshold": 0.5,
+            "silence_duration_ms": 800
+        }),
+    }
+}
+
+/// 固定的识别语言；未指定或 "auto" 时不下发 language，由模型自动判断
+fn fixed_language(realtime: &RealtimeOptions) -> Option<&str> {
+    let language = realtime.language.trim();
+    (!language.is_empty() && !language.eq_ignore_ascii_case("auto")).then_some(language)
+}
+
+/// 去掉空白项后的热词
+fn vocabulary(realtime: &RealtimeOptions) -> Vec<&str> {
+    realtime
+        .vocabulary
+        .iter()
+        .map(|word| word.trim())
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// 集中构建会话配置事件：通用识别参数（语言、热词、标点偏好）按协议映射到各自的字段，
+/// 最后合并用户配置的额外参数（`realtime_session_extra`），服务端新增的识别参数无需改代码即可下发
+fn build_session_update(
+    protocol: RealtimeProtocol,
+    vad_mode: RealtimeVadMode,
+    realtime: &RealtimeOptions,
+    sample_rate: u32,
+) -> serde_json::Value {
+    let vocabulary = vocabulary(realtime);
+    let mut transcription = serde_json::json!({});
+    if let Some(language) = fixed_language(realtime) {
+        transcription["language"] = serde_json::json!(language);
+    }
+
+    let (event_type, mut session) = match protocol {
+        // DashScope：热词作为 corpus 上下文，按设备协商的采样率上传 pcm
+        RealtimeProtocol::DashScope => {
+            if !vocabulary.is_empty() {
+                transcription["corpus"] = serde_json::json!({ "text": vocabulary.join("\n") });
+            }
+            if !realtime.punctuation {
+                transcription["enable_punctuation"] = serde_json::json!(false);
+            }
+            let session = serde_json::json!({
+                "modalities": ["text"],
+                "input_audio_format": "pcm",
+                "sample_rate": sample_rate,
+            });
+            ("session.update", session)
+        }
+        // OpenAI：模型写在 input_audio_transcription.model，热词作为 prompt 提示，音频固定为 24kHz pcm16；
+        // 没有标点开关，标点偏好不下发
+        RealtimeProtocol::OpenAi => {
+            transcription["model"] = serde_json::json!(realtime.model);
+            if !vocabulary.is_empty() {
+                transcription["prompt"] = serde_json::json!(vocabulary.join(", "));
+            }
+            ("transcription_session.update", serde_json::json!({ "input_audio_format": "pcm16" }))
+        }
+    };
+    session["input_audio_transcription"] = transcription;
+    session["turn_detection"] = turn_detection(vad_mode);
+    merge_json(&mut session, &realtime.extra);
+
+    serde_json::json!({
+        "event_id": next_event_id(),
+        "type": event_type,
+        "session": session
+    })
+}
+
+/// 把额外参数逐层合并进会话配置：两边都是对象时递归合并，否则以额外参数为准（null 表示删除该字段）
+fn merge_json(target: &mut serde_json::Value, extra: &serde_json::Map<String, serde_json::Value>) {
+    let Some(target) = target.as_object_mut() else { return };
+    for (key, value) in extra {
+        match (target.get_mut(key), value) {
+            (Some(existing @ serde_json::Value::Object(_)), serde_json::Value::Object(nested)) => merge_json(existing, nested),
+            (_, serde_json::Value::Null) => {
+                target.remove(key);
+            }
+            _ => {
+                target.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+// 客户端 event_id 序号，进程内单调递增
+static EVENT_SEQ: AtomicU64 = AtomicU64::new(0);
+static EVENT_ID_PREFIX: OnceLock<String> = OnceLock::new();
+
+/// 生成客户端事件的 event_id：进程启动时间戳 + 单调递增序号，同一毫秒内发送多条也不会重复
+pub(crate) fn next_event_id() -> String {
+    let prefix = EVENT_ID_PREFIX.get_or_init(|| {
+        let millis = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
+        format!("event_{}", millis)
+    });
+    format!("{}_{}", prefix, EVENT_SEQ.fetch_add(1, Ordering::Relaxed))
+}
+
+/// 发送一块音频（按协议编码为 append 事件或二进制帧）
+async fn send_audio(write: &mut WsSink, encoder: &mut dyn AudioFrameEncoder, pcm_bytes: &[u8]) -> Result<()> {
+    write.send(encoder.encode(pcm_bytes, false)).await
+        .map_err(|e| anyhow::anyhow!("发送音频块失败: {}", e))
+}
+
+/// 断线重发缓存：已发送、尚未出结果的 PCM（按 append 批次保存）
+/// 泄漏修复：缓存有上限，超出后整个丢弃，断线时不再续传，由上层用 StreamingRecorder 保留的完整录音走 HTTP 回退，
+/// 避免长录音在会话里再多占一份完整音频
+struct ResendCache {
+    chunks: Vec<Vec<u8>>,
+    bytes: usize,
+    limit: usize,
+    // 最近一次提交时已发送的批次数，对应结果出来后这些批次不再需要重发
+    committed: usize,
+    // 丢弃过已提交、尚未出结果的音频
+    lost_committed: bool,
+    // 丢弃过尚未提交的音频（之后发送的也不再缓存，直到下一次提交）
+    lost_pending: bool,
+}
+
+impl ResendCache {
+    fn new(limit: usize) -> Self {
+        Self {
+            chunks: Vec::new(),
+            bytes: 0,
+            limit,
+            committed: 0,
+            lost_committed: false,
+            lost_pending: false,
+        }
+    }
+
+    fn push(&mut self, batch: Vec<u8>) {
+        if self.lost_pending {
+            return;
+        }
+        if self.bytes + batch.len() > self.limit {
+            tracing::warn!("断线重发缓存超过 {} 秒上限，已丢弃，本轮断线后不再续传", MAX_RESEND_SECS);
+            self.lost_committed |= self.committed > 0;
+            self.lost_pending = true;
+            self.chunks = Vec::new();
+            self.bytes = 0;
+            self.committed = 0;
+            return;
+        }
+        self.bytes += batch.len();
+        self.chunks.push(batch);
+    }
+
+    /// 已发送的音频全部提交
+    fn commit(&mut self) {
+        self.committed = self.chunks.len();
+        self.lost_committed |= std::mem::take(&mut self.lost_pending);
+    }
+
+    /// 已提交的轮次出了结果，对应音频不再需要重发
+    fn finish_round(&mut self) {
+        self.chunks.drain(..self.committed);
+        self.committed = 0;
+        self.lost_committed = false;
+        self.recount();
+    }
+
+    /// 丢弃尚未提交的音频（本轮没有语音，服务端缓冲区已清空）
+    fn discard_uncommitted(&mut self) {
+        self.chunks.truncate(self.committed);
+        self.lost_pending = false;
+        self.recount();
+    }
+
+    fn clear(&mut self) {
+        self.chunks = Vec::new();
+        self.bytes = 0;
+        self.committed = 0;
+        self.lost_committed = false;
+        self.lost_pending = false;
+    }
+
+    /// 没有任何等待结果的音频
+    fn is_idle(&self) -> bool {
+        self.chunks.is_empty() && !self.lost_committed && !self.lost_pending
+    }
+
+    /// 断线重连时要重发的音频；缓存不完整时为 None
+    fn resendable(&self) -> Option<&[Vec<u8>]> {
+        (!self.lost_committed && !self.lost_pending).then_some(self.chunks.as_slice())
+    }
+
+    fn recount(&mut self) {
+        self.bytes = self.chunks.iter().map(Vec::len).sum();
+    }
+}
+
+/// 把攒下的音频合并成一条 append 发出，并记入断线重发缓存
+async fn flush_audio(
+    write: &mut WsSink,
+    encoder: &mut dyn AudioFrameEncoder,
+    pending_audio: &mut Vec<u8>,
+    sent_audio: &mut ResendCache,
+) -> Result<()> {
+    if pending_audio.is_empty() {
+        return Ok(());
+    }
+    let batch = std::mem::take(pending_audio);
+    let result = send_audio(write, encoder, &batch).await;
+    sent_audio.push(batch);
+    result
+}
+
+/// 取出队列中积压的音频块，攒够 batch_bytes 就合并发送一条，未满阈值的留在 pending_audio
+async fn send_queued_audio(
+    write: &mut WsSink,
+    encoder: &mut dyn AudioFrameEncoder,
+    queue: &AudioQueue,
+    pending_audio: &mut Vec<u8>,
+    sent_audio: &mut ResendCache,
+    batch_bytes: usize,
+    mut dictation: Option<&mut Dictation>,
+) -> Result<()> {
+    for chunk in queue.drain() {
+        if let Some(dictation) = dictation.as_deref_mut() {
+            dictation.observe(&chunk);
+        }
+        pending_audio.extend_from_slice(&chunk);
+        if pending_audio.len() >= batch_bytes {
+            flush_audio(write, encoder, pending_audio, sent_audio).await?;
+        }
+    }
+    Ok(())
+}
+
+/// 提交音频缓冲区（input_audio_buffer.commit）
+async fn send_commit(write: &mut WsSink) -> Result<()> {
+    let event = serde_json::json!({
+        "event_id": next_event_id(),
+        "type": "input_audio_buffer.commit"
+    });
+
+    write.send(Message::Text(event.to_string())).await
+        .map_err(|e| anyhow::anyhow!("发送 commit 失败: {}", e))?;
+    tracing::info!("已发送 input_audio_buffer.commit");
+    Ok(())
+}
+
+/// 清空服务端尚未提交的音频缓冲区（连续听写中没有语音的一轮）
+async fn send_clear(write: &mut WsSink) -> Result<()> {
+    let event = serde_json::json!({
+        "event_id": next_event_id(),
+        "type": "input_audio_buffer.clear"
+    });
+    write.send(Message::Text(event.to_string())).await
+        .map_err(|e| anyhow::anyhow!("发送 input_audio_buffer.clear 失败: {}", e))
+}
+
+/// 开始关闭握手：会话结束事件（session.finish，协议支持时）+ 状态码 1000 的 Close 帧
+/// 之后由接收循环等待服务端的 Close 回执
+async fn send_close(write: &mut WsSink, session_finish: bool) {
+    if session_finish {
+        let event = serde_json::json!({
+            "event_id": next_event_id(),
+            "type": "session.finish"
         });
+        if let Err(e) = write.send(Message::Text(event.to_string())).await {
+            tracing::debug!("发送 session.finish 失败: {}", e);
+        }
+    }
+    let frame = CloseFrame {
+        code: CloseCode::Normal,
+        reason: "".into(),
+    };
+    if let Err(e) = write.send(Message::Close(Some(frame))).await {
+        tracing::debug!("发送 Close 帧失败: {}", e);
+    }
+}
 
-        // 启动接收任务
-        tokio::spawn(async move {
-            let mut final_text = String::new();
-            let mut has_result = false;
-
-            while let Some(msg) = read.next().await {
-                match msg {
-                    Ok(Message::Text(text)) => {
-                        match serde_json::from_str::<serde_json::Value>(&text) {
-                            Ok(data) => {
-                                let event_type = data["type"].as_str().unwrap_or("");
-                                tracing::debug!("收到事件: {}", event_type);
-
-                                match event_type {
-                                    "session.created" | "session.updated" => {
-                                        tracing::info!("会话已创建/更新");
-                                    }
-                                    "input_audio_buffer.committed" => {
-                                        tracing::info!("音频缓冲区已提交");
-                                    }
-                                    "conversation.item.input_audio_transcription.completed" => {
-                                        // 转录完成
-                                        if let Some(transcript) = data["transcript"].as_str() {
-                                            final_text = transcript.to_string();
-                                            has_result = true;
-                                            tracing::info!("转录完成: {}", final_text);
-                                        }
-                                    }
-                                    "response.audio_transcript.delta" => {
-                                        // 增量转录结果
-                                        if let Some(delta) = data["delta"].as_str() {
-                                            final_text.push_str(delta);
-                                            tracing::debug!("增量转录: {}", delta);
-                                        }
-                                    }
-                                    "response.audio_transcript.done" => {
-                                        // 转录完成
-                                        if let Some(transcript) = data["transcript"].as_str() {
-                                            final_text = transcript.to_string();
-                                        }
-                                        has_result = true;
-                                        tracing::info!("转录完成: {}", final_text);
-                                    }
-                                    "response.done" => {
-                                        // 响应完成，发送结果
-                                        has_result = true;
-                                    }
-                                    "error" => {
-                                        let error_msg = data["error"]["message"]
-                                            .as_str()
-                                            .unwrap_or("未知错误");
-                                        tracing::error!("API 错误: {}", error_msg);
-                                        let _ = result_tx.send(Err(anyhow::anyhow!("API 错误: {}", error_msg))).await;
-                                        return;
-                                    }
-                                    _ => {
-                                        tracing::debug!("未处理的事件类型: {}", event_type);
-                                    }
-                                }
+/// 取消转写：已 commit 且结果未出时发送 response.cancel，并清空服务端音频缓冲区
+async fn send_cancel(write: &mut WsSink, response_pending: bool) {
+    let mut events = Vec::with_capacity(2);
+    if response_pending {
+        events.push("response.cancel");
+    }
+    events.push("input_audio_buffer.clear");
+    for kind in events {
+        let event = serde_json::json!({
+            "event_id": next_event_id(),
+            "type": kind
+        });
+        if let Err(e) = write.send(Message::Text(event.to_string())).await {
+            tracing::debug!("发送 {} 失败: {}", kind, e);
+            return;
+        }
+        tracing::info!("已发送 {}", kind);
+    }
+}
+
+/// 处理完一条服务端消息后的去向
+enum EventFlow {
+    Continue,
+    Finished,
+    Failed,
+}
+
+/// 手动断句下一次 commit 的识别结果
+/// 服务端可能因中间停顿把一句话切成多条 completed，全部收齐后再拼接，避免只取到前半句
+#[derive(Default)]
+struct PendingTranscript {
+    segments: Vec<String>,
+    // response.audio_transcript.delta 累积的当前段
+    partial: String,
+    // 已收到 response.done，不会再有新的分段
+    done: bool,
+}
+
+impl PendingTranscript {
+    fn push(&mut self, segment: &str) {
+        self.partial.clear();
+        if has_content(segment) {
+            self.segments.push(segment.trim().to_string());
+        }
+    }
+
+    fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.segments.is_empty() && self.partial.is_empty()
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// 取出所有分段拼接后的文本
+    fn take_text(&mut self) -> String {
+        let text = self.text();
+        self.segments.clear();
+        self.partial.clear();
+        text
+    }
+
+    /// 按顺序拼接所有分段与当前增量（交界处前一段以 ASCII 字母数字或英文标点结尾、后一段以 ASCII 字母数字开头时补一个空格）
+    fn text(&self) -> String {
+        let partial = self.partial.trim();
+        let mut text = String::new();
+        for segment in self.segments.iter().map(String::as_str).chain(Some(partial).filter(|p| has_content(p))) {
+            let joins_words = text.chars().last().is_some_and(|c| c.is_ascii_alphanumeric() || c.is_ascii_punctuation())
+                && segment.chars().next().is_some_and(|c| c.is_ascii_alphanumeric());
+            if joins_words {
+                text.push(' ');
+            }
+            text.push_str(segment);
+        }
+        text
+    }
+}
+
+/// 连续听写的驱动状态：按轮（两次提交之间）跟踪语音，到点后在停顿处自动提交
+struct Dictation {
+    interval: Duration,
+    // 判断停顿的音量阈值（会话的 speech_threshold_dbfs）；关闭本地检测时每块都算作语音，只按 MAX_ROUND_INTERVALS 强制提交
+    speech_threshold: Option<f64>,
+    round_started: Instant,
+    // 本轮出现过语音
+    voiced: bool,
+    // 最近一块是静音：到点后等到停顿处再提交，避免把一句话切成两半
+    at_pause: bool,
+    // 已提交、尚未出结果的轮数
+    in_flight: usize,
+}
+
+impl Dictation {
+    fn new(interval: Duration, speech_threshold: Option<f64>) -> Self {
+        Self {
+            interval,
+            speech_threshold,
+            round_started: Instant::now(),
+            voiced: false,
+            at_pause: false,
+            in_flight: 0,
+        }
+    }
+
+    fn observe(&mut self, pcm_bytes: &[u8]) {
+        let voice = self.speech_threshold.is_none_or(|threshold| {
+            let samples: Vec<i16> = pcm_bytes.chunks_exact(2).map(|b| i16::from_le_bytes([b[0], b[1]])).collect();
+            has_voice(&samples, threshold)
+        });
+        self.voiced |= voice;
+        self.at_pause = !voice;
+    }
+
+    /// 本轮到了提交时间：满间隔且正处于停顿，或一直没有停顿、已满 MAX_ROUND_INTERVALS 倍间隔
+    fn due(&self) -> bool {
+        let elapsed = self.round_started.elapsed();
+        (elapsed >= self.interval && self.at_pause) || elapsed >= self.interval * MAX_ROUND_INTERVALS
+    }
+
+    /// 本轮已提交（或因没有语音被丢弃），开始下一轮
+    fn next_round(&mut self, committed: bool) {
+        if committed {
+            self.in_flight += 1;
+        }
+        self.round_started = Instant::now();
+        self.voiced = false;
+        self.at_pause = false;
+    }
+
+    /// 已提交的轮次出了结果：扣回分段计数
+    fn finish_rounds(&mut self, pending_segments: &AtomicUsize) {
+        let in_flight = std::mem::take(&mut self.in_flight);
+        let _ = pending_segments.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| Some(n.saturating_sub(in_flight)));
+    }
+}
+
+/// 会话驱动任务：统一负责发送命令、接收事件，以及断线后的重连与续传
+struct SessionDriver {
+    config: SessionConfig,
+    cmd_rx: mpsc::Receiver<SessionCommand>,
+    audio_queue: Arc<AudioQueue>,
+    result_tx: mpsc::Sender<Result<String>>,
+    event_tx: mpsc::UnboundedSender<ConnectionEvent>,
+    speech_tx: mpsc::UnboundedSender<SpeechEvent>,
+    status_tx: mpsc::UnboundedSender<SessionStatus>,
+    speech_seen: Arc<AtomicBool>,
+    speech_active: Arc<AtomicBool>,
+    pending_segments: Arc<AtomicUsize>,
+    liveness: Arc<Liveness>,
+    first_transcript: Arc<OnceLock<Instant>>,
+    received_text: Arc<std::sync::Mutex<String>>,
+    // 事件留痕，重连后的新连接继续写入同一文件
+    trace: Option<Arc<WsTrace>>,
+}
+
+impl SessionDriver {
+    async fn run(self, write: WsSink, read: WsSource) {
+        let status_tx = self.status_tx.clone();
+        let audio_queue = Arc::clone(&self.audio_queue);
+        self.drive(write, read).await;
+        tracing::info!("音频发送积压统计: {}", audio_queue.summary());
+        let _ = status_tx.send(SessionStatus::Closed);
+    }
+
+    async fn drive(mut self, mut write: WsSink, mut read: WsSource) {
+        let vad_mode = self.config.options.vad_mode;
+        let batch_bytes = self.config.options.batch_ms as usize * bytes_per_ms(self.config.sample_rate);
+        let mut transcript = PendingTranscript::default();
+        // commit 后每收到一段结果就重新计时，静默 segment_settle 仍无 response.done 时直接拼接发出
+        let mut settle_deadline: Option<tokio::time::Instant> = None;
+        let mut segment_count = 0usize;
+        // 已发送但尚未被服务端提交的 PCM，断线重连后需要重发
+        let mut sent_audio = ResendCache::new(MAX_RESEND_SECS * 1000 * bytes_per_ms(self.config.sample_rate));
+        // 待合并发送的 PCM（攒够 batch_ms 再编码成一条 append 消息）
+        let mut pending_audio: Vec<u8> = Vec::with_capacity(batch_bytes);
+        let mut encoder = self.config.endpoint.audio_encoder();
+        // 连续听写：结果出完后继续下一轮，连接保持到调用方关闭
+        let mut dictation = self.config.options.auto_commit
+            .filter(|_| self.config.options.dictation())
+            .map(|interval| Dictation::new(interval, self.config.options.speech_threshold_dbfs.map(dbfs_to_rms)));
+        let continuous = dictation.is_some();
+        let mut committed = false;
+        let mut closing = false;
+        let mut finished = false;
+        // 关闭握手：调用方的回执通道与等待服务端 Close 的截止时间
+        let mut close_ack: Option<oneshot::Sender<()>> = None;
+        let mut close_deadline: Option<tokio::time::Instant> = None;
+
+        // 保活：定时发送 ping，并检查最近一次收到消息的时间
+        let dead_after = self.config.options.stale_threshold(false);
+        let ping_period = Duration::from_secs(self.config.options.ping_interval_secs.max(1));
+        let mut ping_timer = tokio::time::interval_at(tokio::time::Instant::now() + ping_period, ping_period);
+        ping_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut ping_seq = 0u64;
+        let mut ping_sent: Option<(u64, Instant)> = None;
+        // 保活 ping 等待 pong 的截止时间，超时即判定连接失效（经过代理 / NAT 时连接可能被悄悄掐断）
+        let pong_timeout = (!self.config.options.pong_timeout.is_zero()).then_some(self.config.options.pong_timeout);
+        let mut pong_deadline: Option<tokio::time::Instant> = None;
+        // 连接池取用前的探活：收到 pong 时回执
+        let mut probe_ack: Option<oneshot::Sender<()>> = None;
+        // 看门狗：commit 之后开始计时，等待结果期间超过 stale_after 收不到任何消息就放弃该连接
+        let stale_after = self.config.options.stale_threshold(true);
+        let mut watchdog = tokio::time::interval(self.config.options.timings.watchdog_interval);
+        watchdog.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut committed_at: Option<Instant> = None;
+        self.liveness.mark_received();
+
+        loop {
+            let failure = tokio::select! {
+                _ = self.audio_queue.notified(), if !closing => {
+                    if let Err(e) = send_queued_audio(&mut write, &mut *encoder, &self.audio_queue, &mut pending_audio, &mut sent_audio, batch_bytes, dictation.as_mut()).await {
+                        e
+                    } else {
+                        // 上一轮已出结果后又发来音频：开始新一轮，断线时重新重连续传
+                        if finished && !continuous && !sent_audio.is_idle() {
+                            finished = false;
+                        }
+                        let Some(round) = dictation.as_mut().filter(|d| d.due()) else {
+                            continue;
+                        };
+                        if round.voiced {
+                            tracing::info!("连续听写：本轮已满 {}ms，自动提交", round.round_started.elapsed().as_millis());
+                            self.pending_segments.fetch_add(1, Ordering::SeqCst);
+                            committed = true;
+                            committed_at = Some(Instant::now());
+                            finished = false;
+                            transcript.done = false;
+                            let result = async {
+                                flush_audio(&mut write, &mut *encoder, &mut pending_audio, &mut sent_audio).await?;
+                                send_commit(&mut write).await
+                            }
+                            .await;
+                            sent_audio.commit();
+                            round.next_round(true);
+                            match result {
+                                Ok(()) => continue,
+                                Err(e) => e,
                             }
-                            Err(e) => {
-                                tracing::warn!("解析消息失败: {}", e);
+                        } else {
+                            // 本轮没有语音：清空服务端缓冲区，静音也不再占用重发缓存
+                            pending_audio.clear();
+                            sent_audio.discard_uncommitted();
+                            round.next_round(false);
+                            match send_clear(&mut write).await {
+                                Ok(()) => continue,
+                                Err(e) => e,
                             }
                         }
                     }
-                    Ok(Message::Close(_)) => {
-                        tracing::info!("WebSocket 连接关闭");
+                }
+                cmd = self.cmd_rx.recv(), if !closing => match cmd {
+                    Some(SessionCommand::Commit) => {
+                        // 连续听写的尾段没有语音时不提交，扣回 finish_segments 预先计入的段数
+                        let commit = dictation.as_ref().is_none_or(|d| d.voiced);
+                        if commit {
+                            committed = true;
+                            committed_at = Some(Instant::now());
+                            finished = false;
+                            transcript.done = false;
+                        } else {
+                            let _ = self.pending_segments.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| Some(n.saturating_sub(1)));
+                        }
+                        // commit 前先把队列中的音频和未满阈值的残留音频发出去
+                        let result = async {
+                            send_queued_audio(&mut write, &mut *encoder, &self.audio_queue, &mut pending_audio, &mut sent_audio, batch_bytes, dictation.as_mut()).await?;
+                            if !commit {
+                                return Ok(());
+                            }
+                            flush_audio(&mut write, &mut *encoder, &mut pending_audio, &mut sent_audio).await?;
+                            send_commit(&mut write).await
+                        }
+                        .await;
+                        if commit {
+                            sent_audio.commit();
+                        }
+                        if let Some(round) = dictation.as_mut() {
+                            round.next_round(commit);
+                        }
+                        match result {
+                            Ok(()) => continue,
+                            Err(e) => e,
+                        }
+                    }
+                    Some(SessionCommand::Reset { first_transcript, event_tx, speech_tx, ack }) => {
+                        // 归还连接池：上一次录音已出结果且服务端缓冲区已提交，只清理本地状态，连接保持
+                        sent_audio.clear();
+                        pending_audio.clear();
+                        self.audio_queue.drain();
+                        transcript.clear();
+                        self.received_text.lock().unwrap().clear();
+                        settle_deadline = None;
+                        committed = false;
+                        committed_at = None;
+                        finished = false;
+                        self.first_transcript = first_transcript;
+                        // 上一次录音取走的事件通道随旧发送端释放而关闭，转发任务随之结束
+                        self.event_tx = event_tx;
+                        self.speech_tx = speech_tx;
+                        let _ = ack.send(());
+                        continue;
+                    }
+                    Some(SessionCommand::Probe(ack)) => {
+                        ping_seq += 1;
+                        match write.send(Message::Ping(ping_seq.to_be_bytes().to_vec())).await {
+                            Ok(()) => {
+                                ping_sent = Some((ping_seq, Instant::now()));
+                                probe_ack = Some(ack);
+                                continue;
+                            }
+                            Err(e) => anyhow::anyhow!("发送探活 ping 失败: {}", e),
+                        }
+                    }
+                    // 泄漏修复：会话被丢弃（命令通道关闭）时同样主动关闭连接，
+                    // 否则接收端会一直挂在 read.next() 上，任务与 socket 都无法释放
+                    cmd @ (Some(SessionCommand::Close(_)) | Some(SessionCommand::Cancel(_)) | None) => {
+                        match cmd {
+                            Some(SessionCommand::Close(ack)) => close_ack = Some(ack),
+                            Some(SessionCommand::Cancel(ack)) => {
+                                close_ack = Some(ack);
+                                send_cancel(&mut write, committed && !finished && self.config.endpoint.has_response()).await;
+                                // 取消后不再产出结果
+                                transcript.clear();
+                                settle_deadline = None;
+                                finished = true;
+                            }
+                            _ => {}
+                        }
+                        // 未发出的残留音频直接丢弃
+                        pending_audio.clear();
+                        self.audio_queue.drain();
+                        closing = true;
+                        close_deadline = Some(tokio::time::Instant::now() + self.config.options.timings.session_close_timeout);
+                        send_close(&mut write, self.config.endpoint.has_session_finish()).await;
+                        continue;
+                    }
+                },
+                _ = tokio::time::sleep_until(close_deadline.unwrap_or_else(tokio::time::Instant::now)), if close_deadline.is_some() => {
+                    tracing::warn!("等待服务端 Close 回执超时，直接释放连接");
+                    break;
+                }
+                _ = tokio::time::sleep_until(settle_deadline.unwrap_or_else(tokio::time::Instant::now)), if settle_deadline.is_some() && !finished => {
+                    settle_deadline = None;
+                    tracing::info!("未等到 response.done，使用已收到的 {} 段结果", transcript.segment_count());
+                    let text = transcript.take_text();
+                    tracing::info!("转录完成: {}", privacy::redact(&text));
+                    let _ = self.result_tx.send(Ok(text)).await;
+                    finish_round(dictation.as_mut(), &mut sent_audio, &self.pending_segments);
+                    // 下一轮的音频已经在发时本轮结束不等于会话结束，断线仍要重连续传
+                    finished = continuous || sent_audio.is_idle();
+                    committed = false;
+                    committed_at = None;
+                    continue;
+                }
+                _ = watchdog.tick(), if stale_after.is_some() && committed_at.is_some() && !finished && !closing => {
+                    let silence = committed_at.map_or(Duration::ZERO, |at| awaiting_silence(&self.liveness, at));
+                    if stale_after.is_some_and(|limit| silence >= limit) {
+                        // 不再重连：直接丢弃连接，由上层立即走 HTTP 回退
+                        tracing::warn!("等待结果期间 {}ms 未收到任何消息，判定连接已失效", silence.as_millis());
+                        let _ = self.result_tx.send(Err(ConnectionStale { silence }.into())).await;
+                        finished = true;
                         break;
                     }
-                    Err(e) => {
-                        tracing::error!("WebSocket 错误: {}", e);
-                        let _ = result_tx.send(Err(anyhow::anyhow!("WebSocket 错误: {}", e))).await;
-                        return;
+                    continue;
+                }
+                _ = ping_timer.tick(), if dead_after.is_some() && !closing => {
+                    let silence = self.liveness.silence();
+                    if dead_after.is_some_and(|limit| silence >= limit) {
+                        tracing::warn!("WebSocket 已 {}ms 未收到任何消息，判定为死连接，主动重建", silence.as_millis());
+                        anyhow::anyhow!("连接无响应 {}ms，判定为死连接", silence.as_millis())
+                    } else {
+                        ping_seq += 1;
+                        match write.send(Message::Ping(ping_seq.to_be_bytes().to_vec())).await {
+                            Ok(()) => {
+                                tracing::debug!("发送 ping #{} (距上次收包 {}ms)", ping_seq, silence.as_millis());
+                                ping_sent = Some((ping_seq, Instant::now()));
+                                // 上一个 ping 仍未回 pong 时沿用原截止时间
+                                if pong_deadline.is_none() {
+                                    pong_deadline = pong_timeout.map(|timeout| tokio::time::Instant::now() + timeout);
+                                }
+                                continue;
+                            }
+                            Err(e) => anyhow::anyhow!("发送 ping 失败: {}", e),
+                        }
                     }
-                    _ => {}
                 }
-
-                // 如果已有结果，发送并退出
-                if has_result && !final_text.is_empty() {
-                    // 实时模式下删除所有标点符号
-                    let punctuation = ['。', '，', '！', '？', '、', '；', '：', '"', '"',
-                                       '.', ',', '!', '?', ';', ':', '"', '\'',
-                                       '（', '）', '(', ')', '【', '】', '[', ']',
-                                       '《', '》', '<', '>', '—', '…', '·',
-                                       '\u{2018}', '\u{2019}'];  // 中文单引号 ' '
-                    final_text = final_text.chars()
-                        .filter(|c| !punctuation.contains(c))
-                        .collect();
-
-                    let _ = result_tx.send(Ok(final_text.clone())).await;
-                    break;
+                _ = tokio::time::sleep_until(pong_deadline.unwrap_or_else(tokio::time::Instant::now)), if pong_deadline.is_some() && !closing => {
+                    let waited = ping_sent.map_or(Duration::ZERO, |(_, sent_at)| sent_at.elapsed());
+                    tracing::warn!("保活 ping 发出 {}ms 未收到 pong，判定连接失效，主动重建", waited.as_millis());
+                    anyhow::anyhow!("ping 发出 {}ms 未收到 pong，判定连接失效", waited.as_millis())
                 }
+                msg = read.next() => match msg {
+                    Some(Ok(Message::Pong(payload))) => {
+                        self.liveness.mark_received();
+                        pong_deadline = None;
+                        if let Some(ack) = probe_ack.take() {
+                            let _ = ack.send(());
+                        }
+                        let seq = <[u8; 8]>::try_from(payload.as_slice()).map(u64::from_be_bytes).ok();
+                        match ping_sent {
+                            Some((sent_seq, sent_at)) if seq == Some(sent_seq) => {
+                                tracing::debug!("收到 pong #{}，往返 {}ms", sent_seq, sent_at.elapsed().as_millis());
+                                ping_sent = None;
+                            }
+                            _ => tracing::debug!("收到 pong (无对应 ping)"),
+                        }
+                        continue;
+                    }
+                    Some(Ok(Message::Text(text))) => {
+                        self.liveness.mark_received();
+                        let segments_before = transcript.segment_count();
+                        let flow = self.handle_server_event(
+                            &text,
+                            &mut transcript,
+                            &mut segment_count,
+                            &mut sent_audio,
+                        ).await;
+                        *self.received_text.lock().unwrap() = transcript.text();
+                        match flow {
+                            EventFlow::Continue => {
+                                if committed && !finished && transcript.segment_count() > segments_before {
+                                    settle_deadline = Some(tokio::time::Instant::now() + self.config.options.timings.segment_settle);
+                                }
+                                continue;
+                            }
+                            EventFlow::Finished => {
+                                // 结果已发出，保持连接直到调用方关闭（连续听写则继续下一轮）
+                                settle_deadline = None;
+                                finish_round(dictation.as_mut(), &mut sent_audio, &self.pending_segments);
+                                // 下一轮的音频已经在发时本轮结束不等于会话结束，断线仍要重连续传
+                                finished = continuous || sent_audio.is_idle();
+                                committed = false;
+                                committed_at = None;
+                                continue;
+                            }
+                            EventFlow::Failed => return,
+                        }
+                    }
+                    Some(Ok(Message::Close(_))) | None => {
+                        if closing || (finished && !continuous) {
+                            tracing::info!("WebSocket 连接关闭");
+                            break;
+                        }
+                        anyhow::anyhow!("连接被服务端关闭")
+                    }
+                    Some(Err(e)) => {
+                        if closing || (finished && !continuous) {
+                            tracing::info!("WebSocket 连接已断开: {}", e);
+                            break;
+                        }
+                        anyhow::anyhow!("WebSocket 错误: {}", e)
+                    }
+                    Some(Ok(_)) => {
+                        self.liveness.mark_received();
+                        continue;
+                    }
+                },
+            };
+
+            if finished && !continuous {
+                tracing::info!("会话已完成，忽略连接异常: {}", failure);
+                break;
             }
 
-            // 如果循环结束但没有发送结果
-            if !has_result {
-                let _ = result_tx.send(Err(anyhow::anyhow!("未收到转录结果"))).await;
+            // 会话进行中断线：尝试重连并续传
+            tracing::warn!("WebSocket 会话中断: {}，尝试重连", failure);
+            match self.reconnect(&sent_audio, committed).await {
+                Ok((new_write, new_read)) => {
+                    write = new_write;
+                    read = new_read;
+                    self.liveness.mark_received();
+                    ping_sent = None;
+                    pong_deadline = None;
+                    ping_timer.reset();
+                    let _ = self.status_tx.send(SessionStatus::Connected);
+                    // 服务端收到的是重发的完整音频，分段结果从头累积
+                    transcript.clear();
+                    if committed {
+                        sent_audio.commit();
+                    }
+                    settle_deadline = None;
+                    self.speech_active.store(false, Ordering::SeqCst);
+                    self.pending_segments.store(0, Ordering::SeqCst);
+                }
+                Err(e) => {
+                    tracing::error!("WebSocket 重连彻底失败: {}", e);
+                    let _ = self.event_tx.send(ConnectionEvent::Disconnected {
+                        reason: e.to_string(),
+                    });
+                    let _ = self.status_tx.send(SessionStatus::Error {
+                        code: None,
+                        message: e.to_string(),
+                    });
+                    let _ = self.result_tx.send(Err(e)).await;
+                    return;
+                }
             }
-        });
+        }
 
-        Ok(RealtimeSession {
-            sender: cmd_tx,
-            result_receiver: result_rx,
-        })
+        // 如果循环结束但没有发送结果（服务端 VAD 模式下没有分段也视为正常结束）
+        if vad_mode == RealtimeVadMode::Manual && !finished && (committed || !continuous) {
+            let _ = self.result_tx.send(Err(anyhow::anyhow!("未收到转录结果"))).await;
+        }
+        if let Some(ack) = close_ack {
+            let _ = ack.send(());
+        }
+        tracing::debug!("会话任务结束，共产出 {} 段分段结果", segment_count);
     }
-}
 
-/// 简化的实时转录客户端
-pub struct QwenRealtimeClient {
-    pool: ConnectionPool,
-}
+    /// 断线后按配置次数重连，成功后重发已缓存的音频（以及已发出的 commit）
+    async fn reconnect(&self, sent_audio: &ResendCache, committed: bool) -> Result<(WsSink, WsSource)> {
+        let Some(sent_audio) = sent_audio.resendable() else {
+            anyhow::bail!("断线重发缓存已超过 {} 秒上限，无法续传", MAX_RESEND_SECS);
+        };
+        let max_attempts = self.config.options.max_reconnect_attempts;
+        let mut last_error = anyhow::anyhow!("未配置重连次数");
 
-impl QwenRealtimeClient {
-    pub fn new(api_key: String) -> Self {
-        Self {
-            pool: ConnectionPool::new(api_key),
+        for attempt in 1..=max_attempts {
+            let _ = self.event_tx.send(ConnectionEvent::Reconnecting { attempt, max_attempts });
+            tokio::time::sleep(self.config.options.timings.reconnect_backoff * attempt).await;
+
+            let resumed = async {
+                let (mut write, read) = connect(&self.config, self.trace.clone()).await?;
+                let mut encoder = self.config.endpoint.audio_encoder();
+                for chunk in sent_audio {
+                    send_audio(&mut write, &mut *encoder, chunk).await?;
+                }
+                if committed {
+                    send_commit(&mut write).await?;
+                }
+                Ok::<_, anyhow::Error>((write, read))
+            }
+            .await;
+
+            match resumed {
+                Ok(conn) => {
+                    tracing::info!("WebSocket 重连成功 (第 {} 次)，已重发 {} 个音频块", attempt, sent_audio.len());
+                    let _ = self.event_tx.send(ConnectionEvent::Reconnected { attempt });
+                    return Ok(conn);
+                }
+                Err(e) if e.is::<AuthRejected>() => {
+                    tracing::error!("WebSocket 重连被拒绝，不再重试: {}", e);
+                    return Err(e);
+                }
+                Err(e) => {
+                    tracing::warn!("WebSocket 重连失败 ({}/{}): {}", attempt, max_attempts, e);
+                    last_error = e;
+                }
+            }
         }
+
+        Err(last_error)
     }
 
-    /// 创建新的转录会话
-    pub async fn start_session(&self) -> Result<RealtimeSession> {
-        self.pool.get_session().await
+    /// 处理一条服务端事件
+    async fn handle_server_event(
+        &self,
+        text: &str,
+        pending: &mut PendingTranscript,
+        segment_count: &mut usize,
+        sent_audio: &mut ResendCache,
+    ) -> EventFlow {
+        let vad_mode = self.config.options.vad_mode;
+        let event = match serde_json::from_str::<RealtimeServerEvent>(text) {
+            Ok(event) => event,
+            Err(e) => {
+                tracing::warn!("解析消息失败: {}", e);
+                return EventFlow::Continue;
+            }
+        };
+
+        match event {
+            RealtimeServerEvent::SessionCreated => {
+                tracing::info!("会话已创建");
+            }
+            RealtimeServerEvent::SessionUpdated => {
+                tracing::info!("会话配置已更新");
+                let _ = self.status_tx.send(SessionStatus::Configured);
+            }
+            RealtimeServerEvent::SessionFinished => {
+                tracing::debug!("服务端确认会话结束");
+            }
+            RealtimeServerEvent::SpeechStarted => {
+                self.speech_seen.store(true, Ordering::SeqCst);
+                self.speech_active.store(true, Ordering::SeqCst);
+                let _ = self.speech_tx.send(SpeechEvent::Started);
+                tracing::debug!("服务端检测到语音开始");
+            }
+            RealtimeServerEvent::SpeechStopped => {
+                // 服务端 VAD 检测到停顿，随后会自动提交该段
+                if self.speech_active.swap(false, Ordering::SeqCst) {
+                    self.pending_segments.fetch_add(1, Ordering::SeqCst);
+                }
+                let _ = self.speech_tx.send(SpeechEvent::Stopped);
+                tracing::debug!("服务端检测到语音结束");
+            }
+            RealtimeServerEvent::InputAudioBufferCommitted => {
+                tracing::info!("音频缓冲区已提交");
+                let _ = self.status_tx.send(SessionStatus::Committed);
+                if vad_mode == RealtimeVadMode::Server {
+                    // 已提交的分段不再需要断线重发
+                    sent_audio.clear();
+                }
+            }
+            RealtimeServerEvent::TranscriptionCompleted { transcript } => {
+                // 转录完成
+                if let Some(transcript) = transcript {
+                    self.first_transcript.get_or_init(Instant::now);
+                    if vad_mode == RealtimeVadMode::Server {
+                        // 自动断句：每段结果立即发出，继续接收下一段
+                        let _ = self.pending_segments.fetch_update(
+                            Ordering::SeqCst,
+                            Ordering::SeqCst,
+                            |n| Some(n.saturating_sub(1)),
+                        );
+                        let segment = transcript.trim().to_string();
+                        tracing::info!("分段转录完成: {}", privacy::redact(&segment));
+                        if has_content(&segment) {
+                            *segment_count += 1;
+                            if self.result_tx.send(Ok(segment)).await.is_err() {
+                                return EventFlow::Failed;
+                            }
+                        }
+                        return EventFlow::Continue;
+                    }
+                    // 手动断句：先累积，等 response.done（或静默超时）后统一拼接
+                    tracing::info!("收到第 {} 段转录: {}", pending.segment_count() + 1, privacy::redact(&transcript));
+                    pending.push(&transcript);
+                    if !self.config.endpoint.has_response() {
+                        // 没有 response.done：本次 commit 的结果已齐
+                        pending.done = true;
+                    }
+                }
+            }
+            RealtimeServerEvent::TranscriptDelta { delta } => {
+                // 增量转录结果
+                if let Some(delta) = delta {
+                    self.first_transcript.get_or_init(Instant::now);
+                    pending.partial.push_str(&delta);
+                    tracing::debug!("增量转录: {}", privacy::redact(&delta));
+                }
+            }
+            RealtimeServerEvent::TranscriptDone { transcript } => {
+                // 当前段转录完成
+                let transcript = transcript.unwrap_or_else(|| pending.partial.clone());
+                pending.push(&transcript);
+            }
+            RealtimeServerEvent::ResponseDone => {
+                // 响应完成，不会再有新的分段
+                pending.done = true;
+            }
+            RealtimeServerEvent::Error { error } => {
+                // 错误通过状态通道上报，接收任务继续运行，服务端后续的消息仍能收到
+                let error_msg = error.message.unwrap_or_else(|| "未知错误".to_string());
+                tracing::error!("API 错误: {} (type: {:?}, code: {:?})", error_msg, error.kind, error.code);
+                let _ = self.status_tx.send(SessionStatus::Error {
+                    code: error.code,
+                    message: error_msg.clone(),
+                });
+                if self.result_tx.send(Err(anyhow::anyhow!("API 错误: {}", error_msg))).await.is_err() {
+                    return EventFlow::Failed;
+                }
+            }
+            RealtimeServerEvent::Other => {
+                tracing::debug!("未处理的事件: {}", privacy::redact(text));
+            }
+        }
+
+        // 连续听写：本轮没有识别出文字，直接进入下一轮
+        if pending.done && pending.is_empty() && self.config.options.dictation() {
+            pending.done = false;
+            return EventFlow::Finished;
+        }
+
+        // 响应完成且已有结果：拼接所有分段发出
+        if pending.done && !pending.is_empty() {
+            let text = pending.take_text();
+            tracing::info!("转录完成: {}", privacy::redact(&text));
+            let _ = self.result_tx.send(Ok(text)).await;
+            return EventFlow::Finished;
+        }
+
+        EventFlow::Continue
+    }
+}
+
+/// 一轮结果已发出：已提交的音频不再需要断线重发，之后发送的属于下一轮（连续听写另外结算分段计数）
+fn finish_round(dictation: Option<&mut Dictation>, sent_audio: &mut ResendCache, pending_segments: &AtomicUsize) {
+    sent_audio.finish_round();
+    if let Some(round) = dictation {
+        round.finish_rounds(pending_segments);
+    }
+}
+
+/// dBFS 换算为 16-bit PCM 的 RMS 幅度
+fn dbfs_to_rms(dbfs: f32) -> f64 {
+    i16::MAX as f64 * 10f64.powf(dbfs as f64 / 20.0)
+}
+
+/// 一块音频的音量（RMS）是否达到说话阈值
+fn has_voice(pcm_data: &[i16], threshold: f64) -> bool {
+    if pcm_data.is_empty() {
+        return false;
+    }
+    let sum: f64 = pcm_data.iter().map(|&s| (s as f64) * (s as f64)).sum();
+    (sum / pcm_data.len() as f64).sqrt() >= threshold
+}
+
+/// 实时协议的服务端事件（按 `type` 字段区分，未识别的类型落入 `Other`）
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(tag = "type")]
+enum RealtimeServerEvent {
+    #[serde(rename = "session.created", alias = "transcription_session.created")]
+    SessionCreated,
+    #[serde(rename = "session.updated", alias = "transcription_session.updated")]
+    SessionUpdated,
+    #[serde(rename = "session.finished")]
+    SessionFinished,
+    #[serde(rename = "input_audio_buffer.speech_started")]
+    SpeechStarted,
+    #[serde(rename = "input_audio_buffer.speech_stopped")]
+    SpeechStopped,
+    #[serde(rename = "input_audio_buffer.committed")]
+    InputAudioBufferCommitted,
+    #[serde(rename = "conversation.item.input_audio_transcription.completed")]
+    TranscriptionCompleted {
+        #[serde(default)]
+        transcript: Option<String>,
+    },
+    #[serde(rename = "response.audio_transcript.delta", alias = "conversation.item.input_audio_transcription.delta")]
+    TranscriptDelta {
+        #[serde(default)]
+        delta: Option<String>,
+    },
+    #[serde(rename = "response.audio_transcript.done")]
+    TranscriptDone {
+        #[serde(default)]
+        transcript: Option<String>,
+    },
+    #[serde(rename = "response.done")]
+    ResponseDone,
+    #[serde(rename = "error")]
+    Error {
+        #[serde(default)]
+        error: ApiError,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// error 事件中的错误详情
+#[derive(Debug, Default, Deserialize, PartialEq)]
+struct ApiError {
+    #[serde(rename = "type", default)]
+    kind: Option<String>,
+    #[serde(default)]
+    code: Option<String>,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+/// 分段里有文字（只有标点或空白的分段丢弃）；标点的处理统一交给 text_postprocess
+pub(crate) fn has_content(text: &str) -> bool {
+    text.chars().any(char::is_alphanumeric)
+}
+
+/// 简化的实时转录客户端
+pub struct QwenRealtimeClient {
+    pool: ConnectionPool,
+}
+
+impl QwenRealtimeClient {
+    pub fn new(api_key: String, options: SessionOptions, realtime: RealtimeOptions) -> Self {
+        Self {
+            pool: ConnectionPool::new(api_key, options, realtime),
+        }
+    }
+
+    /// 连接其他兼容协议的服务端点（如 OpenAI Realtime API）
+    pub fn with_endpoint(endpoint: RealtimeEndpoint, options: SessionOptions, realtime: RealtimeOptions) -> Self {
+        Self {
+            pool: ConnectionPool::with_endpoint(endpoint, options, realtime),
+        }
+    }
+
+    /// 创建新的转录会话，`sample_rate` 为上传 PCM 的采样率（见 `session_sample_rate`）
+    pub async fn start_session(&self, sample_rate: u32) -> Result<RealtimeSession> {
+        self.pool.get_session(sample_rate).await
+    }
+
+    /// 录音结束后归还会话，可复用时留作下一次转录的连接
+    pub async fn release(&self, session: RealtimeSession) {
+        self.pool.release(session).await;
+    }
+
+    /// 后台预热下一次转录要用的会话
+    pub fn warm_up(&self) {
+        self.pool.warm_up();
+    }
+
+    /// 是否有刚收到过服务端消息的预热会话
+    pub async fn has_live_session(&self) -> bool {
+        self.pool.has_live_session().await
+    }
+
+    /// 服务支持直接上传的采样率
+    pub fn supported_sample_rates(&self) -> &'static [u32] {
+        self.pool.endpoint.supported_sample_rates()
+    }
+
+    /// 启动前的连通性测试：完成一次握手，成功的连接直接留作预热会话
+    pub async fn check_connectivity(&self) -> Result<()> {
+        self.pool.connect_now().await
+    }
+
+    /// 关闭连接池中的预热会话
+    pub async fn shutdown(&self) {
+        self.pool.clear().await;
+    }
+}
+
+/// 长稳测试（tests/soak.rs）的入口：对本地 mock 服务反复跑完整的按键循环。
+/// 长稳测试要替换全局分配器统计内存，只能放在独立的测试二进制里；仅在 soak feature 下编译并由 crate 根导出
+#[cfg(feature = "soak")]
+pub struct SoakClient {
+    client: QwenRealtimeClient,
+}
+
+#[cfg(feature = "soak")]
+impl SoakClient {
+    pub fn new(url: &str) -> Self {
+        let options = SessionOptions {
+            max_reconnect_attempts: 0,
+            ..SessionOptions::default()
+        };
+        let endpoint = RealtimeEndpoint::dashscope("soak".to_string(), url, DEFAULT_MODEL);
+        Self {
+            client: QwenRealtimeClient::with_endpoint(endpoint, options, RealtimeOptions::default()),
+        }
+    }
+
+    /// 一次手动断句的按键循环，按应用的收尾顺序：录音块经通道交给发送任务，发送任务通过共享的会话槽发送；
+    /// 松开后 join 发送任务 → commit → 取结果 → 关闭会话并清空会话槽
+    pub async fn run_cycle(&self) -> Result<String> {
+        let session = self.client.start_session(DEFAULT_SAMPLE_RATE).await?;
+        let active_session = Arc::new(Mutex::new(Some(session)));
+        spawn_sender(Arc::clone(&active_session)).await??;
+
+        let mut session = active_session.lock().await.take()
+            .ok_or_else(|| anyhow::anyhow!("会话槽已被清空"))?;
+        session.commit_audio().await?;
+        let wait = session.result_timeout();
+        let text = session.wait_for_result(wait).await?;
+        session.close().await?;
+        Ok(text)
+    }
+
+    /// 一次分段的按键循环（服务端 VAD / 连续听写的收尾方式）：结果通道交给分段任务持续消费，
+    /// 关闭会话后结果通道随之关闭，分段任务应自行结束；返回分段任务收到的段数
+    pub async fn run_segmented_cycle(&self) -> Result<usize> {
+        let mut session = self.client.start_session(DEFAULT_SAMPLE_RATE).await?;
+        let mut segment_rx = session.take_segment_receiver()
+            .ok_or_else(|| anyhow::anyhow!("结果通道已被取走"))?;
+        let delivered_notify = Arc::new(Notify::new());
+        let notify = Arc::clone(&delivered_notify);
+        let segment_task = tokio::spawn(async move {
+            let mut delivered = 0;
+            while let Some(result) = segment_rx.recv().await {
+                result?;
+                delivered += 1;
+                notify.notify_one();
+            }
+            anyhow::Ok(delivered)
+        });
+        let active_session = Arc::new(Mutex::new(Some(session)));
+        spawn_sender(Arc::clone(&active_session)).await??;
+
+        let session = active_session.lock().await.take()
+            .ok_or_else(|| anyhow::anyhow!("会话槽已被清空"))?;
+        session.commit_audio().await?;
+        let segment_task_timeout = session.timings().segment_task_timeout;
+        timeout(session.result_timeout(), delivered_notify.notified()).await
+            .map_err(|_| anyhow::anyhow!("分段任务未收到结果"))?;
+        session.close().await?;
+        timeout(segment_task_timeout, segment_task).await
+            .map_err(|_| anyhow::anyhow!("结果通道未关闭，分段任务没有结束"))??
+    }
+}
+
+/// 长稳测试的发送任务：与应用相同，从录音块通道取出全部块后经会话槽逐块发送
+#[cfg(feature = "soak")]
+fn spawn_sender(active_session: Arc<Mutex<Option<RealtimeSession>>>) -> tokio::task::JoinHandle<Result<()>> {
+    let (chunk_tx, chunk_rx) = crossbeam_channel::unbounded::<Vec<i16>>();
+    for _ in 0..5 {
+        let _ = chunk_tx.send(vec![2000i16; 3200]);
+    }
+    drop(chunk_tx);
+    tokio::spawn(async move {
+        while let Ok(chunk) = chunk_rx.recv() {
+            let session_guard = active_session.lock().await;
+            let session = session_guard.as_ref().ok_or_else(|| anyhow::anyhow!("会话已关闭"))?;
+            session.send_audio_chunk(&chunk).await?;
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// 本地 mock provider：收到 commit 后回一条 completed 事件和 response.done
+    async fn spawn_mock_server() -> String {
+        spawn_scripted_server(
+            Vec::new(),
+            vec![
+                serde_json::json!({
+                    "type": "conversation.item.input_audio_transcription.completed",
+                    "transcript": "测试文本。"
+                }),
+                serde_json::json!({ "type": "response.done" }),
+            ],
+        )
+        .await
+    }
+
+    /// 按脚本回复的 mock provider：第一次收到音频时回 `on_audio`，收到 commit 后回 `on_commit`
+    async fn spawn_scripted_server(on_audio: Vec<serde_json::Value>, on_commit: Vec<serde_json::Value>) -> String {
+        spawn_counting_server(on_audio, on_commit, Duration::ZERO).await.0
+    }
+
+    /// 同 `spawn_scripted_server`，另外统计建立过的连接数；`handshake_delay` 模拟 TCP/TLS 握手耗时
+    async fn spawn_counting_server(
+        on_audio: Vec<serde_json::Value>,
+        on_commit: Vec<serde_json::Value>,
+        handshake_delay: Duration,
+    ) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&accepted);
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let _ = stream.set_nodelay(true);
+                counter.fetch_add(1, Ordering::SeqCst);
+                let (on_audio, on_commit) = (on_audio.clone(), on_commit.clone());
+                tokio::spawn(async move {
+                    tokio::time::sleep(handshake_delay).await;
+                    let Ok(mut ws) = tokio_tungstenite::accept_async(stream).await else {
+                        return;
+                    };
+                    let mut audio_seen = false;
+                    while let Some(Ok(msg)) = ws.next().await {
+                        let Message::Text(text) = msg else {
+                            continue;
+                        };
+                        let event: serde_json::Value = serde_json::from_str(&text).unwrap();
+                        let replies = match event["type"].as_str() {
+                            Some("input_audio_buffer.append") if !audio_seen => {
+                                audio_seen = true;
+                                &on_audio
+                            }
+                            Some("input_audio_buffer.commit") => &on_commit,
+                            _ => continue,
+                        };
+                        for reply in replies {
+                            if ws.send(Message::Text(reply.to_string())).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                });
+            }
+        });
+
+        (format!("ws://{}", addr), accepted)
+    }
+
+    async fn wait_for_tasks(metrics: &tokio::runtime::RuntimeMetrics, expected: usize) -> usize {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let alive = metrics.num_alive_tasks();
+            if alive <= expected || Instant::now() >= deadline {
+                return alive;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    fn completed(transcript: &str) -> serde_json::Value {
+        serde_json::json!({
+            "type": "conversation.item.input_audio_transcription.completed",
+            "transcript": transcript
+        })
+    }
+
+    /// 说一句 → 停顿两秒 → 再说一句，松开后 commit 并取结果
+    async fn speak_with_pause(url: String) -> String {
+        let options = SessionOptions {
+            max_reconnect_attempts: 0,
+            ..SessionOptions::default()
+        };
+        let pool = ConnectionPool::new("test-key".to_string(), options, RealtimeOptions::default()).with_url(url);
+        let mut session = pool.get_session(DEFAULT_SAMPLE_RATE).await.unwrap();
+        let voice = vec![2000i16; 3200];
+        let silence = vec![0i16; 3200];
+        session.send_audio_chunk(&voice).await.unwrap();
+        for _ in 0..10 {
+            session.send_audio_chunk(&silence).await.unwrap();
+        }
+        session.send_audio_chunk(&voice).await.unwrap();
+        session.commit_audio().await.unwrap();
+        let wait = session.result_timeout();
+        let text = session.wait_for_result(wait).await.unwrap();
+        session.close().await.unwrap();
+        text
+    }
+
+    #[tokio::test]
+    async fn keeps_every_segment_when_server_splits_on_pause() {
+        // 服务端在两秒停顿处切出前半句，commit 后再返回后半句
+        let url = spawn_scripted_server(
+            vec![completed("今天天气不错，")],
+            vec![completed("我们出去走走吧。"), serde_json::json!({ "type": "response.done" })],
+        )
+        .await;
+        assert_eq!(speak_with_pause(url).await, "今天天气不错，我们出去走走吧。");
+    }
+
+    #[tokio::test]
+    async fn joins_segments_after_settle_without_response_done() {
+        let url = spawn_scripted_server(
+            vec![completed("Hello world.")],
+            vec![completed("see you")],
+        )
+        .await;
+        let started = Instant::now();
+        assert_eq!(speak_with_pause(url).await, "Hello world. see you");
+        assert!(started.elapsed() < Timings::default().realtime_result_floor);
+    }
+
+    #[tokio::test]
+    async fn dictation_commits_each_round_at_pause_and_keeps_connection() {
+        let url = spawn_scripted_server(
+            Vec::new(),
+            vec![completed("好的。"), serde_json::json!({ "type": "response.done" })],
+        )
+        .await;
+        let interval = Duration::from_millis(300);
+        let options = SessionOptions {
+            max_reconnect_attempts: 0,
+            auto_commit: Some(interval),
+            ..SessionOptions::default()
+        };
+        let pool = ConnectionPool::new("test-key".to_string(), options, RealtimeOptions::default()).with_url(url);
+        let mut session = pool.get_session(DEFAULT_SAMPLE_RATE).await.unwrap();
+        assert!(session.is_dictation());
+        let mut segments = session.take_segment_receiver().unwrap();
+        let voice = vec![2000i16; 3200];
+        let silence = vec![0i16; 3200];
+
+        for _ in 0..2 {
+            // 说话期间到点也不提交，等到停顿处再提交本轮
+            session.send_audio_chunk(&voice).await.unwrap();
+            tokio::time::sleep(interval + Duration::from_millis(50)).await;
+            session.send_audio_chunk(&voice).await.unwrap();
+            session.send_audio_chunk(&silence).await.unwrap();
+            let segment = timeout(Duration::from_secs(5), segments.recv()).await.unwrap().unwrap();
+            assert_eq!(segment.unwrap(), "好的。");
+            assert!(session.is_alive());
+        }
+
+        // 尾段只有静音：退出时不再提交，收尾立即完成
+        session.send_audio_chunk(&silence).await.unwrap();
+        let started = Instant::now();
+        session.finish_segments().await.unwrap();
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(segments.try_recv().is_err());
+        session.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn released_session_is_reused_without_new_handshake() {
+        let handshake = Duration::from_millis(300);
+        let (url, accepted) = spawn_counting_server(
+            Vec::new(),
+            vec![completed("测试文本。"), serde_json::json!({ "type": "response.done" })],
+            handshake,
+        )
+        .await;
+        let options = SessionOptions {
+            max_reconnect_attempts: 0,
+            // 每次取用都先 ping 探活
+            timings: Timings { pool_probe_after: Duration::ZERO, ..Timings::default() },
+            ..SessionOptions::default()
+        };
+        let pool = ConnectionPool::new("test-key".to_string(), options, RealtimeOptions::default()).with_url(url);
+        let voice = vec![2000i16; 3200];
+
+        let mut connect_times = Vec::new();
+        for round in 0..3 {
+            let started = Instant::now();
+            let mut session = pool.get_session(DEFAULT_SAMPLE_RATE).await.unwrap();
+            connect_times.push(started.elapsed());
+            let expected = if round == 0 { SessionOrigin::New } else { SessionOrigin::Reused };
+            assert_eq!(session.origin(), expected);
+            assert!(!session.speech_detected());
+            assert_eq!(session.audio_duration(), Duration::ZERO);
+
+            session.send_audio_chunk(&voice).await.unwrap();
+            session.commit_audio().await.unwrap();
+            let wait = session.result_timeout();
+            assert_eq!(session.wait_for_result(wait).await.unwrap(), "测试文本。");
+            assert!(session.first_transcript_at().is_some_and(|at| at >= started));
+            pool.release(session).await;
+        }
+
+        assert_eq!(accepted.load(Ordering::SeqCst), 1);
+        assert!(connect_times[0] >= handshake);
+        assert!(connect_times[1..].iter().all(|&t| t < handshake / 2), "复用耗时: {:?}", connect_times);
+
+        // 被取消的会话不归还
+        let session = pool.get_session(DEFAULT_SAMPLE_RATE).await.unwrap();
+        session.cancel().await;
+        pool.release(session).await;
+        assert!(pool.connection.lock().await.is_none());
+        pool.clear().await;
+    }
+
+    #[tokio::test]
+    async fn reused_session_still_detects_fatal_status() {
+        // 第一次 commit 正常出结果，第二次（复用的连接上）回 API 错误
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let mut commits = 0;
+            while let Some(Ok(msg)) = ws.next().await {
+                let Message::Text(text) = msg else {
+                    continue;
+                };
+                let event: serde_json::Value = serde_json::from_str(&text).unwrap();
+                if event["type"] != "input_audio_buffer.commit" {
+                    continue;
+                }
+                commits += 1;
+                let replies = if commits == 1 {
+                    vec![completed("测试文本。"), serde_json::json!({ "type": "response.done" })]
+                } else {
+                    vec![serde_json::json!({ "type": "error", "error": { "code": "InvalidParameter", "message": "bad audio" } })]
+                };
+                for reply in replies {
+                    if ws.send(Message::Text(reply.to_string())).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        let options = SessionOptions {
+            max_reconnect_attempts: 0,
+            timings: Timings { pool_probe_after: Duration::ZERO, ..Timings::default() },
+            ..SessionOptions::default()
+        };
+        let pool = ConnectionPool::new("test-key".to_string(), options, RealtimeOptions::default()).with_url(url);
+        let voice = vec![2000i16; 3200];
+
+        // 与应用一样每次录音都取走连接状态 / 说话状态通道
+        let mut session = pool.get_session(DEFAULT_SAMPLE_RATE).await.unwrap();
+        let mut first_events = session.take_event_receiver().unwrap();
+        assert!(session.take_speech_receiver().is_some());
+        session.send_audio_chunk(&voice).await.unwrap();
+        session.commit_audio().await.unwrap();
+        assert!(matches!(session.wait_for_stop_result().await, StopResult::Text(text) if text == "测试文本。"));
+        assert!(session.status_receiver.is_some(), "等待结果后会话状态通道应放回");
+        pool.release(session).await;
+        // 换新通道后上一次录音的转发任务随通道关闭而结束
+        assert!(first_events.recv().await.is_none());
+
+        let mut session = pool.get_session(DEFAULT_SAMPLE_RATE).await.unwrap();
+        assert_eq!(session.origin(), SessionOrigin::Reused);
+        assert!(session.take_event_receiver().is_some());
+        assert!(session.take_speech_receiver().is_some());
+        session.send_audio_chunk(&voice).await.unwrap();
+        session.commit_audio().await.unwrap();
+        let started = Instant::now();
+        let wait = session.result_timeout();
+        assert!(matches!(session.wait_for_stop_result().await, StopResult::Fallback(_)));
+        assert!(started.elapsed() < wait / 2, "致命错误应及早结束等待: {:?}", started.elapsed());
+        session.close().await.unwrap();
+        pool.clear().await;
+    }
+
+    #[tokio::test]
+    async fn rejected_handshake_reports_auth_error() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0u8; 4096];
+                let _ = stream.read(&mut buf).await;
+                let _ = stream.write_all(b"HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\n\r\n").await;
+            }
+        });
+
+        let pool = ConnectionPool::new("bad-key".to_string(), SessionOptions::default(), RealtimeOptions::default())
+            .with_url(format!("ws://{}", addr));
+        let error = pool.get_session(DEFAULT_SAMPLE_RATE).await.err().expect("握手应被拒绝");
+        let rejected = error.downcast_ref::<AuthRejected>().expect("应返回 AuthRejected");
+        assert_eq!(rejected.status, 401);
+        assert!(error.to_string().contains("API Key"));
+    }
+
+    #[test]
+    fn watchdog_threshold_depends_on_awaiting_result() {
+        let options = SessionOptions::default();
+        assert_eq!(options.stale_threshold(true), Some(Duration::from_secs(5)));
+        assert_eq!(options.stale_threshold(false), Some(Duration::from_secs(45)));
+
+        let disabled = SessionOptions {
+            ping_interval_secs: 0,
+            stale_after: Duration::ZERO,
+            ..SessionOptions::default()
+        };
+        assert_eq!(disabled.stale_threshold(true), None);
+        assert_eq!(disabled.stale_threshold(false), None);
+    }
+
+    #[tokio::test]
+    async fn stale_connection_fails_fast_while_awaiting_result() {
+        // 服务端收下 commit 后再也不回任何消息
+        let url = spawn_scripted_server(Vec::new(), Vec::new()).await;
+        let stale_after = Duration::from_millis(300);
+        let options = SessionOptions {
+            max_reconnect_attempts: 0,
+            stale_after,
+            timings: Timings { watchdog_interval: Duration::from_millis(50), ..Timings::default() },
+            ..SessionOptions::default()
+        };
+        let pool = ConnectionPool::new("test-key".to_string(), options, RealtimeOptions::default()).with_url(url);
+        let mut session = pool.get_session(DEFAULT_SAMPLE_RATE).await.unwrap();
+        session.send_audio_chunk(&vec![2000i16; 3200]).await.unwrap();
+
+        // 空闲（未 commit）时静默超过阈值不会触发
+        tokio::time::sleep(stale_after * 2).await;
+        session.commit_audio().await.unwrap();
+        let committed = Instant::now();
+
+        let error = session.wait_for_result(Duration::from_secs(10)).await.unwrap_err();
+        let stale = error.downcast_ref::<ConnectionStale>().expect("应返回 ConnectionStale");
+        assert!(stale.silence >= stale_after);
+        // 从 commit 起算，而不是从最后一次收到消息起算
+        assert!(committed.elapsed() >= stale_after);
+        assert!(committed.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn missing_pong_triggers_reconnect() {
+        // 服务端收下 session.update 后不再读取，也就不会回 pong（模拟被代理悄悄掐断的连接）
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&accepted);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let Ok(mut ws) = tokio_tungstenite::accept_async(stream).await else {
+                        return;
+                    };
+                    let _ = ws.next().await;
+                    tokio::time::sleep(Duration::from_secs(30)).await;
+                    drop(ws);
+                });
+            }
+        });
+
+        let options = SessionOptions {
+            max_reconnect_attempts: 1,
+            ping_interval_secs: 1,
+            pong_timeout: Duration::from_millis(200),
+            ..SessionOptions::default()
+        };
+        let pool = ConnectionPool::new("test-key".to_string(), options, RealtimeOptions::default()).with_url(url);
+        let _session = pool.get_session(DEFAULT_SAMPLE_RATE).await.unwrap();
+        assert_eq!(accepted.load(Ordering::SeqCst), 1);
+
+        // 1 秒后发出 ping，200ms 内没有 pong 即重建连接（远早于 3 个 ping 周期的死连接判定）
+        let deadline = Instant::now() + Duration::from_millis(2500);
+        while accepted.load(Ordering::SeqCst) < 2 && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(accepted.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn one_connection_serves_several_rounds() {
+        let (url, accepted) = spawn_counting_server(
+            Vec::new(),
+            vec![completed("测试文本。"), serde_json::json!({ "type": "response.done" })],
+            Duration::ZERO,
+        )
+        .await;
+        let options = SessionOptions {
+            max_reconnect_attempts: 0,
+            ..SessionOptions::default()
+        };
+        let pool = ConnectionPool::new("test-key".to_string(), options, RealtimeOptions::default()).with_url(url);
+        let mut session = pool.get_session(DEFAULT_SAMPLE_RATE).await.unwrap();
+        let voice = vec![2000i16; 3200];
+        let wait = Duration::from_secs(5);
+
+        // 第一轮出结果后接收任务继续运行，接着发第二、三轮：不等上一轮结果就继续发送、commit
+        session.send_audio_chunk(&voice).await.unwrap();
+        session.commit_audio().await.unwrap();
+        assert_eq!(timeout(wait, session.next_result()).await.unwrap().unwrap().unwrap(), "测试文本。");
+        for _ in 0..2 {
+            session.send_audio_chunk(&voice).await.unwrap();
+            session.commit_audio().await.unwrap();
+        }
+        for _ in 0..2 {
+            assert_eq!(timeout(wait, session.next_result()).await.unwrap().unwrap().unwrap(), "测试文本。");
+        }
+
+        // 关闭后驱动任务结束，结果取完即返回 None，不会多出一条"未收到转录结果"
+        session.close().await.unwrap();
+        assert!(timeout(wait, session.next_result()).await.unwrap().is_none());
+        assert_eq!(accepted.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn openai_transcription_finishes_on_completed() {
+        // OpenAI 转录会话没有 response.done，completed 之后不必等待 segment_settle
+        let url = spawn_scripted_server(
+            vec![serde_json::json!({ "type": "transcription_session.updated" })],
+            vec![
+                serde_json::json!({ "type": "input_audio_buffer.committed" }),
+                serde_json::json!({ "type": "conversation.item.input_audio_transcription.delta", "delta": "Hello" }),
+                completed("Hello there."),
+            ],
+        )
+        .await;
+        let options = SessionOptions {
+            max_reconnect_attempts: 0,
+            ..SessionOptions::default()
+        };
+        let endpoint = RealtimeEndpoint::openai("sk-test".to_string(), url);
+        let pool = ConnectionPool::with_endpoint(endpoint, options, RealtimeOptions::default());
+        let mut session = pool.get_session(DEFAULT_SAMPLE_RATE).await.unwrap();
+        assert_eq!(session.sample_rate(), 24000);
+        session.send_audio_chunk(&vec![2000i16; 4800]).await.unwrap();
+        session.commit_audio().await.unwrap();
+        let started = Instant::now();
+        assert_eq!(session.wait_for_result(session.result_timeout()).await.unwrap(), "Hello there.");
+        assert!(started.elapsed() < Timings::default().segment_settle);
+        session.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn reports_api_error_on_status_channel_and_keeps_receiving() {
+        let error = serde_json::json!({
+            "type": "error",
+            "error": { "type": "invalid_request_error", "code": "InvalidParameter", "message": "bad audio" }
+        });
+        let url = spawn_scripted_server(
+            Vec::new(),
+            vec![error, completed("仍然收到"), serde_json::json!({ "type": "response.done" })],
+        )
+        .await;
+        let options = SessionOptions {
+            max_reconnect_attempts: 0,
+            ..SessionOptions::default()
+        };
+        let pool = ConnectionPool::new("test-key".to_string(), options, RealtimeOptions::default()).with_url(url);
+        let mut session = pool.get_session(DEFAULT_SAMPLE_RATE).await.unwrap();
+        let mut status_rx = session.take_status_receiver().unwrap();
+        session.send_audio_chunk(&vec![2000i16; 3200]).await.unwrap();
+        session.commit_audio().await.unwrap();
+
+        assert_eq!(status_rx.recv().await, Some(SessionStatus::Connected));
+        let status = status_rx.recv().await.unwrap();
+        assert!(status.is_fatal());
+        assert_eq!(
+            status,
+            SessionStatus::Error { code: Some("InvalidParameter".to_string()), message: "bad audio".to_string() }
+        );
+
+        // 错误之后接收任务仍在运行，服务端后续的结果照样送达
+        let wait = session.result_timeout();
+        assert!(session.wait_for_result(wait).await.is_err());
+        assert_eq!(session.wait_for_result(wait).await.unwrap(), "仍然收到");
+
+        session.close().await.unwrap();
+        assert_eq!(status_rx.recv().await, Some(SessionStatus::Closed));
+    }
+
+    /// 只记录客户端消息（事件类型 / Close 状态码）、从不回复的 mock provider
+    async fn spawn_recording_server() -> (String, mpsc::UnboundedReceiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let (seen_tx, seen_rx) = mpsc::unbounded_channel::<String>();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            while let Some(Ok(msg)) = ws.next().await {
+                let seen = match msg {
+                    Message::Text(text) => serde_json::from_str::<serde_json::Value>(&text).unwrap()["type"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                    Message::Close(frame) => format!("close:{}", frame.map(|f| u16::from(f.code)).unwrap_or(0)),
+                    _ => continue,
+                };
+                let _ = seen_tx.send(seen);
+            }
+        });
+        (url, seen_rx)
+    }
+
+    async fn collect_seen(mut seen_rx: mpsc::UnboundedReceiver<String>) -> Vec<String> {
+        let mut seen = Vec::new();
+        while let Some(event) = seen_rx.recv().await {
+            seen.push(event);
+        }
+        seen
+    }
+
+    #[tokio::test]
+    async fn close_sends_finish_event_and_normal_close_frame() {
+        let (url, seen_rx) = spawn_recording_server().await;
+        let options = SessionOptions {
+            max_reconnect_attempts: 0,
+            ..SessionOptions::default()
+        };
+        let pool = ConnectionPool::new("test-key".to_string(), options, RealtimeOptions::default()).with_url(url);
+        let session = pool.get_session(DEFAULT_SAMPLE_RATE).await.unwrap();
+        let started = Instant::now();
+        session.close().await.unwrap();
+        // 服务端回执 Close 后立即返回，不必等满超时
+        assert!(started.elapsed() < Timings::default().session_close_timeout);
+
+        assert_eq!(collect_seen(seen_rx).await, vec!["session.update", "session.finish", "close:1000"]);
+    }
+
+    #[tokio::test]
+    async fn cancel_after_commit_stops_server_transcription() {
+        let (url, seen_rx) = spawn_recording_server().await;
+        let options = SessionOptions {
+            max_reconnect_attempts: 0,
+            ..SessionOptions::default()
+        };
+        let pool = ConnectionPool::new("test-key".to_string(), options, RealtimeOptions::default()).with_url(url);
+        let mut session = pool.get_session(DEFAULT_SAMPLE_RATE).await.unwrap();
+        session.send_audio_chunk(&[2000i16; 3200]).await.unwrap();
+        session.commit_audio().await.unwrap();
+
+        // 等待结果期间从别处取消：等待方立即返回，不必等满超时
+        let canceller = session.canceller();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            canceller.cancel().await;
+        });
+        let started = Instant::now();
+        assert!(session.wait_for_result(session.result_timeout()).await.is_err());
+        assert!(started.elapsed() < Timings::default().realtime_result_floor);
+        assert!(session.is_cancelled());
+
+        assert_eq!(
+            collect_seen(seen_rx).await,
+            vec![
+                "session.update",
+                "input_audio_buffer.append",
+                "input_audio_buffer.commit",
+                "response.cancel",
+                "input_audio_buffer.clear",
+                "session.finish",
+                "close:1000",
+            ]
+        );
+    }
+
+    #[test]
+    fn audio_backlog_warns_once_and_applies_policy() {
+        let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+        let queue = AudioQueue::new(5, BacklogPolicy::DropOldest, event_tx.clone());
+        for i in 0..10u8 {
+            queue.push(vec![i]);
+        }
+        // 积压到 4 块（80%）时告警一次，之后只丢弃最旧的块
+        assert!(matches!(event_rx.try_recv(), Ok(ConnectionEvent::NetworkSlow { backlog: 4, limit: 5 })));
+        assert!(event_rx.try_recv().is_err());
+        let chunks: Vec<u8> = queue.drain().into_iter().flatten().collect();
+        assert_eq!(chunks, vec![5, 6, 7, 8, 9]);
+        assert_eq!(queue.summary(), "峰值 5 块 / 上限 5，积压告警 1 次，丢弃 5 块");
+
+        // 清空后再次积压会重新告警
+        for i in 0..4u8 {
+            queue.push(vec![i]);
+        }
+        assert!(matches!(event_rx.try_recv(), Ok(ConnectionEvent::NetworkSlow { .. })));
+
+        let unbounded = AudioQueue::new(5, BacklogPolicy::Unbounded, event_tx);
+        for i in 0..10u8 {
+            unbounded.push(vec![i]);
+        }
+        assert_eq!(unbounded.drain().len(), 10);
+        assert_eq!(unbounded.dropped.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stop_falls_back_to_http_after_injected_result_floor() {
+        let options = SessionOptions {
+            timings: Timings { realtime_result_floor: Duration::from_secs(3), ..Timings::default() },
+            ..SessionOptions::default()
+        };
+        // 服务端一直没有响应（驱动端点保持存活但不产出任何结果）
+        let (mut session, _endpoints) = new_session(&options, DEFAULT_SAMPLE_RATE);
+        session.send_audio_chunk(&[2000i16; 3200]).await.unwrap();
+        session.commit_audio().await.unwrap();
+
+        // 短音频按注入的下限等待，超时后交给 HTTP 回退
+        let started = tokio::time::Instant::now();
+        match session.wait_for_stop_result().await {
+            StopResult::Fallback(e) => assert!(e.to_string().starts_with("转录超时"), "{}", e),
+            other => panic!("应回退到 HTTP: {:?}", other),
+        }
+        assert_eq!(started.elapsed(), Duration::from_secs(3));
+
+        // 会话已关闭：不等超时，立即回退
+        let (mut session, endpoints) = new_session(&options, DEFAULT_SAMPLE_RATE);
+        endpoints.status_tx.send(SessionStatus::Closed).unwrap();
+        let started = tokio::time::Instant::now();
+        assert!(matches!(session.wait_for_stop_result().await, StopResult::Fallback(_)));
+        assert_eq!(started.elapsed(), Duration::ZERO);
+
+        // 鉴权失败：HTTP 同样会被拒绝，不回退
+        let (mut session, endpoints) = new_session(&options, DEFAULT_SAMPLE_RATE);
+        endpoints.result_tx.send(Err(AuthRejected { status: 401 }.into())).await.unwrap();
+        assert!(matches!(session.wait_for_stop_result().await, StopResult::AuthRejected(_)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn realtime_timeout_returns_received_deltas() {
+        let options = SessionOptions::default();
+        let (mut session, endpoints) = new_session(&options, DEFAULT_SAMPLE_RATE);
+        session.send_audio_chunk(&[2000i16; 3200]).await.unwrap();
+        session.commit_audio().await.unwrap();
+
+        // 驱动任务已累积一段完成的结果和半句增量，但迟迟等不到最终结果
+        let mut pending = PendingTranscript::default();
+        pending.push("第一句。");
+        pending.partial.push_str("hello wor");
+        *endpoints.received_text.lock().unwrap() = pending.text();
+
+        let wait = session.result_timeout();
+        let started = tokio::time::Instant::now();
+        assert_eq!(session.wait_for_result(wait).await.unwrap(), "第一句。hello wor");
+        assert_eq!(started.elapsed(), wait);
+
+        // 只有标点的增量不算内容，仍按超时报错走 HTTP 回退
+        *endpoints.received_text.lock().unwrap() = "。".to_string();
+        let err = session.wait_for_result(wait).await.unwrap_err();
+        assert!(err.to_string().starts_with("转录超时"), "{}", err);
+    }
+
+    #[test]
+    fn resend_cache_is_bounded_and_recovers_after_the_round() {
+        let mut cache = ResendCache::new(10);
+        cache.push(vec![0; 4]);
+        cache.push(vec![0; 4]);
+        cache.commit();
+        assert_eq!(cache.resendable().map(<[_]>::len), Some(2));
+
+        // 下一轮的音频超出上限：整个缓存丢弃，断线无法续传
+        cache.push(vec![0; 4]);
+        assert!(cache.chunks.is_empty() && cache.resendable().is_none());
+        cache.push(vec![0; 1]);
+        assert!(cache.chunks.is_empty());
+
+        // 丢失的音频都提交并出了结果后，之后的音频重新缓存
+        cache.commit();
+        cache.push(vec![0; 2]);
+        assert!(cache.resendable().is_none());
+        cache.finish_round();
+        assert_eq!(cache.resendable().map(<[_]>::len), Some(1));
+        assert!(!cache.is_idle());
+        cache.discard_uncommitted();
+        assert!(cache.is_idle());
+    }
+
+    #[test]
+    fn evicts_idle_connections_after_timeout() {
+        let idle_limit = Timings::default().pool_idle_timeout;
+        let dead_after = Some(Duration::from_secs(45));
+        let quiet = Duration::from_secs(1);
+
+        assert_eq!(evict_reason(idle_limit - Duration::from_secs(1), quiet, true, idle_limit, dead_after), None);
+        assert_eq!(evict_reason(idle_limit, quiet, true, idle_limit, dead_after), Some(EvictReason::IdleExpired));
+        // 空闲超时优先于心跳异常
+        assert_eq!(evict_reason(idle_limit, quiet, false, idle_limit, dead_after), Some(EvictReason::IdleExpired));
+    }
+
+    #[test]
+    fn evicts_unhealthy_connections() {
+        let idle = Duration::from_secs(10);
+        let idle_limit = Timings::default().pool_idle_timeout;
+        let dead_after = Some(Duration::from_secs(45));
+
+        assert_eq!(evict_reason(idle, Duration::from_secs(1), false, idle_limit, dead_after), Some(EvictReason::Unhealthy));
+        assert_eq!(evict_reason(idle, Duration::from_secs(45), true, idle_limit, dead_after), Some(EvictReason::Unhealthy));
+        assert_eq!(evict_reason(idle, Duration::from_secs(44), true, idle_limit, dead_after), None);
+        // 关闭心跳检测时不按静默时长判定
+        assert_eq!(evict_reason(idle, Duration::from_secs(600), true, idle_limit, None), None);
+    }
+
+    #[test]
+    fn session_update_carries_language_and_vocabulary() {
+        let realtime = RealtimeOptions {
+            language: "en".to_string(),
+            vocabulary: vec!["Tauri".to_string(), " ".to_string(), "DashScope".to_string()],
+            ..RealtimeOptions::default()
+        };
+        let event = build_session_update(RealtimeProtocol::DashScope, RealtimeVadMode::Manual, &realtime, DEFAULT_SAMPLE_RATE);
+        let transcription = &event["session"]["input_audio_transcription"];
+        assert_eq!(transcription["language"], "en");
+        assert_eq!(transcription["corpus"]["text"], "Tauri\nDashScope");
+        assert!(event["session"]["turn_detection"].is_null());
+    }
+
+    #[test]
+    fn session_update_omits_language_when_auto() {
+        let event = build_session_update(RealtimeProtocol::DashScope, RealtimeVadMode::Server, &RealtimeOptions::default(), 48000);
+        let transcription = &event["session"]["input_audio_transcription"];
+        assert!(transcription.get("language").is_none());
+        assert!(transcription.get("corpus").is_none());
+        assert_eq!(event["session"]["turn_detection"]["type"], "server_vad");
+        assert_eq!(event["session"]["sample_rate"], 48000);
+    }
+
+    #[test]
+    fn session_update_merges_punctuation_and_extra_params() {
+        let extra = serde_json::json!({
+            "input_audio_transcription": { "enable_itn": true, "corpus": null },
+            "modalities": ["text", "audio"]
+        });
+        let realtime = RealtimeOptions {
+            vocabulary: vec!["Tauri".to_string()],
+            punctuation: false,
+            extra: extra.as_object().unwrap().clone(),
+            ..RealtimeOptions::default()
+        };
+        let event = build_session_update(RealtimeProtocol::DashScope, RealtimeVadMode::Manual, &realtime, DEFAULT_SAMPLE_RATE);
+        let transcription = &event["session"]["input_audio_transcription"];
+        assert_eq!(transcription["enable_punctuation"], false);
+        assert_eq!(transcription["enable_itn"], true);
+        // null 删除字段，非对象值整体替换
+        assert!(transcription.get("corpus").is_none());
+        assert_eq!(event["session"]["modalities"], serde_json::json!(["text", "audio"]));
+        assert_eq!(event["session"]["sample_rate"], DEFAULT_SAMPLE_RATE);
+
+        // OpenAI 没有标点开关
+        let event = build_session_update(RealtimeProtocol::OpenAi, RealtimeVadMode::Manual, &realtime, DEFAULT_SAMPLE_RATE);
+        assert!(event["session"]["input_audio_transcription"].get("enable_punctuation").is_none());
+    }
+
+    #[test]
+    fn openai_session_update_uses_transcription_fields() {
+        let realtime = RealtimeOptions {
+            language: "en".to_string(),
+            vocabulary: vec!["Tauri".to_string(), "DashScope".to_string()],
+            model: "gpt-4o-transcribe".to_string(),
+            ..RealtimeOptions::default()
+        };
+        let endpoint = RealtimeEndpoint::openai("sk-test".to_string(), "wss://api.openai.com/v1/realtime?intent=transcription".to_string());
+        let event = endpoint.session_update(RealtimeVadMode::Manual, &realtime, DEFAULT_SAMPLE_RATE);
+        assert_eq!(event["type"], "transcription_session.update");
+        assert_eq!(event["session"]["input_audio_format"], "pcm16");
+        let transcription = &event["session"]["input_audio_transcription"];
+        assert_eq!(transcription["model"], "gpt-4o-transcribe");
+        assert_eq!(transcription["language"], "en");
+        assert_eq!(transcription["prompt"], "Tauri, DashScope");
+        assert!(event["session"]["turn_detection"].is_null());
+        // OpenAI 只接受 24kHz，设备协商出的采样率被忽略
+        assert_eq!(endpoint.sample_rate(48000), 24000);
+        assert_eq!(RealtimeEndpoint::dashscope("key".to_string(), DEFAULT_WEBSOCKET_URL, DEFAULT_MODEL).sample_rate(48000), 48000);
+    }
+
+    #[test]
+    fn uses_device_sample_rate_when_supported() {
+        assert_eq!(session_sample_rate(48000), 48000);
+        assert_eq!(session_sample_rate(24000), 24000);
+        assert_eq!(session_sample_rate(16000), 16000);
+        // 不支持的采样率回退到 16kHz，由录音端重采样
+        assert_eq!(session_sample_rate(44100), DEFAULT_SAMPLE_RATE);
+        assert_eq!(session_sample_rate(32000), DEFAULT_SAMPLE_RATE);
+        assert_eq!(bytes_per_ms(48000), 96);
+    }
+
+    #[tokio::test]
+    async fn fixed_sample_rate_overrides_device_rate() {
+        let url = spawn_mock_server().await;
+        let options = SessionOptions {
+            sample_rate: Some(48000),
+            ..SessionOptions::default()
+        };
+        let pool = ConnectionPool::new("test-key".to_string(), options, RealtimeOptions::default()).with_url(url);
+        let session = pool.get_session(16000).await.unwrap();
+        assert_eq!(session.sample_rate(), 48000);
+        session.close().await.unwrap();
+
+        let dashscope = RealtimeEndpoint::dashscope("key".to_string(), DEFAULT_WEBSOCKET_URL, DEFAULT_MODEL);
+        assert!(check_sample_rate(48000, dashscope.supported_sample_rates(), DEFAULT_MODEL).is_ok());
+        let openai = RealtimeEndpoint::openai("key".to_string(), "wss://example.com".to_string());
+        assert!(check_sample_rate(48000, openai.supported_sample_rates(), "gpt-4o-transcribe").is_err());
+    }
+
+    #[test]
+    fn detects_voice_by_rms() {
+        let threshold = dbfs_to_rms(-40.0);
+        assert!(!has_voice(&[], threshold));
+        assert!(!has_voice(&[0; 320], threshold));
+        // 底噪
+        assert!(!has_voice(&[50, -60, 40, -30].repeat(80), threshold));
+        assert!(has_voice(&[2000, -1800, 1500, -2200].repeat(80), threshold));
+        // 小音量麦克风：调低阈值后可识别
+        let quiet = [200, -180, 150, -220].repeat(80);
+        assert!(!has_voice(&quiet, threshold));
+        assert!(has_voice(&quiet, dbfs_to_rms(-50.0)));
+    }
+
+    #[test]
+    fn dictation_pauses_follow_session_threshold() {
+        let quiet: Vec<u8> = [200i16, -180, 150, -220].repeat(80).iter().flat_map(|s| s.to_le_bytes()).collect();
+        let interval = Duration::from_secs(1);
+
+        let mut default = Dictation::new(interval, Some(dbfs_to_rms(-40.0)));
+        default.observe(&quiet);
+        assert!(!default.voiced && default.at_pause);
+
+        // 小音量麦克风调低阈值后，连续听写也不把说话当作停顿
+        let mut sensitive = Dictation::new(interval, Some(dbfs_to_rms(-50.0)));
+        sensitive.observe(&quiet);
+        assert!(sensitive.voiced && !sensitive.at_pause);
+
+        let mut disabled = Dictation::new(interval, None);
+        disabled.observe(&[0; 640]);
+        assert!(disabled.voiced && !disabled.at_pause);
+    }
+
+    #[tokio::test]
+    async fn disabled_speech_threshold_always_counts_as_speech() {
+        let options = SessionOptions { speech_threshold_dbfs: None, ..SessionOptions::default() };
+        let (session, _endpoints) = new_session(&options, DEFAULT_SAMPLE_RATE);
+        session.send_audio_chunk(&[0; 320]).await.unwrap();
+        assert!(session.speech_detected());
+
+        let (session, _endpoints) = new_session(&SessionOptions::default(), DEFAULT_SAMPLE_RATE);
+        session.send_audio_chunk(&[0; 320]).await.unwrap();
+        assert!(!session.speech_detected());
+    }
+
+    #[tokio::test]
+    async fn dropping_pool_cancels_cleanup_task() {
+        let metrics = tokio::runtime::Handle::current().metrics();
+        let pool = ConnectionPool::new("test-key".to_string(), SessionOptions::default(), RealtimeOptions::default());
+        assert_eq!(metrics.num_alive_tasks(), 1);
+
+        drop(pool);
+        assert_eq!(wait_for_tasks(&metrics, 0).await, 0);
+    }
+
+    fn parse(payload: &str) -> RealtimeServerEvent {
+        serde_json::from_str(payload).expect("事件应能解析")
+    }
+
+    #[test]
+    fn parses_session_created() {
+        let payload = r#"{"event_id":"event_RfaHPYsm2MibGLdY7xkK5","type":"session.created","session":{"object":"realtime.session","model":"qwen3-asr-flash-realtime","modalities":["text"],"input_audio_format":"pcm16","input_audio_transcription":null,"turn_detection":{"type":"server_vad","threshold":0.5,"prefix_padding_ms":300,"silence_duration_ms":800},"id":"sess_Rk3UoAwXmtdb2BS3hcwVe"}}"#;
+        assert_eq!(parse(payload), RealtimeServerEvent::SessionCreated);
+    }
+
+    #[test]
+    fn parses_session_updated() {
+        let payload = r#"{"event_id":"event_Rfac1EqKDVUWl3lv1KjDi","type":"session.updated","session":{"object":"realtime.session","model":"qwen3-asr-flash-realtime","modalities":["text"],"input_audio_format":"pcm16","turn_detection":null,"id":"sess_Rk3UoAwXmtdb2BS3hcwVe"}}"#;
+        assert_eq!(parse(payload), RealtimeServerEvent::SessionUpdated);
+    }
+
+    #[test]
+    fn parses_vad_events() {
+        let started = r#"{"event_id":"event_RfaM6ndeKqPmAfQ6dmcy3","type":"input_audio_buffer.speech_started","audio_start_ms":1184,"item_id":"item_RfaM6vJi62SynP4s8cNgw"}"#;
+        let stopped = r#"{"event_id":"event_RfaMAqMCGTGO4VEuF1HrI","type":"input_audio_buffer.speech_stopped","audio_end_ms":3392,"item_id":"item_RfaM6vJi62SynP4s8cNgw"}"#;
+        assert_eq!(parse(started), RealtimeServerEvent::SpeechStarted);
+        assert_eq!(parse(stopped), RealtimeServerEvent::SpeechStopped);
+    }
+
+    #[test]
+    fn parses_committed() {
+        let payload = r#"{"event_id":"event_RfaMAw7SbGVmxDnmCWr7I","type":"input_audio_buffer.committed","previous_item_id":null,"item_id":"item_RfaM6vJi62SynP4s8cNgw"}"#;
+        assert_eq!(parse(payload), RealtimeServerEvent::InputAudioBufferCommitted);
+    }
+
+    #[test]
+    fn parses_transcription_completed() {
+        let payload = r#"{"event_id":"event_RfaMBw2LNkYcnG5NLh3Tf","type":"conversation.item.input_audio_transcription.completed","item_id":"item_RfaM6vJi62SynP4s8cNgw","content_index":0,"transcript":"今天天气不错，适合出去走走。"}"#;
+        assert_eq!(
+            parse(payload),
+            RealtimeServerEvent::TranscriptionCompleted {
+                transcript: Some("今天天气不错，适合出去走走。".to_string())
+            }
+        );
+    }
+
+    #[test]
+    fn parses_transcript_delta_and_done() {
+        let delta = r#"{"event_id":"event_B5x1","type":"response.audio_transcript.delta","response_id":"resp_001","item_id":"item_001","output_index":0,"content_index":0,"delta":"今天"}"#;
+        let done = r#"{"event_id":"event_B5x2","type":"response.audio_transcript.done","response_id":"resp_001","item_id":"item_001","output_index":0,"content_index":0,"transcript":"今天天气不错"}"#;
+        assert_eq!(
+            parse(delta),
+            RealtimeServerEvent::TranscriptDelta { delta: Some("今天".to_string()) }
+        );
+        assert_eq!(
+            parse(done),
+            RealtimeServerEvent::TranscriptDone { transcript: Some("今天天气不错".to_string()) }
+        );
+    }
+
+    #[test]
+    fn parses_response_done() {
+        let payload = r#"{"event_id":"event_B5x3","type":"response.done","response":{"id":"resp_001","object":"realtime.response","status":"completed","output":[],"usage":{"total_tokens":120,"input_tokens":100,"output_tokens":20}}}"#;
+        assert_eq!(parse(payload), RealtimeServerEvent::ResponseDone);
+    }
+
+    #[test]
+    fn parses_error_with_nested_details() {
+        let payload = r#"{"event_id":"event_RfaHJIGXcwrfi6xGxTTv0","type":"error","error":{"type":"invalid_request_error","code":"invalid_value","message":"Invalid value: 'pcm8'. Supported values are: 'pcm16'.","param":"session.input_audio_format","event_id":"event_1757059151341"}}"#;
+        assert_eq!(
+            parse(payload),
+            RealtimeServerEvent::Error {
+                error: ApiError {
+                    kind: Some("invalid_request_error".to_string()),
+                    code: Some("invalid_value".to_string()),
+                    message: Some("Invalid value: 'pcm8'. Supported values are: 'pcm16'.".to_string()),
+                }
+            }
+        );
+    }
+
+    #[test]
+    fn tolerates_missing_fields_and_unknown_events() {
+        let no_transcript = r#"{"type":"conversation.item.input_audio_transcription.completed","item_id":"item_001"}"#;
+        assert_eq!(
+            parse(no_transcript),
+            RealtimeServerEvent::TranscriptionCompleted { transcript: None }
+        );
+
+        let partial = r#"{"event_id":"event_RfaM9Mh1hXd9bOwDO6fVQ","type":"conversation.item.input_audio_transcription.text","item_id":"item_RfaM6vJi62SynP4s8cNgw","content_index":0,"text":"今天","stash":"天气"}"#;
+        assert_eq!(parse(partial), RealtimeServerEvent::Other);
+
+        let bare_error = r#"{"type":"error"}"#;
+        assert_eq!(parse(bare_error), RealtimeServerEvent::Error { error: ApiError::default() });
+    }
+
+    #[test]
+    fn event_ids_are_unique_across_threads() {
+        let handles: Vec<_> = (0..4)
+            .map(|_| std::thread::spawn(|| (0..1000).map(|_| next_event_id()).collect::<Vec<_>>()))
+            .collect();
+        let mut ids: Vec<String> = handles.into_iter().flat_map(|h| h.join().unwrap()).collect();
+        let total = ids.len();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), total);
+        assert!(ids.iter().all(|id| id.starts_with("event_")));
     }
 }
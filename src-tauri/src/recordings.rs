@@ -0,0 +1,152 @@
+// 录音文件保留（audio.keep_recordings，默认关闭）
+// 开启后（隐私模式下除外）每次转录前把 WAV 写到 recordings/{日期}/{时分秒}.wav，写盘放到后台线程与转录并行；
+// 启动时按 audio.keep_recordings_days 删除过期的日期目录
+
+use anyhow::Result;
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use std::path::{Path, PathBuf};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// 录音保留目录（配置目录下的 PushToTalk/recordings）
+pub fn recordings_dir() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir()
+        .ok_or_else(|| anyhow::anyhow!("无法获取配置目录"))?;
+    Ok(config_dir.join("PushToTalk").join("recordings"))
+}
+
+/// 本次录音的文件路径：{日期}/{时分秒}.wav，同一秒内已有文件时追加序号
+fn path_for(dir: &Path, now: NaiveDateTime) -> PathBuf {
+    let day_dir = dir.join(now.format(DATE_FORMAT).to_string());
+    let stem = now.format("%H%M%S").to_string();
+    let mut path = day_dir.join(format!("{}.wav", stem));
+    let mut seq = 1;
+    while path.exists() {
+        seq += 1;
+        path = day_dir.join(format!("{}-{}.wav", stem, seq));
+    }
+    path
+}
+
+/// 在后台写入本次录音，立即返回文件路径（不等待写盘完成）；写入失败只记日志，隐私模式下不写入
+pub fn keep(wav: Vec<u8>) -> Option<PathBuf> {
+    let dir = match recordings_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            tracing::warn!("保存录音文件失败: {}", e);
+            return None;
+        }
+    };
+    keep_in(&dir, wav)
+}
+
+pub(crate) fn keep_in(dir: &Path, wav: Vec<u8>) -> Option<PathBuf> {
+    if crate::privacy::enabled() {
+        tracing::debug!("隐私模式：不保留录音文件");
+        return None;
+    }
+    let path = path_for(dir, Local::now().naive_local());
+    let target = path.clone();
+    tokio::task::spawn_blocking(move || {
+        let result = target
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&target, &wav));
+        match result {
+            Ok(()) => tracing::info!("录音已保存: {}", target.display()),
+            Err(e) => tracing::warn!("保存录音文件失败 ({}): {}", target.display(), e),
+        }
+    });
+    Some(path)
+}
+
+/// 删除超过保留天数的日期目录（今天算第 1 天），返回删除的目录数；`keep_days` 为 0 时不清理
+/// 名称不是日期的目录与文件不动
+fn prune(dir: &Path, keep_days: u32, today: NaiveDate) -> usize {
+    if keep_days == 0 {
+        return 0;
+    }
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(date) = name.to_str().and_then(|name| NaiveDate::parse_from_str(name, DATE_FORMAT).ok()) else {
+            continue;
+        };
+        if (today - date).num_days() < keep_days as i64 || !entry.path().is_dir() {
+            continue;
+        }
+        match std::fs::remove_dir_all(entry.path()) {
+            Ok(()) => removed += 1,
+            Err(e) => tracing::warn!("清理过期录音失败 ({}): {}", entry.path().display(), e),
+        }
+    }
+    removed
+}
+
+/// 启动时清理过期录音（在后台线程进行，不拖慢启动）
+pub fn prune_expired(keep_days: u32) {
+    std::thread::spawn(move || {
+        let Ok(dir) = recordings_dir() else {
+            return;
+        };
+        let removed = prune(&dir, keep_days, Local::now().date_naive());
+        if removed > 0 {
+            tracing::info!("已清理 {} 天前的录音目录 {} 个", keep_days, removed);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_files_by_time_and_prunes_expired_days() {
+        let dir = std::env::temp_dir().join(format!("ptt-recordings-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let now = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap().and_hms_opt(9, 5, 7).unwrap();
+
+        let first = path_for(&dir, now);
+        assert_eq!(first, dir.join("2024-06-01").join("090507.wav"));
+        std::fs::create_dir_all(first.parent().unwrap()).unwrap();
+        std::fs::write(&first, b"wav").unwrap();
+        assert_eq!(path_for(&dir, now), dir.join("2024-06-01").join("090507-2.wav"));
+
+        for day in ["2024-05-25", "2024-05-26", "notes"] {
+            std::fs::create_dir_all(dir.join(day)).unwrap();
+        }
+        let today = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        assert_eq!(prune(&dir, 0, today), 0);
+        // 保留 7 天：6 月 1 日往前到 5 月 26 日
+        assert_eq!(prune(&dir, 7, today), 1);
+        assert!(!dir.join("2024-05-25").exists());
+        assert!(dir.join("2024-05-26").exists() && dir.join("notes").exists() && first.exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn privacy_mode_keeps_no_recording() {
+        let dir = std::env::temp_dir().join(format!("ptt-recordings-private-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        // 不改全局开关，只在当前线程开启隐私模式
+        assert_eq!(crate::privacy::with_enabled(true, || keep_in(&dir, b"wav".to_vec())), None);
+        assert!(!dir.exists());
+
+        let path = crate::privacy::with_enabled(false, || keep_in(&dir, b"wav".to_vec())).unwrap();
+        // 后台写盘，等待写完
+        for _ in 0..100 {
+            if std::fs::read(&path).is_ok_and(|data| data == b"wav") {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert_eq!(std::fs::read(&path).unwrap(), b"wav");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,219 @@
+// HTTP 模式长录音分段识别
+// 录音超过目标时长时，在每个切点之前的一小段窗口里找最安静的位置切开（避免把字切断），
+// 各段并行请求 HTTP 识别，按原顺序拼接结果；长录音的等待时间从"整段识别耗时"降到"最慢一段的耗时"
+
+use anyhow::Result;
+use futures_util::{stream, StreamExt, TryStreamExt};
+use hound::{WavReader, WavWriter};
+use std::future::Future;
+use std::io::Cursor;
+use std::time::Duration;
+
+use crate::audio_processing;
+use crate::config::HttpSegmentation;
+
+// 计算能量的窗口长度（毫秒），寻找切点时按半个窗口步进
+const ENERGY_WINDOW_MS: usize = 20;
+
+/// 按配置把内存 WAV 切成若干段；不需要分段或无法解析时原样返回一段
+pub fn split_wav(wav: Vec<u8>, config: &HttpSegmentation) -> Vec<Vec<u8>> {
+    if !config.enabled || config.segment_secs == 0 {
+        return vec![wav];
+    }
+    match try_split(&wav, config) {
+        Ok(Some(segments)) => segments,
+        Ok(None) => vec![wav],
+        Err(e) => {
+            tracing::warn!("录音分段失败，整段上传: {}", e);
+            vec![wav]
+        }
+    }
+}
+
+fn try_split(wav: &[u8], config: &HttpSegmentation) -> Result<Option<Vec<Vec<u8>>>> {
+    let reader = WavReader::new(Cursor::new(wav))?;
+    let spec = reader.spec();
+    // 录音器输出的都是 16-bit 单声道，其它格式不切
+    if spec.channels != 1 || spec.bits_per_sample != 16 || spec.sample_format != hound::SampleFormat::Int {
+        return Ok(None);
+    }
+    let samples = reader.into_samples::<i16>().collect::<Result<Vec<_>, _>>()?;
+
+    let rate = spec.sample_rate as usize;
+    let segment_len = config.segment_secs as usize * rate;
+    let search_len = (config.silence_search_secs as usize * rate).min(segment_len / 2);
+    let window = (ENERGY_WINDOW_MS * rate / 1000).max(1);
+    let cuts = cut_points(&samples, segment_len, search_len, window);
+    if cuts.is_empty() {
+        return Ok(None);
+    }
+
+    let mut segments = Vec::with_capacity(cuts.len() + 1);
+    let mut start = 0;
+    for end in cuts.into_iter().chain(std::iter::once(samples.len())) {
+        let mut cursor = Cursor::new(Vec::new());
+        {
+            let mut writer = WavWriter::new(&mut cursor, spec)?;
+            for &sample in &samples[start..end] {
+                writer.write_sample(sample)?;
+            }
+            writer.finalize()?;
+        }
+        segments.push(cursor.into_inner());
+        start = end;
+    }
+    tracing::info!(
+        "长录音分段: {:.1} 秒 -> {} 段",
+        samples.len() as f32 / rate as f32,
+        segments.len()
+    );
+    Ok(Some(segments))
+}
+
+/// 计算切点（样本下标）：每段到达目标长度前的 search_len 范围内，取能量最低的窗口中点
+fn cut_points(samples: &[i16], segment_len: usize, search_len: usize, window: usize) -> Vec<usize> {
+    let mut cuts = Vec::new();
+    let mut start = 0;
+    while samples.len() - start > segment_len {
+        let target = start + segment_len;
+        let from = target.saturating_sub(search_len).max(start + window);
+        let step = (window / 2).max(1);
+
+        let mut best = (u64::MAX, target);
+        let mut position = from;
+        while position + window <= target {
+            let energy: u64 = samples[position..position + window]
+                .iter()
+                .map(|&s| (s as i64 * s as i64) as u64)
+                .sum();
+            if energy < best.0 {
+                best = (energy, position + window / 2);
+            }
+            position += step;
+        }
+
+        cuts.push(best.1);
+        start = best.1;
+    }
+    cuts
+}
+
+/// 各段中最长一段的时长（提交前据此检查服务商的单次时长上限），无法解析的段按 0 计
+pub fn longest_segment(segments: &[Vec<u8>]) -> Duration {
+    segments
+        .iter()
+        .filter_map(|wav| audio_processing::wav_duration(wav))
+        .max()
+        .unwrap_or_default()
+}
+
+/// 并行识别各段（最多 max_parallel 个请求同时进行），按原顺序拼接；任一段失败则整体失败
+pub async fn transcribe_segments<F, Fut>(
+    segments: Vec<Vec<u8>>,
+    max_parallel: usize,
+    transcribe: F,
+) -> Result<String>
+where
+    F: FnMut(Vec<u8>) -> Fut,
+    Fut: Future<Output = Result<String>>,
+{
+    let total = segments.len();
+    if total <= 1 {
+        let mut transcribe = transcribe;
+        return match segments.into_iter().next() {
+            Some(wav) => transcribe(wav).await,
+            None => Ok(String::new()),
+        };
+    }
+
+    let texts: Vec<String> = stream::iter(segments)
+        .map(transcribe)
+        .buffered(max_parallel.max(1))
+        .enumerate()
+        .map(|(index, result)| {
+            result.map_err(|e| anyhow::anyhow!("第 {}/{} 段识别失败: {}", index + 1, total, e))
+        })
+        .try_collect()
+        .await?;
+    Ok(join_texts(&texts))
+}
+
+/// 拼接各段文本：中文直接相连，两侧都是英文单词 / 数字时补一个空格
+fn join_texts(texts: &[String]) -> String {
+    let mut joined = String::new();
+    for text in texts.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+        let needs_space = matches!(
+            (joined.chars().last(), text.chars().next()),
+            (Some(prev), Some(next)) if prev.is_ascii_graphic() && next.is_ascii_alphanumeric()
+        );
+        if needs_space {
+            joined.push(' ');
+        }
+        joined.push_str(text);
+    }
+    joined
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cuts_long_recordings_at_silence() {
+        // 50 秒 16kHz 语音（正弦波代替），在 17.2 秒和 35.5 秒处各有 300ms 停顿
+        let rate = 16000;
+        let pauses = [(17_200, 17_500), (35_500, 35_800)];
+        let samples: Vec<i16> = (0..50 * rate)
+            .map(|i| {
+                let ms = i * 1000 / rate;
+                if pauses.iter().any(|&(from, to)| (from..to).contains(&ms)) {
+                    0
+                } else {
+                    ((i as f32 * 0.05).sin() * 8000.0) as i16
+                }
+            })
+            .collect();
+
+        let cuts = cut_points(&samples, 20 * rate, 4 * rate, ENERGY_WINDOW_MS * rate / 1000);
+        assert_eq!(cuts.len(), 2);
+        for (cut, (from, to)) in cuts.iter().zip(pauses) {
+            let ms = cut * 1000 / rate;
+            assert!((from..to).contains(&ms), "切点 {}ms 不在停顿 {}..{}ms 内", ms, from, to);
+        }
+
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: rate as u32,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut cursor = Cursor::new(Vec::new());
+        {
+            let mut writer = WavWriter::new(&mut cursor, spec).unwrap();
+            samples.iter().for_each(|&s| writer.write_sample(s).unwrap());
+            writer.finalize().unwrap();
+        }
+        let wav = cursor.into_inner();
+        let config = HttpSegmentation { segment_secs: 20, silence_search_secs: 4, ..HttpSegmentation::default() };
+        let segments = split_wav(wav.clone(), &config);
+        let lengths: Vec<u32> = segments
+            .iter()
+            .map(|s| WavReader::new(Cursor::new(s)).unwrap().duration())
+            .collect();
+        assert_eq!(lengths.iter().sum::<u32>() as usize, samples.len());
+        let longest = *lengths.iter().max().unwrap() as u64 * 1000 / rate as u64;
+        assert_eq!(longest_segment(&segments), Duration::from_millis(longest));
+        assert!(longest_segment(&segments) < Duration::from_secs(20));
+
+        // 不超过目标时长的录音原样返回
+        let short = HttpSegmentation { segment_secs: 60, ..config };
+        let whole = split_wav(wav, &short);
+        assert_eq!(whole.len(), 1);
+        assert_eq!(longest_segment(&whole), Duration::from_secs(50));
+
+        assert_eq!(
+            join_texts(&["今天开会。".into(), " 讨论 Q3".into(), "plan.".into(), "".into()]),
+            "今天开会。讨论 Q3 plan."
+        );
+    }
+}
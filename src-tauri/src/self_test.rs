@@ -0,0 +1,94 @@
+// 端到端延迟自测
+// 同一段音频依次走已配置 Key 的 HTTP、实时、SenseVoice 识别，记录每条路径的耗时与返回文本，
+// 便于对比不同服务的速度以及优化前后的效果。内置音频是合成的"音节"，只用来测延迟，识别文本通常为空；
+// 需要对比识别结果时可指定一段真实录音
+
+use anyhow::Result;
+use std::future::Future;
+use std::time::Instant;
+
+use crate::audio_file;
+use crate::privacy;
+
+// 内置测试音频：16kHz 单声道，开头留 300ms 静音
+const CLIP_SAMPLE_RATE: u32 = 16000;
+const CLIP_SECS: f32 = 3.0;
+const CLIP_LEAD_SECS: f32 = 0.3;
+
+/// 一条识别路径的测量结果
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SelfTestResult {
+    pub provider: &'static str,
+    /// 从发起识别到拿到结果（或失败）的耗时，实时路径包含建立连接
+    pub elapsed_ms: u64,
+    pub text: Option<String>,
+    pub error: Option<String>,
+}
+
+/// 自测报告
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SelfTestReport {
+    /// 测试音频时长
+    pub audio_ms: u64,
+    /// 是否为内置测试音频
+    pub builtin_audio: bool,
+    pub results: Vec<SelfTestResult>,
+}
+
+/// 内置测试音频（16kHz 单声道 WAV）：140Hz 基频加几路谐波，按每秒 4 个"音节"起伏
+pub fn builtin_clip() -> Result<Vec<u8>> {
+    let rate = CLIP_SAMPLE_RATE as f32;
+    let samples: Vec<f32> = (0..(CLIP_SECS * rate) as usize)
+        .map(|i| {
+            let t = i as f32 / rate - CLIP_LEAD_SECS;
+            if t < 0.0 {
+                return 0.0;
+            }
+            let envelope = (std::f32::consts::PI * 4.0 * t).sin().abs();
+            let voice: f32 = [(1.0, 0.5), (2.0, 0.3), (3.0, 0.2), (5.0, 0.1)]
+                .iter()
+                .map(|&(harmonic, amplitude)| amplitude * (std::f32::consts::TAU * 140.0 * harmonic * t).sin())
+                .sum();
+            0.5 * envelope * voice
+        })
+        .collect();
+    audio_file::encode_wav(&samples)
+}
+
+/// 执行一条识别路径并计时，失败记入 error 而不中断其余路径
+pub async fn measure<F>(provider: &'static str, transcribe: F) -> SelfTestResult
+where
+    F: Future<Output = Result<String>>,
+{
+    let started = Instant::now();
+    let result = transcribe.await;
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+    match result {
+        Ok(text) => {
+            tracing::info!("自测 {}: {}ms, {}", provider, elapsed_ms, privacy::redact(&text));
+            SelfTestResult { provider, elapsed_ms, text: Some(text), error: None }
+        }
+        Err(e) => {
+            tracing::warn!("自测 {} 失败: {}ms, {}", provider, elapsed_ms, e);
+            SelfTestResult { provider, elapsed_ms, text: None, error: Some(e.to_string()) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_processing;
+
+    #[tokio::test]
+    async fn builtin_clip_is_audible_and_failures_are_recorded() {
+        let clip = builtin_clip().unwrap();
+        assert_eq!(audio_processing::wav_duration(&clip).unwrap().as_millis(), 3000);
+        assert!(!audio_processing::wav_levels(&clip).unwrap().is_silent(-30.0));
+
+        let ok = measure("qwen_http", async { Ok("你好".to_string()) }).await;
+        assert_eq!((ok.text.as_deref(), ok.error), (Some("你好"), None));
+        let failed = measure("sensevoice", async { Err(anyhow::anyhow!("401")) }).await;
+        assert_eq!((failed.text, failed.error.as_deref()), (None, Some("401")));
+    }
+}
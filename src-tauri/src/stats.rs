@@ -0,0 +1,184 @@
+// 使用统计模块
+// 累计录音时长、转录字数、各 provider 成功/失败次数，持久化到配置目录
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// 后台落盘的攒批间隔，连续多次更新只写一次文件
+const SAVE_DELAY_MS: u64 = 2000;
+
+// provider 统计标签
+pub const QWEN_REALTIME: &str = "qwen_realtime";
+pub const QWEN_HTTP: &str = "qwen_http";
+pub const SENSEVOICE: &str = "sensevoice";
+// HTTP 模式主备并行（千问 + SenseVoice，取先成功的一方）
+pub const QWEN_HTTP_WITH_FALLBACK: &str = "qwen_http+sensevoice";
+
+/// 单个 provider 的调用结果计数
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProviderStats {
+    pub success: u64,
+    pub failure: u64,
+}
+
+/// 累计使用统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageStats {
+    /// 总录音时长（毫秒）
+    #[serde(default)]
+    pub total_recording_ms: u64,
+    /// 总转录字数（不含空白）
+    #[serde(default)]
+    pub total_chars: u64,
+    /// 成功完成的转录次数
+    #[serde(default)]
+    pub transcriptions: u64,
+    /// 按 provider 统计的成功/失败次数
+    #[serde(default)]
+    pub providers: BTreeMap<String, ProviderStats>,
+}
+
+impl UsageStats {
+    fn path() -> Result<PathBuf> {
+        let config_dir = dirs::config_dir()
+            .ok_or_else(|| anyhow::anyhow!("无法获取配置目录"))?;
+        let app_dir = config_dir.join("PushToTalk");
+        std::fs::create_dir_all(&app_dir)?;
+        Ok(app_dir.join("usage_stats.json"))
+    }
+
+    pub fn load() -> Self {
+        Self::path()
+            .ok()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(Self::path()?, content)?;
+        Ok(())
+    }
+
+    fn add_recording(&mut self, duration: Duration) {
+        self.total_recording_ms += duration.as_millis() as u64;
+    }
+
+    fn add_success(&mut self, provider: &str, text: &str) {
+        self.transcriptions += 1;
+        self.total_chars += text.chars().filter(|c| !c.is_whitespace()).count() as u64;
+        self.providers.entry(provider.to_string()).or_default().success += 1;
+    }
+
+    fn add_failure(&mut self, provider: &str) {
+        self.providers.entry(provider.to_string()).or_default().failure += 1;
+    }
+}
+
+/// 线程安全的统计记录器：更新只改内存，由后台线程攒批落盘
+pub struct StatsTracker {
+    stats: Arc<Mutex<UsageStats>>,
+    save_tx: mpsc::Sender<()>,
+}
+
+impl StatsTracker {
+    pub fn new() -> Self {
+        Self::with_saver(UsageStats::load(), Duration::from_millis(SAVE_DELAY_MS), |stats| stats.save())
+    }
+
+    fn with_saver(
+        stats: UsageStats,
+        delay: Duration,
+        save: impl Fn(&UsageStats) -> Result<()> + Send + 'static,
+    ) -> Self {
+        let stats = Arc::new(Mutex::new(stats));
+        let (save_tx, save_rx) = mpsc::channel::<()>();
+
+        let worker_stats = Arc::clone(&stats);
+        std::thread::Builder::new()
+            .name("usage-stats".to_string())
+            .spawn(move || {
+                // 发送端释放（应用退出）时 recv 返回错误，线程结束
+                while save_rx.recv().is_ok() {
+                    std::thread::sleep(delay);
+                    save_rx.try_iter().for_each(drop);
+                    // 先复制快照，写文件期间不占用锁
+                    let snapshot = worker_stats.lock().unwrap().clone();
+                    if let Err(e) = save(&snapshot) {
+                        tracing::warn!("保存使用统计失败: {}", e);
+                    }
+                }
+            })
+            .expect("无法启动使用统计线程");
+
+        Self { stats, save_tx }
+    }
+
+    /// 录音提交识别时累计时长（误触、静音等未提交的录音不计）
+    pub fn record_recording(&self, duration: Duration) {
+        self.update(|stats| stats.add_recording(duration));
+    }
+
+    /// 转录成功时累计字数
+    pub fn record_success(&self, provider: &str, text: &str) {
+        self.update(|stats| stats.add_success(provider, text));
+    }
+
+    pub fn record_failure(&self, provider: &str) {
+        self.update(|stats| stats.add_failure(provider));
+    }
+
+    pub fn snapshot(&self) -> UsageStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    fn update(&self, apply: impl FnOnce(&mut UsageStats)) {
+        apply(&mut self.stats.lock().unwrap());
+        let _ = self.save_tx.send(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accumulates_chars_duration_and_provider_counts() {
+        let mut stats = UsageStats::default();
+        stats.add_recording(Duration::from_millis(1500));
+        stats.add_recording(Duration::from_millis(2500));
+        stats.add_success("qwen_realtime", "今天 天气\n不错");
+        stats.add_success("sensevoice", "hello world");
+        stats.add_failure("qwen_realtime");
+
+        assert_eq!(stats.total_recording_ms, 4000);
+        assert_eq!(stats.total_chars, 6 + 10);
+        assert_eq!(stats.transcriptions, 2);
+        assert_eq!(stats.providers["qwen_realtime"].success, 1);
+        assert_eq!(stats.providers["qwen_realtime"].failure, 1);
+        assert_eq!(stats.providers["sensevoice"].failure, 0);
+    }
+
+    #[test]
+    fn batches_saves_in_background() {
+        let (saved_tx, saved_rx) = mpsc::channel();
+        let tracker = StatsTracker::with_saver(UsageStats::default(), Duration::from_millis(100), move |stats| {
+            let _ = saved_tx.send(stats.transcriptions);
+            Ok(())
+        });
+        for _ in 0..10 {
+            tracker.record_success("qwen_realtime", "你好");
+        }
+        assert_eq!(tracker.snapshot().transcriptions, 10);
+
+        // 连续 10 次更新只落盘一次，写入的是最新快照
+        assert_eq!(saved_rx.recv_timeout(Duration::from_secs(2)).unwrap(), 10);
+        assert!(saved_rx.recv_timeout(Duration::from_millis(300)).is_err());
+    }
+}
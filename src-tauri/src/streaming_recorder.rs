@@ -1,86 +1,243 @@
 // 流式音频录制模块
 // 支持边录音边发送 PCM 数据块到 WebSocket
+// 采集流（如 cpal::Stream）不是 Send，只在专用录音线程中创建、持有和释放
 
 use anyhow::Result;
-use cpal::Stream;
-use crossbeam_channel::{Receiver, Sender, bounded};
+use crossbeam_channel::{Receiver, Sender, bounded, unbounded};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
 
-// API 要求的目标采样率
+use crate::audio_processing::{self, AudioLevel, AutoGain, ChannelProbe, DurationLimit, LevelMeter};
+use crate::audio_recorder::AudioClip;
+use crate::audio_source::{Capture, StreamInfo};
+use crate::config::{AudioConfig, AudioProcessing};
+use crate::denoise::{self, Denoiser};
+use crate::input_source::{self, ResidentInput};
+use crate::resampler::{self, Resampler};
+
+// HTTP 接口要求的采样率（完整音频 WAV）
 const TARGET_SAMPLE_RATE: u32 = 16000;
-// 每个音频块的样本数（0.2秒 @ 16kHz = 3200 样本）
-const CHUNK_SAMPLES: usize = 3200;
+// 音频块通道缓冲的音频时长
+const CHANNEL_BUFFER_MS: u32 = 10_000;
+
+/// 按块时长与采样率计算每块样本数（块时长由 AudioConfig::effective_chunk_ms 钳制）
+/// 以整数个 i16 样本切分，编码成字节后总是偶数长度，不会把一个样本拆到两块
+pub fn chunk_samples(chunk_ms: u32, sample_rate: u32) -> usize {
+    chunk_ms as usize * (sample_rate as usize / 1000)
+}
+
+/// 流式转换器：把设备的交错样本转成 `output_rate` 的单声道 PCM 块
+/// 混缩（按声道选择）→ 自动增益 → 重采样 → 降噪，各级都跨回调保留状态，块边界不会爆音
+struct ChunkConverter {
+    channels: u16,
+    processing: AudioProcessing,
+    // 流式自动增益：按滑动窗口峰值逐块调整
+    auto_gain: Option<AutoGain>,
+    // 有状态的分块重采样器，跨回调保留滤波器历史（与设备采样率相同时直传）
+    resampler: Resampler,
+    // 流式降噪：重采样之后按 48kHz 下 480 样本的整帧处理，跨回调保留不满一帧的样本
+    denoiser: Option<Denoiser>,
+    // 累积的样本，满一块才输出
+    pending: Vec<f32>,
+    chunk_samples: usize,
+}
+
+impl ChunkConverter {
+    fn new(audio: &AudioConfig, processing: AudioProcessing, device_sample_rate: u32, channels: u16, output_rate: u32) -> Self {
+        Self {
+            channels,
+            processing,
+            auto_gain: processing.agc.then(|| AutoGain::new(
+                device_sample_rate, audio.agc_target_dbfs, audio.agc_max_gain_db, audio.silence_threshold_dbfs,
+            )),
+            resampler: Resampler::new(device_sample_rate, output_rate),
+            denoiser: processing.denoise.then(|| Denoiser::new(output_rate)),
+            pending: Vec::new(),
+            chunk_samples: chunk_samples(audio.effective_chunk_ms(), output_rate),
+        }
+    }
+
+    /// 输入一段交错样本，返回已凑满的音频块
+    fn push(&mut self, data: &[f32]) -> Vec<Vec<i16>> {
+        let mut mono = audio_processing::process(data, self.channels, &self.processing);
+        if let Some(agc) = self.auto_gain.as_mut() {
+            agc.process(&mut mono);
+        }
+        let mut resampled = self.resampler.process(&mono);
+        if let Some(denoiser) = self.denoiser.as_mut() {
+            resampled = denoiser.process(&resampled);
+        }
+        self.pending.extend(resampled);
+
+        let full = self.pending.len() / self.chunk_samples * self.chunk_samples;
+        self.pending
+            .drain(..full)
+            .collect::<Vec<_>>()
+            .chunks(self.chunk_samples)
+            .map(StreamingRecorder::f32_to_i16)
+            .collect()
+    }
+}
+
+/// 按设备参数生成本次录音的采集回调（在录音线程中、采集流建立前调用）
+type CallbackBuilder = Box<dyn FnOnce(&StreamInfo) -> Box<dyn FnMut(&[f32]) + Send> + Send>;
+
+/// 录音线程命令：采集流与常驻流（cpal::Stream）不是 Send，只在专用线程中创建、持有和释放
+enum StreamCommand {
+    Start {
+        device: Option<String>,
+        audio: Arc<AudioConfig>,
+        build: CallbackBuilder,
+        // 采集流错误（设备被拔出等）上报到上层
+        errors: mpsc::UnboundedSender<String>,
+        // 回复实际的设备参数（含预滚动时长）
+        reply: Sender<Result<StreamInfo>>,
+    },
+    // 释放本次录音的采集流（常驻模式下只取下回调），完成后回执
+    Stop { reply: Sender<()> },
+    // 音频配置更新：空闲时按 keep_stream_open 建立或释放常驻采集流
+    Standby {
+        device: Option<String>,
+        audio: Arc<AudioConfig>,
+    },
+    // 查询输入设备的采样率
+    SampleRate {
+        device: Option<String>,
+        audio: Arc<AudioConfig>,
+        reply: Sender<Result<u32>>,
+    },
+}
+
+/// 录音线程：按命令建立 / 释放采集流，持有常驻流；命令通道关闭（录音器释放）时退出并释放全部采集流
+fn run_stream_thread(commands: Receiver<StreamCommand>) {
+    let mut resident: Option<ResidentInput> = None;
+    let mut capture: Option<Capture> = None;
+    while let Ok(command) = commands.recv() {
+        match command {
+            StreamCommand::Start { device, audio, build, errors, reply } => {
+                drop(capture.take());
+                let start = || -> Result<StreamInfo> {
+                    // 按 audio.source 选择麦克风、系统回环或两者混合；常驻模式下复用已打开的采集流
+                    let input = input_source::open(&mut resident, &audio, device.as_deref())?;
+                    let info = input.info().clone();
+                    let on_samples = build(&info);
+                    let err_fn = move |err: cpal::StreamError| {
+                        tracing::error!("录音流错误: {}", err);
+                        let _ = errors.send(err.to_string());
+                    };
+                    let (stream, pre_roll) = input.start(on_samples, err_fn)?;
+                    capture = Some(stream);
+                    Ok(StreamInfo { pre_roll, ..info })
+                };
+                let _ = reply.send(start());
+            }
+            StreamCommand::Stop { reply } => {
+                drop(capture.take());
+                let _ = reply.send(());
+            }
+            StreamCommand::Standby { device, audio } => {
+                // 录音中不动采集流，下次开始录音时按新配置处理
+                if capture.is_none() {
+                    input_source::standby(&mut resident, &audio, device.as_deref());
+                }
+            }
+            StreamCommand::SampleRate { device, audio, reply } => {
+                let rate = match resident.as_ref() {
+                    Some(resident) => Ok(resident.info().sample_rate),
+                    None => input_source::prepare(&audio, device.as_deref()).map(|input| input.info.sample_rate),
+                };
+                let _ = reply.send(rate);
+            }
+        }
+    }
+}
 
 /// 流式音频录制器
 /// 边录音边输出 PCM 数据块，同时保留完整音频用于备用方案
+/// 采集流（mix 模式下为麦克风与回环两路）与 audio.keep_stream_open 的常驻流都在 "streaming-recorder" 线程中持有
 pub struct StreamingRecorder {
     device_sample_rate: u32,
     channels: u16,
+    // 音频处理参数（全局默认 + 按设备覆盖），开始录音时按当前设备合并
+    audio: AudioConfig,
+    processing: AudioProcessing,
     is_recording: Arc<Mutex<bool>>,
-    stream: Option<Stream>,
+    // 发往录音线程的命令
+    commands: Sender<StreamCommand>,
     // 用于流式输出的通道
     chunk_sender: Option<Sender<Vec<i16>>>,
     // 累积的完整音频数据（用于备用方案）
     full_audio_data: Arc<Mutex<Vec<f32>>>,
+    // 为 false 时不再累积完整音频（分段结果已送达、不会再整段回退）
+    keep_full_audio: Arc<AtomicBool>,
+    // 录音流错误通道（设备被拔出等），由上层取走后监听
+    device_errors: Option<mpsc::UnboundedReceiver<String>>,
+    // 录音达到最长时长的通知通道，由上层取走后监听
+    duration_limit: Option<mpsc::UnboundedReceiver<()>>,
+    // 录音电平通道（audio.level_interval_ms 为 0 时为 None），由上层取走后转发给前端
+    levels: Option<mpsc::UnboundedReceiver<AudioLevel>>,
+    // 本次录音选择的设备找不到、已回退默认设备时为该设备名
+    missing_device: Option<String>,
+    // 本次录音开头拼接的预滚动时长
+    pre_roll: std::time::Duration,
 }
 
 impl StreamingRecorder {
     pub fn new() -> Result<Self> {
+        let (commands, command_rx) = unbounded();
+        std::thread::Builder::new()
+            .name("streaming-recorder".to_string())
+            .spawn(move || run_stream_thread(command_rx))?;
+
         Ok(Self {
             device_sample_rate: 48000,
             channels: 1,
+            audio: AudioConfig::default(),
+            processing: AudioConfig::default().defaults(),
             is_recording: Arc::new(Mutex::new(false)),
-            stream: None,
+            commands,
             chunk_sender: None,
             full_audio_data: Arc::new(Mutex::new(Vec::new())),
+            keep_full_audio: Arc::new(AtomicBool::new(true)),
+            device_errors: None,
+            duration_limit: None,
+            levels: None,
+            missing_device: None,
+            pre_roll: std::time::Duration::ZERO,
         })
     }
 
-    /// 将音频从设备采样率降采样到目标采样率 (16kHz)
-    fn resample(input: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
-        if from_rate == to_rate {
-            return input.to_vec();
-        }
-
-        let ratio = from_rate as f64 / to_rate as f64;
-        let output_len = (input.len() as f64 / ratio) as usize;
-        let mut output = Vec::with_capacity(output_len);
-
-        for i in 0..output_len {
-            let src_idx = i as f64 * ratio;
-            let idx_floor = src_idx.floor() as usize;
-            let idx_ceil = (idx_floor + 1).min(input.len().saturating_sub(1));
-            let frac = src_idx - idx_floor as f64;
-
-            if idx_floor < input.len() {
-                let sample = input[idx_floor] as f64 * (1.0 - frac)
-                    + input.get(idx_ceil).copied().unwrap_or(0.0) as f64 * frac;
-                output.push(sample as f32);
-            }
-        }
-
-        output
+    /// 每个音频块的时长（毫秒），取自 `audio.chunk_ms` 并已钳制
+    pub fn chunk_ms(&self) -> u32 {
+        self.audio.effective_chunk_ms()
     }
 
-    /// 将多声道音频转换为单声道
-    fn to_mono(input: &[f32], channels: u16) -> Vec<f32> {
-        if channels == 1 {
-            return input.to_vec();
-        }
-
-        let channels = channels as usize;
-        let output_len = input.len() / channels;
-        let mut output = Vec::with_capacity(output_len);
+    /// 最短录音时长，取自 `audio.min_recording_ms`；录音时长包含开头的预滚动，这里一并加上
+    pub fn min_recording(&self) -> std::time::Duration {
+        self.audio.min_recording() + self.pre_roll
+    }
 
-        for i in 0..output_len {
-            let mut sum = 0.0f32;
-            for ch in 0..channels {
-                sum += input[i * channels + ch];
-            }
-            output.push(sum / channels as f32);
-        }
+    /// 更新音频处理参数（下次开始录音时生效）；空闲时按 keep_stream_open 打开或释放常驻录音流
+    pub fn set_audio_config(&mut self, audio: AudioConfig) {
+        let _ = self.commands.send(StreamCommand::Standby {
+            device: audio.input_device.clone(),
+            audio: Arc::new(audio.clone()),
+        });
+        self.audio = audio;
+    }
 
-        output
+    /// 查询输入设备的采样率（开始录音前用于协商实时会话的采样率）
+    pub fn device_sample_rate(&self) -> Result<u32> {
+        let (reply, reply_rx) = bounded(1);
+        self.commands
+            .send(StreamCommand::SampleRate {
+                device: self.audio.input_device.clone(),
+                audio: Arc::new(self.audio.clone()),
+                reply,
+            })
+            .map_err(|_| anyhow::anyhow!("录音线程已退出"))?;
+        reply_rx.recv().map_err(|_| anyhow::anyhow!("录音线程已退出"))?
     }
 
     /// 将 f32 样本转换为 i16
@@ -91,198 +248,179 @@ impl StreamingRecorder {
     }
 
     /// 启动流式录音，返回音频块接收通道
-    pub fn start_streaming(&mut self) -> Result<Receiver<Vec<i16>>> {
-        use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-
+    /// 音频块按 `output_rate` 输出：与设备采样率相同时直接发送原始 PCM，否则先重采样
+    pub fn start_streaming(&mut self, output_rate: u32) -> Result<Receiver<Vec<i16>>> {
         tracing::info!("开始流式录音...");
 
         // 清空之前的数据
         self.full_audio_data.lock().unwrap().clear();
+        self.keep_full_audio.store(true, Ordering::SeqCst);
         *self.is_recording.lock().unwrap() = true;
 
-        // 创建音频块通道（缓冲 50 个块，约 10 秒）
-        let (chunk_tx, chunk_rx) = bounded::<Vec<i16>>(50);
+        // 创建音频块通道（缓冲约 10 秒音频）
+        let chunk_ms = self.chunk_ms();
+        let chunk_samples = chunk_samples(chunk_ms, output_rate);
+        let capacity = (CHANNEL_BUFFER_MS / chunk_ms) as usize;
+        let (chunk_tx, chunk_rx) = bounded::<Vec<i16>>(capacity);
         self.chunk_sender = Some(chunk_tx.clone());
 
-        let host = cpal::default_host();
-        let device = host
-            .default_input_device()
-            .ok_or_else(|| anyhow::anyhow!("没有找到默认音频输入设备"))?;
-
-        let supported_config = device
-            .default_input_config()
-            .map_err(|e| anyhow::anyhow!("无法获取默认音频配置: {}", e))?;
-
-        let config = supported_config.config();
-        self.device_sample_rate = config.sample_rate.0;
-        self.channels = config.channels;
-
-        tracing::info!("流式录音配置: 采样率={}Hz, 声道={}, 目标采样率={}Hz, 块大小={}样本",
-            self.device_sample_rate, self.channels, TARGET_SAMPLE_RATE, CHUNK_SAMPLES);
-
+        // 设备错误（如录音中拔出麦克风）上报到通道，由上层中止录音
+        let (error_tx, error_rx) = mpsc::unbounded_channel::<String>();
+        self.device_errors = Some(error_rx);
+        // 达到最长录音时长时停止采集（不再发送音频块），由上层按松开按键的流程停止并提交
+        let (limit_tx, limit_rx) = mpsc::unbounded_channel::<()>();
+        self.duration_limit = Some(limit_rx);
+        // 录音电平转发到上层，供前端画音量条；录音流释放后通道关闭
+        let (level_tx, level_rx) = mpsc::unbounded_channel::<AudioLevel>();
+        self.levels = (self.audio.level_interval_ms > 0).then_some(level_rx);
+
+        let audio = Arc::new(self.audio.clone());
         let is_recording = Arc::clone(&self.is_recording);
         let full_audio_data = Arc::clone(&self.full_audio_data);
-        let device_sample_rate = self.device_sample_rate;
-        let channels = self.channels;
-
-        // 用于累积样本直到达到块大小
-        let pending_samples: Arc<Mutex<Vec<f32>>> = Arc::new(Mutex::new(Vec::new()));
-        let pending_samples_clone = Arc::clone(&pending_samples);
-
-        let err_fn = |err| tracing::error!("录音流错误: {}", err);
-
-        let stream = match supported_config.sample_format() {
-            cpal::SampleFormat::F32 => device.build_input_stream(
-                &config,
-                move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                    if !*is_recording.lock().unwrap() {
-                        return;
+        let keep_full_audio = Arc::clone(&self.keep_full_audio);
+        let callback_audio = Arc::clone(&audio);
+        // 采集回调按实际打开的设备（采样率、声道、按设备的处理参数）在录音线程中生成
+        let build: CallbackBuilder = Box::new(move |info: &StreamInfo| {
+            let audio = callback_audio;
+            let (device_sample_rate, channels) = (info.sample_rate, info.channels);
+            let processing = audio.for_device(&info.device_name);
+            audio_processing::log_processing(&info.device_name, channels, &processing);
+            let mut converter = ChunkConverter::new(&audio, processing, device_sample_rate, channels, output_rate);
+            let mut limit = DurationLimit::new(audio.max_duration_secs, device_sample_rate, channels, limit_tx);
+            let mut meter = LevelMeter::new(audio.level_interval_ms, device_sample_rate, channels, level_tx);
+            // 多声道设备检测前 0.5 秒各声道的能量，声道选择不合适时提示
+            let mut probe = ChannelProbe::new(device_sample_rate, channels, processing.channel_selection);
+
+            // 采集回调：样本已转成交错的 f32（mix 模式下已叠加系统回环）
+            Box::new(move |data: &[f32]| {
+                if !*is_recording.lock().unwrap() {
+                    return;
+                }
+                let mut data = data.to_vec();
+                data.truncate(limit.admit(data.len()));
+                if data.is_empty() {
+                    return;
+                }
+                audio_processing::apply_gain(&mut data, audio_processing::input_gain());
+
+                // 保存原始数据用于备用方案
+                if keep_full_audio.load(Ordering::Relaxed) {
+                    full_audio_data.lock().unwrap().extend_from_slice(&data);
+                }
+                if let Some(meter) = meter.as_mut() {
+                    meter.push(&data);
+                }
+                if let Some(probe) = probe.as_mut() {
+                    probe.push(&data);
+                }
+
+                for chunk in converter.push(&data) {
+                    if chunk_tx.try_send(chunk).is_err() {
+                        tracing::warn!("音频块通道已满，丢弃块");
                     }
+                }
+            })
+        });
+
+        let (reply, reply_rx) = bounded(1);
+        self.commands
+            .send(StreamCommand::Start {
+                device: self.audio.input_device.clone(),
+                audio,
+                build,
+                errors: error_tx,
+                reply,
+            })
+            .map_err(|_| anyhow::anyhow!("录音线程已退出"))?;
+        let info = reply_rx.recv().map_err(|_| anyhow::anyhow!("录音线程已退出"))??;
+
+        self.missing_device = info.missing_device.clone();
+        self.device_sample_rate = info.sample_rate;
+        self.channels = info.channels;
+        self.processing = self.audio.for_device(&info.device_name);
+        self.pre_roll = info.pre_roll;
+
+        tracing::info!("流式录音配置: 采样率={}Hz, 声道={}, 输出采样率={}Hz{}, 块大小={}样本",
+            self.device_sample_rate, self.channels, output_rate,
+            if output_rate == self.device_sample_rate { "（直传）" } else { "" },
+            chunk_samples);
 
-                    // 保存原始数据用于备用方案
-                    full_audio_data.lock().unwrap().extend_from_slice(data);
+        tracing::info!("流式录音已启动");
+        Ok(chunk_rx)
+    }
 
-                    // 处理数据：转单声道 + 降采样
-                    let mono = Self::to_mono(data, channels);
-                    let resampled = Self::resample(&mono, device_sample_rate, TARGET_SAMPLE_RATE);
+    /// 取走本次录音的设备错误通道（录音流释放后通道随之关闭）
+    pub fn take_device_errors(&mut self) -> Option<mpsc::UnboundedReceiver<String>> {
+        self.device_errors.take()
+    }
 
-                    // 累积样本
-                    let mut pending = pending_samples_clone.lock().unwrap();
-                    pending.extend(resampled);
+    /// 取走本次录音达到最长时长的通知通道（录音流释放后通道随之关闭）
+    pub fn take_duration_limit(&mut self) -> Option<mpsc::UnboundedReceiver<()>> {
+        self.duration_limit.take()
+    }
 
-                    // 当累积足够样本时，发送块
-                    while pending.len() >= CHUNK_SAMPLES {
-                        let chunk: Vec<f32> = pending.drain(..CHUNK_SAMPLES).collect();
-                        let chunk_i16 = Self::f32_to_i16(&chunk);
+    /// 取走本次录音的电平通道（录音流释放后通道随之关闭）
+    pub fn take_levels(&mut self) -> Option<mpsc::UnboundedReceiver<AudioLevel>> {
+        self.levels.take()
+    }
 
-                        if chunk_tx.try_send(chunk_i16).is_err() {
-                            tracing::warn!("音频块通道已满，丢弃块");
-                        }
-                    }
-                },
-                err_fn,
-                None,
-            )?,
-            cpal::SampleFormat::I16 => {
-                let is_recording_i16 = Arc::clone(&is_recording);
-                let full_audio_data_i16 = Arc::clone(&full_audio_data);
-                let pending_samples_i16 = Arc::clone(&pending_samples);
-                let chunk_tx_i16 = chunk_tx.clone();
-
-                device.build_input_stream(
-                    &config,
-                    move |data: &[i16], _: &cpal::InputCallbackInfo| {
-                        if !*is_recording_i16.lock().unwrap() {
-                            return;
-                        }
-
-                        // 转换为 f32
-                        let f32_data: Vec<f32> = data.iter()
-                            .map(|&s| s as f32 / i16::MAX as f32)
-                            .collect();
-
-                        // 保存原始数据
-                        full_audio_data_i16.lock().unwrap().extend(&f32_data);
-
-                        // 处理数据
-                        let mono = Self::to_mono(&f32_data, channels);
-                        let resampled = Self::resample(&mono, device_sample_rate, TARGET_SAMPLE_RATE);
-
-                        // 累积样本
-                        let mut pending = pending_samples_i16.lock().unwrap();
-                        pending.extend(resampled);
-
-                        while pending.len() >= CHUNK_SAMPLES {
-                            let chunk: Vec<f32> = pending.drain(..CHUNK_SAMPLES).collect();
-                            let chunk_i16 = Self::f32_to_i16(&chunk);
-
-                            if chunk_tx_i16.try_send(chunk_i16).is_err() {
-                                tracing::warn!("音频块通道已满，丢弃块");
-                            }
-                        }
-                    },
-                    err_fn,
-                    None,
-                )?
-            }
-            cpal::SampleFormat::U16 => {
-                let is_recording_u16 = Arc::clone(&is_recording);
-                let full_audio_data_u16 = Arc::clone(&full_audio_data);
-                let pending_samples_u16 = Arc::clone(&pending_samples);
-                let chunk_tx_u16 = chunk_tx.clone();
-
-                device.build_input_stream(
-                    &config,
-                    move |data: &[u16], _: &cpal::InputCallbackInfo| {
-                        if !*is_recording_u16.lock().unwrap() {
-                            return;
-                        }
-
-                        // 转换为 f32
-                        let f32_data: Vec<f32> = data.iter()
-                            .map(|&s| (s as f32 - 32768.0) / 32768.0)
-                            .collect();
-
-                        // 保存原始数据
-                        full_audio_data_u16.lock().unwrap().extend(&f32_data);
-
-                        // 处理数据
-                        let mono = Self::to_mono(&f32_data, channels);
-                        let resampled = Self::resample(&mono, device_sample_rate, TARGET_SAMPLE_RATE);
-
-                        // 累积样本
-                        let mut pending = pending_samples_u16.lock().unwrap();
-                        pending.extend(resampled);
-
-                        while pending.len() >= CHUNK_SAMPLES {
-                            let chunk: Vec<f32> = pending.drain(..CHUNK_SAMPLES).collect();
-                            let chunk_i16 = Self::f32_to_i16(&chunk);
-
-                            if chunk_tx_u16.try_send(chunk_i16).is_err() {
-                                tracing::warn!("音频块通道已满，丢弃块");
-                            }
-                        }
-                    },
-                    err_fn,
-                    None,
-                )?
-            }
-            _ => return Err(anyhow::anyhow!("不支持的采样格式")),
-        };
+    /// 取走本次录音找不到的输入设备名（已回退默认设备）
+    pub fn take_missing_device(&mut self) -> Option<String> {
+        self.missing_device.take()
+    }
 
-        stream.play()?;
-        self.stream = Some(stream);
+    /// 分段结果已送达、不会再整段回退时调用：停止累积完整音频并释放已缓冲的部分
+    /// 连续听写可能持续很久，完整缓冲不应一直留在内存中
+    pub fn release_full_audio(&self) {
+        self.keep_full_audio.store(false, Ordering::SeqCst);
+        let released = std::mem::take(&mut *self.full_audio_data.lock().unwrap());
+        tracing::info!("已释放完整录音缓冲 ({} 样本)", released.len());
+    }
 
-        tracing::info!("流式录音已启动");
-        Ok(chunk_rx)
+    /// 本次录音的完整音频是否已释放
+    pub fn full_audio_released(&self) -> bool {
+        !self.keep_full_audio.load(Ordering::SeqCst)
     }
 
-    /// 停止流式录音，返回完整的音频数据（WAV 格式，用于备用方案）
-    pub fn stop_streaming(&mut self) -> Result<Vec<u8>> {
+    /// 停止流式录音，返回完整的音频数据（WAV 格式及时长，用于备用方案）
+    pub fn stop_streaming(&mut self) -> Result<AudioClip> {
         use hound::{WavSpec, WavWriter};
         use std::io::Cursor;
 
         tracing::info!("停止流式录音...");
 
         *self.is_recording.lock().unwrap() = false;
-        self.stream = None;
+        // 录音线程释放采集流后回执，此后回调不会再写入
+        let (reply, reply_rx) = bounded(1);
+        if self.commands.send(StreamCommand::Stop { reply }).is_ok() {
+            let _ = reply_rx.recv();
+        }
         self.chunk_sender = None;
 
-        // 等待数据写入完成
-        std::thread::sleep(std::time::Duration::from_millis(100));
-
         // 获取完整音频数据
-        let raw_audio = self.full_audio_data.lock().unwrap().clone();
+        // 泄漏修复：取走而非克隆，避免长录音的大缓冲区在两次录音之间一直占用内存
+        let raw_audio = std::mem::take(&mut *self.full_audio_data.lock().unwrap());
 
         if raw_audio.is_empty() {
             return Err(anyhow::anyhow!("没有录制到音频数据"));
         }
 
-        // 转换为单声道
-        let mono_audio = Self::to_mono(&raw_audio, self.channels);
+        // 转换为单声道并应用增益
+        let mono_audio = audio_processing::process(&raw_audio, self.channels, &self.processing);
 
         // 降采样到 16kHz
-        let resampled_audio = Self::resample(&mono_audio, self.device_sample_rate, TARGET_SAMPLE_RATE);
+        let mut resampled_audio = resampler::resample(&mono_audio, self.device_sample_rate, TARGET_SAMPLE_RATE);
+
+        // 降噪
+        if self.processing.denoise {
+            resampled_audio = denoise::denoise(&resampled_audio, TARGET_SAMPLE_RATE);
+        }
+
+        // 自动增益：整段归一化（静音录音不放大）
+        if self.processing.agc {
+            audio_processing::normalize(&mut resampled_audio, self.audio.agc_target_dbfs,
+                self.audio.agc_max_gain_db, self.audio.silence_threshold_dbfs);
+        }
 
         // 写入 WAV 格式
         let spec = WavSpec {
@@ -295,17 +433,16 @@ impl StreamingRecorder {
         let mut cursor = Cursor::new(Vec::new());
         {
             let mut writer = WavWriter::new(&mut cursor, spec)?;
-            for &sample in resampled_audio.iter() {
-                let amplitude = (sample * i16::MAX as f32).clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+            for amplitude in audio_processing::quantize(&resampled_audio, self.audio.dither) {
                 writer.write_sample(amplitude)?;
             }
             writer.finalize()?;
         }
 
-        let wav_data = cursor.into_inner();
-        tracing::info!("流式录音停止，完整音频: {} bytes", wav_data.len());
+        let clip = AudioClip::new(cursor.into_inner(), resampled_audio.len(), TARGET_SAMPLE_RATE);
+        tracing::info!("流式录音停止，完整音频: {} bytes, 时长: {}ms", clip.wav.len(), clip.duration_ms);
 
-        Ok(wav_data)
+        Ok(clip)
     }
 
     /// 检查是否正在录音
@@ -314,6 +451,46 @@ impl StreamingRecorder {
     }
 }
 
-// 实现 Send 和 Sync traits
-unsafe impl Send for StreamingRecorder {}
-unsafe impl Sync for StreamingRecorder {}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_samples_follow_duration_within_bounds() {
+        let mut audio = AudioConfig::default();
+        assert_eq!(chunk_samples(audio.effective_chunk_ms(), 16000), 3200);
+        assert_eq!(chunk_samples(audio.effective_chunk_ms(), 48000), 9600);
+        assert_eq!(chunk_samples(50, 16000), 800);
+
+        audio.chunk_ms = 0;
+        assert_eq!(chunk_samples(audio.effective_chunk_ms(), 16000), 640);
+        audio.chunk_ms = 60_000;
+        assert_eq!(chunk_samples(audio.effective_chunk_ms(), 16000), 16000);
+    }
+
+    #[test]
+    fn converts_device_rates_to_16k_mono_chunks() {
+        let audio = AudioConfig::default();
+        for rate in [44100, 48000, 32000] {
+            // 立体声 1 秒 1kHz 正弦：左 0.6、右 0.2，混缩后幅度 0.4；按不整齐的回调大小送入
+            let tone = |frame: usize| (frame as f64 / rate as f64 * 1000.0 * std::f64::consts::TAU).sin() as f32;
+            let input: Vec<f32> = (0..rate as usize).flat_map(|frame| [tone(frame) * 0.6, tone(frame) * 0.2]).collect();
+            let mut converter = ChunkConverter::new(&audio, audio.defaults(), rate, 2, 16000);
+            let chunks: Vec<Vec<i16>> = input
+                .chunks((rate as usize / 100 + 7) * 2)
+                .flat_map(|data| converter.push(data))
+                .collect();
+
+            // 200ms 一块，滤波器滞后不到一块，1 秒输出 4 块整
+            assert_eq!(chunks.len(), 4, "{}Hz", rate);
+            assert!(chunks.iter().all(|chunk| chunk.len() == 3200));
+
+            // 与 16kHz 下的理想正弦逐点比较（跳过开头滤波器补零的过渡段），块边界处也没有跳变
+            let output: Vec<f32> = chunks.concat().iter().map(|&s| s as f32 / i16::MAX as f32).collect();
+            let error = (320..output.len())
+                .map(|n| (output[n] - 0.4 * (n as f64 / 16.0 * std::f64::consts::TAU).sin() as f32).abs())
+                .fold(0f32, f32::max);
+            assert!(error < 0.01, "{}Hz: {}", rate, error);
+        }
+    }
+}
@@ -5,9 +5,31 @@ use std::thread;
 use std::time::Duration;
 use anyhow::Result;
 
+use crate::active_window::{self, WindowId};
+use crate::config::{SplitDelimiter, SplitMessagesConfig};
+use crate::hotkey_service;
+use crate::privacy;
+
+/// 多条消息之间的发送间隔
+const MESSAGE_INTERVAL_MS: u64 = 300;
+
+/// 句末标点，按句拆分时作为断点（标点保留在前一句末尾）
+const SENTENCE_TERMINATORS: &[char] = &['。', '！', '？', '；', '!', '?', ';', '…'];
+
 pub struct TextInserter {
     clipboard: Clipboard,
     enigo: Enigo,
+    // 录音开始时的前台窗口，插入前若焦点已离开则切回该窗口
+    target_window: Option<WindowId>,
+    // 最近一次插入的文本，用于撤销
+    last_insert: Option<LastInsert>,
+}
+
+/// 最近一次插入：文本、实际粘贴到的窗口、插入完成时监听到的按键次数
+struct LastInsert {
+    text: String,
+    window: Option<WindowId>,
+    key_presses: u64,
 }
 
 impl TextInserter {
@@ -15,11 +37,42 @@ impl TextInserter {
         Ok(Self {
             clipboard: Clipboard::new()?,
             enigo: Enigo::new(&Settings::default())?,
+            target_window: None,
+            last_insert: None,
         })
     }
 
+    /// 记录当前前台窗口作为插入目标（按下快捷键开始录音时调用）
+    pub fn capture_target(&mut self) {
+        self.target_window = active_window::foreground_window();
+        tracing::debug!("记录插入目标窗口: {:?}", self.target_window);
+    }
+
+    /// 插入目标窗口，未记录时为 None
+    pub fn target_window(&self) -> Option<WindowId> {
+        self.target_window
+    }
+
+    /// 焦点已离开目标窗口时切回；切回失败则仍插入到当前焦点窗口
+    fn restore_focus(&self) {
+        let Some(target) = self.target_window else {
+            return;
+        };
+        if active_window::foreground_window() == Some(target) {
+            return;
+        }
+        if active_window::focus_window(target) {
+            tracing::info!("前台窗口已变化，已切回录音开始时的窗口");
+            // 等待窗口激活完成再粘贴
+            thread::sleep(Duration::from_millis(100));
+        } else {
+            tracing::warn!("无法切回录音开始时的窗口（可能已关闭），插入到当前焦点窗口");
+        }
+    }
+
     pub fn insert_text(&mut self, text: &str) -> Result<()> {
-        tracing::info!("准备插入文本: {}", text);
+        tracing::info!("准备插入文本: {}", privacy::redact(text));
+        self.restore_focus();
 
         // 1. 保存当前剪贴板内容
         let original_clipboard = self.clipboard.get_text().ok();
@@ -45,7 +98,264 @@ impl TextInserter {
             self.clipboard.set_text(original)?;
         }
 
+        // 7. 记录本次插入（模拟的 Ctrl+V 此时已被键盘监听计入）
+        self.last_insert = Some(LastInsert {
+            text: text.to_string(),
+            window: active_window::foreground_window(),
+            key_presses: hotkey_service::key_press_count(),
+        });
+
         tracing::info!("文本插入完成");
         Ok(())
     }
+
+    /// 撤销最近一次插入：切回插入的窗口，按插入的字符数模拟退格，返回删除的字符数
+    ///
+    /// 插入后又有键盘输入（光标位置和内容都不再可知）或窗口已关闭时放弃撤销
+    pub fn undo_last_insert(&mut self) -> Result<usize> {
+        let Some(last) = self.last_insert.take() else {
+            anyhow::bail!("没有可撤销的插入");
+        };
+        if hotkey_service::key_press_count() != last.key_presses {
+            anyhow::bail!("插入后又有键盘输入，已放弃撤销，请手动修改");
+        }
+        if let Some(window) = last.window {
+            if active_window::foreground_window() != Some(window) {
+                if !active_window::focus_window(window) {
+                    anyhow::bail!("插入文本的窗口已关闭或无法切回，已放弃撤销");
+                }
+                // 等待窗口激活完成再删除
+                thread::sleep(Duration::from_millis(100));
+            }
+        }
+
+        let count = backspace_count(&last.text);
+        for _ in 0..count {
+            self.enigo.key(Key::Backspace, Direction::Click)?;
+        }
+        tracing::info!("已撤销最近一次插入（{} 个字符）", count);
+        Ok(count)
+    }
+
+    /// 只复制到剪贴板，不模拟粘贴（output_target = clipboard）
+    pub fn copy_to_clipboard(&mut self, text: &str) -> Result<()> {
+        self.clipboard.set_text(text)?;
+        tracing::info!("文本已复制到剪贴板: {}", privacy::redact(text));
+        Ok(())
+    }
+
+    /// 拆分发送：逐条"插入 + 回车 + 间隔"，返回已发送条数
+    ///
+    /// 发送过程中前台窗口发生变化时立即停止，剩余消息不再发送
+    pub fn insert_messages(&mut self, messages: &[String]) -> Result<usize> {
+        // 已经回车发出的消息无法撤销
+        self.last_insert = None;
+        self.restore_focus();
+        let target_window = active_window::foreground_window();
+        tracing::info!("拆分发送 {} 条消息", messages.len());
+
+        for (i, message) in messages.iter().enumerate() {
+            if i > 0 {
+                thread::sleep(Duration::from_millis(MESSAGE_INTERVAL_MS));
+            }
+
+            if active_window::foreground_window() != target_window {
+                anyhow::bail!(
+                    "目标窗口焦点已变化，已停止发送（已发送 {}/{} 条）",
+                    i,
+                    messages.len()
+                );
+            }
+
+            self.insert_text(message)?;
+            self.enigo.key(Key::Return, Direction::Click)?;
+            self.last_insert = None;
+        }
+
+        Ok(messages.len())
+    }
+}
+
+/// 将识别结果拆分为多条消息
+///
+/// - `Sentence`: 换行处必拆；同一行内按句末标点断句，再把相邻句子合并到不超过 `max_chars`
+/// - `BlankLine`: 按空行分段，每段一条
+///
+/// 单句/单段超过 `max_chars` 时按字符硬切；`max_chars` 为 0 表示不限制长度
+pub fn split_messages(text: &str, max_chars: usize, delimiter: SplitDelimiter) -> Vec<String> {
+    let mut messages = Vec::new();
+
+    match delimiter {
+        SplitDelimiter::Sentence => {
+            for line in text.lines() {
+                let mut current = String::new();
+                for sentence in split_sentences(line) {
+                    if max_chars > 0
+                        && !current.is_empty()
+                        && char_len(&current) + char_len(&sentence) > max_chars
+                    {
+                        messages.push(std::mem::take(&mut current));
+                    }
+                    current.push_str(&sentence);
+                }
+                if !current.is_empty() {
+                    messages.push(current);
+                }
+            }
+        }
+        SplitDelimiter::BlankLine => {
+            let mut paragraph: Vec<&str> = Vec::new();
+            for line in text.lines().chain(std::iter::once("")) {
+                if line.trim().is_empty() {
+                    if !paragraph.is_empty() {
+                        messages.push(paragraph.join("\n"));
+                        paragraph.clear();
+                    }
+                } else {
+                    paragraph.push(line.trim_end());
+                }
+            }
+        }
+    }
+
+    messages
+        .into_iter()
+        .map(|m| m.trim().to_string())
+        .filter(|m| !m.is_empty())
+        .flat_map(|m| hard_wrap(&m, max_chars))
+        .collect()
+}
+
+/// 先按拆分规则断句，再逐条执行 `clean`（标点删除、替换等），结果按行拼接
+///
+/// 拆分发送时 `split_messages` 会在换行处拆开，断句结果因此在清理后仍然保留；
+/// 按空行分段不依赖标点，直接整体清理
+pub fn split_then_clean(text: &str, split: &SplitMessagesConfig, clean: impl Fn(&str) -> String) -> String {
+    if split.delimiter != SplitDelimiter::Sentence {
+        return clean(text);
+    }
+    split_messages(text, split.max_chars, split.delimiter)
+        .iter()
+        .map(|message| clean(message))
+        .filter(|message| !message.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 按句末标点断句，标点保留在句尾
+fn split_sentences(line: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    let mut current = String::new();
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        current.push(c);
+        if SENTENCE_TERMINATORS.contains(&c) {
+            // 连续标点（如 "？！"、"……"）归入同一句
+            while let Some(&next) = chars.peek() {
+                if SENTENCE_TERMINATORS.contains(&next) {
+                    current.push(next);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            sentences.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.trim().is_empty() {
+        sentences.push(current);
+    }
+
+    sentences
+}
+
+/// 超长文本按字符数硬切
+fn hard_wrap(text: &str, max_chars: usize) -> Vec<String> {
+    if max_chars == 0 || char_len(text) <= max_chars {
+        return vec![text.to_string()];
+    }
+    let chars: Vec<char> = text.chars().collect();
+    chars
+        .chunks(max_chars)
+        .map(|chunk| chunk.iter().collect::<String>().trim().to_string())
+        .filter(|m| !m.is_empty())
+        .collect()
+}
+
+fn char_len(text: &str) -> usize {
+    text.chars().count()
+}
+
+/// 删除一段已插入文本需要的退格次数（按字符计，"\r\n" 在编辑器里是一个换行）
+fn backspace_count(text: &str) -> usize {
+    char_len(text) - text.matches("\r\n").count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sentence_mode_merges_sentences_up_to_max_chars() {
+        let text = "今天开会。讨论了三件事。第一是预算！第二是排期？";
+        let messages = split_messages(text, 12, SplitDelimiter::Sentence);
+        assert_eq!(messages, vec!["今天开会。讨论了三件事。", "第一是预算！第二是排期？"]);
+    }
+
+    #[test]
+    fn sentence_mode_without_limit_keeps_line_together() {
+        let text = "第一句。第二句。";
+        let messages = split_messages(text, 0, SplitDelimiter::Sentence);
+        assert_eq!(messages, vec!["第一句。第二句。"]);
+    }
+
+    #[test]
+    fn sentence_mode_always_splits_on_newline() {
+        let text = "第一行。\n第二行\n\n第三行。";
+        let messages = split_messages(text, 100, SplitDelimiter::Sentence);
+        assert_eq!(messages, vec!["第一行。", "第二行", "第三行。"]);
+    }
+
+    #[test]
+    fn sentence_mode_keeps_consecutive_punctuation() {
+        let text = "真的吗？！好的。";
+        let messages = split_messages(text, 5, SplitDelimiter::Sentence);
+        assert_eq!(messages, vec!["真的吗？！", "好的。"]);
+    }
+
+    #[test]
+    fn blank_line_mode_splits_paragraphs() {
+        let text = "第一段第一行\n第一段第二行\n\n  \n第二段。";
+        let messages = split_messages(text, 0, SplitDelimiter::BlankLine);
+        assert_eq!(messages, vec!["第一段第一行\n第一段第二行", "第二段。"]);
+    }
+
+    #[test]
+    fn overlong_sentence_is_hard_wrapped() {
+        let text = "一二三四五六七八九十";
+        let messages = split_messages(text, 4, SplitDelimiter::Sentence);
+        assert_eq!(messages, vec!["一二三四", "五六七八", "九十"]);
+    }
+
+    #[test]
+    fn sentences_survive_punctuation_stripping() {
+        let split = SplitMessagesConfig { enabled: true, max_chars: 6, delimiter: SplitDelimiter::Sentence };
+        let cleaned = split_then_clean("今天开会。讨论预算！好的", &split, crate::text_postprocess::strip_all_punctuation);
+        assert_eq!(cleaned, "今天开会\n讨论预算\n好的");
+        assert_eq!(split_messages(&cleaned, split.max_chars, split.delimiter), vec!["今天开会", "讨论预算", "好的"]);
+    }
+
+    #[test]
+    fn empty_text_yields_no_messages() {
+        assert!(split_messages("", 10, SplitDelimiter::Sentence).is_empty());
+        assert!(split_messages(" \n\n ", 10, SplitDelimiter::BlankLine).is_empty());
+    }
+
+    #[test]
+    fn undo_deletes_one_backspace_per_character() {
+        assert_eq!(backspace_count("你好，world"), 8);
+        assert_eq!(backspace_count("第一行\r\n第二行\n"), 8);
+        assert_eq!(backspace_count(""), 0);
+    }
 }
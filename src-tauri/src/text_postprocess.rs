@@ -0,0 +1,216 @@
+// 转录文本后处理
+// 实时与 HTTP 两条转录路径的结果都经过这里，由 TranscriptCleanup 的开关统一驱动：
+// 口头禅清理、删除全部标点、删除末尾标点是可组合的独立步骤，识别客户端本身只返回原始结果；
+// 之后再按 text_replacements 的顺序做自定义替换
+
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+use regex::{NoExpand, Regex, RegexBuilder};
+
+use crate::config::{TextReplacement, TranscriptCleanup};
+
+// 识别结果末尾可能出现的标点
+const TRAILING_PUNCTUATION: &[char] = &[
+    '。', '，', '！', '？', '、', '；', '：', '"', '“', '”', '\'', '‘', '’', '.', ',', '!', '?', ';', ':',
+];
+// 删除全部标点时去掉的符号（含括号、书名号、引号、破折号等），也是各模块共用的标点表
+const ALL_PUNCTUATION: &[char] = &[
+    '。', '，', '！', '？', '、', '；', '：', '"', '“', '”', '\'', '‘', '’', '.', ',', '!', '?', ';', ':',
+    '（', '）', '(', ')', '【', '】', '[', ']', '《', '》', '<', '>', '—', '…', '·',
+];
+// 按字符查找用的标点集合，首次使用时由 ALL_PUNCTUATION 构建
+static PUNCTUATION_SET: LazyLock<HashSet<char>> = LazyLock::new(|| ALL_PUNCTUATION.iter().copied().collect());
+// 分句用的标点（口头禅只在分句内清理）
+const CLAUSE_DELIMITERS: &[char] = &['。', '，', '！', '？', '、', '；', '：', '.', ',', '!', '?', ';', ':', '\n'];
+// 句末标点
+const SENTENCE_ENDINGS: &[char] = &['。', '！', '？', '.', '!', '?'];
+// 无实际含义的语气词：整句只有它们时删除整句，出现在句首时去掉
+const INTERJECTIONS: &[char] = &['嗯', '啊', '呃', '额'];
+// 连续重复时只保留一次的口头禅
+const REPEATED_FILLERS: &[&str] = &["这个", "那个", "就是", "然后"];
+
+/// 按配置依次执行各后处理步骤
+pub fn apply(text: &str, cleanup: &TranscriptCleanup) -> String {
+    let mut text = text.to_string();
+    if cleanup.remove_fillers {
+        text = remove_fillers(&text);
+    }
+    if cleanup.strip_all_punctuation {
+        text = strip_all_punctuation(&text);
+    }
+    if cleanup.strip_trailing_punctuation {
+        text = strip_trailing_punctuation(&text);
+    }
+    text
+}
+
+/// 去除末尾的标点符号
+pub fn strip_trailing_punctuation(text: &str) -> String {
+    text.trim_end_matches(TRAILING_PUNCTUATION).to_string()
+}
+
+/// 是否为标点（中英文标点、括号、书名号、引号、破折号等）
+pub fn is_punctuation(c: char) -> bool {
+    PUNCTUATION_SET.contains(&c)
+}
+
+/// 删除所有标点符号
+pub fn strip_all_punctuation(text: &str) -> String {
+    text.chars().filter(|&c| !is_punctuation(c)).collect()
+}
+
+/// 清理分句中的语气词和连续重复的口头禅，保留标点和其余内容
+pub fn remove_fillers(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while !rest.is_empty() {
+        let end = rest.find(CLAUSE_DELIMITERS).unwrap_or(rest.len());
+        let (clause, tail) = rest.split_at(end);
+        let (delimiter, next) = tail.split_at(tail.chars().next().map_or(0, char::len_utf8));
+        rest = next;
+
+        let trimmed = clause.trim();
+        if !trimmed.is_empty() && trimmed.trim_start_matches(INTERJECTIONS).is_empty() {
+            // 整句都是语气词（如"嗯，"）：连同分隔符一起去掉；若它带着句末标点，把句末标点还给前一句
+            if delimiter.starts_with(SENTENCE_ENDINGS) && !result.is_empty() {
+                result.truncate(result.trim_end_matches(CLAUSE_DELIMITERS).len());
+                result.push_str(delimiter);
+            }
+            continue;
+        }
+        result.push_str(&collapse_repeats(clause.trim_start_matches(INTERJECTIONS)));
+        result.push_str(delimiter);
+    }
+    result
+}
+
+/// "这个这个" → "这个"，"就是就是说" → "就是说"
+fn collapse_repeats(clause: &str) -> String {
+    let mut result = String::with_capacity(clause.len());
+    let mut rest = clause;
+    while let Some(c) = rest.chars().next() {
+        if let Some(filler) = REPEATED_FILLERS.iter().find(|f| rest.starts_with(**f)) {
+            let mut after = &rest[filler.len()..];
+            while after.starts_with(filler) {
+                after = &after[filler.len()..];
+            }
+            result.push_str(filler);
+            rest = after;
+            continue;
+        }
+        result.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    result
+}
+
+/// 按顺序执行自定义替换规则；空 pattern 与无效的正则跳过
+pub fn apply_replacements(text: &str, rules: &[TextReplacement]) -> String {
+    let mut text = text.to_string();
+    for rule in rules {
+        let re = match compile_replacement(rule) {
+            Ok(Some(re)) => re,
+            Ok(None) => continue,
+            Err(e) => {
+                tracing::warn!("替换规则 \"{}\" 无效，已跳过: {}", rule.pattern, e);
+                continue;
+            }
+        };
+        text = if rule.regex {
+            re.replace_all(&text, rule.replacement.as_str()).into_owned()
+        } else {
+            // 原文匹配时 replacement 中的 $ 按字面处理
+            re.replace_all(&text, NoExpand(&rule.replacement)).into_owned()
+        };
+    }
+    text
+}
+
+/// 检查替换规则中的正则能否解析（保存配置前调用）
+pub fn validate_replacements(rules: &[TextReplacement]) -> Result<(), String> {
+    for rule in rules {
+        compile_replacement(rule).map_err(|e| format!("替换规则 \"{}\" 无效: {}", rule.pattern, e))?;
+    }
+    Ok(())
+}
+
+/// 原文匹配的 pattern 先转义再与正则规则统一编译，不区分大小写也由正则实现
+fn compile_replacement(rule: &TextReplacement) -> Result<Option<Regex>, regex::Error> {
+    if rule.pattern.is_empty() {
+        return Ok(None);
+    }
+    let pattern = if rule.regex { rule.pattern.clone() } else { regex::escape(&rule.pattern) };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!rule.case_sensitive)
+        .build()
+        .map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_are_independent() {
+        let text = "嗯，这个这个方案就是就是说可以。";
+
+        let keep_period = TranscriptCleanup { remove_fillers: true, strip_trailing_punctuation: false, ..Default::default() };
+        assert_eq!(apply(text, &keep_period), "这个方案就是说可以。");
+
+        let only_punctuation = TranscriptCleanup { remove_fillers: false, ..Default::default() };
+        assert_eq!(apply(text, &only_punctuation), "嗯，这个这个方案就是就是说可以");
+
+        let both = TranscriptCleanup { remove_fillers: true, ..Default::default() };
+        assert_eq!(apply(text, &both), "这个方案就是说可以");
+
+        // 删除全部标点在口头禅清理之后执行，不影响按标点分句
+        let all = TranscriptCleanup { remove_fillers: true, strip_all_punctuation: true, ..Default::default() };
+        assert_eq!(apply(text, &all), "这个方案就是说可以");
+        assert_eq!(apply("“你好”，（世界）《书》—好…", &all), "你好世界书好");
+    }
+
+    #[test]
+    fn punctuation_set_covers_shared_table() {
+        assert!(ALL_PUNCTUATION.iter().all(|&c| is_punctuation(c)));
+        assert!(TRAILING_PUNCTUATION.iter().all(|&c| is_punctuation(c)));
+        assert!(!is_punctuation('好') && !is_punctuation('a') && !is_punctuation(' '));
+        assert_eq!(strip_all_punctuation("《测试》（一）：好的——“可以”…"), "测试一好的可以");
+    }
+
+    fn rule(pattern: &str, replacement: &str, regex: bool, case_sensitive: bool) -> TextReplacement {
+        TextReplacement { pattern: pattern.into(), replacement: replacement.into(), regex, case_sensitive }
+    }
+
+    #[test]
+    fn replacements_apply_in_order() {
+        let rules = vec![
+            rule("佩奇", "裴琪", false, true),
+            rule("gpt 四", "GPT-4", false, false),
+            rule("C++", "$cpp", false, true),
+            rule(r"(\d+)\s*点\s*(\d+)", "$1.$2", true, true),
+            rule("", "空规则", false, true),
+            rule("(未闭合", "x", true, true),
+            rule("GPT-4", "GPT-4o", false, true),
+        ];
+        assert_eq!(
+            apply_replacements("佩奇说 Gpt 四 比 C++ 快 3 点 5 倍", &rules),
+            "裴琪说 GPT-4o 比 $cpp 快 3.5 倍"
+        );
+        // 区分大小写的规则不匹配其他大小写
+        assert_eq!(apply_replacements("Rust rust", &[rule("rust", "Rust", false, true)]), "Rust Rust");
+        assert_eq!(apply_replacements("RUST", &[rule("rust", "Rust", false, true)]), "RUST");
+
+        assert!(validate_replacements(&rules[..5]).is_ok());
+        assert!(validate_replacements(&rules).is_err());
+    }
+
+    #[test]
+    fn keeps_meaningful_words() {
+        // 单独出现的"这个""然后"是正常用词，句中的"啊"不是句首语气词
+        assert_eq!(remove_fillers("这个问题然后再说，好啊。"), "这个问题然后再说，好啊。");
+        assert_eq!(remove_fillers("先这样。嗯。"), "先这样。");
+        assert_eq!(remove_fillers("好的，嗯。"), "好的。");
+        assert_eq!(remove_fillers("呃我们走吧"), "我们走吧");
+    }
+}
@@ -0,0 +1,69 @@
+// 识别流程的超时与重试间隔
+// 各模块统一从这里取值（不再散落常量），测试中可注入并配合 tokio 的 start_paused / advance 快进时间
+
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timings {
+    /// HTTP 识别请求的整体超时
+    pub http_request_timeout: Duration,
+    /// HTTP 建连超时
+    pub http_connect_timeout: Duration,
+    /// 连接池中空闲 HTTP 连接的保留时长
+    pub http_pool_idle_timeout: Duration,
+    /// 启动前 API Key 校验请求的超时
+    pub api_key_check_timeout: Duration,
+    /// 千问 HTTP 失败后的重试间隔
+    pub http_retry_delay: Duration,
+    /// 实时识别结果等待超时的下限（长音频按时长放宽）
+    pub realtime_result_floor: Duration,
+    /// 服务端 VAD 收尾时轮询未返回分段的间隔
+    pub segment_poll_interval: Duration,
+    /// 手动断句 commit 后迟迟没有 response.done 时，最后一段结果之后再等多久即拼接发出
+    pub segment_settle: Duration,
+    /// 松开按键后等待分段插入任务结束的超时
+    pub segment_task_timeout: Duration,
+    /// 实时会话断线重连的退避基数（第 n 次重连等待 n 倍）
+    pub reconnect_backoff: Duration,
+    /// 关闭实时会话时等待服务端 Close 回执的超时
+    pub session_close_timeout: Duration,
+    /// 死连接看门狗的检查间隔
+    pub watchdog_interval: Duration,
+    /// 从连接池取用会话时，距上次收包超过该时长先 ping 探活
+    pub pool_probe_after: Duration,
+    /// 探活等待 pong 的超时，超时视为连接已失效
+    pub pool_probe_timeout: Duration,
+    /// 连接池中预热会话的空闲上限，超过后关闭
+    pub pool_idle_timeout: Duration,
+    /// 连接池清理任务的检查周期
+    pub pool_cleanup_interval: Duration,
+    /// 按下快捷键时网络快速探测的超时
+    pub network_probe_timeout: Duration,
+    /// 网络探测结果的缓存时长（连续按键不重复探测；预热会话在该时长内收到过消息也视为在线）
+    pub network_probe_cache: Duration,
+}
+
+impl Default for Timings {
+    fn default() -> Self {
+        Self {
+            http_request_timeout: Duration::from_secs(30),
+            http_connect_timeout: Duration::from_secs(10),
+            http_pool_idle_timeout: Duration::from_secs(30),
+            api_key_check_timeout: Duration::from_secs(10),
+            http_retry_delay: Duration::from_millis(500),
+            realtime_result_floor: Duration::from_secs(10),
+            segment_poll_interval: Duration::from_millis(50),
+            segment_settle: Duration::from_millis(500),
+            segment_task_timeout: Duration::from_secs(5),
+            reconnect_backoff: Duration::from_millis(500),
+            session_close_timeout: Duration::from_secs(2),
+            watchdog_interval: Duration::from_millis(500),
+            pool_probe_after: Duration::from_secs(3),
+            pool_probe_timeout: Duration::from_millis(500),
+            pool_idle_timeout: Duration::from_secs(180),
+            pool_cleanup_interval: Duration::from_secs(30),
+            network_probe_timeout: Duration::from_secs(1),
+            network_probe_cache: Duration::from_secs(5),
+        }
+    }
+}
@@ -0,0 +1,239 @@
+// 本地语音活动检测（VAD）
+// 按键到开口、说完到松键的首尾静音以及中间思考的长停顿都会原样上传，增加识别延迟和费用。
+// 这里用 WebRTC VAD（libfvad，按子带能量的高斯混合模型判定）逐个 30ms 帧检测语音，
+// HTTP 模式发送前裁掉首尾静音，并可选把超长的中段停顿压缩成短停顿。
+// 语音段前后保留余量，避免把轻声的词头（如 s / sh 开头）当成静音切掉
+
+use webrtc_vad::{SampleRate, Vad, VadMode};
+
+use crate::config::VadConfig;
+
+// WebRTC VAD 支持 10 / 20 / 30ms 帧，取 30ms
+const FRAME_MS: u32 = 30;
+// 语音段前保留的余量，保护轻声词头
+const ONSET_PADDING_MS: u32 = 300;
+// 语音段后保留的余量，保留尾音的自然衰减
+const RELEASE_PADDING_MS: u32 = 300;
+// 连续语音帧少于它视为瞬态噪声（键盘声、碰麦）
+const MIN_SPEECH_FRAMES: usize = 2;
+
+/// 一次 VAD 裁剪的结果（毫秒）
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct VadTrim {
+    pub leading_ms: u32,
+    pub trailing_ms: u32,
+    pub compressed_ms: u32,
+}
+
+impl VadTrim {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// 裁掉首尾静音并按配置压缩中段长停顿（单声道样本）；没有检测到语音或采样率不受支持时保持原样
+pub fn apply(samples: &mut Vec<f32>, sample_rate: u32, config: &VadConfig) -> VadTrim {
+    if !config.enabled || samples.is_empty() {
+        return VadTrim::default();
+    }
+    let Ok(rate) = SampleRate::try_from(sample_rate as i32) else {
+        tracing::warn!("本地 VAD 不支持采样率 {}Hz，跳过", sample_rate);
+        return VadTrim::default();
+    };
+    let ms_to_samples = |ms: u32| (sample_rate as u64 * ms as u64 / 1000) as usize;
+    let to_ms = |len: usize| (len as u64 * 1000 / sample_rate as u64) as u32;
+    let frame = ms_to_samples(FRAME_MS).max(1);
+
+    let segments = speech_segments(samples, frame, rate, config.aggressiveness);
+    let (Some(first), Some(last)) = (segments.first(), segments.last()) else {
+        return VadTrim::default();
+    };
+
+    let start = first.0.saturating_sub(ms_to_samples(ONSET_PADDING_MS));
+    let end = (last.1 + ms_to_samples(RELEASE_PADDING_MS)).min(samples.len());
+    let mut trim = VadTrim {
+        leading_ms: to_ms(start),
+        trailing_ms: to_ms(samples.len() - end),
+        compressed_ms: 0,
+    };
+
+    // 保留的区间：首尾裁剪后，把超长停顿的中间部分挖掉，两侧各留一半压缩后的时长
+    let mut keep = vec![(start, end)];
+    if config.compress_pauses {
+        let max_pause = ms_to_samples(config.max_pause_ms);
+        let side = ms_to_samples(config.compressed_pause_ms.min(config.max_pause_ms)) / 2;
+        keep.clear();
+        let mut from = start;
+        for pair in segments.windows(2) {
+            let (pause_start, pause_end) = (pair[0].1, pair[1].0);
+            if pause_end - pause_start > max_pause {
+                keep.push((from, pause_start + side));
+                trim.compressed_ms += to_ms(pause_end - pause_start - side * 2);
+                from = pause_end - side;
+            }
+        }
+        keep.push((from, end));
+    }
+
+    if keep.len() == 1 {
+        samples.truncate(end);
+        samples.drain(..start);
+    } else {
+        let kept: Vec<f32> = keep.iter().flat_map(|&(from, to)| samples[from..to].iter().copied()).collect();
+        *samples = kept;
+    }
+    trim
+}
+
+/// 检测语音段（样本区间，左闭右开）；末尾不足一帧的样本按非语音处理
+fn speech_segments(samples: &[f32], frame: usize, rate: SampleRate, aggressiveness: u8) -> Vec<(usize, usize)> {
+    let mode = match aggressiveness {
+        0 => VadMode::Quality,
+        1 => VadMode::LowBitrate,
+        2 => VadMode::Aggressive,
+        _ => VadMode::VeryAggressive,
+    };
+    let frames: Vec<Vec<i16>> = samples
+        .chunks_exact(frame)
+        .map(|chunk| chunk.iter().map(|&s| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).collect())
+        .collect();
+    // 刚创建的 VAD 噪声模型还没适应底噪，开头几帧会把噪声判成语音：先整段过一遍让模型适应，再正式判定
+    let mut vad = Vad::new_with_rate_and_mode(rate, mode);
+    for pcm in &frames {
+        let _ = vad.is_voice_segment(pcm);
+    }
+    let voiced: Vec<bool> = frames.iter().map(|pcm| vad.is_voice_segment(pcm).unwrap_or(false)).collect();
+
+    let mut segments = Vec::new();
+    let mut run_start = None;
+    for (i, &speech) in voiced.iter().chain(std::iter::once(&false)).enumerate() {
+        match (speech, run_start) {
+            (true, None) => run_start = Some(i),
+            (false, Some(from)) => {
+                if i - from >= MIN_SPEECH_FRAMES {
+                    segments.push((from * frame, (i * frame).min(samples.len())));
+                }
+                run_start = None;
+            }
+            _ => {}
+        }
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 16000;
+
+    /// 确定性的白噪声，`dbfs` 为 RMS 电平
+    fn noise(len: usize, dbfs: f32, seed: &mut u32) -> Vec<f32> {
+        let amplitude = 10f32.powf(dbfs / 20.0) * 3f32.sqrt();
+        (0..len)
+            .map(|_| {
+                *seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+                ((*seed >> 8) as f32 / (1 << 24) as f32 * 2.0 - 1.0) * amplitude
+            })
+            .collect()
+    }
+
+    /// 真实录音 tests/data/speech_16k.wav（取自 libfvad 测试数据，1.5s 英文口述），按 `gain` 缩放
+    fn speech(gain: f32) -> Vec<f32> {
+        let wav = include_bytes!("../tests/data/speech_16k.wav");
+        let mut reader = hound::WavReader::new(&wav[..]).unwrap();
+        assert_eq!(reader.spec().sample_rate, RATE);
+        reader.samples::<i16>().map(|s| s.unwrap() as f32 / 32768.0 * gain).collect()
+    }
+
+    fn ms(ms: usize) -> usize {
+        ms * RATE as usize / 1000
+    }
+
+    /// 模拟一段口述：0.8s 静音 → 轻声摩擦音词头（-42dBFS，"s"）→ 录音前半段 → 3s 停顿 → 录音后半段 → 0.9s 静音，
+    /// 背景底噪 -60dBFS。返回样本与词头的起止位置
+    fn dictation_clip() -> (Vec<f32>, (usize, usize)) {
+        let mut seed = 7;
+        let speech = speech(1.0);
+        let mut clip = noise(ms(800), -60.0, &mut seed);
+        let onset_start = clip.len();
+        clip.extend(noise(ms(120), -42.0, &mut seed));
+        let onset_end = clip.len();
+        clip.extend(&speech[..ms(700)]);
+        clip.extend(noise(ms(3000), -60.0, &mut seed));
+        clip.extend(&speech[ms(700)..]);
+        clip.extend(noise(ms(900), -60.0, &mut seed));
+        (clip, (onset_start, onset_end))
+    }
+
+    #[test]
+    fn trims_silence_without_cutting_soft_onsets() {
+        let (clip, (onset_start, onset_end)) = dictation_clip();
+
+        for aggressiveness in 0..=3 {
+            let config = VadConfig { aggressiveness, ..VadConfig::default() };
+            let mut samples = clip.clone();
+            let trim = apply(&mut samples, RATE, &config);
+
+            // 轻声词头完整保留，且前面至少留有 250ms 余量（帧对齐最多让检测晚一帧）
+            let leading = ms(trim.leading_ms as usize);
+            assert!(leading + ms(250) <= onset_start, "激进程度 {}: {:?}", aggressiveness, trim);
+            assert_eq!(samples[onset_start - leading..onset_end - leading], clip[onset_start..onset_end]);
+
+            // 首尾各裁掉约 0.5s（录音结尾的弱尾音由 VAD 判定，各档位差一两帧），中段停顿不压缩
+            assert!((480..=510).contains(&trim.leading_ms), "{:?}", trim);
+            assert!((420..=540).contains(&trim.trailing_ms), "{:?}", trim);
+            assert_eq!(trim.compressed_ms, 0);
+            assert_eq!(samples.len(), clip.len() - leading - ms(trim.trailing_ms as usize));
+        }
+    }
+
+    /// 真实录音衰减 20dB 模拟小声说话，叠加 -60dBFS 底噪，前后各补 1s 静音：轻声的起音不能被裁掉
+    #[test]
+    fn keeps_soft_onset_of_real_recording() {
+        let speech = speech(0.1);
+
+        let mut seed = 3;
+        let mut clip = noise(ms(1000), -60.0, &mut seed);
+        let (start, end) = (clip.len(), clip.len() + speech.len());
+        clip.extend(speech.iter().zip(noise(speech.len(), -60.0, &mut seed)).map(|(s, n)| s + n));
+        clip.extend(noise(ms(1000), -60.0, &mut seed));
+
+        for aggressiveness in 0..=3 {
+            let config = VadConfig { aggressiveness, ..VadConfig::default() };
+            let mut samples = clip.clone();
+            let trim = apply(&mut samples, RATE, &config);
+
+            // 起音前的静音裁掉大半，起音之后的 300ms 原样保留
+            let leading = ms(trim.leading_ms as usize);
+            assert!(trim.leading_ms >= 400 && leading <= start, "激进程度 {}: {:?}", aggressiveness, trim);
+            let onset = start - leading..start - leading + ms(300);
+            assert_eq!(samples[onset], clip[start..start + ms(300)], "激进程度 {}", aggressiveness);
+
+            // 激进程度 3 会把小声说话的弱尾音当成噪声，其余档位整段语音都保留
+            if aggressiveness < 3 {
+                assert!(trim.trailing_ms >= 400, "激进程度 {}: {:?}", aggressiveness, trim);
+                assert_eq!(samples[start - leading..end - leading], clip[start..end], "激进程度 {}", aggressiveness);
+            }
+        }
+    }
+
+    #[test]
+    fn compresses_long_pauses() {
+        let (clip, _) = dictation_clip();
+        let config = VadConfig { compress_pauses: true, ..VadConfig::default() };
+        let mut samples = clip.clone();
+        let trim = apply(&mut samples, RATE, &config);
+
+        // 3s 停顿压缩为 0.5s（VAD 的拖尾会把前一段语音延长约 0.2s）
+        assert!((2250..=2400).contains(&trim.compressed_ms), "{:?}", trim);
+        let removed = trim.leading_ms + trim.trailing_ms + trim.compressed_ms;
+        assert!((samples.len() as i64 - (clip.len() - ms(removed as usize)) as i64).abs() <= 16);
+
+        // 只有底噪没有语音时保持原样，交给静音检测处理
+        let mut seed = 1;
+        let mut silence = noise(ms(2000), -70.0, &mut seed);
+        assert!(apply(&mut silence, RATE, &config).is_empty());
+        assert_eq!(silence.len(), ms(2000));
+    }
+}
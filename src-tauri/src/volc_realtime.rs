@@ -0,0 +1,615 @@
+// 火山引擎流式语音识别（大模型版 sauc）WebSocket 客户端
+// 二进制协议：4 字节 header + [sequence] + payload size + payload（JSON 参数 gzip 压缩，音频直接发原始 PCM），
+// 首帧 full client request 下发 JSON 参数，之后按序发送 audio only 帧，最后一帧带 last 标志；
+// 会话对外与千问实时识别共用 RealtimeSession，上层无需区分服务商
+
+use anyhow::Result;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+use tokio::time::timeout;
+use tokio_tungstenite::tungstenite::{http, protocol::{CloseFrame, frame::coding::CloseCode}, Message};
+
+use crate::audio_frame::AudioFrameEncoder;
+use crate::config::{RealtimeVadMode, VolcConfig};
+use crate::privacy;
+use crate::qwen_realtime::{
+    self, AudioQueue, ConnectionStale, RealtimeSession, SessionCommand, SessionEndpoints, SessionOptions,
+    SessionStatus, WsSink, WsSource,
+};
+use crate::ws_trace::{self, WsTrace};
+
+// 火山引擎流式识别只接受 16kHz 单声道 16-bit PCM
+const SAMPLE_RATE: u32 = 16000;
+
+// header 第 0 字节：协议版本 + header 长度（单位 4 字节）
+const PROTOCOL_VERSION: u8 = 0b0001;
+const HEADER_SIZE: u8 = 0b0001;
+// 消息类型
+const FULL_CLIENT_REQUEST: u8 = 0b0001;
+const AUDIO_ONLY_REQUEST: u8 = 0b0010;
+const FULL_SERVER_RESPONSE: u8 = 0b1001;
+const SERVER_ERROR_RESPONSE: u8 = 0b1111;
+// 消息类型相关标志：bit0 = header 后带 sequence，bit1 = 最后一包
+const FLAG_SEQUENCE: u8 = 0b0001;
+const FLAG_LAST: u8 = 0b0010;
+// 序列化与压缩方式
+const SERIALIZATION_NONE: u8 = 0b0000;
+const SERIALIZATION_JSON: u8 = 0b0001;
+const COMPRESSION_NONE: u8 = 0b0000;
+const COMPRESSION_GZIP: u8 = 0b0001;
+
+static CONNECT_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// 每条连接唯一的 X-Api-Connect-Id，便于对照服务端日志排查
+fn next_connect_id() -> String {
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    format!("ptt-{}-{}-{}", std::process::id(), millis, CONNECT_SEQ.fetch_add(1, Ordering::Relaxed))
+}
+
+fn gzip(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+fn gunzip(data: &[u8]) -> Result<Vec<u8>> {
+    let mut decoded = Vec::new();
+    GzDecoder::new(data)
+        .read_to_end(&mut decoded)
+        .map_err(|e| anyhow::anyhow!("解压服务端响应失败: {}", e))?;
+    Ok(decoded)
+}
+
+/// 拼装一帧：header + [sequence] + payload size + payload
+fn pack_frame(message_type: u8, flags: u8, serialization: u8, compression: u8, sequence: Option<i32>, payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(12 + payload.len());
+    frame.push((PROTOCOL_VERSION << 4) | HEADER_SIZE);
+    frame.push((message_type << 4) | flags);
+    frame.push((serialization << 4) | compression);
+    frame.push(0);
+    if let Some(sequence) = sequence {
+        frame.extend_from_slice(&sequence.to_be_bytes());
+    }
+    frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// 打包一帧，payload 先 gzip 压缩
+fn encode_frame(message_type: u8, flags: u8, serialization: u8, sequence: Option<i32>, payload: &[u8]) -> Result<Vec<u8>> {
+    Ok(pack_frame(message_type, flags, serialization, COMPRESSION_GZIP, sequence, &gzip(payload)?))
+}
+
+/// 首帧：识别参数（JSON）
+fn full_client_request(request: &serde_json::Value) -> Result<Vec<u8>> {
+    encode_frame(FULL_CLIENT_REQUEST, FLAG_SEQUENCE, SERIALIZATION_JSON, Some(1), request.to_string().as_bytes())
+}
+
+/// 音频帧编码：原始 PCM 直接放进二进制帧，不做 gzip（PCM 几乎压不动，压缩只会白耗 CPU）；
+/// sequence 逐帧递增，最后一包取负数
+struct VolcAudioEncoder {
+    sequence: i32,
+}
+
+impl VolcAudioEncoder {
+    fn new() -> Self {
+        // full client request 占用 sequence 1，音频从 2 开始
+        Self { sequence: 1 }
+    }
+}
+
+impl AudioFrameEncoder for VolcAudioEncoder {
+    fn encode(&mut self, pcm: &[u8], last: bool) -> Message {
+        self.sequence += 1;
+        let (flags, sequence) = if last {
+            (FLAG_SEQUENCE | FLAG_LAST, -self.sequence)
+        } else {
+            (FLAG_SEQUENCE, self.sequence)
+        };
+        Message::Binary(pack_frame(AUDIO_ONLY_REQUEST, flags, SERIALIZATION_NONE, COMPRESSION_NONE, Some(sequence), pcm))
+    }
+}
+
+/// 服务端下发的一帧
+#[derive(Debug, PartialEq)]
+enum ServerFrame {
+    /// 识别结果；`text` 为截至当前的完整文本，`last` 表示最后一包
+    Response { sequence: Option<i32>, last: bool, text: Option<String> },
+    Error { code: u32, message: String },
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ResponsePayload {
+    #[serde(default)]
+    result: Option<ResponseResult>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ResponseResult {
+    #[serde(default)]
+    text: String,
+}
+
+/// 按大端读取 4 字节，越界时报错
+fn read_u32(data: &[u8], pos: &mut usize) -> Result<u32> {
+    let bytes = data
+        .get(*pos..*pos + 4)
+        .ok_or_else(|| anyhow::anyhow!("服务端响应帧不完整"))?;
+    *pos += 4;
+    Ok(u32::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+/// 按 payload size 读取 payload
+fn read_payload<'a>(data: &'a [u8], pos: &mut usize) -> Result<&'a [u8]> {
+    let size = read_u32(data, pos)? as usize;
+    let payload = data
+        .get(*pos..*pos + size)
+        .ok_or_else(|| anyhow::anyhow!("服务端响应帧不完整：payload 声明 {} 字节，实际 {} 字节", size, data.len() - *pos))?;
+    *pos += size;
+    Ok(payload)
+}
+
+/// 解析服务端帧
+fn decode_frame(data: &[u8]) -> Result<ServerFrame> {
+    if data.len() < 4 {
+        anyhow::bail!("服务端响应帧不完整：只有 {} 字节", data.len());
+    }
+    let header_size = (data[0] & 0x0f) as usize * 4;
+    let message_type = data[1] >> 4;
+    let flags = data[1] & 0x0f;
+    let serialization = data[2] >> 4;
+    let compression = data[2] & 0x0f;
+    let mut pos = header_size;
+
+    match message_type {
+        FULL_SERVER_RESPONSE => {
+            let sequence = if flags & FLAG_SEQUENCE != 0 {
+                Some(read_u32(data, &mut pos)? as i32)
+            } else {
+                None
+            };
+            let payload = read_payload(data, &mut pos)?;
+            let payload = match compression {
+                COMPRESSION_GZIP => gunzip(payload)?,
+                COMPRESSION_NONE => payload.to_vec(),
+                other => anyhow::bail!("不支持的压缩方式: {}", other),
+            };
+            let text = if serialization == SERIALIZATION_JSON && !payload.is_empty() {
+                let parsed: ResponsePayload = serde_json::from_slice(&payload)
+                    .map_err(|e| anyhow::anyhow!("解析服务端响应失败: {}", e))?;
+                parsed.result.map(|result| result.text)
+            } else {
+                None
+            };
+            Ok(ServerFrame::Response {
+                sequence,
+                // 最后一包：标志位 bit1，或 sequence 为负数
+                last: flags & FLAG_LAST != 0 || sequence.is_some_and(|seq| seq < 0),
+                text,
+            })
+        }
+        SERVER_ERROR_RESPONSE => {
+            let code = read_u32(data, &mut pos)?;
+            let message = read_payload(data, &mut pos)?;
+            let message = match compression {
+                COMPRESSION_GZIP => gunzip(message).unwrap_or_else(|_| message.to_vec()),
+                _ => message.to_vec(),
+            };
+            Ok(ServerFrame::Error {
+                code,
+                message: String::from_utf8_lossy(&message).into_owned(),
+            })
+        }
+        other => anyhow::bail!("未知的服务端消息类型: {:#06b}", other),
+    }
+}
+
+/// 识别参数
+fn request_params(vocabulary: &[String]) -> serde_json::Value {
+    let mut request = serde_json::json!({
+        "model_name": "bigmodel",
+        "enable_itn": true,
+        "enable_punc": true,
+        "result_type": "full"
+    });
+    if !vocabulary.is_empty() {
+        let hotwords: Vec<_> = vocabulary.iter().map(|word| serde_json::json!({ "word": word })).collect();
+        request["corpus"] = serde_json::json!({
+            "context": serde_json::json!({ "hotwords": hotwords }).to_string()
+        });
+    }
+    serde_json::json!({
+        "user": { "uid": "push-to-talk" },
+        "audio": {
+            "format": "pcm",
+            "codec": "raw",
+            "rate": SAMPLE_RATE,
+            "bits": 16,
+            "channel": 1
+        },
+        "request": request
+    })
+}
+
+/// 火山引擎流式识别客户端（每次按键新建连接，不做预热）
+pub struct VolcRealtimeClient {
+    config: VolcConfig,
+    options: SessionOptions,
+    vocabulary: Vec<String>,
+}
+
+impl VolcRealtimeClient {
+    pub fn new(config: VolcConfig, options: SessionOptions, vocabulary: Vec<String>) -> Self {
+        if options.vad_mode == RealtimeVadMode::Server {
+            tracing::warn!("火山引擎流式识别不支持服务端 VAD 断句，改为松开按键后统一出结果");
+        }
+        Self {
+            config,
+            // 整段识别：松开按键时发送最后一包，服务端返回完整文本
+            options: SessionOptions {
+                vad_mode: RealtimeVadMode::Manual,
+                auto_commit: None,
+                ..options
+            },
+            vocabulary,
+        }
+    }
+
+    /// 建立连接并发送 full client request
+    async fn connect(&self) -> Result<(WsSink, WsSource)> {
+        if self.config.app_key.trim().is_empty() || self.config.access_key.trim().is_empty() {
+            anyhow::bail!("火山引擎 App Key / Access Key 未配置");
+        }
+        tracing::info!("创建火山引擎 WebSocket 连接: {}", self.config.url);
+
+        let request = http::Request::builder()
+            .uri(&self.config.url)
+            .header("Host", http::Uri::try_from(&self.config.url)?.host().unwrap_or_default())
+            .header("X-Api-App-Key", &self.config.app_key)
+            .header("X-Api-Access-Key", &self.config.access_key)
+            .header("X-Api-Resource-Id", &self.config.resource_id)
+            .header("X-Api-Connect-Id", next_connect_id())
+            .header("Connection", "Upgrade")
+            .header("Upgrade", "websocket")
+            .header("Sec-WebSocket-Version", "13")
+            .header("Sec-WebSocket-Key", tokio_tungstenite::tungstenite::handshake::client::generate_key())
+            .body(())?;
+
+        let (ws_stream, response) = qwen_realtime::open_websocket(request, &self.options.dns_overrides).await?;
+        let log_id = response
+            .headers()
+            .get("X-Tt-Logid")
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("-");
+        tracing::info!("火山引擎 WebSocket 连接成功 (logid: {})", log_id);
+
+        let (mut write, read) = ws_trace::split(ws_stream, WsTrace::start(self.options.ws_trace));
+        let frame = full_client_request(&request_params(&self.vocabulary))?;
+        write.send(Message::Binary(frame)).await
+            .map_err(|e| anyhow::anyhow!("发送识别参数失败: {}", e))?;
+        tracing::info!("已发送 full client request (热词 {} 个)", self.vocabulary.len());
+        Ok((write, read))
+    }
+
+    /// 服务支持直接上传的采样率
+    pub fn supported_sample_rates(&self) -> &'static [u32] {
+        &[SAMPLE_RATE]
+    }
+
+    /// 创建新的转录会话；火山引擎固定 16kHz，忽略设备协商出的采样率
+    pub async fn start_session(&self, sample_rate: u32) -> Result<RealtimeSession> {
+        if sample_rate != SAMPLE_RATE {
+            tracing::debug!("火山引擎只支持 {}Hz，录音将重采样（设备协商 {}Hz）", SAMPLE_RATE, sample_rate);
+        }
+        let (write, read) = self.connect().await?;
+
+        let (session, endpoints) = qwen_realtime::new_session(&self.options, SAMPLE_RATE);
+        let _ = endpoints.status_tx.send(SessionStatus::Connected);
+        let driver = VolcDriver {
+            endpoints,
+            close_timeout: self.options.timings.session_close_timeout,
+            stale_after: self.options.stale_threshold(true),
+            watchdog_interval: self.options.timings.watchdog_interval,
+        };
+        tokio::spawn(driver.run(write, read));
+        Ok(session)
+    }
+
+    /// 不做预热：空闲连接会被服务端断开
+    pub fn warm_up(&self) {}
+
+    /// 启动前的连通性测试：完成握手并等到服务端对识别参数的首个响应
+    pub async fn check_connectivity(&self) -> Result<()> {
+        let (mut write, mut read) = self.connect().await?;
+        let wait = self.options.timings.session_close_timeout.max(Duration::from_secs(5));
+        let result = match timeout(wait, read.next()).await {
+            Ok(Some(Ok(Message::Binary(data)))) => match decode_frame(&data)? {
+                ServerFrame::Response { .. } => Ok(()),
+                ServerFrame::Error { code, message } => Err(anyhow::anyhow!("服务端错误 {}: {}", code, message)),
+            },
+            Ok(Some(Ok(other))) => Err(anyhow::anyhow!("服务端返回了非预期的消息: {:?}", other)),
+            Ok(Some(Err(e))) => Err(anyhow::anyhow!("WebSocket 错误: {}", e)),
+            Ok(None) => Err(anyhow::anyhow!("连接被服务端关闭")),
+            Err(_) => Err(anyhow::anyhow!("等待服务端响应超时")),
+        };
+        send_close(&mut write).await;
+        result
+    }
+
+    /// 没有常驻连接，无需清理
+    pub async fn shutdown(&self) {}
+}
+
+/// 发送 Close 帧（1000）
+async fn send_close(write: &mut WsSink) {
+    let frame = CloseFrame {
+        code: CloseCode::Normal,
+        reason: "".into(),
+    };
+    if let Err(e) = write.send(Message::Close(Some(frame))).await {
+        tracing::debug!("发送 Close 帧失败: {}", e);
+    }
+}
+
+/// 取出队列中积压的音频合并为一帧发送；`last` 时即使没有剩余音频也发送最后一包
+async fn send_queued_audio(write: &mut WsSink, queue: &AudioQueue, encoder: &mut VolcAudioEncoder, last: bool) -> Result<()> {
+    let pcm: Vec<u8> = queue.drain().into_iter().flatten().collect();
+    if pcm.is_empty() && !last {
+        return Ok(());
+    }
+    write.send(encoder.encode(&pcm, last)).await
+        .map_err(|e| anyhow::anyhow!("发送音频帧失败: {}", e))?;
+    if last {
+        tracing::info!("已发送最后一包音频 (sequence {})", -encoder.sequence);
+    }
+    Ok(())
+}
+
+/// 火山引擎会话驱动任务：处理与千问会话相同的命令，结果与状态经由共用的通道上报
+struct VolcDriver {
+    endpoints: SessionEndpoints,
+    close_timeout: Duration,
+    // 等待结果期间的死连接判定阈值（None 表示关闭看门狗）
+    stale_after: Option<Duration>,
+    watchdog_interval: Duration,
+}
+
+impl VolcDriver {
+    async fn run(self, write: WsSink, read: WsSource) {
+        let status_tx = self.endpoints.status_tx.clone();
+        let audio_queue = std::sync::Arc::clone(&self.endpoints.audio_queue);
+        self.drive(write, read).await;
+        tracing::info!("音频发送积压统计: {}", audio_queue.summary());
+        let _ = status_tx.send(SessionStatus::Closed);
+    }
+
+    async fn drive(mut self, mut write: WsSink, mut read: WsSource) {
+        let audio_queue = std::sync::Arc::clone(&self.endpoints.audio_queue);
+        let mut encoder = VolcAudioEncoder::new();
+        let mut text = String::new();
+        let mut configured = false;
+        let mut committed = false;
+        let mut closing = false;
+        let mut finished = false;
+        let mut close_ack: Option<oneshot::Sender<()>> = None;
+        let mut close_deadline: Option<tokio::time::Instant> = None;
+        let mut watchdog = tokio::time::interval(self.watchdog_interval);
+        watchdog.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut committed_at: Option<Instant> = None;
+        self.endpoints.liveness.mark_received();
+
+        loop {
+            let failure = tokio::select! {
+                _ = audio_queue.notified(), if !closing && !committed => {
+                    match send_queued_audio(&mut write, &audio_queue, &mut encoder, false).await {
+                        Ok(()) => continue,
+                        Err(e) => e,
+                    }
+                }
+                cmd = self.endpoints.cmd_rx.recv(), if !closing => match cmd {
+                    Some(SessionCommand::Commit) => {
+                        if committed {
+                            continue;
+                        }
+                        committed = true;
+                        committed_at = Some(Instant::now());
+                        match send_queued_audio(&mut write, &audio_queue, &mut encoder, true).await {
+                            Ok(()) => continue,
+                            Err(e) => e,
+                        }
+                    }
+                    // 火山引擎会话不进入连接池，用完即关闭
+                    Some(SessionCommand::Reset { .. } | SessionCommand::Probe(_)) => continue,
+                    // 协议没有取消指令，取消与关闭都直接断开连接，服务端随之停止识别
+                    cmd @ (Some(SessionCommand::Close(_)) | Some(SessionCommand::Cancel(_)) | None) => {
+                        match cmd {
+                            Some(SessionCommand::Close(ack)) => close_ack = Some(ack),
+                            Some(SessionCommand::Cancel(ack)) => {
+                                close_ack = Some(ack);
+                                text.clear();
+                                finished = true;
+                            }
+                            _ => {}
+                        }
+                        audio_queue.drain();
+                        closing = true;
+                        close_deadline = Some(tokio::time::Instant::now() + self.close_timeout);
+                        send_close(&mut write).await;
+                        continue;
+                    }
+                },
+                _ = tokio::time::sleep_until(close_deadline.unwrap_or_else(tokio::time::Instant::now)), if close_deadline.is_some() => {
+                    tracing::warn!("等待服务端 Close 回执超时，直接释放连接");
+                    break;
+                }
+                _ = watchdog.tick(), if self.stale_after.is_some() && committed_at.is_some() && !finished && !closing => {
+                    let silence = committed_at.map_or(Duration::ZERO, |at| qwen_realtime::awaiting_silence(&self.endpoints.liveness, at));
+                    if self.stale_after.is_some_and(|limit| silence >= limit) {
+                        ConnectionStale { silence }.into()
+                    } else {
+                        continue;
+                    }
+                }
+                msg = read.next() => match msg {
+                    Some(Ok(Message::Binary(data))) => {
+                        self.endpoints.liveness.mark_received();
+                        match decode_frame(&data) {
+                            Ok(ServerFrame::Response { last, text: latest, .. }) => {
+                                if !configured {
+                                    configured = true;
+                                    let _ = self.endpoints.status_tx.send(SessionStatus::Configured);
+                                }
+                                if let Some(latest) = latest {
+                                    if !latest.is_empty() {
+                                        self.endpoints.first_transcript.get_or_init(Instant::now);
+                                    }
+                                    text = latest;
+                                    *self.endpoints.received_text.lock().unwrap() = text.trim().to_string();
+                                }
+                                if last && committed && !finished {
+                                    finished = true;
+                                    let _ = self.endpoints.status_tx.send(SessionStatus::Committed);
+                                    let result = text.trim().to_string();
+                                    tracing::info!("转录完成: {}", privacy::redact(&result));
+                                    let _ = self.endpoints.result_tx.send(Ok(result)).await;
+                                }
+                                continue;
+                            }
+                            Ok(ServerFrame::Error { code, message }) => {
+                                tracing::error!("火山引擎返回错误 {}: {}", code, message);
+                                let _ = self.endpoints.status_tx.send(SessionStatus::Error {
+                                    code: Some(code.to_string()),
+                                    message: message.clone(),
+                                });
+                                if !finished {
+                                    let _ = self.endpoints.result_tx
+                                        .send(Err(anyhow::anyhow!("服务端错误 {}: {}", code, message)))
+                                        .await;
+                                }
+                                return;
+                            }
+                            Err(e) => e,
+                        }
+                    }
+                    Some(Ok(Message::Close(_))) | None => {
+                        if closing || finished {
+                            tracing::info!("WebSocket 连接关闭");
+                            break;
+                        }
+                        anyhow::anyhow!("连接被服务端关闭")
+                    }
+                    Some(Err(e)) => {
+                        if closing || finished {
+                            tracing::info!("WebSocket 连接已断开: {}", e);
+                            break;
+                        }
+                        anyhow::anyhow!("WebSocket 错误: {}", e)
+                    }
+                    Some(Ok(_)) => {
+                        self.endpoints.liveness.mark_received();
+                        continue;
+                    }
+                },
+            };
+
+            if finished {
+                tracing::info!("会话已完成，忽略连接异常: {}", failure);
+                break;
+            }
+            // 协议不支持续传，出错直接结束，由上层回退到 HTTP 识别
+            tracing::error!("火山引擎会话中断: {}", failure);
+            let _ = self.endpoints.status_tx.send(SessionStatus::Error {
+                code: None,
+                message: failure.to_string(),
+            });
+            let _ = self.endpoints.result_tx.send(Err(failure)).await;
+            return;
+        }
+
+        if !finished {
+            let _ = self.endpoints.result_tx.send(Err(anyhow::anyhow!("未收到转录结果"))).await;
+        }
+        if let Some(ack) = close_ack {
+            let _ = ack.send(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server_response(sequence: i32, last: bool, text: &str) -> Vec<u8> {
+        let flags = if last { FLAG_SEQUENCE | FLAG_LAST } else { FLAG_SEQUENCE };
+        let payload = serde_json::json!({ "result": { "text": text } }).to_string();
+        encode_frame(FULL_SERVER_RESPONSE, flags, SERIALIZATION_JSON, Some(sequence), payload.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn packs_client_frames() {
+        let frame = full_client_request(&request_params(&["火山".to_string()])).unwrap();
+        assert_eq!(frame[..4], [0x11, 0x11, 0x11, 0x00]);
+        assert_eq!(i32::from_be_bytes(frame[4..8].try_into().unwrap()), 1);
+        let mut pos = 8;
+        let request: serde_json::Value = serde_json::from_slice(&gunzip(read_payload(&frame, &mut pos).unwrap()).unwrap()).unwrap();
+        assert_eq!(pos, frame.len());
+        assert_eq!(request["audio"]["rate"], 16000);
+        assert!(request["request"]["corpus"]["context"].as_str().unwrap().contains("火山"));
+
+        // 音频帧不压缩，原始 PCM 直接作为 payload
+        let mut encoder = VolcAudioEncoder::new();
+        let pcm = [1u8, 2, 3, 4];
+        let Message::Binary(frame) = encoder.encode(&pcm, false) else {
+            panic!("应为二进制帧");
+        };
+        assert_eq!(frame[..4], [0x11, 0x21, 0x00, 0x00]);
+        assert_eq!(i32::from_be_bytes(frame[4..8].try_into().unwrap()), 2);
+        let mut pos = 8;
+        assert_eq!(read_payload(&frame, &mut pos).unwrap(), pcm);
+
+        // 最后一包：last 标志 + 负 sequence，允许没有音频
+        let Message::Binary(frame) = encoder.encode(&[], true) else {
+            panic!("应为二进制帧");
+        };
+        assert_eq!(frame[1], 0x23);
+        assert_eq!(i32::from_be_bytes(frame[4..8].try_into().unwrap()), -3);
+        let mut pos = 8;
+        assert!(read_payload(&frame, &mut pos).unwrap().is_empty());
+    }
+
+    #[test]
+    fn unpacks_server_frames() {
+        assert_eq!(
+            decode_frame(&server_response(2, false, "你好")).unwrap(),
+            ServerFrame::Response { sequence: Some(2), last: false, text: Some("你好".to_string()) }
+        );
+        assert_eq!(
+            decode_frame(&server_response(-5, true, "你好，世界。")).unwrap(),
+            ServerFrame::Response { sequence: Some(-5), last: true, text: Some("你好，世界。".to_string()) }
+        );
+
+        // 错误帧：header + code + message size + message（未压缩）
+        let message = b"invalid audio";
+        let mut frame = vec![0x11, 0xf0, 0x10, 0x00];
+        frame.extend_from_slice(&45000001u32.to_be_bytes());
+        frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+        frame.extend_from_slice(message);
+        assert_eq!(
+            decode_frame(&frame).unwrap(),
+            ServerFrame::Error { code: 45000001, message: "invalid audio".to_string() }
+        );
+
+        // 截断的帧报错而不是越界
+        let truncated = server_response(2, false, "你好");
+        assert!(decode_frame(&truncated[..truncated.len() - 3]).is_err());
+        assert!(decode_frame(&[0x11, 0x90]).is_err());
+    }
+}
@@ -0,0 +1,369 @@
+// WebSocket 事件留痕（排障用，默认关闭）
+// 开启后实时识别连接收发的每条事件带时间戳追加写入 logs/ws-trace-{id}.jsonl，会话结束写入摘要；
+// 音频只记大小不记内容，隐私模式下不留痕（会话中途开启时识别文本只记字数）。关闭时读写端只多一次 Option 判断
+
+use anyhow::Result;
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{Sink, Stream, StreamExt};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use crate::logging;
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+const TRACE_PREFIX: &str = "ws-trace-";
+const TRACE_SUFFIX: &str = ".jsonl";
+// 留痕文件最多保留的个数与总大小，新建文件前按修改时间删除最旧的
+const MAX_TRACE_FILES: usize = 20;
+const MAX_TRACE_TOTAL_BYTES: u64 = 50 * 1024 * 1024;
+// 单个文件的大小上限，超出后只计数不再逐条写入
+const MAX_TRACE_FILE_BYTES: u64 = 10 * 1024 * 1024;
+// 上传音频的事件类型：只记录消息大小
+const AUDIO_EVENT_TYPE: &str = "input_audio_buffer.append";
+// 隐私模式下只记字数的文本字段
+const TEXT_FIELDS: [&str; 4] = ["transcript", "text", "delta", "stash"];
+
+static NEXT_TRACE_SEQ: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Send,
+    Recv,
+}
+
+impl Direction {
+    fn as_str(self) -> &'static str {
+        match self {
+            Direction::Send => "send",
+            Direction::Recv => "recv",
+        }
+    }
+}
+
+#[derive(Default)]
+struct TraceState {
+    writer: Option<BufWriter<File>>,
+    written: u64,
+    truncated: bool,
+    sent: u64,
+    received: u64,
+    audio_messages: u64,
+    audio_bytes: u64,
+    // 各类事件的条数，如 "send:input_audio_buffer.commit"
+    events: BTreeMap<String, u64>,
+}
+
+/// 一个会话的留痕文件；读写端共享，最后一个引用释放时写入摘要
+pub struct WsTrace {
+    path: PathBuf,
+    started: Instant,
+    state: Mutex<TraceState>,
+}
+
+impl WsTrace {
+    /// 开启时在日志目录下新建留痕文件；失败只记日志，不影响识别
+    /// 隐私模式下不建留痕文件
+    pub fn start(enabled: bool) -> Option<Arc<Self>> {
+        Self::start_in(enabled, logging::log_dir)
+    }
+
+    pub(crate) fn start_in(enabled: bool, dir: impl FnOnce() -> Result<PathBuf>) -> Option<Arc<Self>> {
+        if !enabled {
+            return None;
+        }
+        if crate::privacy::enabled() {
+            tracing::debug!("隐私模式：不记录 WebSocket 留痕");
+            return None;
+        }
+        let result = dir().and_then(|dir| Self::create_in(&dir));
+        match result {
+            Ok(trace) => {
+                tracing::info!("WebSocket 事件留痕: {}", trace.path.display());
+                Some(Arc::new(trace))
+            }
+            Err(e) => {
+                tracing::warn!("创建 WebSocket 留痕文件失败: {}", e);
+                None
+            }
+        }
+    }
+
+    fn create_in(dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        prune(dir, MAX_TRACE_FILES - 1, MAX_TRACE_TOTAL_BYTES);
+        let unix_ms = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        let seq = NEXT_TRACE_SEQ.fetch_add(1, Ordering::Relaxed);
+        let path = dir.join(format!("{}{}-{}{}", TRACE_PREFIX, unix_ms, seq, TRACE_SUFFIX));
+        let file = File::create(&path)?;
+        Ok(Self {
+            path,
+            started: Instant::now(),
+            state: Mutex::new(TraceState {
+                writer: Some(BufWriter::new(file)),
+                ..TraceState::default()
+            }),
+        })
+    }
+
+    fn record(&self, dir: Direction, message: &Message) {
+        let (kind, mut entry) = describe(message, crate::privacy::enabled());
+        // 上行的二进制帧（火山引擎）与音频事件计入音频统计
+        let audio = kind == AUDIO_EVENT_TYPE || (dir == Direction::Send && kind == "binary");
+        let mut state = self.state.lock().unwrap();
+        match dir {
+            Direction::Send => state.sent += 1,
+            Direction::Recv => state.received += 1,
+        }
+        if audio {
+            state.audio_messages += 1;
+            state.audio_bytes += entry["bytes"].as_u64().unwrap_or_default();
+        }
+        *state.events.entry(format!("{}:{}", dir.as_str(), kind)).or_default() += 1;
+
+        entry["t_ms"] = json!(self.started.elapsed().as_millis() as u64);
+        entry["dir"] = json!(dir.as_str());
+        entry["type"] = json!(kind);
+        self.append(&mut state, &entry);
+    }
+
+    fn append(&self, state: &mut TraceState, entry: &Value) {
+        if state.truncated {
+            return;
+        }
+        let Some(writer) = state.writer.as_mut() else { return };
+        let line = entry.to_string();
+        let written = writeln!(writer, "{}", line);
+        state.written += line.len() as u64 + 1;
+        if let Err(e) = written {
+            tracing::warn!("写入 WebSocket 留痕失败: {}", e);
+            state.writer = None;
+        } else if state.written >= MAX_TRACE_FILE_BYTES {
+            state.truncated = true;
+            let _ = writeln!(writer, "{}", json!({ "dir": "info", "type": "truncated", "bytes": state.written }));
+        }
+    }
+}
+
+impl Drop for WsTrace {
+    fn drop(&mut self) {
+        let state = self.state.get_mut().unwrap();
+        let summary = json!({
+            "t_ms": self.started.elapsed().as_millis() as u64,
+            "dir": "summary",
+            "sent": state.sent,
+            "received": state.received,
+            "audio_messages": state.audio_messages,
+            "audio_bytes": state.audio_bytes,
+            "truncated": state.truncated,
+            "events": state.events,
+        });
+        let Some(writer) = state.writer.as_mut() else { return };
+        if let Err(e) = writeln!(writer, "{}", summary).and_then(|_| writer.flush()) {
+            tracing::warn!("写入 WebSocket 留痕摘要失败: {}", e);
+            return;
+        }
+        tracing::info!(
+            "WebSocket 留痕已写入 {} (发送 {} 条, 接收 {} 条)",
+            self.path.display(),
+            state.sent,
+            state.received
+        );
+    }
+}
+
+/// 消息的事件类型与要记录的内容：音频只留大小，其余 JSON 事件完整记录（隐私模式下文本只记字数）
+fn describe(message: &Message, hide_text: bool) -> (String, Value) {
+    match message {
+        Message::Text(text) => {
+            if text.contains(AUDIO_EVENT_TYPE) {
+                return (AUDIO_EVENT_TYPE.to_string(), json!({ "bytes": text.len() }));
+            }
+            match serde_json::from_str::<Value>(text) {
+                Ok(mut event) => {
+                    let kind = event.get("type").and_then(Value::as_str).unwrap_or("unknown").to_string();
+                    if hide_text {
+                        redact_text(&mut event);
+                    }
+                    (kind, json!({ "event": event }))
+                }
+                Err(_) => ("text".to_string(), json!({ "bytes": text.len() })),
+            }
+        }
+        // 火山引擎的音频与响应都是二进制帧（gzip），只记大小
+        Message::Binary(data) => ("binary".to_string(), json!({ "bytes": data.len() })),
+        Message::Ping(data) => ("ping".to_string(), json!({ "payload": String::from_utf8_lossy(data) })),
+        Message::Pong(data) => ("pong".to_string(), json!({ "payload": String::from_utf8_lossy(data) })),
+        Message::Close(frame) => (
+            "close".to_string(),
+            json!({ "code": frame.as_ref().map(|f| u16::from(f.code)), "reason": frame.as_ref().map(|f| f.reason.to_string()) }),
+        ),
+        Message::Frame(_) => ("frame".to_string(), json!({})),
+    }
+}
+
+fn redact_text(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, field) in map.iter_mut() {
+                match field {
+                    Value::String(text) if TEXT_FIELDS.contains(&key.as_str()) => {
+                        *field = json!(format!("[{} 字]", text.chars().count()));
+                    }
+                    _ => redact_text(field),
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_text),
+        _ => {}
+    }
+}
+
+/// 按修改时间删除最旧的留痕文件，直到个数不超过 `keep` 且总大小不超过 `max_bytes`
+fn prune(dir: &Path, keep: usize, max_bytes: u64) {
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+    let mut files: Vec<(SystemTime, u64, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with(TRACE_PREFIX) && name.ends_with(TRACE_SUFFIX)
+        })
+        .filter_map(|entry| {
+            let meta = entry.metadata().ok()?;
+            Some((meta.modified().unwrap_or(UNIX_EPOCH), meta.len(), entry.path()))
+        })
+        .collect();
+    files.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.2.cmp(&b.2)));
+
+    let mut total: u64 = files.iter().map(|(_, len, _)| len).sum();
+    let mut remaining = files.len();
+    for (_, len, path) in files {
+        if remaining <= keep && total <= max_bytes {
+            break;
+        }
+        match std::fs::remove_file(&path) {
+            Ok(()) => tracing::debug!("清理旧的 WebSocket 留痕: {}", path.display()),
+            Err(e) => tracing::warn!("清理 WebSocket 留痕失败 ({}): {}", path.display(), e),
+        }
+        total = total.saturating_sub(len);
+        remaining -= 1;
+    }
+}
+
+/// 拆分 WebSocket 读写端，开启留痕时两端各自记录经过的消息
+pub(crate) fn split(ws_stream: WsStream, trace: Option<Arc<WsTrace>>) -> (TracedSink, TracedSource) {
+    let (write, read) = ws_stream.split();
+    (
+        TracedSink { inner: write, trace: trace.clone() },
+        TracedSource { inner: read, trace },
+    )
+}
+
+/// 记录发送消息的 WebSocket 写端
+pub(crate) struct TracedSink {
+    inner: SplitSink<WsStream, Message>,
+    trace: Option<Arc<WsTrace>>,
+}
+
+impl Sink<Message> for TracedSink {
+    type Error = WsError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.inner).poll_ready(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        if let Some(ref trace) = self.trace {
+            trace.record(Direction::Send, &item);
+        }
+        Pin::new(&mut self.inner).start_send(item)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
+
+/// 记录接收消息的 WebSocket 读端
+pub(crate) struct TracedSource {
+    inner: SplitStream<WsStream>,
+    trace: Option<Arc<WsTrace>>,
+}
+
+impl Stream for TracedSource {
+    type Item = Result<Message, WsError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let item = futures_util::ready!(Pin::new(&mut self.inner).poll_next(cx));
+        if let (Some(trace), Some(Ok(message))) = (&self.trace, &item) {
+            trace.record(Direction::Recv, message);
+        }
+        Poll::Ready(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_events_without_audio_and_prunes_old_files() {
+        let dir = std::env::temp_dir().join(format!("ws-trace-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for i in 0..3 {
+            std::fs::write(dir.join(format!("{}old-{}{}", TRACE_PREFIX, i, TRACE_SUFFIX)), "x").unwrap();
+        }
+        std::fs::write(dir.join("other.log"), "x").unwrap();
+        prune(&dir, 1, MAX_TRACE_TOTAL_BYTES);
+        let left = std::fs::read_dir(&dir).unwrap().count();
+        assert_eq!(left, 2, "只保留 1 个留痕文件，其他文件不动");
+
+        let trace = WsTrace::create_in(&dir).unwrap();
+        let path = trace.path.clone();
+        let audio = json!({ "type": "input_audio_buffer.append", "audio": "QUJDRA==" }).to_string();
+        trace.record(Direction::Send, &Message::Text(audio.clone()));
+        trace.record(Direction::Send, &Message::Text(json!({ "type": "input_audio_buffer.commit" }).to_string()));
+        trace.record(
+            Direction::Recv,
+            &Message::Text(json!({ "type": "conversation.item.input_audio_transcription.completed", "transcript": "你好" }).to_string()),
+        );
+        drop(trace);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains("QUJDRA=="), "音频内容不应写入留痕");
+        let lines: Vec<Value> = contents.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0]["type"], "input_audio_buffer.append");
+        assert_eq!(lines[0]["bytes"], audio.len() as u64);
+        assert_eq!(lines[2]["event"]["transcript"], "你好");
+        let summary = &lines[3];
+        assert_eq!(summary["dir"], "summary");
+        assert_eq!(summary["sent"], 2);
+        assert_eq!(summary["received"], 1);
+        assert_eq!(summary["audio_bytes"], audio.len() as u64);
+        assert_eq!(summary["events"]["send:input_audio_buffer.commit"], 1);
+
+        let completed = json!({ "type": "conversation.item.input_audio_transcription.completed", "transcript": "你好" });
+        let (_, entry) = describe(&Message::Text(completed.to_string()), true);
+        assert_eq!(entry["event"]["transcript"], "[2 字]", "隐私模式下只记字数");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
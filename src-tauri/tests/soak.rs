@@ -0,0 +1,122 @@
+// 识别管线长稳测试：2000 次按键循环后断言遗留任务数与内存增量
+// 需要替换全局分配器统计存活内存，放在独立的测试二进制里，不影响单元测试；运行：cargo test --features soak --test soak
+// 循环交替走手动断句（发送任务 join 后 commit 取结果）与分段（结果通道关闭后分段任务结束）两种收尾方式。
+// 录音器（cpal 设备）与前端历史记录不在覆盖范围内
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicIsize, Ordering};
+use std::time::{Duration, Instant};
+
+use futures_util::{SinkExt, StreamExt};
+use push_to_talk_lib::SoakClient;
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+
+/// 统计当前存活堆内存的分配器，用于内存增量断言
+struct CountingAllocator;
+
+static LIVE_BYTES: AtomicIsize = AtomicIsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LIVE_BYTES.fetch_add(layout.size() as isize, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE_BYTES.fetch_sub(layout.size() as isize, Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// 本地 mock provider：收到 commit 后回一条 completed 事件和 response.done
+async fn spawn_mock_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let Ok(mut ws) = tokio_tungstenite::accept_async(stream).await else {
+                    return;
+                };
+                while let Some(Ok(msg)) = ws.next().await {
+                    let Message::Text(text) = msg else {
+                        continue;
+                    };
+                    let event: serde_json::Value = serde_json::from_str(&text).unwrap();
+                    if event["type"] != "input_audio_buffer.commit" {
+                        continue;
+                    }
+                    let replies = [
+                        serde_json::json!({
+                            "type": "conversation.item.input_audio_transcription.completed",
+                            "transcript": "测试文本。"
+                        }),
+                        serde_json::json!({ "type": "response.done" }),
+                    ];
+                    for reply in replies {
+                        if ws.send(Message::Text(reply.to_string())).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+            });
+        }
+    });
+    format!("ws://{}/realtime", addr)
+}
+
+async fn wait_for_tasks(metrics: &tokio::runtime::RuntimeMetrics, expected: usize) -> usize {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let alive = metrics.num_alive_tasks();
+        if alive <= expected || Instant::now() >= deadline {
+            return alive;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+/// 一次按键 = 一次手动断句循环 + 一次分段循环
+async fn run_cycle(client: &SoakClient) {
+    assert_eq!(client.run_cycle().await.unwrap(), "测试文本。");
+    assert_eq!(client.run_segmented_cycle().await.unwrap(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn soak_session_cycles_release_tasks_and_memory() {
+    const CYCLES: usize = 2000;
+    const WARMUP_CYCLES: usize = 100;
+    const MAX_MEMORY_GROWTH: isize = 2 * 1024 * 1024;
+
+    let url = spawn_mock_server().await;
+    let client = SoakClient::new(&url);
+    let metrics = tokio::runtime::Handle::current().metrics();
+
+    // 预热：让 tokio / TLS / 日志等一次性分配先完成
+    for _ in 0..WARMUP_CYCLES {
+        run_cycle(&client).await;
+    }
+    // 只剩 mock 服务的 accept 任务与连接池清理任务
+    let baseline_tasks = wait_for_tasks(&metrics, 2).await;
+    let baseline_bytes = LIVE_BYTES.load(Ordering::Relaxed);
+
+    for _ in WARMUP_CYCLES..CYCLES {
+        run_cycle(&client).await;
+    }
+
+    let alive_tasks = wait_for_tasks(&metrics, baseline_tasks).await;
+    assert_eq!(alive_tasks, baseline_tasks, "会话结束后仍有遗留任务");
+
+    let growth = LIVE_BYTES.load(Ordering::Relaxed) - baseline_bytes;
+    assert!(
+        growth < MAX_MEMORY_GROWTH,
+        "{} 次循环后内存增长 {} bytes，超过阈值 {} bytes",
+        CYCLES - WARMUP_CYCLES,
+        growth,
+        MAX_MEMORY_GROWTH
+    );
+}
@@ -62,6 +62,9 @@ interface TranscriptionResult {
   asr_time_ms: number;
   llm_time_ms: number | null;
   total_time_ms: number;
+  duration_ms: number | null;
+  recording_path: string | null;
+  privacy_mode: boolean;
 }
 
 // --- 历史记录 ---
@@ -76,15 +79,18 @@ interface HistoryRecord {
   totalTimeMs: number;
   success: boolean;
   errorMessage: string | null;
+  recordingPath?: string | null;
 }
 
 const HISTORY_KEY = 'pushtotalk_history';
 const MAX_HISTORY = 50;
 
+// 泄漏修复：读取时同样按 MAX_HISTORY 截断，旧版本写入的超长历史不再整体留在内存里
 const loadHistory = (): HistoryRecord[] => {
   try {
     const data = localStorage.getItem(HISTORY_KEY);
-    return data ? JSON.parse(data) : [];
+    const records: HistoryRecord[] = data ? JSON.parse(data) : [];
+    return records.slice(0, MAX_HISTORY);
   } catch { return []; }
 };
 
@@ -249,6 +255,8 @@ function App() {
         setLlmTime(result.llm_time_ms);
         setTotalTime(result.total_time_ms);
         setStatus("running");
+        // 隐私模式下不把识别原文 / 润色文本写入历史记录
+        if (result.privacy_mode) return;
         // 添加成功记录到历史
         const record: HistoryRecord = {
           id: nanoid(8),
@@ -260,7 +268,8 @@ function App() {
           llmTimeMs: result.llm_time_ms,
           totalTimeMs: result.total_time_ms,
           success: true,
-          errorMessage: null
+          errorMessage: null,
+          recordingPath: result.recording_path
         };
         setHistory(prev => {
           const updated = [record, ...prev].slice(0, MAX_HISTORY);
@@ -268,8 +277,8 @@ function App() {
           return updated;
         });
       });
-      await listen<string>("error", (event) => {
-        const errMsg = event.payload;
+      await listen<{ code: string; message: string }>("error", (event) => {
+        const errMsg = event.payload.message;
         setError(errMsg);
         setStatus("running");
         // 添加失败记录到历史